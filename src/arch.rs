@@ -0,0 +1,59 @@
+//! Architecture and disassembly settings of the compared executables.
+
+use std::str::FromStr;
+
+use capstone::arch::x86::ArchMode;
+use serde::Deserialize;
+
+/// Architecture used to disassemble the executables.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Hash)]
+pub enum Arch {
+    /// 32-bit x86, the architecture of the game.
+    #[default]
+    #[serde(rename = "x86")]
+    X86,
+    /// 64-bit x86, for ports and tools built alongside the project.
+    #[serde(rename = "x86_64")]
+    X86_64,
+}
+
+impl Arch {
+    /// Capstone mode disassembling this architecture.
+    pub fn mode(&self) -> ArchMode {
+        match self {
+            Arch::X86 => ArchMode::Mode32,
+            Arch::X86_64 => ArchMode::Mode64,
+        }
+    }
+
+    /// Size in bytes of a pointer.
+    pub fn pointer_size(&self) -> usize {
+        match self {
+            Arch::X86 => 4,
+            Arch::X86_64 => 8,
+        }
+    }
+}
+
+impl FromStr for Arch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "x86" => Ok(Self::X86),
+            "x86_64" => Ok(Self::X86_64),
+            _ => Err(format!(
+                "Unknown architecture \"{s}\", expected one of x86 or x86_64"
+            )),
+        }
+    }
+}
+
+/// Assembly syntax of the disassembly.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Syntax {
+    #[default]
+    Intel,
+    Att,
+}
@@ -0,0 +1,194 @@
+//! Reading inputs wrapped in archives.
+//!
+//! Build artifacts are usually distributed as `.zip`, `.7z` or `.gz` files.
+//! Those are decompressed in memory so they can be passed directly.
+
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+
+/// Extensions of executable files looked up in archives.
+pub const EXECUTABLE_EXTENSIONS: &[&str] = &["exe", "dll"];
+
+/// Extensions of PDB files looked up in archives.
+pub const PDB_EXTENSIONS: &[&str] = &["pdb"];
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    IoError {
+        error: std::io::Error,
+    },
+    ZipError {
+        error: zip::result::ZipError,
+    },
+    SevenZipError {
+        error: sevenz_rust::Error,
+    },
+    EntryNotFound {
+        archive: PathBuf,
+    },
+    AmbiguousEntry {
+        archive: PathBuf,
+        entries: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArchiveError::EntryNotFound { archive } => {
+                write!(f, "No suitable file found in {}!", archive.display())
+            }
+            ArchiveError::AmbiguousEntry { archive, entries } => write!(
+                f,
+                "Multiple candidate files found in {}: {}",
+                archive.display(),
+                entries.join(", ")
+            ),
+            _ => std::fmt::Debug::fmt(self, f),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ArchiveError::IoError { error } => Some(error),
+            ArchiveError::ZipError { error } => Some(error),
+            ArchiveError::SevenZipError { error } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ArchiveError {
+    fn from(error: std::io::Error) -> Self {
+        ArchiveError::IoError { error }
+    }
+}
+
+impl From<zip::result::ZipError> for ArchiveError {
+    fn from(error: zip::result::ZipError) -> Self {
+        ArchiveError::ZipError { error }
+    }
+}
+
+impl From<sevenz_rust::Error> for ArchiveError {
+    fn from(error: sevenz_rust::Error) -> Self {
+        ArchiveError::SevenZipError { error }
+    }
+}
+
+fn extension_of(path: &Path) -> Option<String> {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+}
+
+/// Pick the entry to extract among the files of an archive.
+///
+/// An entry with one of the given extensions is preferred; an archive
+/// containing a single file is accepted whatever its name.
+fn select_entry(
+    archive: &Path,
+    names: &[String],
+    extensions: &[&str],
+) -> Result<usize, ArchiveError> {
+    let candidates: Vec<usize> = names
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| {
+            extension_of(Path::new(name))
+                .map(|extension| extensions.contains(&extension.as_str()))
+                .unwrap_or(false)
+        })
+        .map(|(idx, _)| idx)
+        .collect();
+
+    match (candidates.as_slice(), names.len()) {
+        ([idx], _) => Ok(*idx),
+        ([], 1) => Ok(0),
+        ([], _) => Err(ArchiveError::EntryNotFound {
+            archive: archive.into(),
+        }),
+        (candidates, _) => Err(ArchiveError::AmbiguousEntry {
+            archive: archive.into(),
+            entries: candidates.iter().map(|idx| names[*idx].clone()).collect(),
+        }),
+    }
+}
+
+fn read_zip(path: &Path, data: Vec<u8>, extensions: &[&str]) -> Result<Vec<u8>, ArchiveError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data))?;
+    let mut indices = Vec::new();
+    let mut names = Vec::new();
+
+    for idx in 0..archive.len() {
+        let entry = archive.by_index(idx)?;
+
+        if entry.is_file() {
+            indices.push(idx);
+            names.push(entry.name().to_string());
+        }
+    }
+
+    let selected = select_entry(path, &names, extensions)?;
+    let mut entry = archive.by_index(indices[selected])?;
+    let mut res = Vec::with_capacity(entry.size() as usize);
+
+    entry.read_to_end(&mut res)?;
+
+    Ok(res)
+}
+
+fn read_7z(path: &Path, data: Vec<u8>, extensions: &[&str]) -> Result<Vec<u8>, ArchiveError> {
+    let len = data.len() as u64;
+    let mut archive =
+        sevenz_rust::SevenZReader::new(Cursor::new(data), len, sevenz_rust::Password::empty())?;
+
+    let names: Vec<String> = archive
+        .archive()
+        .files
+        .iter()
+        .filter(|entry| !entry.is_directory() && entry.has_stream())
+        .map(|entry| entry.name().to_string())
+        .collect();
+
+    let selected = names[select_entry(path, &names, extensions)?].clone();
+    let mut res = Vec::new();
+
+    archive.for_each_entries(|entry, reader| {
+        if entry.name() == selected {
+            reader.read_to_end(&mut res)?;
+            return Ok(false);
+        }
+
+        // Entries must be consumed for the following ones to be readable.
+        std::io::copy(reader, &mut std::io::sink())?;
+
+        Ok(true)
+    })?;
+
+    Ok(res)
+}
+
+/// Read an input file, transparently extracting it from an archive.
+///
+/// `extensions` are the extensions of the file looked up inside `.zip` and
+/// `.7z` archives. Other files are returned as is.
+pub fn read_input(path: &Path, extensions: &[&str]) -> Result<Vec<u8>, ArchiveError> {
+    let data = std::fs::read(path)?;
+
+    match extension_of(path).as_deref() {
+        Some("gz") => {
+            let mut res = Vec::new();
+
+            GzDecoder::new(data.as_slice()).read_to_end(&mut res)?;
+
+            Ok(res)
+        }
+        Some("zip") => read_zip(path, data, extensions),
+        Some("7z") => read_7z(path, data, extensions),
+        _ => Ok(data),
+    }
+}
@@ -0,0 +1,230 @@
+//! Shields.io endpoint badges.
+//!
+//! The color of the badge follows the global match percentage: red below
+//! `red_below`, yellow below `yellow_below` and green otherwise. Thresholds
+//! are configured in the `[badge]` table of the mapping, which can also
+//! define categories of functions getting a badge of their own:
+//!
+//! ```toml
+//! [[badge.category]]
+//! name = "player"
+//! label = "Player"
+//! prefixes = ["Player::"]
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::format::PercentageFormat;
+use crate::{Executable, FunctionStats};
+
+/// Settings of the badge, the `[badge]` table of the mapping.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct BadgeConfig {
+    /// Percentage under which the badge is red, 25% by default.
+    pub red_below: Option<f32>,
+    /// Percentage under which the badge is yellow, 75% by default.
+    pub yellow_below: Option<f32>,
+    #[serde(default, rename = "category")]
+    pub categories: Vec<BadgeCategory>,
+}
+
+impl BadgeConfig {
+    pub fn thresholds(&self) -> BadgeThresholds {
+        let default = BadgeThresholds::default();
+
+        BadgeThresholds {
+            red_below: self.red_below.unwrap_or(default.red_below),
+            yellow_below: self.yellow_below.unwrap_or(default.yellow_below),
+        }
+    }
+}
+
+/// Functions of a game subsystem or milestone, getting a badge of their own.
+#[derive(Clone, Debug, Deserialize)]
+pub struct BadgeCategory {
+    /// Name of the category, used in the name of the badge file.
+    pub name: String,
+    /// Text on the left side of the badge, the name by default.
+    pub label: Option<String>,
+    /// Prefixes of the names of the functions of the category (`Player::`).
+    #[serde(default)]
+    pub prefixes: Vec<String>,
+    /// Names of the functions of the category.
+    #[serde(default)]
+    pub functions: Vec<String>,
+}
+
+impl BadgeCategory {
+    pub fn label(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.functions.iter().any(|x| x == name)
+            || self.prefixes.iter().any(|x| name.starts_with(x.as_str()))
+    }
+
+    /// Average match percentage of the functions of the category, `None` when it has none.
+    pub fn percent(&self, stats: &FunctionStats) -> Option<f32> {
+        let values: Vec<f32> = stats
+            .iter()
+            .filter(|(name, _)| self.contains(name))
+            .map(|(_, value)| value.unwrap_or(0.0))
+            .collect();
+
+        if values.is_empty() {
+            None
+        } else {
+            Some(values.iter().sum::<f32>() / values.len() as f32)
+        }
+    }
+
+    /// Match percentage of the functions of the category weighted by their
+    /// size, `None` when it has none.
+    pub fn weighted_percent(&self, stats: &FunctionStats, executable: &Executable) -> Option<f32> {
+        let sizes: Vec<(f32, usize)> = stats
+            .iter()
+            .filter(|(name, _)| self.contains(name))
+            .map(|(name, value)| {
+                let size = executable.get_function(name).map_or(0, |x| x.data.len());
+
+                (value.unwrap_or(0.0), size)
+            })
+            .collect();
+        let total_size: usize = sizes.iter().map(|(_, size)| size).sum();
+
+        if sizes.is_empty() {
+            None
+        } else if total_size == 0 {
+            Some(0.0)
+        } else {
+            Some(
+                sizes
+                    .iter()
+                    .map(|(value, size)| value * *size as f32)
+                    .sum::<f32>()
+                    / total_size as f32,
+            )
+        }
+    }
+
+    /// Path of the badge of the category, next to the global one (`badge.json` gives `badge_player.json`).
+    pub fn badge_path(&self, global_path: &Path) -> PathBuf {
+        let stem = global_path
+            .file_stem()
+            .map(|x| x.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "badge".into());
+        let file_name = match global_path.extension() {
+            Some(extension) => format!("{stem}_{}.{}", self.name, extension.to_string_lossy()),
+            None => format!("{stem}_{}", self.name),
+        };
+
+        global_path.with_file_name(file_name)
+    }
+}
+
+/// Percentages at which the color of the badge changes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BadgeThresholds {
+    pub red_below: f32,
+    pub yellow_below: f32,
+}
+
+impl Default for BadgeThresholds {
+    fn default() -> Self {
+        Self {
+            red_below: 25.0,
+            yellow_below: 75.0,
+        }
+    }
+}
+
+impl BadgeThresholds {
+    /// Shields.io color of the badge for a percentage.
+    pub fn color(&self, percent: f32) -> &'static str {
+        if percent < self.red_below {
+            "red"
+        } else if percent < self.yellow_below {
+            "yellow"
+        } else {
+            "green"
+        }
+    }
+}
+
+/// Shields.io style of the badge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BadgeStyle {
+    Flat,
+    FlatSquare,
+    Plastic,
+    ForTheBadge,
+    Social,
+}
+
+impl FromStr for BadgeStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "flat" => Ok(Self::Flat),
+            "flat-square" => Ok(Self::FlatSquare),
+            "plastic" => Ok(Self::Plastic),
+            "for-the-badge" => Ok(Self::ForTheBadge),
+            "social" => Ok(Self::Social),
+            _ => Err(format!(
+                "Unknown style \"{s}\", expected one of flat, flat-square, plastic, for-the-badge or social"
+            )),
+        }
+    }
+}
+
+/// Content of a Shields.io endpoint badge.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Badge {
+    pub schema_version: u32,
+    pub label: String,
+    pub message: String,
+    pub color: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub style: Option<BadgeStyle>,
+}
+
+impl Badge {
+    /// Badge showing a match percentage, colored after the thresholds.
+    pub fn for_percentage(
+        label: &str,
+        percent: f32,
+        percentage_format: &PercentageFormat,
+        thresholds: &BadgeThresholds,
+    ) -> Self {
+        Self {
+            schema_version: 1,
+            label: label.into(),
+            message: percentage_format.format(percent),
+            color: thresholds.color(percent).into(),
+            style: None,
+        }
+    }
+
+    pub fn with_style(mut self, style: Option<BadgeStyle>) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Replace the percentage shown by another text, keeping the color.
+    pub fn with_message(mut self, message: String) -> Self {
+        self.message = message;
+        self
+    }
+
+    /// Serialize the badge to the JSON expected by the Shields.io endpoint.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        Ok(serde_json::to_string(self)? + "\n")
+    }
+}
@@ -1,20 +1,59 @@
 use std::{
-    collections::HashMap,
     error::Error,
-    fs::File,
-    io::Write,
+    fmt::Write,
+    io::Cursor,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
-use argh::FromArgs;
+use argh::{ArgsInfo, FlagInfoKind, FromArgs, Optionality, SubCommands};
 use capstone::{
-    arch::x86::{ArchMode, ArchSyntax},
+    arch::x86::ArchSyntax,
     prelude::{BuildsCapstone, BuildsCapstoneSyntax},
     Capstone,
 };
-use satsuki::{Executable, Mapping};
+use object::Object;
+use satsuki::{
+    arch::{Arch, Syntax},
+    archive,
+    badge::{Badge, BadgeStyle, BadgeThresholds},
+    boundaries, bytesig,
+    cache::{CacheKey, DisassemblyCache},
+    callgraph::{self, CallGraph, CallGraphFormat},
+    cfg::{self, ControlFlowGraph},
+    check,
+    color::{ColorChoice, Colorizer},
+    compare,
+    coverage::{self, Coverage},
+    data,
+    decompme::{self, ScratchRequest},
+    demangle,
+    diff::{self, DiffAlgorithm, DiffOptions, Pairing},
+    dump::{self, MemoryDump},
+    explain,
+    format::PercentageFormat,
+    frame, golden,
+    graph::{self, ChartSize, Series},
+    group,
+    headers::ExecutableHeaders,
+    history::{self, History},
+    imports,
+    limits::FunctionLimits,
+    list::{self, FunctionOrder, NameFilter},
+    masm,
+    profile::{Dialect, FormattingProfile, HexNotation, LiteralStyle, Radix},
+    rename::{self, RenameOutcome},
+    report::{GroupBy, ReportFormat, StatsReport},
+    signature::{self, Signatures},
+    sink::{FileSink, HttpSink, ReportSink, SinkError, StdoutSink},
+    strings, stubs, suggest, tables, trace, validate, vtable,
+    watch::{self, FileWatcher},
+    workspace::{self, Defaults, TargetReport, Workspace, WorkspaceReport},
+    x87::X87Rules,
+    ErrorContext, Executable, ExecutableError, Function, FunctionStats, Mapping,
+};
 
-#[derive(FromArgs, PartialEq, Debug)]
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
 /// Top-level command.
 struct TopLevel {
     #[argh(subcommand)]
@@ -27,18 +66,131 @@ struct TopLevel {
     /// mapping CSV file related to the executable.
     #[argh(option)]
     mapping_file_csv: Option<PathBuf>,
+
+    /// IDA names export (IDC script or names list) related to the executable.
+    #[argh(option)]
+    mapping_file_ida: Option<PathBuf>,
+
+    /// name of the section containing the code, overriding the mapping.
+    #[argh(option)]
+    section: Option<String>,
+
+    /// architecture of the executables: x86 (the default) or x86_64, overriding the mapping.
+    #[argh(option)]
+    arch: Option<Arch>,
+
+    /// demangle the Itanium names of the symbol tables, for executables built with GCC or MinGW.
+    #[argh(switch)]
+    demangle_symbols: bool,
+
+    /// load files without headers as flat binaries at this address, overriding the mapping.
+    #[argh(option, from_str_fn(parse_offset))]
+    raw_base: Option<usize>,
+
+    /// version of the executable the mapping addresses are selected for (1.02h).
+    #[argh(option)]
+    game_version: Option<String>,
+
+    /// color output: always, never or auto (the default, honoring NO_COLOR).
+    #[argh(option, default = "ColorChoice::Auto")]
+    color: ColorChoice,
 }
 
-#[derive(FromArgs, PartialEq, Debug)]
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
 #[argh(subcommand)]
 enum SubCommandEnum {
     Disassemble(DisassembleSubCommand),
     Stats(StatsSubCommand),
     Badge(BadgeSubCommand),
+    HeadersDiff(HeadersDiffSubCommand),
+    GroupDiff(GroupDiffSubCommand),
+    Diff(DiffSubCommand),
+    ApplyNames(ApplyNamesSubCommand),
+    Test(TestSubCommand),
+    Trace(TraceSubCommand),
+    Upload(UploadSubCommand),
+    ExportAsm(ExportAsmSubCommand),
+    Stubs(StubsSubCommand),
+    Tables(TablesSubCommand),
+    Workspace(WorkspaceSubCommand),
+    List(ListSubCommand),
+    DumpAll(DumpAllSubCommand),
+    Watch(WatchSubCommand),
+    Check(CheckSubCommand),
+    CompareReports(CompareReportsSubCommand),
+    History(HistorySubCommand),
+    Graph(GraphSubCommand),
+    ValidateMapping(ValidateMappingSubCommand),
+    DetectFunctions(DetectFunctionsSubCommand),
+    Explain(ExplainSubCommand),
+    Hexdump(HexdumpSubCommand),
+    Cfg(CfgSubCommand),
+    Callgraph(CallgraphSubCommand),
+    Xref(XrefSubCommand),
+    DataStats(DataStatsSubCommand),
+    Vtables(VtablesSubCommand),
+    StringsDiff(StringsDiffSubCommand),
+    Coverage(CoverageSubCommand),
+    Gaps(GapsSubCommand),
+    MakeSigs(MakeSigsSubCommand),
+    ApplySigs(ApplySigsSubCommand),
+    SuggestMatches(SuggestMatchesSubCommand),
+    FindSimilar(FindSimilarSubCommand),
+}
+
+/// Declare a subcommand comparing functions, with the options of the diff
+/// shared by all of them appended to its own.
+macro_rules! diff_subcommand {
+    ($(#[$attr:meta])* struct $name:ident { $($field:tt)* }) => {
+        $(#[$attr])*
+        struct $name {
+            $($field)*
+
+            /// mask relocated operands (calls and addresses into the executable) before diffing.
+            #[argh(switch)]
+            ignore_relocs: bool,
+
+            /// how functions are paired with the reimplementation: name (the default) or address.
+            #[argh(option, default = "Pairing::Name")]
+            pair_by: Pairing,
+
+            /// compare the int3/nop padding at the end of functions instead of trimming it.
+            #[argh(switch)]
+            keep_padding: bool,
+
+            /// treat nop-equivalent filler (nop, lea esi, [esi], mov edi, edi...) of any encoding as equal.
+            #[argh(switch)]
+            normalize_nops: bool,
+
+            /// match instructions differing only by their immediate operands (constants and addresses).
+            #[argh(switch)]
+            fuzzy_immediates: bool,
+
+            /// x87 normalization rules, comma separated: wait, fxch, commute or all.
+            #[argh(option, default = "X87Rules::default()")]
+            x87: X87Rules,
+        }
+
+        impl $name {
+            /// Options of the diff selected on the command line.
+            fn diff_options(&self, algorithm: DiffAlgorithm) -> DiffOptions {
+                DiffOptions {
+                    algorithm,
+                    ignore_relocations: self.ignore_relocs,
+                    pairing: self.pair_by,
+                    keep_padding: self.keep_padding,
+                    normalize_nops: self.normalize_nops,
+                    fuzzy_immediates: self.fuzzy_immediates,
+                    x87: self.x87,
+                }
+            }
+        }
+    };
 }
 
+diff_subcommand! {
 /// Stats
-#[derive(FromArgs, PartialEq, Debug)]
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
 #[argh(subcommand, name = "stats")]
 struct StatsSubCommand {
     /// original executable file to disassemble.
@@ -56,10 +208,83 @@ struct StatsSubCommand {
     /// output file containing the stats.
     #[argh(option)]
     output_file: Option<PathBuf>,
+
+    /// URL the stats are POSTed to instead of being written out.
+    #[argh(option)]
+    output_url: Option<String>,
+
+    /// algorithm used to compare functions: raw, instruction, aligned or blocks.
+    #[argh(option, default = "DiffAlgorithm::Raw")]
+    diff_algorithm: DiffAlgorithm,
+
+    /// skip functions bigger than this number of bytes.
+    #[argh(option)]
+    max_function_size: Option<usize>,
+
+    /// skip functions taking longer than this number of milliseconds to compare.
+    #[argh(option)]
+    function_time_budget: Option<u64>,
+
+    /// number of decimals used for percentages.
+    #[argh(option, default = "2")]
+    precision: usize,
+
+    /// disable colored output, same as --color=never.
+    #[argh(switch)]
+    no_color: bool,
+
+    /// output format: text, csv, json, html, markdown or objdiff. Guessed from the output file extension by default.
+    #[argh(option)]
+    format: Option<ReportFormat>,
+
+    /// explain how the score of this function is computed instead of writing the stats.
+    #[argh(option)]
+    explain: Option<String>,
+
+    /// undecorate the MSVC names of the functions in the report.
+    #[argh(switch)]
+    demangle: bool,
+
+    /// previous json report to annotate the functions with their progress since.
+    #[argh(option)]
+    baseline: Option<PathBuf>,
+
+    /// sqlite database a snapshot of the stats is appended to.
+    #[argh(option)]
+    history: Option<PathBuf>,
+
+    /// list the stats per group of functions instead of per function: module.
+    #[argh(option)]
+    group_by: Option<GroupBy>,
+
+    /// weigh the global percentage by the size of the functions.
+    #[argh(switch)]
+    weighted: bool,
+
+    /// order of the functions: address (the default), name or size.
+    #[argh(option, default = "FunctionOrder::Address")]
+    sort: FunctionOrder,
+
+    /// also report the share of matching instructions (mnemonic and normalized operands) of each function.
+    #[argh(switch)]
+    instruction_match: bool,
+
+    /// also report the stack frame size (locals and saved registers) of each function and its reimplementation.
+    #[argh(switch)]
+    frame_sizes: bool,
+
+    /// only list the functions missing from the reimplementation.
+    #[argh(switch)]
+    only_missing: bool,
+
+    /// only list the functions matching below this percentage, missing ones included.
+    #[argh(option)]
+    below: Option<f32>,
+}
 }
 
 /// Generate a badge to be used on README.md.
-#[derive(FromArgs, PartialEq, Debug)]
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
 #[argh(subcommand, name = "badge")]
 struct BadgeSubCommand {
     /// original executable file to disassemble.
@@ -77,244 +302,3440 @@ struct BadgeSubCommand {
     /// output file containing the badge json.
     #[argh(positional)]
     output_file: PathBuf,
+
+    /// percentage under which the badge is red, overrides the mapping (25 by default).
+    #[argh(option)]
+    red_below: Option<f32>,
+
+    /// percentage under which the badge is yellow, overrides the mapping (75 by default).
+    #[argh(option)]
+    yellow_below: Option<f32>,
+
+    /// text on the left side of the badge.
+    #[argh(option, default = "String::from(\"progress\")")]
+    label: String,
+
+    /// number of decimals used for percentages.
+    #[argh(option, default = "2")]
+    precision: usize,
+
+    /// style of the badge: flat, flat-square, plastic, for-the-badge or social.
+    #[argh(option)]
+    style: Option<BadgeStyle>,
+
+    /// show the share of functions per status (e.g. 37% matching, 12% wip) instead of the global percentage.
+    #[argh(switch)]
+    status_breakdown: bool,
+
+    /// weigh the percentages by the size of the functions.
+    #[argh(switch)]
+    weighted: bool,
 }
 
-#[derive(FromArgs, PartialEq, Debug)]
-/// Disassemble a function by name.
-#[argh(subcommand, name = "disassemble")]
-struct DisassembleSubCommand {
-    /// executable file to disassemble.
+/// Trace where the value of an operand comes from.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "trace")]
+struct TraceSubCommand {
+    /// executable file to analyze.
     #[argh(positional)]
     executable_file: PathBuf,
 
-    /// the function name to disassemble.
+    /// the function containing the instruction.
     #[argh(positional)]
     function_name: String,
 
+    /// offset of the instruction relative to the start of the function.
+    #[argh(positional, from_str_fn(parse_offset))]
+    offset: usize,
+
+    /// index of the operand to trace, defaults to the last operand read.
+    #[argh(option)]
+    operand: Option<usize>,
+
     /// pdb file related to the executable.
     #[argh(option)]
     pdb_file: Option<PathBuf>,
 
-    /// force usage of address zero when disassembling.
-    #[argh(switch)]
-    force_address_zero: bool,
-
-    /// use at&t syntax when printing assembly.
-    #[argh(switch)]
-    att: bool,
+    /// linker map file related to the executable, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
 
-    /// enable name resolution for calls.
-    #[argh(switch)]
-    resolve_names: bool,
+    /// file the trace is written to instead of stdout.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
 }
 
-fn parse_object_with_mapping(
-    executable_file: &Path,
-    mapping: Mapping,
-) -> Result<Executable, Box<dyn Error>> {
-    if !executable_file.exists() {
-        eprintln!("Executable not found!\n");
-        std::process::exit(1);
-    }
+/// Create a decomp.me scratch for a function.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "upload")]
+struct UploadSubCommand {
+    /// executable file containing the function.
+    #[argh(positional)]
+    executable_file: PathBuf,
 
-    let raw_data = std::fs::read(executable_file)?;
-    let raw_obj = object::File::parse(&*raw_data)?;
-    let executable = satsuki::Executable::from_object_with_mapping(&raw_obj, mapping)?;
+    /// the function name to upload.
+    #[argh(positional)]
+    function_name: String,
 
-    Ok(executable)
-}
+    /// pdb file related to the executable.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
 
-fn parse_object_with_pdb(
-    executable_file: &Path,
-    pdb_file: &Path,
-    mapping: Mapping,
-) -> Result<Executable, Box<dyn Error>> {
-    if !executable_file.exists() {
-        eprintln!("Executable not found!\n");
-        std::process::exit(1);
-    }
+    /// linker map file related to the executable, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
 
-    if !pdb_file.exists() {
-        eprintln!("PDB not found!\n");
-        std::process::exit(1);
-    }
+    /// decomp.me compiler identifier.
+    #[argh(option, default = "decompme::DEFAULT_COMPILER.into()")]
+    compiler: String,
 
-    let raw_data = std::fs::read(executable_file)?;
-    let raw_obj = object::File::parse(&*raw_data)?;
-    let pdb_file = pdb::PDB::open(std::fs::File::open(pdb_file)?)?;
-    let executable = satsuki::Executable::from_object_with_pdb(&raw_obj, mapping, pdb_file)?;
+    /// flags passed to the compiler.
+    #[argh(option, default = "decompme::DEFAULT_COMPILER_FLAGS.into()")]
+    compiler_flags: String,
 
-    Ok(executable)
+    /// file whose content is used as the scratch context (headers, types...).
+    #[argh(option)]
+    context_file: Option<PathBuf>,
+
+    /// decomp.me instance to create the scratch on.
+    #[argh(option, default = "decompme::DEFAULT_API_URL.into()")]
+    api_url: String,
+
+    /// print the scratch request instead of sending it.
+    #[argh(switch)]
+    dry_run: bool,
 }
 
-fn handle_disassemble(
-    mapping: Mapping,
-    args: &DisassembleSubCommand,
-) -> Result<(), Box<dyn Error>> {
-    let capstone = Capstone::new()
-        .x86()
-        .mode(ArchMode::Mode32)
-        .syntax(if args.att {
-            ArchSyntax::Att
-        } else {
-            ArchSyntax::Intel
-        })
-        .detail(true)
-        .build()
-        .expect("Cannot create Capstone context");
+/// Export the target assembly of functions in the dialect accepted by decomp.me.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "export-asm")]
+struct ExportAsmSubCommand {
+    /// executable file containing the functions.
+    #[argh(positional)]
+    executable_file: PathBuf,
 
-    let executable;
+    /// names of the functions to export.
+    #[argh(positional)]
+    function_names: Vec<String>,
 
-    if let Some(pdb_file) = &args.pdb_file {
-        executable = parse_object_with_pdb(&args.executable_file, pdb_file, mapping)?;
-    } else {
-        executable = parse_object_with_mapping(&args.executable_file, mapping)?;
-    }
+    /// directory the assembly files are written to.
+    #[argh(option)]
+    out: PathBuf,
 
-    match executable.get_function(&args.function_name) {
-        Some(function) => {
-            let res = function
-                .disassemble(
-                    &capstone,
-                    &executable,
-                    args.force_address_zero,
-                    args.resolve_names,
-                )
-                .unwrap();
+    /// pdb file related to the executable.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
 
-            println!("{}", res);
-        }
-        None => {
-            eprintln!("Function {} not found in executable!", args.function_name);
-            std::process::exit(1);
-        }
-    }
+    /// linker map file related to the executable, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
 
-    Ok(())
-}
+    /// base of numeric literals: hex or dec.
+    #[argh(option, default = "Radix::Hex")]
+    radix: Radix,
 
-fn remap_report_value(data: (&String, &Option<f32>)) -> (String, String) {
-    let (key, value) = data;
-    if let Some(value) = value {
-        (key.clone(), format!("{value}%"))
-    } else {
-        (key.clone(), "MISSING".into())
-    }
+    /// notation of hexadecimal literals: prefix (0x1f) or suffix (1Fh).
+    #[argh(option, default = "HexNotation::Prefix")]
+    hex_notation: HexNotation,
+
+    /// minimum number of digits of numeric literals, padded with zeros.
+    #[argh(option, default = "0")]
+    literal_width: usize,
+
+    /// render negative displacements as their unsigned 32-bit value.
+    #[argh(switch)]
+    unsigned_literals: bool,
+
+    /// assembler dialect of the operands: capstone or masm (PTR keywords, OFFSET, segment overrides).
+    #[argh(option, default = "Dialect::Capstone")]
+    dialect: Dialect,
 }
 
-fn handle_stats_report(mapping: Mapping, args: &StatsSubCommand) -> Result<(), Box<dyn Error>> {
-    let original_executable =
-        parse_object_with_mapping(&args.original_executable_file, mapping.clone())?;
-    let reimplement_executable = parse_object_with_pdb(
-        &args.reimplementation_executable_file,
-        &args.pdb_file,
-        mapping.clone(),
-    )?;
+diff_subcommand! {
+/// Fail when a function or the global percentage is below its threshold in the mapping.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "check")]
+struct CheckSubCommand {
+    /// original executable file.
+    #[argh(positional)]
+    original_executable_file: PathBuf,
 
-    let mut global_match = 0.0;
+    /// reimplementation executable file.
+    #[argh(positional)]
+    reimplementation_executable_file: PathBuf,
 
-    let stats: HashMap<String, String> = original_executable
-        .generate_stats(&reimplement_executable)
-        .iter()
-        .map(|x| {
-            if let Some(value) = x.1 {
-                global_match += value;
-            }
+    /// pdb file related to the reimplementation.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
 
-            remap_report_value(x)
-        })
-        .collect();
+    /// linker map file related to the reimplementation, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
 
-    let global_raw_diff = global_match / original_executable.functions_count() as f32;
+    /// algorithm used to compare functions: raw, instruction, aligned or blocks.
+    #[argh(option, default = "DiffAlgorithm::Raw")]
+    diff_algorithm: DiffAlgorithm,
 
-    if let Some(output_file) = &args.output_file {
-        let mut file = File::create(output_file)?;
+    /// number of decimals used for percentages.
+    #[argh(option, default = "2")]
+    precision: usize,
+}
+}
 
-        match output_file.extension() {
-            Some(test) if test.to_string_lossy() == "csv" => {
-                writeln!(file, "\"Function name\",\"Status\"")?;
+/// Compare two JSON stats reports, failing when a function regressed or disappeared.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "compare-reports")]
+struct CompareReportsSubCommand {
+    /// previous JSON stats report.
+    #[argh(positional)]
+    old_report: PathBuf,
 
-                for (key, value) in stats {
-                    writeln!(file, "\"{key}\",\"{value}\"")?;
-                }
-            }
-            _ => {
-                for (key, value) in stats {
-                    writeln!(file, "{key}: {value}")?;
-                }
-            }
-        }
-    } else {
-        for (key, value) in stats {
-            println!("{key}: {value}")
-        }
-    }
+    /// new JSON stats report.
+    #[argh(positional)]
+    new_report: PathBuf,
 
-    println!("GLOBAL: {global_raw_diff}%");
+    /// number of decimals used for percentages.
+    #[argh(option, default = "2")]
+    precision: usize,
 
-    Ok(())
+    /// disable colored output, same as --color=never.
+    #[argh(switch)]
+    no_color: bool,
 }
 
-fn handle_badge(mapping: Mapping, args: &BadgeSubCommand) -> Result<(), Box<dyn Error>> {
-    let original_executable =
-        parse_object_with_mapping(&args.original_executable_file, mapping.clone())?;
-    let reimplement_executable = parse_object_with_pdb(
-        &args.reimplementation_executable_file,
-        &args.pdb_file,
-        mapping.clone(),
-    )?;
+/// Show how the match percentages evolved over the snapshots of a history database.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "history")]
+struct HistorySubCommand {
+    /// sqlite database filled by stats --history.
+    #[argh(positional)]
+    database: PathBuf,
+
+    /// name or mapping id of the function to show, the global percentage is shown otherwise.
+    #[argh(option)]
+    function: Option<String>,
 
-    let mut global_match = 0.0;
+    /// number of decimals used for percentages.
+    #[argh(option, default = "2")]
+    precision: usize,
 
-    for (_, value) in original_executable.generate_stats(&reimplement_executable) {
-        global_match += value.unwrap_or(0.0);
-    }
+    /// disable colored output, same as --color=never.
+    #[argh(switch)]
+    no_color: bool,
+}
 
-    let global_raw_diff = global_match / original_executable.functions_count() as f32;
-    let mut file = File::create(&args.output_file)?;
-    writeln!(file, "{{\"schemaVersion\": 1, \"label\": \"progress\", \"message\": \"{:.1$}%\", \"color\": \"yellow\"}}", global_raw_diff, 2)?;
+/// Render an SVG chart of the progress over time from a history database.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "graph")]
+struct GraphSubCommand {
+    /// sqlite database filled by stats --history.
+    #[argh(positional)]
+    database: PathBuf,
 
-    Ok(())
+    /// output file containing the svg.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+
+    /// prefix of the names of a category of functions to draw a line for (e.g. Player::), can be repeated.
+    #[argh(option)]
+    category: Vec<String>,
+
+    /// title of the chart.
+    #[argh(option, default = "String::from(\"Progress\")")]
+    title: String,
+
+    /// width of the chart in pixels.
+    #[argh(option, default = "640")]
+    width: usize,
+
+    /// height of the chart in pixels.
+    #[argh(option, default = "320")]
+    height: usize,
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let args: TopLevel = argh::from_env();
+/// Check the mapping for overlapping, misplaced, empty or duplicated functions.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "validate-mapping")]
+struct ValidateMappingSubCommand {
+    /// executable file described by the mapping.
+    #[argh(positional)]
+    executable_file: PathBuf,
 
-    let (mapping_file, is_csv) = match (args.mapping_file, args.mapping_file_csv) {
-        (None, None) => {
-            eprintln!("This tool needs a mapping file to function. Pass one using --mapping-file or --mapping-file-csv");
-            std::process::exit(1);
-        },
-        (Some(_), Some(_)) => {
-            eprintln!("Can't pass both a TOML and CSV mapping file. Only pass one of --mapping-file or --mapping-file-csv");
-            std::process::exit(1);
-        },
-        (Some(toml), None) => (toml, false),
-        (None, Some(csv)) => (csv, true),
-    };
+    /// pdb file related to the executable, used to check the function sizes.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
+}
 
-    if !mapping_file.exists() {
-        eprintln!("Mapping not found!\n");
-        std::process::exit(1);
-    }
+/// Find the functions of the original executable missing from the mapping, printed as mapping entries.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "detect-functions")]
+struct DetectFunctionsSubCommand {
+    /// original executable file.
+    #[argh(positional)]
+    original_executable_file: PathBuf,
 
-    let raw_mapping = std::fs::read_to_string(mapping_file)?;
+    /// output file containing the mapping entries.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+}
 
-    let mapping = if is_csv {
-        let mut rdr = csv::ReaderBuilder::new()
-            .has_headers(false)
-            .flexible(true)
-            .from_reader(raw_mapping.as_bytes());
-        Mapping {
-            function: Some(Result::from_iter(rdr.deserialize())?)
-        }
-    } else {
-        toml::from_str::<Mapping>(&raw_mapping)?
-    };
+diff_subcommand! {
+/// List the byte and instruction ranges of a function differing from the reimplementation.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "explain")]
+struct ExplainSubCommand {
+    /// original executable file.
+    #[argh(positional)]
+    original_executable_file: PathBuf,
 
+    /// reimplementation executable file.
+    #[argh(positional)]
+    reimplementation_executable_file: PathBuf,
+
+    /// pdb file related to the reimplementation executable.
+    #[argh(positional)]
+    pdb_file: PathBuf,
+
+    /// the function name to explain.
+    #[argh(positional)]
+    function_name: String,
+
+    /// use at&t syntax when printing assembly.
+    #[argh(switch)]
+    att: bool,
+}
+}
+
+/// Dump the raw bytes of a function by name.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "hexdump")]
+struct HexdumpSubCommand {
+    /// executable file to dump.
+    #[argh(positional)]
+    executable_file: PathBuf,
+
+    /// the function name to dump.
+    #[argh(positional)]
+    function_name: String,
+
+    /// pdb file related to the executable.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
+
+    /// linker map file related to the executable, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
+
+    /// force usage of address zero when dumping.
+    #[argh(switch)]
+    force_address_zero: bool,
+}
+
+/// Export the control flow graph of a function in Graphviz DOT format.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "cfg")]
+struct CfgSubCommand {
+    /// original executable file.
+    #[argh(positional)]
+    executable_file: PathBuf,
+
+    /// the function name to export.
+    #[argh(positional)]
+    function_name: String,
+
+    /// reimplementation executable file, coloring the blocks after how they match it.
+    #[argh(option)]
+    reimplementation: Option<PathBuf>,
+
+    /// pdb file related to the reimplementation.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
+
+    /// linker map file related to the reimplementation, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
+
+    /// mask relocated operands (calls and addresses into the executable) before diffing.
+    #[argh(switch)]
+    ignore_relocs: bool,
+
+    /// how functions are paired with the reimplementation: name (the default) or address.
+    #[argh(option, default = "Pairing::Name")]
+    pair_by: Pairing,
+
+    /// output file containing the graph.
+    #[argh(option)]
+    out: Option<PathBuf>,
+}
+
+/// Export the call graph of every mapped function.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "callgraph")]
+struct CallgraphSubCommand {
+    /// executable file to analyze.
+    #[argh(positional)]
+    executable_file: PathBuf,
+
+    /// pdb file related to the executable.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
+
+    /// linker map file related to the executable, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
+
+    /// output format: dot or json. Guessed from the output file extension by default.
+    #[argh(option)]
+    format: Option<CallGraphFormat>,
+
+    /// only list the leaf functions, calling no other function than themselves.
+    #[argh(switch)]
+    leaves: bool,
+
+    /// output file containing the graph.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+}
+
+/// List the call sites of a function or import.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "xref")]
+struct XrefSubCommand {
+    /// executable file to analyze.
+    #[argh(positional)]
+    executable_file: PathBuf,
+
+    /// the function name to look for, or an import (KERNEL32!Sleep).
+    #[argh(positional)]
+    function_name: String,
+
+    /// pdb file related to the executable.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
+
+    /// linker map file related to the executable, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
+}
+
+/// Compare the global variables of the mapping with the reimplementation.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "data-stats")]
+struct DataStatsSubCommand {
+    /// original executable file.
+    #[argh(positional)]
+    original_executable_file: PathBuf,
+
+    /// reimplementation executable file.
+    #[argh(positional)]
+    reimplementation_executable_file: PathBuf,
+
+    /// pdb file related to the reimplementation.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
+
+    /// linker map file related to the reimplementation, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
+
+    /// file the stats are written to instead of stdout.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+}
+
+/// Compare the vtables of the mapping with the reimplementation, slot by slot.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "vtables")]
+struct VtablesSubCommand {
+    /// original executable file.
+    #[argh(positional)]
+    original_executable_file: PathBuf,
+
+    /// reimplementation executable file.
+    #[argh(positional)]
+    reimplementation_executable_file: PathBuf,
+
+    /// pdb file related to the reimplementation.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
+
+    /// linker map file related to the reimplementation, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
+
+    /// file the comparison is written to instead of stdout.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+}
+
+/// Report the strings of the data sections missing or different in the reimplementation.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "strings-diff")]
+struct StringsDiffSubCommand {
+    /// original executable file.
+    #[argh(positional)]
+    original_executable_file: PathBuf,
+
+    /// reimplementation executable file.
+    #[argh(positional)]
+    reimplementation_executable_file: PathBuf,
+
+    /// pdb file related to the reimplementation.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
+
+    /// linker map file related to the reimplementation, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
+
+    /// file the differences are written to instead of stdout.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+}
+
+/// Report how much of the code sections is covered by known functions.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "coverage")]
+struct CoverageSubCommand {
+    /// executable file to analyze.
+    #[argh(positional)]
+    executable_file: PathBuf,
+
+    /// pdb file related to the executable, its functions counting as known.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
+
+    /// linker map file related to the executable, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
+
+    /// number of the largest unknown regions listed.
+    #[argh(option, default = "10")]
+    count: usize,
+
+    /// file the report is written to instead of stdout.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+}
+
+/// List the ranges of the code sections claimed by no known function.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "gaps")]
+struct GapsSubCommand {
+    /// executable file to analyze.
+    #[argh(positional)]
+    executable_file: PathBuf,
+
+    /// pdb file related to the executable, its functions counting as known.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
+
+    /// linker map file related to the executable, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
+
+    /// smallest gap listed, in bytes.
+    #[argh(option, default = "1")]
+    min_size: usize,
+
+    /// number of instructions disassembled at the start of each gap.
+    #[argh(option, default = "5")]
+    preview: usize,
+
+    /// file the gaps are written to instead of stdout.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+}
+
+/// Compute byte signatures of the functions of the original executable, relocated bytes wildcarded.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "make-sigs")]
+struct MakeSigsSubCommand {
+    /// original executable file.
+    #[argh(positional)]
+    original_executable_file: PathBuf,
+
+    /// smallest number of bytes of a signature that aren't wildcards.
+    #[argh(option, default = "16")]
+    min_size: usize,
+
+    /// output file containing the signatures.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+}
+
+/// Scan an executable for byte signatures, printing the functions found as mapping entries.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "apply-sigs")]
+struct ApplySigsSubCommand {
+    /// executable file to scan.
+    #[argh(positional)]
+    executable_file: PathBuf,
+
+    /// signatures file, as written by make-sigs.
+    #[argh(positional)]
+    signatures_file: PathBuf,
+
+    /// output file containing the mapping entries.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+}
+
+/// Suggest names for the unnamed functions of the mapping, printed as mapping entries.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "suggest-matches")]
+struct SuggestMatchesSubCommand {
+    /// original executable file.
+    #[argh(positional)]
+    original_executable_file: PathBuf,
+
+    /// reimplementation executable file.
+    #[argh(positional)]
+    reimplementation_executable_file: PathBuf,
+
+    /// pdb file related to the reimplementation.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
+
+    /// linker map file related to the reimplementation, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
+
+    /// minimum similarity percentage of the suggested pairs.
+    #[argh(option, default = "90.0")]
+    min_score: f32,
+
+    /// output file containing the mapping entries.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+}
+
+/// Rank the functions of the reimplementation by similarity to a function of the original.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "find-similar")]
+struct FindSimilarSubCommand {
+    /// original executable file.
+    #[argh(positional)]
+    original_executable_file: PathBuf,
+
+    /// reimplementation executable file.
+    #[argh(positional)]
+    reimplementation_executable_file: PathBuf,
+
+    /// pdb file related to the reimplementation executable.
+    #[argh(positional)]
+    pdb_file: PathBuf,
+
+    /// the function name to search for.
+    #[argh(positional)]
+    function_name: String,
+
+    /// number of functions listed.
+    #[argh(option, default = "10")]
+    count: usize,
+}
+
+diff_subcommand! {
+/// Recompute the stats every time the reimplementation is rebuilt or the mapping is edited.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "watch")]
+struct WatchSubCommand {
+    /// original executable file.
+    #[argh(positional)]
+    original_executable_file: PathBuf,
+
+    /// reimplementation executable file, watched for changes.
+    #[argh(positional)]
+    reimplementation_executable_file: PathBuf,
+
+    /// pdb file related to the reimplementation, watched for changes.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
+
+    /// linker map file related to the reimplementation, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
+
+    /// only compare this function.
+    #[argh(option)]
+    function: Option<String>,
+
+    /// interval between two checks for changes, in milliseconds.
+    #[argh(option, default = "500")]
+    interval: u64,
+
+    /// algorithm used to compare functions: raw, instruction, aligned or blocks.
+    #[argh(option, default = "DiffAlgorithm::Raw")]
+    diff_algorithm: DiffAlgorithm,
+
+    /// number of decimals used for percentages.
+    #[argh(option, default = "2")]
+    precision: usize,
+
+    /// disable colored output, same as --color=never.
+    #[argh(switch)]
+    no_color: bool,
+
+    /// directory caching the results for unchanged functions between runs.
+    #[argh(option)]
+    cache_dir: Option<PathBuf>,
+}
+}
+
+/// Disassemble every known function to a directory, one file per function.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "dump-all")]
+struct DumpAllSubCommand {
+    /// executable file to disassemble.
+    #[argh(positional)]
+    executable_file: PathBuf,
+
+    /// directory the assembly files are written to.
+    #[argh(option)]
+    out: PathBuf,
+
+    /// pdb file related to the executable.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
+
+    /// linker map file related to the executable, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
+
+    /// force usage of address zero when disassembling.
+    #[argh(switch)]
+    force_address_zero: bool,
+
+    /// use at&t syntax when printing assembly.
+    #[argh(switch)]
+    att: bool,
+
+    /// enable name resolution for calls and data references, annotating string literals.
+    #[argh(switch)]
+    resolve_names: bool,
+
+    /// directory caching the results for unchanged functions between runs.
+    #[argh(option)]
+    cache_dir: Option<PathBuf>,
+}
+
+/// List the functions known in an executable.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "list")]
+struct ListSubCommand {
+    /// executable file to list the functions of.
+    #[argh(positional)]
+    executable_file: PathBuf,
+
+    /// pdb file related to the executable.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
+
+    /// linker map file related to the executable, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
+
+    /// order of the functions: name (the default), address or size.
+    #[argh(option, default = "FunctionOrder::Name")]
+    sort: FunctionOrder,
+
+    /// undecorate the MSVC names of the functions.
+    #[argh(switch)]
+    demangle: bool,
+
+    /// only list the functions matching this glob pattern (Enemy::*).
+    #[argh(option)]
+    filter: Option<String>,
+
+    /// interpret --filter as a regular expression matching anywhere in the name.
+    #[argh(switch)]
+    regex: bool,
+
+    /// output file containing the list.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+}
+
+/// Generate declaration stubs for mapped functions missing from the reimplementation.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "stubs")]
+struct StubsSubCommand {
+    /// original executable file.
+    #[argh(positional)]
+    original_executable_file: PathBuf,
+
+    /// reimplementation executable file, every mapped function is stubbed without it.
+    #[argh(option)]
+    reimplementation: Option<PathBuf>,
+
+    /// pdb file related to the reimplementation.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
+
+    /// linker map file related to the reimplementation, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
+
+    /// output file containing the declarations.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+}
+
+/// Extract the data tables referenced by functions as C array initializers.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "tables")]
+struct TablesSubCommand {
+    /// original executable file.
+    #[argh(positional)]
+    executable_file: PathBuf,
+
+    /// names of the functions whose tables are extracted, every mapped function by default.
+    #[argh(positional)]
+    function_names: Vec<String>,
+
+    /// maximum number of elements of a table.
+    #[argh(option, default = "256")]
+    max_elements: usize,
+
+    /// output file containing the tables.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+}
+
+diff_subcommand! {
+/// Compute the stats of every binary of a workspace.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "workspace")]
+struct WorkspaceSubCommand {
+    /// workspace file listing the binaries to compare.
+    #[argh(option, default = "PathBuf::from(workspace::DEFAULT_WORKSPACE_FILE)")]
+    workspace_file: PathBuf,
+
+    /// output file containing the stats.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+
+    /// algorithm used to compare functions: raw, instruction, aligned or blocks.
+    #[argh(option, default = "DiffAlgorithm::Raw")]
+    diff_algorithm: DiffAlgorithm,
+
+    /// number of decimals used for percentages.
+    #[argh(option, default = "2")]
+    precision: usize,
+
+    /// output format: text, csv, json, markdown or objdiff. Guessed from the output file extension by default.
+    #[argh(option)]
+    format: Option<ReportFormat>,
+
+    /// disable colored output, same as --color=never.
+    #[argh(switch)]
+    no_color: bool,
+}
+}
+
+/// Compare the disassembly of every function against golden files.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "test")]
+struct TestSubCommand {
+    /// executable file to disassemble.
+    #[argh(positional)]
+    executable_file: PathBuf,
+
+    /// directory containing the golden .asm files.
+    #[argh(positional)]
+    golden_directory: PathBuf,
+
+    /// pdb file related to the executable.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
+
+    /// linker map file related to the executable, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
+
+    /// use at&t syntax when printing assembly.
+    #[argh(switch)]
+    att: bool,
+
+    /// write the current disassembly of every function as the new goldens.
+    #[argh(switch)]
+    update: bool,
+}
+
+/// Apply names from a CSV/TSV list of address and name pairs to a mapping.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "apply-names")]
+struct ApplyNamesSubCommand {
+    /// CSV or TSV file containing address and name pairs.
+    #[argh(positional)]
+    names_file: PathBuf,
+
+    /// mapping TOML file to update.
+    #[argh(option)]
+    mapping: PathBuf,
+
+    /// replace existing names instead of reporting them as conflicts.
+    #[argh(switch)]
+    force: bool,
+
+    /// only report what would be changed.
+    #[argh(switch)]
+    dry_run: bool,
+}
+
+diff_subcommand! {
+/// Show the original and reimplementation disassembly of a function side by side.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "diff")]
+struct DiffSubCommand {
+    /// original executable file to disassemble.
+    #[argh(positional)]
+    original_executable_file: PathBuf,
+
+    /// reimplementation executable file to disassemble.
+    #[argh(positional)]
+    reimplementation_executable_file: PathBuf,
+
+    /// pdb file related to the reimplementation executable.
+    #[argh(positional)]
+    pdb_file: PathBuf,
+
+    /// the function name to diff.
+    #[argh(positional)]
+    function_name: String,
+
+    /// use at&t syntax when printing assembly.
+    #[argh(switch)]
+    att: bool,
+
+    /// disable colored output, same as --color=never.
+    #[argh(switch)]
+    no_color: bool,
+
+    /// explain how the score is computed instead of showing the diff.
+    #[argh(switch)]
+    explain: bool,
+
+    /// directory caching the results for unchanged functions between runs.
+    #[argh(option)]
+    cache_dir: Option<PathBuf>,
+
+    /// file the diff is written to instead of stdout, without colors.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+}
+}
+
+diff_subcommand! {
+/// Diff a group of functions, factoring out the instruction sequences they share.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "group-diff")]
+struct GroupDiffSubCommand {
+    /// original executable file to disassemble.
+    #[argh(positional)]
+    original_executable_file: PathBuf,
+
+    /// reimplementation executable file to disassemble.
+    #[argh(positional)]
+    reimplementation_executable_file: PathBuf,
+
+    /// pdb file related to the reimplementation executable.
+    #[argh(positional)]
+    pdb_file: PathBuf,
+
+    /// category of the mapping ([[badge.category]]) or name prefix of the functions belonging to the group.
+    #[argh(positional)]
+    group: String,
+
+    /// minimum number of instructions of a shared sequence.
+    #[argh(option, default = "4")]
+    min_sequence: usize,
+
+    /// file the diff is written to instead of stdout.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+}
+}
+
+/// Compare the PE headers of the original and reimplementation executables.
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+#[argh(subcommand, name = "headers-diff")]
+struct HeadersDiffSubCommand {
+    /// original executable file.
+    #[argh(positional)]
+    original_executable_file: PathBuf,
+
+    /// reimplementation executable file.
+    #[argh(positional)]
+    reimplementation_executable_file: PathBuf,
+
+    /// file the differences are written to instead of stdout.
+    #[argh(option)]
+    output_file: Option<PathBuf>,
+}
+
+#[derive(FromArgs, ArgsInfo, PartialEq, Debug)]
+/// Disassemble a function by name.
+#[argh(subcommand, name = "disassemble")]
+struct DisassembleSubCommand {
+    /// executable file to disassemble.
+    #[argh(positional)]
+    executable_file: PathBuf,
+
+    /// the function name to disassemble.
+    #[argh(positional)]
+    function_name: String,
+
+    /// pdb file related to the executable.
+    #[argh(option)]
+    pdb_file: Option<PathBuf>,
+
+    /// linker map file related to the executable, used when no pdb is available.
+    #[argh(option)]
+    map_file: Option<PathBuf>,
+
+    /// force usage of address zero when disassembling.
+    #[argh(switch)]
+    force_address_zero: bool,
+
+    /// use at&t syntax when printing assembly.
+    #[argh(switch)]
+    att: bool,
+
+    /// enable name resolution for calls and data references, annotating string literals.
+    #[argh(switch)]
+    resolve_names: bool,
+
+    /// annotate the arguments pushed before calls using the callee signatures.
+    #[argh(switch)]
+    annotate_args: bool,
+
+    /// file containing C prototypes of the callees, used by --annotate-args.
+    #[argh(option)]
+    declarations: Option<PathBuf>,
+
+    /// base of numeric literals: hex or dec.
+    #[argh(option, default = "Radix::Hex")]
+    radix: Radix,
+
+    /// notation of hexadecimal literals: prefix (0x1f) or suffix (1Fh).
+    #[argh(option, default = "HexNotation::Prefix")]
+    hex_notation: HexNotation,
+
+    /// minimum number of digits of numeric literals, padded with zeros.
+    #[argh(option, default = "0")]
+    literal_width: usize,
+
+    /// render negative displacements as their unsigned 32-bit value.
+    #[argh(switch)]
+    unsigned_literals: bool,
+
+    /// assembler dialect of the operands: capstone or masm (PTR keywords, OFFSET, segment overrides).
+    #[argh(option, default = "Dialect::Capstone")]
+    dialect: Dialect,
+
+    /// undecorate the MSVC names of called functions.
+    #[argh(switch)]
+    demangle: bool,
+
+    /// prefix each instruction with its encoding.
+    #[argh(switch)]
+    show_bytes: bool,
+
+    /// prefix each instruction with its address, zero based with --force-address-zero.
+    #[argh(switch)]
+    show_addresses: bool,
+}
+
+fn create_profile(
+    radix: Radix,
+    hex_notation: HexNotation,
+    literal_width: usize,
+    unsigned_literals: bool,
+    demangle: bool,
+    dialect: Dialect,
+) -> FormattingProfile {
+    FormattingProfile {
+        literals: LiteralStyle {
+            radix,
+            hex_notation,
+            min_width: literal_width,
+            signed: !unsigned_literals,
+        },
+        demangle,
+        show_bytes: false,
+        show_addresses: false,
+        dialect,
+    }
+}
+
+/// Combine the global `--color` option with a subcommand `--no-color` switch.
+fn color_choice(color: ColorChoice, no_color: bool) -> ColorChoice {
+    if no_color {
+        ColorChoice::Never
+    } else {
+        color
+    }
+}
+
+fn parse_offset(value: &str) -> Result<usize, String> {
+    rename::parse_address(value).ok_or_else(|| format!("Invalid offset \"{value}\""))
+}
+
+/// Attach the file being loaded to an error.
+fn loading_error(file: &Path) -> impl Fn(ExecutableError) -> ExecutableError + '_ {
+    move |err| err.with_context(ErrorContext::new("loading").file(file))
+}
+
+fn parse_object<'data>(
+    executable_file: &Path,
+    raw_data: &'data [u8],
+) -> Result<object::File<'data>, ExecutableError> {
+    object::File::parse(raw_data)
+        .map_err(|err| loading_error(executable_file)(ExecutableError::from(err)))
+}
+
+/// Name the import address table slots of a loaded executable.
+fn add_imports(
+    executable: &mut Executable,
+    executable_file: &Path,
+    raw_data: &[u8],
+) -> Result<(), ExecutableError> {
+    let slots = imports::import_slots(raw_data).map_err(loading_error(executable_file))?;
+    executable.add_imports(&slots);
+
+    Ok(())
+}
+
+/// Base address of a flat binary, when the mapping sets one and the file
+/// isn't recognized as an executable.
+fn raw_base(mapping: &Mapping, raw_data: &[u8]) -> Option<usize> {
+    mapping
+        .executable
+        .raw_base
+        .filter(|_| object::FileKind::parse(raw_data).is_err())
+}
+
+/// Load a flat binary with the functions of the mapping.
+fn parse_raw_with_mapping(
+    executable_file: &Path,
+    raw_data: &[u8],
+    base: usize,
+    mapping: Mapping,
+) -> Result<Executable, Box<dyn Error>> {
+    Ok(
+        satsuki::Executable::from_raw_with_mapping(raw_data, base, mapping)
+            .map_err(loading_error(executable_file))?,
+    )
+}
+
+/// Load a module of a minidump with the functions of the mapping, leaving
+/// out the functions whose memory wasn't captured.
+fn parse_dump_with_mapping(
+    executable_file: &Path,
+    raw_data: &[u8],
+    mut mapping: Mapping,
+) -> Result<Executable, Box<dyn Error>> {
+    let memory_dump =
+        MemoryDump::parse_minidump(raw_data).map_err(loading_error(executable_file))?;
+    let module = memory_dump
+        .module(mapping.executable.dump_module.as_deref())
+        .map_err(loading_error(executable_file))?;
+    let sections = memory_dump
+        .sections(module)
+        .map_err(loading_error(executable_file))?;
+    let base = module.base;
+
+    if let Some(mut functions) = mapping.function.take() {
+        functions.retain(|function| {
+            let Some(address) = mapping.rebase_to(base, function.address) else {
+                return true;
+            };
+            let is_captured = !dump::overlaps_gap(&sections, &(address..address + function.size));
+
+            if !is_captured {
+                eprintln!(
+                    "warning: skipped {}: not captured by the dump",
+                    function.name.as_deref().unwrap_or("<unnamed>")
+                );
+            }
+
+            is_captured
+        });
+
+        mapping.function = Some(functions);
+    }
+
+    Ok(
+        satsuki::Executable::from_dump_with_mapping(&sections, base, mapping)
+            .map_err(loading_error(executable_file))?,
+    )
+}
+
+fn parse_object_with_mapping(
+    executable_file: &Path,
+    mapping: Mapping,
+) -> Result<Executable, Box<dyn Error>> {
+    if !executable_file.exists() {
+        eprintln!("Executable not found!\n");
+        std::process::exit(1);
+    }
+
+    let raw_data = archive::read_input(executable_file, archive::EXECUTABLE_EXTENSIONS)?;
+
+    if dump::is_minidump(&raw_data) {
+        return parse_dump_with_mapping(executable_file, &raw_data, mapping);
+    }
+
+    if let Some(base) = raw_base(&mapping, &raw_data) {
+        return parse_raw_with_mapping(executable_file, &raw_data, base, mapping);
+    }
+
+    let raw_obj = parse_object(executable_file, &raw_data)?;
+    let mut executable = satsuki::Executable::from_object_with_mapping(&raw_obj, mapping)
+        .map_err(loading_error(executable_file))?;
+    add_imports(&mut executable, executable_file, &raw_data)?;
+
+    Ok(executable)
+}
+
+fn parse_object_with_pdb(
+    executable_file: &Path,
+    pdb_file: &Path,
+    mapping: Mapping,
+) -> Result<Executable, Box<dyn Error>> {
+    if !executable_file.exists() {
+        eprintln!("Executable not found!\n");
+        std::process::exit(1);
+    }
+
+    if !pdb_file.exists() {
+        eprintln!("PDB not found!\n");
+        std::process::exit(1);
+    }
+
+    let raw_data = archive::read_input(executable_file, archive::EXECUTABLE_EXTENSIONS)?;
+    let raw_obj = parse_object(executable_file, &raw_data)?;
+    let pdb_data = archive::read_input(pdb_file, archive::PDB_EXTENSIONS)?;
+    let pdb = pdb::PDB::open(Cursor::new(pdb_data))
+        .map_err(|err| loading_error(pdb_file)(ExecutableError::from(err)))?;
+    let mut executable = satsuki::Executable::from_object_with_pdb(&raw_obj, mapping, pdb)
+        .map_err(loading_error(pdb_file))?;
+    add_imports(&mut executable, executable_file, &raw_data)?;
+
+    Ok(executable)
+}
+
+fn parse_object_with_map(
+    executable_file: &Path,
+    map_file: &Path,
+    mapping: Mapping,
+) -> Result<Executable, Box<dyn Error>> {
+    if !executable_file.exists() {
+        eprintln!("Executable not found!\n");
+        std::process::exit(1);
+    }
+
+    if !map_file.exists() {
+        eprintln!("Map file not found!\n");
+        std::process::exit(1);
+    }
+
+    let raw_data = archive::read_input(executable_file, archive::EXECUTABLE_EXTENSIONS)?;
+    let raw_obj = parse_object(executable_file, &raw_data)?;
+    let map_content = std::fs::read_to_string(map_file)?;
+    let mut executable = satsuki::Executable::from_object_with_map(&raw_obj, mapping, &map_content)
+        .map_err(loading_error(map_file))?;
+    add_imports(&mut executable, executable_file, &raw_data)?;
+
+    Ok(executable)
+}
+
+/// Load an executable from its DWARF debug information, or from the mapping
+/// alone when it has none or is a memory dump or a flat binary.
+fn parse_object_with_dwarf(
+    executable_file: &Path,
+    mapping: Mapping,
+) -> Result<Executable, Box<dyn Error>> {
+    if !executable_file.exists() {
+        eprintln!("Executable not found!\n");
+        std::process::exit(1);
+    }
+
+    let raw_data = archive::read_input(executable_file, archive::EXECUTABLE_EXTENSIONS)?;
+
+    if dump::is_minidump(&raw_data) {
+        return parse_dump_with_mapping(executable_file, &raw_data, mapping);
+    }
+
+    if let Some(base) = raw_base(&mapping, &raw_data) {
+        return parse_raw_with_mapping(executable_file, &raw_data, base, mapping);
+    }
+
+    let raw_obj = parse_object(executable_file, &raw_data)?;
+
+    let mut executable = if raw_obj.section_by_name(".debug_info").is_some() {
+        satsuki::Executable::from_object_with_dwarf(&raw_obj, mapping)
+    } else {
+        satsuki::Executable::from_object_with_mapping(&raw_obj, mapping)
+    }
+    .map_err(loading_error(executable_file))?;
+    add_imports(&mut executable, executable_file, &raw_data)?;
+
+    Ok(executable)
+}
+
+/// Load an executable with the best symbol source available: a pdb, a map
+/// file, DWARF debug information, or the mapping alone.
+fn parse_object_with_symbols(
+    executable_file: &Path,
+    pdb_file: Option<&Path>,
+    map_file: Option<&Path>,
+    mapping: Mapping,
+) -> Result<Executable, Box<dyn Error>> {
+    match (pdb_file, map_file) {
+        (Some(pdb_file), _) => parse_object_with_pdb(executable_file, pdb_file, mapping),
+        (None, Some(map_file)) => parse_object_with_map(executable_file, map_file, mapping),
+        (None, None) => parse_object_with_dwarf(executable_file, mapping),
+    }
+}
+
+fn parse_headers(executable_file: &Path) -> Result<ExecutableHeaders, Box<dyn Error>> {
+    if !executable_file.exists() {
+        eprintln!("Executable not found!\n");
+        std::process::exit(1);
+    }
+
+    let raw_data = archive::read_input(executable_file, archive::EXECUTABLE_EXTENSIONS)?;
+
+    Ok(ExecutableHeaders::parse(&raw_data)?)
+}
+
+fn create_capstone(arch: Arch, att: bool) -> Capstone {
+    Capstone::new()
+        .x86()
+        .mode(arch.mode())
+        .syntax(if att {
+            ArchSyntax::Att
+        } else {
+            ArchSyntax::Intel
+        })
+        .detail(true)
+        .build()
+        .expect("Cannot create Capstone context")
+}
+
+/// Key of the cache entries produced by a disassembler configuration.
+fn cache_context(arch: Arch, att: bool) -> CacheKey {
+    CacheKey::new("satsuki").with(&arch).with(&att)
+}
+
+fn handle_disassemble(
+    mapping: Mapping,
+    args: &DisassembleSubCommand,
+) -> Result<(), Box<dyn Error>> {
+    // MASM only reads the Intel syntax.
+    let att = args.dialect == Dialect::Capstone && (args.att || mapping.syntax() == Syntax::Att);
+    let capstone = create_capstone(mapping.arch(), att);
+
+    let executable = parse_object_with_symbols(
+        &args.executable_file,
+        args.pdb_file.as_deref(),
+        args.map_file.as_deref(),
+        mapping,
+    )?;
+
+    match executable.get_function(&args.function_name) {
+        Some(function) => {
+            let profile = FormattingProfile {
+                show_bytes: args.show_bytes,
+                show_addresses: args.show_addresses,
+                ..create_profile(
+                    args.radix,
+                    args.hex_notation,
+                    args.literal_width,
+                    args.unsigned_literals,
+                    args.demangle,
+                    args.dialect,
+                )
+            };
+            let mut signatures = Signatures::new();
+
+            if args.annotate_args {
+                if let Some(pdb_file) = &args.pdb_file {
+                    let pdb_data = archive::read_input(pdb_file, archive::PDB_EXTENSIONS)?;
+                    let mut pdb_file = pdb::PDB::open(Cursor::new(pdb_data))?;
+
+                    signatures.extend(signature::signatures_from_pdb(&mut pdb_file)?);
+                }
+
+                if let Some(declarations) = &args.declarations {
+                    signatures.extend(signature::parse_declarations(&std::fs::read_to_string(
+                        declarations,
+                    )?));
+                }
+            }
+
+            let res = function
+                .disassemble_annotated(
+                    &capstone,
+                    &executable,
+                    args.force_address_zero,
+                    args.resolve_names,
+                    &profile,
+                    &signatures,
+                )
+                .unwrap();
+
+            println!("{}", res);
+        }
+        None => {
+            eprintln!("Function {} not found in executable!", args.function_name);
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_hexdump(mapping: Mapping, args: &HexdumpSubCommand) -> Result<(), Box<dyn Error>> {
+    let executable = parse_object_with_symbols(
+        &args.executable_file,
+        args.pdb_file.as_deref(),
+        args.map_file.as_deref(),
+        mapping,
+    )?;
+
+    match executable.get_function(&args.function_name) {
+        Some(function) => print!("{}", function.hexdump(args.force_address_zero)?),
+        None => {
+            eprintln!("Function {} not found in executable!", args.function_name);
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_cfg(mapping: Mapping, args: &CfgSubCommand) -> Result<(), Box<dyn Error>> {
+    let capstone = create_capstone(mapping.arch(), false);
+    let original_executable = parse_object_with_mapping(&args.executable_file, mapping.clone())?;
+    let options = DiffOptions {
+        ignore_relocations: args.ignore_relocs,
+        pairing: args.pair_by,
+        ..DiffOptions::default()
+    };
+
+    let original_function = match original_executable.get_function(&args.function_name) {
+        Some(function) => function,
+        None => {
+            eprintln!("Function {} not found in executable!", args.function_name);
+            std::process::exit(1);
+        }
+    };
+
+    let instructions =
+        original_executable.decode_function(&capstone, original_function, &options)?;
+    let graph = ControlFlowGraph::build(&instructions);
+
+    let matches = match &args.reimplementation {
+        Some(reimplementation) => {
+            let reimplement_executable = parse_object_with_symbols(
+                reimplementation,
+                args.pdb_file.as_deref(),
+                args.map_file.as_deref(),
+                mapping,
+            )?;
+
+            let reimplement_function =
+                match reimplement_executable.paired_function(original_function, args.pair_by) {
+                    Some(function) => function,
+                    None => {
+                        eprintln!(
+                            "Function {} not found in reimplementation executable!",
+                            args.function_name
+                        );
+                        std::process::exit(1);
+                    }
+                };
+
+            let reimplement_instructions = reimplement_executable.decode_function(
+                &capstone,
+                reimplement_function,
+                &options,
+            )?;
+            let reimplement_graph = ControlFlowGraph::build(&reimplement_instructions);
+
+            Some(cfg::block_matches(
+                (&graph, &instructions),
+                (&reimplement_graph, &reimplement_instructions),
+            ))
+        }
+        None => None,
+    };
+
+    let res = cfg::to_dot(
+        &args.function_name,
+        (&graph, &instructions),
+        matches.as_deref(),
+    )?;
+
+    write_output(args.out.as_deref(), &res, "text/vnd.graphviz")?;
+
+    Ok(())
+}
+
+fn handle_callgraph(mapping: Mapping, args: &CallgraphSubCommand) -> Result<(), Box<dyn Error>> {
+    let capstone = create_capstone(mapping.arch(), false);
+    let executable = parse_object_with_symbols(
+        &args.executable_file,
+        args.pdb_file.as_deref(),
+        args.map_file.as_deref(),
+        mapping,
+    )?;
+
+    let graph = CallGraph::build(&capstone, &executable)?;
+
+    if args.leaves {
+        let mut leaves: Vec<(usize, &str)> = graph
+            .leaves()
+            .map(|name| (graph.functions[name].address, name))
+            .collect();
+        leaves.sort();
+
+        for (address, name) in leaves {
+            println!("{address:#x} {name}");
+        }
+
+        return Ok(());
+    }
+
+    let format = match (args.format, &args.output_file) {
+        (Some(format), _) => format,
+        (None, Some(output_file)) => CallGraphFormat::from_path(output_file),
+        (None, None) => CallGraphFormat::Dot,
+    };
+
+    let res = match format {
+        CallGraphFormat::Dot => graph.to_dot()?,
+        CallGraphFormat::Json => graph.to_json()?,
+    };
+
+    write_output(args.output_file.as_deref(), &res, format.content_type())?;
+
+    Ok(())
+}
+
+fn handle_xref(mapping: Mapping, args: &XrefSubCommand) -> Result<(), Box<dyn Error>> {
+    let capstone = create_capstone(mapping.arch(), false);
+    let executable = parse_object_with_symbols(
+        &args.executable_file,
+        args.pdb_file.as_deref(),
+        args.map_file.as_deref(),
+        mapping,
+    )?;
+
+    // Aliases of the mapping are resolved to the name calls are resolved to.
+    let name = match executable.get_function(&args.function_name) {
+        Some(function) => function.name.as_str(),
+        None if executable
+            .data_symbols()
+            .any(|x| x.name == args.function_name) =>
+        {
+            args.function_name.as_str()
+        }
+        None => {
+            eprintln!("Function {} not found in executable!", args.function_name);
+            std::process::exit(1);
+        }
+    };
+
+    let callers = callgraph::callers(&capstone, &executable, name)?;
+
+    for (caller, offset) in &callers {
+        println!("{:#x} {}+{offset:#x}", caller.address + offset, caller.name);
+    }
+
+    println!("{} call sites", callers.len());
+
+    Ok(())
+}
+
+fn handle_data_stats(mapping: Mapping, args: &DataStatsSubCommand) -> Result<(), Box<dyn Error>> {
+    let original_executable =
+        parse_object_with_mapping(&args.original_executable_file, mapping.clone())?;
+    let reimplement_executable = parse_object_with_symbols(
+        &args.reimplementation_executable_file,
+        args.pdb_file.as_deref(),
+        args.map_file.as_deref(),
+        mapping.clone(),
+    )?;
+
+    let stats = data::data_stats(&original_executable, &reimplement_executable, &mapping);
+    let res = data::render(&stats)?;
+
+    write_output(args.output_file.as_deref(), &res, "text/plain")?;
+
+    Ok(())
+}
+
+fn handle_vtables(mapping: Mapping, args: &VtablesSubCommand) -> Result<(), Box<dyn Error>> {
+    let original_executable =
+        parse_object_with_mapping(&args.original_executable_file, mapping.clone())?;
+    let reimplement_executable = parse_object_with_symbols(
+        &args.reimplementation_executable_file,
+        args.pdb_file.as_deref(),
+        args.map_file.as_deref(),
+        mapping.clone(),
+    )?;
+
+    let reports = vtable::compare_all(&original_executable, &reimplement_executable, &mapping);
+    let res = vtable::render(&reports)?;
+
+    write_output(args.output_file.as_deref(), &res, "text/plain")?;
+
+    Ok(())
+}
+
+fn handle_strings_diff(
+    mapping: Mapping,
+    args: &StringsDiffSubCommand,
+) -> Result<(), Box<dyn Error>> {
+    let original_executable =
+        parse_object_with_mapping(&args.original_executable_file, mapping.clone())?;
+    let reimplement_executable = parse_object_with_symbols(
+        &args.reimplementation_executable_file,
+        args.pdb_file.as_deref(),
+        args.map_file.as_deref(),
+        mapping,
+    )?;
+
+    let res = strings::compare(
+        &original_executable.strings(),
+        &reimplement_executable.strings(),
+    )
+    .render()?;
+
+    write_output(args.output_file.as_deref(), &res, "text/plain")?;
+
+    Ok(())
+}
+
+/// Coverage of an executable by the functions of the mapping and, if given,
+/// of its symbols.
+fn compute_coverage(
+    executable_file: &Path,
+    pdb_file: Option<&Path>,
+    map_file: Option<&Path>,
+    mapping: Mapping,
+) -> Result<(Executable, Coverage), Box<dyn Error>> {
+    let executable = parse_object_with_mapping(executable_file, mapping.clone())?;
+    let symbols_executable = match (pdb_file, map_file) {
+        (None, None) => None,
+        (pdb_file, map_file) => Some(parse_object_with_symbols(
+            executable_file,
+            pdb_file,
+            map_file,
+            mapping,
+        )?),
+    };
+
+    let coverage = Coverage::compute(&executable, &Vec::from_iter(symbols_executable.as_ref()));
+
+    Ok((executable, coverage))
+}
+
+fn handle_coverage(mapping: Mapping, args: &CoverageSubCommand) -> Result<(), Box<dyn Error>> {
+    let (_, coverage) = compute_coverage(
+        &args.executable_file,
+        args.pdb_file.as_deref(),
+        args.map_file.as_deref(),
+        mapping,
+    )?;
+    let res = coverage.render(args.count)?;
+
+    write_output(args.output_file.as_deref(), &res, "text/plain")?;
+
+    Ok(())
+}
+
+fn handle_gaps(mapping: Mapping, args: &GapsSubCommand) -> Result<(), Box<dyn Error>> {
+    let capstone = create_capstone(mapping.arch(), false);
+    let (executable, coverage) = compute_coverage(
+        &args.executable_file,
+        args.pdb_file.as_deref(),
+        args.map_file.as_deref(),
+        mapping,
+    )?;
+
+    let gaps: Vec<_> = coverage
+        .gaps
+        .into_iter()
+        .filter(|x| x.len() >= args.min_size)
+        .collect();
+    let res = coverage::render_gaps(&capstone, &executable, &gaps, args.preview)?;
+
+    write_output(args.output_file.as_deref(), &res, "text/plain")?;
+
+    Ok(())
+}
+
+fn handle_make_sigs(mapping: Mapping, args: &MakeSigsSubCommand) -> Result<(), Box<dyn Error>> {
+    let capstone = create_capstone(mapping.arch(), false);
+    let executable = parse_object_with_mapping(&args.original_executable_file, mapping)?;
+    let signatures = bytesig::compute_all(&capstone, &executable, args.min_size)?;
+    let res = bytesig::render(&signatures)?;
+
+    write_output(args.output_file.as_deref(), &res, "text/plain")?;
+
+    eprintln!(
+        "{} signatures of {} functions",
+        signatures.len(),
+        executable.functions_count()
+    );
+
+    Ok(())
+}
+
+fn handle_apply_sigs(mapping: Mapping, args: &ApplySigsSubCommand) -> Result<(), Box<dyn Error>> {
+    let signatures = bytesig::parse(&std::fs::read_to_string(&args.signatures_file)?)?;
+    let executable = parse_object_with_mapping(&args.executable_file, mapping)?;
+    let scan = bytesig::scan(&signatures, &executable);
+
+    for name in &scan.ambiguous {
+        eprintln!("warning: skipped {name}: matches several functions");
+    }
+
+    let res = bytesig::render_toml(&scan.matches)?;
+
+    write_output(args.output_file.as_deref(), &res, "application/toml")?;
+
+    eprintln!("{} functions found", scan.matches.len());
+
+    Ok(())
+}
+
+fn handle_suggest_matches(
+    mut mapping: Mapping,
+    args: &SuggestMatchesSubCommand,
+) -> Result<(), Box<dyn Error>> {
+    let capstone = create_capstone(mapping.arch(), false);
+    suggest::name_unnamed(&mut mapping);
+
+    let original_executable =
+        parse_object_with_mapping(&args.original_executable_file, mapping.clone())?;
+    let reimplement_executable = parse_object_with_symbols(
+        &args.reimplementation_executable_file,
+        args.pdb_file.as_deref(),
+        args.map_file.as_deref(),
+        mapping.clone(),
+    )?;
+
+    let suggestions = suggest::suggest(
+        &capstone,
+        &original_executable,
+        &reimplement_executable,
+        &mapping,
+        args.min_score,
+    )?;
+    let res = suggest::render_toml(&suggestions)?;
+
+    write_output(args.output_file.as_deref(), &res, "application/toml")?;
+
+    eprintln!("{} functions matched", suggestions.len());
+
+    Ok(())
+}
+
+fn handle_find_similar(
+    mapping: Mapping,
+    args: &FindSimilarSubCommand,
+) -> Result<(), Box<dyn Error>> {
+    let capstone = create_capstone(mapping.arch(), false);
+    let original_executable =
+        parse_object_with_mapping(&args.original_executable_file, mapping.clone())?;
+    let reimplement_executable = parse_object_with_pdb(
+        &args.reimplementation_executable_file,
+        &args.pdb_file,
+        mapping.clone(),
+    )?;
+
+    let function = match original_executable.get_function(&args.function_name) {
+        Some(function) => function,
+        None => {
+            eprintln!("Function {} not found in executable!", args.function_name);
+            std::process::exit(1);
+        }
+    };
+
+    let functions = suggest::find_similar(
+        &capstone,
+        &original_executable,
+        function,
+        &reimplement_executable,
+        &mapping,
+    )?;
+
+    print!("{}", suggest::render_similar(&functions, args.count)?);
+
+    Ok(())
+}
+
+fn handle_stats_report(
+    mapping: Mapping,
+    color: ColorChoice,
+    args: &StatsSubCommand,
+) -> Result<(), Box<dyn Error>> {
+    let original_executable =
+        parse_object_with_mapping(&args.original_executable_file, mapping.clone())?;
+    let reimplement_executable = parse_object_with_pdb(
+        &args.reimplementation_executable_file,
+        &args.pdb_file,
+        mapping.clone(),
+    )?;
+
+    let capstone = create_capstone(mapping.arch(), false);
+    let options = args.diff_options(args.diff_algorithm);
+
+    if let Some(function_name) = &args.explain {
+        let original_function = original_executable.get_function(function_name);
+        let reimplement_function =
+            original_function.and_then(|x| reimplement_executable.paired_function(x, args.pair_by));
+
+        let (original_function, reimplement_function) =
+            match (original_function, reimplement_function) {
+                (Some(a), Some(b)) => (a, b),
+                _ => {
+                    eprintln!("Function {function_name} not found in both executables!");
+                    std::process::exit(1);
+                }
+            };
+
+        print!(
+            "{}",
+            explain::explain(
+                &capstone,
+                (&original_executable, original_function),
+                (&reimplement_executable, reimplement_function),
+                &options,
+            )?
+        );
+
+        return Ok(());
+    }
+
+    let limits = FunctionLimits {
+        max_size: args.max_function_size,
+        time_budget: args.function_time_budget.map(Duration::from_millis),
+    };
+    let format = match (args.format, &args.output_file) {
+        (Some(format), _) => format,
+        (None, Some(output_file)) => ReportFormat::from_path(output_file),
+        (None, None) => ReportFormat::Text,
+    };
+
+    // Only the CSV report lists the instruction counts.
+    let stats = original_executable.generate_stats_with_counts(
+        &capstone,
+        &reimplement_executable,
+        &options,
+        &limits,
+        format == ReportFormat::Csv,
+    )?;
+
+    for function in &stats.skipped {
+        eprintln!("warning: skipped {}: {}", function.name, function.reason);
+    }
+
+    let percentage_format = PercentageFormat::new(args.precision);
+    let mut report = StatsReport::new(&original_executable, &stats.stats)
+        .with_mapping_ids(&mapping)
+        .with_work_statuses(&mapping)
+        .with_instruction_counts(&stats.instruction_counts)
+        .with_skipped(stats.skipped);
+
+    if args.instruction_match {
+        let (instruction_stats, _) = original_executable.generate_stats_with_limits(
+            &capstone,
+            &reimplement_executable,
+            &DiffOptions::instruction_match(args.pair_by),
+            &limits,
+        )?;
+
+        report = report.with_instruction_stats(&instruction_stats);
+    }
+
+    if args.frame_sizes {
+        report = report.with_frame_sizes(&frame::frame_sizes(
+            &capstone,
+            &original_executable,
+            &reimplement_executable,
+            args.pair_by,
+        )?);
+    }
+
+    if args.weighted {
+        report = report.with_weighted_global();
+    }
+
+    if args.group_by == Some(GroupBy::Module) {
+        report = report.with_modules(&reimplement_executable, args.pair_by);
+    }
+
+    if args.demangle {
+        report = report.with_demangled_names();
+    }
+
+    report = report.sorted(args.sort);
+
+    if let Some(baseline) = &args.baseline {
+        report = report.with_baseline(&load_report(baseline)?);
+    }
+
+    if let Some(history) = &args.history {
+        History::open(history)?.record(&report)?;
+    }
+
+    report = report.filtered(args.only_missing, args.below);
+
+    let colorizer = match (&args.output_file, &args.output_url) {
+        (None, None) => Colorizer::for_stdout(color_choice(color, args.no_color)),
+        _ => Colorizer::default(),
+    };
+
+    let mut sink: Box<dyn ReportSink> = match (&args.output_file, &args.output_url) {
+        (_, Some(output_url)) => Box::new(HttpSink::new(output_url)),
+        (Some(output_file), None) => Box::new(FileSink::new(output_file)),
+        (None, None) => Box::new(StdoutSink),
+    };
+
+    let mut res = report.render(format, &percentage_format, &colorizer)?;
+    let global = format!(
+        "GLOBAL: {}\n",
+        percentage_format.format(report.global.percent)
+    );
+
+    if format == ReportFormat::Text {
+        res.push_str(&global);
+    }
+
+    sink.write_report(&res, format.content_type())?;
+
+    // Summary of a report written elsewhere, or that has no room for it.
+    if args.output_file.is_some() || args.output_url.is_some() || format == ReportFormat::Csv {
+        StdoutSink.write_report(&global, "text/plain")?;
+    }
+
+    Ok(())
+}
+
+fn handle_badge(mapping: Mapping, args: &BadgeSubCommand) -> Result<(), Box<dyn Error>> {
+    let original_executable =
+        parse_object_with_mapping(&args.original_executable_file, mapping.clone())?;
+    let reimplement_executable = parse_object_with_pdb(
+        &args.reimplementation_executable_file,
+        &args.pdb_file,
+        mapping.clone(),
+    )?;
+
+    let capstone = create_capstone(mapping.arch(), false);
+    let stats = original_executable.generate_stats(
+        &capstone,
+        &reimplement_executable,
+        &DiffOptions::default(),
+    )?;
+    let mut report = StatsReport::new(&original_executable, &stats).with_work_statuses(&mapping);
+
+    if args.weighted {
+        report = report.with_weighted_global();
+    }
+
+    let default_thresholds = mapping.badge.thresholds();
+    let thresholds = BadgeThresholds {
+        red_below: args.red_below.unwrap_or(default_thresholds.red_below),
+        yellow_below: args.yellow_below.unwrap_or(default_thresholds.yellow_below),
+    };
+    let percentage_format = PercentageFormat::new(args.precision);
+    let mut badge = Badge::for_percentage(
+        &args.label,
+        report.global.percent,
+        &percentage_format,
+        &thresholds,
+    )
+    .with_style(args.style);
+
+    if args.status_breakdown {
+        badge = badge.with_message(report.format_work_status_breakdown(&percentage_format));
+    }
+
+    let badge = badge.to_json()?;
+
+    for category in &mapping.badge.categories {
+        let percent = if args.weighted {
+            category.weighted_percent(&stats, &original_executable)
+        } else {
+            category.percent(&stats)
+        };
+        let percent = match percent {
+            Some(percent) => percent,
+            None => {
+                eprintln!("warning: category {} has no function", category.name);
+                continue;
+            }
+        };
+
+        let category_badge =
+            Badge::for_percentage(category.label(), percent, &percentage_format, &thresholds)
+                .with_style(args.style)
+                .to_json()?;
+
+        FileSink::new(category.badge_path(&args.output_file))
+            .write_report(&category_badge, "application/json")?;
+    }
+
+    FileSink::new(&args.output_file).write_report(&badge, "application/json")?;
+
+    Ok(())
+}
+
+fn handle_diff(
+    mapping: Mapping,
+    color: ColorChoice,
+    args: &DiffSubCommand,
+) -> Result<(), Box<dyn Error>> {
+    let arch = mapping.arch();
+    let att = args.att || mapping.syntax() == Syntax::Att;
+    let capstone = create_capstone(arch, att);
+    let original_executable =
+        parse_object_with_mapping(&args.original_executable_file, mapping.clone())?;
+    let reimplement_executable = parse_object_with_pdb(
+        &args.reimplementation_executable_file,
+        &args.pdb_file,
+        mapping,
+    )?;
+
+    let options = args.diff_options(DiffAlgorithm::Aligned);
+
+    let original_function = match original_executable.get_function(&args.function_name) {
+        Some(function) => function,
+        None => {
+            eprintln!(
+                "Function {} not found in original executable!",
+                args.function_name
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let reimplement_function =
+        match reimplement_executable.paired_function(original_function, args.pair_by) {
+            Some(function) => function,
+            None => {
+                eprintln!(
+                    "Function {} not found in reimplementation executable!",
+                    args.function_name
+                );
+                std::process::exit(1);
+            }
+        };
+
+    if args.explain {
+        let res = explain::explain(
+            &capstone,
+            (&original_executable, original_function),
+            (&reimplement_executable, reimplement_function),
+            &options,
+        )?;
+
+        write_output(args.output_file.as_deref(), &res, "text/plain")?;
+
+        return Ok(());
+    }
+
+    let colorizer = match &args.output_file {
+        None => Colorizer::for_stdout(color_choice(color, args.no_color)),
+        Some(_) => Colorizer::default(),
+    };
+    let render = || -> Result<String, Box<dyn Error>> {
+        let original_instructions =
+            original_executable.decode_function(&capstone, original_function, &options)?;
+        let reimplement_instructions =
+            reimplement_executable.decode_function(&capstone, reimplement_function, &options)?;
+
+        let score = diff::score(
+            options.algorithm,
+            (&original_instructions, &original_function.data),
+            (&reimplement_instructions, &reimplement_function.data),
+        );
+
+        Ok(format!(
+            "{}\nMATCH: {}\n",
+            diff::side_by_side(
+                &original_instructions,
+                &reimplement_instructions,
+                &colorizer
+            ),
+            PercentageFormat::default().format(score)
+        ))
+    };
+
+    let res = match &args.cache_dir {
+        Some(cache_dir) => {
+            let mut key = cache_context(arch, att)
+                .with("diff")
+                .with(&options)
+                .with(&colorizer.is_enabled())
+                .with_function(original_function)
+                .with_function(reimplement_function);
+
+            if options.ignore_relocations {
+                key = key
+                    .with_sections(&original_executable)
+                    .with_sections(&reimplement_executable);
+            }
+
+            DisassemblyCache::open(cache_dir)?.get_or_insert_with(&key, render)?
+        }
+        None => render()?,
+    };
+
+    write_output(args.output_file.as_deref(), &res, "text/plain")?;
+
+    Ok(())
+}
+
+fn handle_group_diff(mapping: Mapping, args: &GroupDiffSubCommand) -> Result<(), Box<dyn Error>> {
+    let capstone = create_capstone(mapping.arch(), false);
+    let original_executable =
+        parse_object_with_mapping(&args.original_executable_file, mapping.clone())?;
+    let reimplement_executable = parse_object_with_pdb(
+        &args.reimplementation_executable_file,
+        &args.pdb_file,
+        mapping.clone(),
+    )?;
+
+    let options = args.diff_options(DiffAlgorithm::Aligned);
+
+    let category = mapping
+        .badge
+        .categories
+        .iter()
+        .find(|x| x.name == args.group);
+    let is_member = |name: &str| match category {
+        Some(category) => category.contains(name),
+        None => name.starts_with(&args.group),
+    };
+
+    let mut functions: Vec<(&String, &Function)> = original_executable
+        .functions_iter()
+        .filter(|(name, _)| is_member(name))
+        .collect();
+    functions.sort_by_key(|(name, _)| *name);
+
+    let mut original_functions = Vec::new();
+    for (_, function) in &functions {
+        original_functions
+            .push(original_executable.decode_function(&capstone, function, &options)?);
+    }
+
+    let sequences = group::find_shared_sequences(&original_functions, args.min_sequence);
+    let percentage_format = PercentageFormat::default();
+
+    let mut res = String::new();
+
+    for sequence in &sequences {
+        writeln!(res, "S{}:", sequence.id)?;
+        for instruction in &sequence.instructions {
+            writeln!(res, "    {instruction}")?;
+        }
+        writeln!(res)?;
+    }
+
+    for (idx, (name, original_function)) in functions.iter().enumerate() {
+        let original_instructions = &original_functions[idx];
+
+        let reimplement_instructions =
+            match reimplement_executable.paired_function(original_function, args.pair_by) {
+                Some(function) => {
+                    reimplement_executable.decode_function(&capstone, function, &options)?
+                }
+                None => {
+                    writeln!(res, "== {name}: MISSING ==\n")?;
+                    continue;
+                }
+            };
+
+        let score = diff::aligned_score(
+            &diff::keys(original_instructions),
+            &diff::keys(&reimplement_instructions),
+        );
+        writeln!(res, "== {name}: {} ==", percentage_format.format(score))?;
+        writeln!(
+            res,
+            "{}",
+            group::unified_diff(
+                original_instructions,
+                &reimplement_instructions,
+                &group::sequences_of(&sequences, idx)
+            )
+        )?;
+    }
+
+    write_output(args.output_file.as_deref(), &res, "text/plain")?;
+
+    Ok(())
+}
+
+fn handle_trace(mapping: Mapping, args: &TraceSubCommand) -> Result<(), Box<dyn Error>> {
+    let capstone = create_capstone(mapping.arch(), false);
+
+    let executable = parse_object_with_symbols(
+        &args.executable_file,
+        args.pdb_file.as_deref(),
+        args.map_file.as_deref(),
+        mapping,
+    )?;
+
+    match executable.get_function(&args.function_name) {
+        Some(function) => {
+            let res = trace::trace_operand(&capstone, function, args.offset, args.operand)?;
+
+            write_output(args.output_file.as_deref(), &res.render()?, "text/plain")?;
+        }
+        None => {
+            eprintln!("Function {} not found in executable!", args.function_name);
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_upload(mapping: Mapping, args: &UploadSubCommand) -> Result<(), Box<dyn Error>> {
+    let capstone = create_capstone(mapping.arch(), false);
+
+    let executable = parse_object_with_symbols(
+        &args.executable_file,
+        args.pdb_file.as_deref(),
+        args.map_file.as_deref(),
+        mapping,
+    )?;
+
+    let function = match executable.get_function(&args.function_name) {
+        Some(function) => function,
+        None => {
+            eprintln!("Function {} not found in executable!", args.function_name);
+            std::process::exit(1);
+        }
+    };
+
+    let disassembly = function.disassemble(&capstone, &executable, true, false)?;
+    let mut request = ScratchRequest::new(&args.function_name, &disassembly);
+
+    request.compiler = args.compiler.clone();
+    request.compiler_flags = args.compiler_flags.clone();
+
+    if let Some(context_file) = &args.context_file {
+        request.context = std::fs::read_to_string(context_file)?;
+    }
+
+    if args.dry_run {
+        println!("{}", serde_json::to_string_pretty(&request)?);
+        return Ok(());
+    }
+
+    let scratch = decompme::create_scratch(&args.api_url, &request)?;
+
+    println!("{}", scratch.url(&args.api_url));
+
+    if let Some(claim_url) = scratch.claim_url(&args.api_url) {
+        println!("Claim it at {claim_url}");
+    }
+
+    Ok(())
+}
+
+fn handle_export_asm(mapping: Mapping, args: &ExportAsmSubCommand) -> Result<(), Box<dyn Error>> {
+    let capstone = create_capstone(mapping.arch(), false);
+
+    let executable = parse_object_with_symbols(
+        &args.executable_file,
+        args.pdb_file.as_deref(),
+        args.map_file.as_deref(),
+        mapping,
+    )?;
+
+    let mut functions = Vec::new();
+
+    for name in &args.function_names {
+        match executable.get_function(name) {
+            Some(function) => functions.push((name, function)),
+            None => {
+                eprintln!("Function {name} not found in executable!");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let profile = create_profile(
+        args.radix,
+        args.hex_notation,
+        args.literal_width,
+        args.unsigned_literals,
+        false,
+        args.dialect,
+    );
+
+    std::fs::create_dir_all(&args.out)?;
+
+    for (name, function) in &functions {
+        let disassembly =
+            function.disassemble_with_profile(&capstone, &executable, true, false, &profile)?;
+        let (extension, content) = match args.dialect {
+            Dialect::Capstone => ("s", decompme::format_target_asm(name, &disassembly)),
+            Dialect::Masm => ("asm", masm::format_procedure(name, &disassembly)),
+        };
+        let path = args
+            .out
+            .join(format!("{}.{extension}", golden::function_file_name(name)));
+
+        std::fs::write(&path, content)?;
+    }
+
+    println!("{} functions exported", functions.len());
+
+    Ok(())
+}
+
+fn handle_check(mapping: Mapping, args: &CheckSubCommand) -> Result<(), Box<dyn Error>> {
+    let capstone = create_capstone(mapping.arch(), false);
+    let original_executable =
+        parse_object_with_mapping(&args.original_executable_file, mapping.clone())?;
+    let reimplement_executable = parse_object_with_symbols(
+        &args.reimplementation_executable_file,
+        args.pdb_file.as_deref(),
+        args.map_file.as_deref(),
+        mapping.clone(),
+    )?;
+
+    let options = args.diff_options(args.diff_algorithm);
+    let raw_stats =
+        original_executable.generate_stats(&capstone, &reimplement_executable, &options)?;
+    let report = StatsReport::new(&original_executable, &raw_stats);
+    let percentage_format = PercentageFormat::new(args.precision);
+
+    let (violations, count) = check::check(&report, &mapping);
+
+    if !violations.is_empty() {
+        eprint!("{}", check::render(&violations, &percentage_format)?);
+        eprintln!("{} of {count} thresholds not met", violations.len());
+        std::process::exit(1);
+    }
+
+    println!("All {count} thresholds met");
+
+    Ok(())
+}
+
+fn load_report(path: &Path) -> Result<StatsReport, Box<dyn Error>> {
+    if !path.exists() {
+        eprintln!("Report {} not found!\n", path.display());
+        std::process::exit(1);
+    }
+
+    Ok(StatsReport::from_json(&std::fs::read_to_string(path)?)?)
+}
+
+fn handle_compare_reports(
+    color: ColorChoice,
+    args: &CompareReportsSubCommand,
+) -> Result<(), Box<dyn Error>> {
+    let old_report = load_report(&args.old_report)?;
+    let new_report = load_report(&args.new_report)?;
+
+    let percentage_format = PercentageFormat::new(args.precision);
+    let colorizer = Colorizer::for_stdout(color_choice(color, args.no_color));
+    let changes = compare::compare(&old_report, &new_report);
+
+    print!(
+        "{}",
+        compare::render(
+            &changes,
+            &old_report,
+            &new_report,
+            &percentage_format,
+            &colorizer
+        )?
+    );
+
+    if changes.iter().any(|x| x.kind.is_regression()) {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn handle_history(color: ColorChoice, args: &HistorySubCommand) -> Result<(), Box<dyn Error>> {
+    if !args.database.exists() {
+        eprintln!("History database {} not found!\n", args.database.display());
+        std::process::exit(1);
+    }
+
+    let history = History::open(&args.database)?;
+    let entries = match &args.function {
+        Some(function) => history.function(function)?,
+        None => history.global()?,
+    };
+
+    if entries.is_empty() {
+        eprintln!("No snapshot found!");
+        std::process::exit(1);
+    }
+
+    let percentage_format = PercentageFormat::new(args.precision);
+    let colorizer = Colorizer::for_stdout(color_choice(color, args.no_color));
+
+    print!(
+        "{}",
+        history::render(&entries, &percentage_format, &colorizer)?
+    );
+
+    Ok(())
+}
+
+fn handle_graph(args: &GraphSubCommand) -> Result<(), Box<dyn Error>> {
+    if !args.database.exists() {
+        eprintln!("History database {} not found!\n", args.database.display());
+        std::process::exit(1);
+    }
+
+    let history = History::open(&args.database)?;
+    let mut series = vec![Series {
+        name: "Global".into(),
+        entries: history.global()?,
+    }];
+
+    for category in &args.category {
+        series.push(Series {
+            name: category.clone(),
+            entries: history.category(category)?,
+        });
+    }
+
+    let size = ChartSize {
+        width: args.width,
+        height: args.height,
+    };
+    let res = graph::render_svg(&series, &args.title, size)?;
+
+    write_output(args.output_file.as_deref(), &res, "image/svg+xml")?;
+
+    Ok(())
+}
+
+fn handle_validate_mapping(
+    mapping: Mapping,
+    args: &ValidateMappingSubCommand,
+) -> Result<(), Box<dyn Error>> {
+    let pdb_executable = match &args.pdb_file {
+        Some(pdb_file) => Some(parse_object_with_pdb(
+            &args.executable_file,
+            pdb_file,
+            mapping.clone(),
+        )?),
+        None => None,
+    };
+
+    let raw_data = archive::read_input(&args.executable_file, archive::EXECUTABLE_EXTENSIONS)?;
+    let raw_obj = parse_object(&args.executable_file, &raw_data)?;
+    let problems = validate::validate(&raw_obj, &mapping, pdb_executable.as_ref())
+        .map_err(loading_error(&args.executable_file))?;
+    let count = mapping.function.as_ref().map_or(0, |x| x.len());
+
+    if !problems.is_empty() {
+        eprint!("{}", validate::render(&problems)?);
+        eprintln!("{} problems in {count} functions", problems.len());
+        std::process::exit(1);
+    }
+
+    println!("All {count} functions valid");
+
+    Ok(())
+}
+
+fn handle_detect_functions(
+    mapping: Mapping,
+    args: &DetectFunctionsSubCommand,
+) -> Result<(), Box<dyn Error>> {
+    let capstone = create_capstone(mapping.arch(), false);
+    let raw_data = archive::read_input(
+        &args.original_executable_file,
+        archive::EXECUTABLE_EXTENSIONS,
+    )?;
+    let raw_obj = parse_object(&args.original_executable_file, &raw_data)?;
+    let candidates = boundaries::detect(&capstone, &raw_obj, &mapping)
+        .map_err(loading_error(&args.original_executable_file))?;
+    let res = boundaries::render_toml(&candidates)?;
+
+    write_output(args.output_file.as_deref(), &res, "application/toml")?;
+
+    eprintln!("{} functions found", candidates.len());
+
+    Ok(())
+}
+
+fn handle_explain(mapping: Mapping, args: &ExplainSubCommand) -> Result<(), Box<dyn Error>> {
+    let att = args.att || mapping.syntax() == Syntax::Att;
+    let capstone = create_capstone(mapping.arch(), att);
+    let original_executable =
+        parse_object_with_mapping(&args.original_executable_file, mapping.clone())?;
+    let reimplement_executable = parse_object_with_pdb(
+        &args.reimplementation_executable_file,
+        &args.pdb_file,
+        mapping,
+    )?;
+
+    let options = args.diff_options(DiffAlgorithm::Aligned);
+
+    let original_function = original_executable.get_function(&args.function_name);
+    let reimplement_function =
+        original_function.and_then(|x| reimplement_executable.paired_function(x, args.pair_by));
+
+    let (original_function, reimplement_function) = match (original_function, reimplement_function)
+    {
+        (Some(a), Some(b)) => (a, b),
+        _ => {
+            eprintln!(
+                "Function {} not found in both executables!",
+                args.function_name
+            );
+            std::process::exit(1);
+        }
+    };
+
+    print!(
+        "{}",
+        explain::mismatches(
+            &capstone,
+            (&original_executable, original_function),
+            (&reimplement_executable, reimplement_function),
+            &options,
+        )?
+    );
+
+    Ok(())
+}
+
+/// What a watch session derives from the mapping, rebuilt when it changes.
+struct WatchState {
+    capstone: Capstone,
+    context: CacheKey,
+    original_executable: Executable,
+    mapping: Mapping,
+}
+
+impl WatchState {
+    fn load(mapping: Mapping, args: &WatchSubCommand) -> Result<Self, Box<dyn Error>> {
+        let original_executable =
+            parse_object_with_mapping(&args.original_executable_file, mapping.clone())?;
+
+        if let Some(function_name) = &args.function {
+            if original_executable.get_function(function_name).is_none() {
+                return Err(
+                    format!("Function {function_name} not found in original executable!").into(),
+                );
+            }
+        }
+
+        Ok(Self {
+            capstone: create_capstone(mapping.arch(), false),
+            context: cache_context(mapping.arch(), false),
+            original_executable,
+            mapping,
+        })
+    }
+}
+
+fn handle_watch(top_level: &TopLevel, args: &WatchSubCommand) -> Result<(), Box<dyn Error>> {
+    let mut state = match WatchState::load(load_mapping(top_level)?, args) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
+
+    let options = args.diff_options(args.diff_algorithm);
+    let percentage_format = PercentageFormat::new(args.precision);
+    let colorizer = Colorizer::for_stdout(color_choice(top_level.color, args.no_color));
+    let cache = match &args.cache_dir {
+        Some(cache_dir) => Some(DisassemblyCache::open(cache_dir)?),
+        None => None,
+    };
+
+    let compute_stats = |state: &WatchState| -> Result<StatsReport, Box<dyn Error>> {
+        let WatchState {
+            capstone,
+            context,
+            original_executable,
+            mapping,
+        } = state;
+        let reimplement_executable = parse_object_with_symbols(
+            &args.reimplementation_executable_file,
+            args.pdb_file.as_deref(),
+            args.map_file.as_deref(),
+            mapping.clone(),
+        )?;
+
+        let raw_stats = match &args.function {
+            Some(function_name) => {
+                let mut res = FunctionStats::new();
+                let original_function = original_executable.get_function(function_name);
+                let reimplement_function = original_function
+                    .and_then(|x| reimplement_executable.paired_function(x, args.pair_by));
+
+                if let (Some(a), Some(b)) = (original_function, reimplement_function) {
+                    let score = match &cache {
+                        Some(cache) => cache.function_diff(
+                            capstone,
+                            context,
+                            (original_executable, a),
+                            (&reimplement_executable, b),
+                            &options,
+                        )?,
+                        None => original_executable.compute_function_diff(
+                            capstone,
+                            a,
+                            &reimplement_executable,
+                            b,
+                            &options,
+                        )?,
+                    };
+
+                    res.insert(function_name.clone(), Some(score));
+                } else {
+                    res.insert(function_name.clone(), None);
+                }
+
+                res
+            }
+            None => match &cache {
+                Some(cache) => {
+                    cache
+                        .generate_stats(
+                            capstone,
+                            context,
+                            original_executable,
+                            &reimplement_executable,
+                            &options,
+                            &FunctionLimits::default(),
+                            false,
+                        )?
+                        .stats
+                }
+                None => original_executable.generate_stats(
+                    capstone,
+                    &reimplement_executable,
+                    &options,
+                )?,
+            },
+        };
+
+        Ok(StatsReport::new(original_executable, &raw_stats))
+    };
+
+    let mut watched = vec![args.reimplementation_executable_file.clone()];
+    watched.extend(args.pdb_file.clone());
+    watched.extend(args.map_file.clone());
+
+    let mut watcher = FileWatcher::new(watched);
+    let mut mapping_watcher = FileWatcher::new([mapping_file(top_level).0.to_path_buf()]);
+    let mut previous = compute_stats(&state)?;
+
+    print!("{}", previous.render_text(&percentage_format, &colorizer)?);
+
+    if args.function.is_none() {
+        println!(
+            "GLOBAL: {}",
+            percentage_format.format(previous.global.percent)
+        );
+    }
+
+    loop {
+        std::thread::sleep(Duration::from_millis(args.interval));
+
+        let is_mapping_changed = mapping_watcher.poll();
+        let is_reimplementation_changed = watcher.poll();
+
+        if !is_mapping_changed && !is_reimplementation_changed {
+            continue;
+        }
+
+        let mut is_mapping_reloaded = false;
+
+        if is_mapping_changed {
+            // Names and categories are resolved again, keeping the previous
+            // mapping while the new one is invalid so that a reimplementation
+            // change seen by the same poll is still reported.
+            match load_mapping(top_level).and_then(|mapping| WatchState::load(mapping, args)) {
+                Ok(new_state) => {
+                    state = new_state;
+                    is_mapping_reloaded = true;
+                }
+                Err(err) => eprintln!("warning: cannot reload the mapping: {err}"),
+            }
+        }
+
+        let current = match compute_stats(&state) {
+            Ok(current) => current,
+            Err(err) => {
+                eprintln!("warning: cannot load the reimplementation: {err}");
+                continue;
+            }
+        };
+
+        if is_mapping_reloaded {
+            println!("--- mapping changed");
+        }
+
+        if is_reimplementation_changed {
+            println!("--- reimplementation changed");
+        }
+
+        print!(
+            "{}",
+            watch::render_changes(&previous, &current, &percentage_format, &colorizer)?
+        );
+
+        if args.function.is_none() {
+            print!(
+                "{}",
+                watch::render_global(&previous, &current, &percentage_format, &colorizer)
+            );
+        }
+
+        previous = current;
+    }
+}
+
+fn handle_dump_all(mapping: Mapping, args: &DumpAllSubCommand) -> Result<(), Box<dyn Error>> {
+    let arch = mapping.arch();
+    let att = args.att || mapping.syntax() == Syntax::Att;
+    let capstone = create_capstone(arch, att);
+
+    let executable = parse_object_with_symbols(
+        &args.executable_file,
+        args.pdb_file.as_deref(),
+        args.map_file.as_deref(),
+        mapping,
+    )?;
+
+    std::fs::create_dir_all(&args.out)?;
+
+    let cache = match &args.cache_dir {
+        Some(cache_dir) => Some(DisassemblyCache::open(cache_dir)?),
+        None => None,
+    };
+    let mut context = cache_context(arch, att)
+        .with("dump-all")
+        .with(&args.force_address_zero)
+        .with(&args.resolve_names);
+
+    if args.resolve_names {
+        context = context.with_names(&executable);
+    }
+
+    for (name, function) in executable.functions_iter() {
+        let disassemble = || {
+            function.disassemble(
+                &capstone,
+                &executable,
+                args.force_address_zero,
+                args.resolve_names,
+            )
+        };
+        let disassembly = match &cache {
+            Some(cache) => {
+                cache.get_or_insert_with(&context.clone().with_function(function), disassemble)?
+            }
+            None => disassemble()?,
+        };
+        let path = args
+            .out
+            .join(format!("{}.s", golden::function_file_name(name)));
+
+        std::fs::write(&path, disassembly)?;
+    }
+
+    println!("{} functions written", executable.functions_count());
+
+    Ok(())
+}
+
+fn handle_list(mapping: Mapping, args: &ListSubCommand) -> Result<(), Box<dyn Error>> {
+    let executable = parse_object_with_symbols(
+        &args.executable_file,
+        args.pdb_file.as_deref(),
+        args.map_file.as_deref(),
+        mapping,
+    )?;
+
+    let mut functions = list::functions(&executable, args.sort);
+
+    if let Some(filter) = &args.filter {
+        let filter = if args.regex {
+            NameFilter::regex(filter)?
+        } else {
+            NameFilter::glob(filter)?
+        };
+
+        functions.retain(|x| {
+            filter.matches(&x.name)
+                || filter.matches(&demangle::display_name(&x.name, args.demangle))
+        });
+    }
+
+    let res = list::render(&functions, args.demangle)?;
+
+    write_output(args.output_file.as_deref(), &res, "text/plain")?;
+
+    Ok(())
+}
+
+fn handle_stubs(mapping: Mapping, args: &StubsSubCommand) -> Result<(), Box<dyn Error>> {
+    let capstone = create_capstone(mapping.arch(), false);
+    let original_executable =
+        parse_object_with_mapping(&args.original_executable_file, mapping.clone())?;
+
+    let reimplement_executable = match &args.reimplementation {
+        Some(reimplementation) => Some(parse_object_with_symbols(
+            reimplementation,
+            args.pdb_file.as_deref(),
+            args.map_file.as_deref(),
+            mapping,
+        )?),
+        None => None,
+    };
+
+    let mut stubs = Vec::new();
+
+    for (name, function) in original_executable.functions_iter() {
+        let implemented = reimplement_executable
+            .as_ref()
+            .map(|x| x.get_function(name).is_some())
+            .unwrap_or(false);
+
+        if !implemented {
+            stubs.push(stubs::analyze(&capstone, function)?);
+        }
+    }
+
+    stubs.sort_by_key(|x| x.address);
+
+    let res = stubs::render(&stubs)?;
+
+    write_output(args.output_file.as_deref(), &res, "text/x-c")?;
+
+    Ok(())
+}
+
+fn handle_tables(mapping: Mapping, args: &TablesSubCommand) -> Result<(), Box<dyn Error>> {
+    let capstone = create_capstone(mapping.arch(), false);
+    let executable = parse_object_with_mapping(&args.executable_file, mapping)?;
+
+    let raw_data = archive::read_input(&args.executable_file, archive::EXECUTABLE_EXTENSIONS)?;
+    let raw_obj = object::File::parse(&*raw_data)?;
+    let sections = tables::data_sections(&raw_obj)?;
+
+    let mut functions = Vec::new();
+
+    if args.function_names.is_empty() {
+        functions.extend(list::functions(&executable, FunctionOrder::Address));
+    }
+
+    for name in &args.function_names {
+        match executable.get_function(name) {
+            Some(function) => functions.push(function),
+            None => {
+                eprintln!("Function {name} not found in executable!");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let tables = tables::extract_tables(&capstone, functions, &sections, args.max_elements)?;
+    let res = tables::render(&tables)?;
+
+    write_output(args.output_file.as_deref(), &res, "text/x-c")?;
+
+    Ok(())
+}
+
+fn handle_workspace(color: ColorChoice, args: &WorkspaceSubCommand) -> Result<(), Box<dyn Error>> {
+    if !args.workspace_file.exists() {
+        eprintln!("Workspace file not found!\n");
+        std::process::exit(1);
+    }
+
+    let workspace = Workspace::load(&args.workspace_file)?;
+    let options = args.diff_options(args.diff_algorithm);
+
+    let format = match (args.format, &args.output_file) {
+        (Some(format), _) => format,
+        (None, Some(output_file)) => ReportFormat::from_path(output_file),
+        (None, None) => ReportFormat::Text,
+    };
+
+    let mut targets = Vec::new();
+
+    for target in &workspace.targets {
+        let (mapping_file, mapping_format) = match select_mapping(
+            target.mapping.as_deref(),
+            target.mapping_csv.as_deref(),
+            target.mapping_ida.as_deref(),
+        ) {
+            Ok(res) => res,
+            Err(_) => {
+                eprintln!(
+                    "Target {} needs exactly one of mapping, mapping_csv or mapping_ida",
+                    target.name
+                );
+                std::process::exit(1);
+            }
+        };
+
+        let mut mapping = read_mapping(mapping_file, mapping_format)?;
+
+        if let Some(version) = &target.game_version {
+            mapping.select_version(version)?;
+        }
+
+        let capstone = create_capstone(mapping.arch(), false);
+        let original_executable = parse_object_with_mapping(&target.original, mapping.clone())?;
+        let reimplement_executable = parse_object_with_symbols(
+            &target.reimplementation,
+            target.pdb.as_deref(),
+            target.map.as_deref(),
+            mapping.clone(),
+        )?;
+
+        let stats = original_executable.generate_stats_with_counts(
+            &capstone,
+            &reimplement_executable,
+            &options,
+            &FunctionLimits::default(),
+            format == ReportFormat::Csv,
+        )?;
+
+        let report = StatsReport::new(&original_executable, &stats.stats)
+            .with_mapping_ids(&mapping)
+            .with_work_statuses(&mapping)
+            .with_instruction_counts(&stats.instruction_counts);
+
+        targets.push(TargetReport {
+            name: target.name.clone(),
+            report,
+        });
+    }
+
+    let report = WorkspaceReport::new(targets);
+    let percentage_format = PercentageFormat::new(args.precision);
+
+    let colorizer = match &args.output_file {
+        None => Colorizer::for_stdout(color_choice(color, args.no_color)),
+        Some(_) => Colorizer::default(),
+    };
+
+    let mut sink: Box<dyn ReportSink> = match &args.output_file {
+        Some(output_file) => Box::new(FileSink::new(output_file)),
+        None => Box::new(StdoutSink),
+    };
+
+    report.write_to(sink.as_mut(), format, &percentage_format, &colorizer)?;
+
+    Ok(())
+}
+
+fn handle_test(mapping: Mapping, args: &TestSubCommand) -> Result<(), Box<dyn Error>> {
+    let capstone = create_capstone(mapping.arch(), args.att || mapping.syntax() == Syntax::Att);
+
+    let executable = parse_object_with_symbols(
+        &args.executable_file,
+        args.pdb_file.as_deref(),
+        args.map_file.as_deref(),
+        mapping,
+    )?;
+
+    if args.update {
+        std::fs::create_dir_all(&args.golden_directory)?;
+    } else if !args.golden_directory.is_dir() {
+        eprintln!("Golden directory not found!\n");
+        std::process::exit(1);
+    }
+
+    let mut names: Vec<&String> = executable.functions_iter().map(|(name, _)| name).collect();
+    names.sort();
+
+    let mut checked_count = 0;
+    let mut drift_count = 0;
+
+    for name in names {
+        let function = executable.get_function(name).unwrap();
+        let golden_path = args.golden_directory.join(format!(
+            "{}.{}",
+            golden::function_file_name(name),
+            golden::GOLDEN_EXTENSION
+        ));
+
+        let current = function.disassemble(&capstone, &executable, true, true)?;
+
+        if args.update {
+            std::fs::write(&golden_path, current)?;
+            continue;
+        }
+
+        if !golden_path.exists() {
+            continue;
+        }
+
+        checked_count += 1;
+
+        if let Some(res) = golden::compare(name, &std::fs::read_to_string(&golden_path)?, &current)
+        {
+            drift_count += 1;
+            print!("{res}");
+        }
+    }
+
+    if args.update {
+        println!("Goldens updated");
+        return Ok(());
+    }
+
+    println!("{checked_count} functions checked, {drift_count} drifted");
+
+    if drift_count != 0 {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn handle_apply_names(args: &ApplyNamesSubCommand) -> Result<(), Box<dyn Error>> {
+    if !args.names_file.exists() {
+        eprintln!("Names file not found!\n");
+        std::process::exit(1);
+    }
+
+    if !args.mapping.exists() {
+        eprintln!("Mapping not found!\n");
+        std::process::exit(1);
+    }
+
+    let names = rename::parse_name_list(&std::fs::read_to_string(&args.names_file)?)?;
+    let mut mapping = std::fs::read_to_string(&args.mapping)?.parse::<toml_edit::Document>()?;
+
+    let outcomes = rename::apply_names(&mut mapping, &names, args.force)?;
+
+    let mut applied_count = 0;
+    let mut conflict_count = 0;
+
+    for outcome in &outcomes {
+        match outcome {
+            RenameOutcome::Unchanged { .. } => continue,
+            outcome if outcome.is_conflict() => conflict_count += 1,
+            _ => applied_count += 1,
+        }
+
+        println!("{outcome}");
+    }
+
+    println!("{applied_count} names applied, {conflict_count} conflicts");
+
+    if !args.dry_run && applied_count != 0 {
+        std::fs::write(&args.mapping, mapping.to_string())?;
+    }
+
+    Ok(())
+}
+
+fn handle_headers_diff(args: &HeadersDiffSubCommand) -> Result<(), Box<dyn Error>> {
+    let original_headers = parse_headers(&args.original_executable_file)?;
+    let reimplement_headers = parse_headers(&args.reimplementation_executable_file)?;
+
+    let mut res = original_headers.diff(&reimplement_headers)?;
+
+    if res.is_empty() {
+        res = "Headers are matching\n".into();
+    }
+
+    write_output(args.output_file.as_deref(), &res, "text/plain")?;
+
+    Ok(())
+}
+
+/// Format of a mapping file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MappingFormat {
+    Toml,
+    Csv,
+    Ida,
+}
+
+fn read_mapping(mapping_file: &Path, format: MappingFormat) -> Result<Mapping, Box<dyn Error>> {
+    if !mapping_file.exists() {
+        eprintln!("Mapping not found!\n");
+        std::process::exit(1);
+    }
+
+    let raw_mapping = std::fs::read_to_string(mapping_file)?;
+
+    let mapping = match format {
+        MappingFormat::Csv => {
+            let mut rdr = csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .from_reader(raw_mapping.as_bytes());
+            Mapping {
+                function: Some(Result::from_iter(rdr.deserialize())?),
+                ..Mapping::default()
+            }
+        }
+        MappingFormat::Ida => Mapping::from_ida_names(&raw_mapping),
+        MappingFormat::Toml => toml::from_str::<Mapping>(&raw_mapping)?,
+    };
+
+    Ok(mapping)
+}
+
+/// Pick the only mapping file given, if any.
+fn select_mapping<'a>(
+    toml: Option<&'a Path>,
+    csv: Option<&'a Path>,
+    ida: Option<&'a Path>,
+) -> Result<(&'a Path, MappingFormat), usize> {
+    let mapping_files = [
+        (toml, MappingFormat::Toml),
+        (csv, MappingFormat::Csv),
+        (ida, MappingFormat::Ida),
+    ];
+
+    let mut selected = mapping_files
+        .into_iter()
+        .filter_map(|(path, format)| path.map(|x| (x, format)));
+
+    match (selected.next(), selected.count()) {
+        (Some(res), 0) => Ok(res),
+        (None, _) => Err(0),
+        (Some(_), count) => Err(count + 1),
+    }
+}
+
+/// Mapping file given on the command line and its format.
+fn mapping_file(args: &TopLevel) -> (&Path, MappingFormat) {
+    match select_mapping(
+        args.mapping_file.as_deref(),
+        args.mapping_file_csv.as_deref(),
+        args.mapping_file_ida.as_deref(),
+    ) {
+        Ok(res) => res,
+        Err(0) => {
+            eprintln!("This tool needs a mapping file to function. Pass one using --mapping-file, --mapping-file-csv or --mapping-file-ida");
+            std::process::exit(1);
+        }
+        Err(_) => {
+            eprintln!("Can't pass several mapping files. Only pass one of --mapping-file, --mapping-file-csv or --mapping-file-ida");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Write a rendered report to the output file, or to stdout without one.
+fn write_output(
+    output_file: Option<&Path>,
+    res: &str,
+    content_type: &str,
+) -> Result<(), SinkError> {
+    match output_file {
+        Some(output_file) => FileSink::new(output_file).write_report(res, content_type),
+        None => StdoutSink.write_report(res, content_type),
+    }
+}
+
+fn load_mapping(args: &TopLevel) -> Result<Mapping, Box<dyn Error>> {
+    let (mapping_file, format) = mapping_file(args);
+    let mut mapping = read_mapping(mapping_file, format)?;
+
+    if let Some(section) = &args.section {
+        mapping.section = Some(section.clone());
+    }
+
+    if args.arch.is_some() {
+        mapping.executable.arch = args.arch;
+    }
+
+    if args.demangle_symbols {
+        mapping.executable.demangle_symbols = true;
+    }
+
+    if args.raw_base.is_some() {
+        mapping.executable.raw_base = args.raw_base;
+    }
+
+    if let Some(version) = &args.game_version {
+        mapping.select_version(version)?;
+    }
+
+    Ok(mapping)
+}
+
+/// Find the `[[function]]` entry of a TOML mapping defining a function,
+/// returning its line number and its text.
+fn find_mapping_entry(raw_mapping: &str, name: &str, address: usize) -> Option<(usize, String)> {
+    let document = raw_mapping.parse::<toml_edit::Document>().ok()?;
+    let index = document
+        .get("function")?
+        .as_array_of_tables()?
+        .iter()
+        .position(|x| {
+            x.get("name").and_then(|x| x.as_str()) == Some(name)
+                && x.get("address").and_then(|x| x.as_integer()) == Some(address as i64)
+        })?;
+
+    let lines: Vec<&str> = raw_mapping.lines().collect();
+    let start = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.trim() == "[[function]]")
+        .nth(index)?
+        .0;
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| line.trim().is_empty() || line.trim_start().starts_with('['))
+        .map_or(lines.len(), |x| start + 1 + x);
+
+    Some((start + 1, lines[start..end].join("\n")))
+}
+
+/// Print an error, pointing at the mapping entry responsible for it when possible.
+fn report_error(args: &TopLevel, err: &(dyn Error + 'static)) {
+    eprintln!("Error: {err}");
+
+    let mut err = match err.downcast_ref::<ExecutableError>() {
+        Some(err) => err,
+        None => return,
+    };
+
+    while let ExecutableError::Context { error, .. } = err {
+        err = error;
+    }
+
+    let (name, address) = match err {
+        ExecutableError::FunctionOutOfRange { name, address, .. }
+        | ExecutableError::FunctionOutsideSections { name, address } => (name, address),
+        _ => return,
+    };
+
+    if let Some(mapping_file) = &args.mapping_file {
+        let entry = std::fs::read_to_string(mapping_file)
+            .ok()
+            .and_then(|x| find_mapping_entry(&x, name, *address));
+
+        if let Some((line, text)) = entry {
+            eprintln!("\nDefined in {}:{line}:\n{text}", mapping_file.display());
+        }
+    }
+}
+
+/// Defaults of the workspace file of the working directory, if any,
+/// overridden by the environment.
+fn load_defaults() -> Defaults {
+    let path = Path::new(workspace::DEFAULT_WORKSPACE_FILE);
+    let mut res = match path.exists() {
+        true => Defaults::load(path).unwrap_or_else(|err| {
+            eprintln!("Invalid {}: {err}", path.display());
+            std::process::exit(1);
+        }),
+        false => Defaults::default(),
+    };
+
+    res.apply_env();
+    res
+}
+
+/// Arguments of a command: its flags (`--name`), the ones of them taking a
+/// value and its required positional arguments.
+fn command_args(command: &str) -> (Vec<&'static str>, Vec<&'static str>, Vec<&'static str>) {
+    let info = TopLevel::get_args_info()
+        .commands
+        .into_iter()
+        .find(|x| x.name == command);
+    let (flags, positionals) = match info {
+        Some(info) => (info.command.flags, info.command.positionals),
+        None => return Default::default(),
+    };
+
+    let options = flags
+        .iter()
+        .filter(|x| matches!(x.kind, FlagInfoKind::Option { .. }))
+        .map(|x| x.long)
+        .collect();
+    let required = positionals
+        .iter()
+        .filter(|x| x.optionality == Optionality::Required)
+        .map(|x| x.name)
+        .collect();
+
+    (flags.iter().map(|x| x.long).collect(), options, required)
+}
+
+/// Parse the command line, completed with the defaults of the workspace file
+/// of the working directory and the environment: the mapping, the flags and
+/// path options of the command and the paths of the executables missing from
+/// its positional arguments.
+fn parse_args() -> TopLevel {
+    let strings: Vec<String> = std::env::args().collect();
+    let command = strings
+        .first()
+        .and_then(|x| Path::new(x).file_name())
+        .map_or("satsuki".into(), |x| x.to_string_lossy().into_owned());
+    let defaults = load_defaults();
+    let mut args = strings.get(1..).unwrap_or_default().to_vec();
+
+    let subcommand = args.iter().position(|arg| {
+        <SubCommandEnum as SubCommands>::COMMANDS
+            .iter()
+            .any(|x| x.name == arg)
+    });
+
+    if let Some(idx) = subcommand {
+        let mut subcommand_args = args.split_off(idx + 1);
+        let (accepted, options, positionals) = command_args(&args[idx]);
+        defaults.insert_flags(&args[idx], &accepted, &mut subcommand_args);
+        defaults.insert_paths(&accepted, &mut subcommand_args);
+        defaults.insert_positionals(&positionals, &options, &mut subcommand_args);
+        args.extend(subcommand_args);
+    }
+
+    let given = &args[..subcommand.unwrap_or(args.len())];
+    let mut top_level = Vec::new();
+
+    if let (false, Some(mapping)) = (
+        given.iter().any(|x| x.starts_with("--mapping-file")),
+        &defaults.mapping,
+    ) {
+        top_level.extend(["--mapping-file".into(), mapping.to_string_lossy().into()]);
+    }
+
+    if let (false, Some(version)) = (
+        given.iter().any(|x| x == "--game-version"),
+        &defaults.game_version,
+    ) {
+        top_level.extend(["--game-version".into(), version.clone()]);
+    }
+
+    args.splice(0..0, top_level);
+
+    let strs: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    TopLevel::from_args(&[&command], &strs).unwrap_or_else(|early_exit| match early_exit.status {
+        Ok(()) => {
+            println!("{}", early_exit.output);
+            std::process::exit(0);
+        }
+        Err(()) => {
+            eprintln!(
+                "{}\nRun {command} --help for more information.",
+                early_exit.output
+            );
+            std::process::exit(1);
+        }
+    })
+}
+
+fn main() {
+    let args = parse_args();
+
+    if let Err(err) = run(&args) {
+        report_error(&args, &*err);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &TopLevel) -> Result<(), Box<dyn Error>> {
     match &args.subcommand {
-        SubCommandEnum::Disassemble(args) => handle_disassemble(mapping, args),
-        SubCommandEnum::Stats(args) => handle_stats_report(mapping, args),
-        SubCommandEnum::Badge(args) => handle_badge(mapping, args),
+        SubCommandEnum::Disassemble(sub_args) => handle_disassemble(load_mapping(args)?, sub_args),
+        SubCommandEnum::Stats(sub_args) => {
+            handle_stats_report(load_mapping(args)?, args.color, sub_args)
+        }
+        SubCommandEnum::Badge(sub_args) => handle_badge(load_mapping(args)?, sub_args),
+        SubCommandEnum::HeadersDiff(sub_args) => handle_headers_diff(sub_args),
+        SubCommandEnum::GroupDiff(sub_args) => handle_group_diff(load_mapping(args)?, sub_args),
+        SubCommandEnum::Diff(sub_args) => handle_diff(load_mapping(args)?, args.color, sub_args),
+        SubCommandEnum::ApplyNames(sub_args) => handle_apply_names(sub_args),
+        SubCommandEnum::Test(sub_args) => handle_test(load_mapping(args)?, sub_args),
+        SubCommandEnum::Trace(sub_args) => handle_trace(load_mapping(args)?, sub_args),
+        SubCommandEnum::Upload(sub_args) => handle_upload(load_mapping(args)?, sub_args),
+        SubCommandEnum::ExportAsm(sub_args) => handle_export_asm(load_mapping(args)?, sub_args),
+        SubCommandEnum::Stubs(sub_args) => handle_stubs(load_mapping(args)?, sub_args),
+        SubCommandEnum::List(sub_args) => handle_list(load_mapping(args)?, sub_args),
+        SubCommandEnum::DumpAll(sub_args) => handle_dump_all(load_mapping(args)?, sub_args),
+        SubCommandEnum::Check(sub_args) => handle_check(load_mapping(args)?, sub_args),
+        SubCommandEnum::CompareReports(sub_args) => handle_compare_reports(args.color, sub_args),
+        SubCommandEnum::History(sub_args) => handle_history(args.color, sub_args),
+        SubCommandEnum::Graph(sub_args) => handle_graph(sub_args),
+        SubCommandEnum::ValidateMapping(sub_args) => {
+            handle_validate_mapping(load_mapping(args)?, sub_args)
+        }
+        SubCommandEnum::DetectFunctions(sub_args) => {
+            handle_detect_functions(load_mapping(args)?, sub_args)
+        }
+        SubCommandEnum::Explain(sub_args) => handle_explain(load_mapping(args)?, sub_args),
+        SubCommandEnum::Hexdump(sub_args) => handle_hexdump(load_mapping(args)?, sub_args),
+        SubCommandEnum::Cfg(sub_args) => handle_cfg(load_mapping(args)?, sub_args),
+        SubCommandEnum::Callgraph(sub_args) => handle_callgraph(load_mapping(args)?, sub_args),
+        SubCommandEnum::Xref(sub_args) => handle_xref(load_mapping(args)?, sub_args),
+        SubCommandEnum::DataStats(sub_args) => handle_data_stats(load_mapping(args)?, sub_args),
+        SubCommandEnum::Vtables(sub_args) => handle_vtables(load_mapping(args)?, sub_args),
+        SubCommandEnum::StringsDiff(sub_args) => handle_strings_diff(load_mapping(args)?, sub_args),
+        SubCommandEnum::Coverage(sub_args) => handle_coverage(load_mapping(args)?, sub_args),
+        SubCommandEnum::Gaps(sub_args) => handle_gaps(load_mapping(args)?, sub_args),
+        SubCommandEnum::MakeSigs(sub_args) => handle_make_sigs(load_mapping(args)?, sub_args),
+        SubCommandEnum::ApplySigs(sub_args) => handle_apply_sigs(load_mapping(args)?, sub_args),
+        SubCommandEnum::SuggestMatches(sub_args) => {
+            handle_suggest_matches(load_mapping(args)?, sub_args)
+        }
+        SubCommandEnum::FindSimilar(sub_args) => handle_find_similar(load_mapping(args)?, sub_args),
+        SubCommandEnum::Watch(sub_args) => handle_watch(args, sub_args),
+        SubCommandEnum::Tables(sub_args) => handle_tables(load_mapping(args)?, sub_args),
+        SubCommandEnum::Workspace(sub_args) => handle_workspace(args.color, sub_args),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mapping_entries_are_found_by_name_and_address() {
+        let raw_mapping = r#"[[function]]
+name = "a"
+address = 0x401000
+size = 16
+
+[[function]]
+name = "b"
+address = 0x401010
+size = 4096
+"#;
+
+        assert_eq!(
+            find_mapping_entry(raw_mapping, "b", 0x401010),
+            Some((
+                6,
+                "[[function]]\nname = \"b\"\naddress = 0x401010\nsize = 4096".into()
+            ))
+        );
+        assert_eq!(find_mapping_entry(raw_mapping, "b", 0x401000), None);
     }
 }
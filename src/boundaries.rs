@@ -0,0 +1,218 @@
+//! Detection of the functions missing from the mapping.
+//!
+//! The code sections of the original executable are swept for the places
+//! functions usually start at: the entry point, the targets of direct calls,
+//! the `push ebp; mov ebp, esp` prologue and the first instruction after the
+//! `int3` padding aligning functions. Every candidate outside of the mapped
+//! functions extends up to the next known start, trailing padding excluded.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::ops::Range;
+
+use capstone::arch::x86::X86OperandType;
+use capstone::Capstone;
+use object::{File, Object};
+
+use crate::trace::operands;
+use crate::{CodeSection, ExecutableError, Mapping};
+
+/// Byte used by MSVC to pad functions to their alignment.
+const INT3: u8 = 0xcc;
+const NOP: u8 = 0x90;
+
+/// `push ebp; mov ebp, esp` in its two encodings.
+const PROLOGUES: [&[u8]; 2] = [&[0x55, 0x8b, 0xec], &[0x55, 0x89, 0xe5]];
+
+/// Why an address is believed to start a function, strongest first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Evidence {
+    EntryPoint,
+    CallTarget,
+    Prologue,
+    AfterPadding,
+}
+
+impl Evidence {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Evidence::EntryPoint => "entry point",
+            Evidence::CallTarget => "call target",
+            Evidence::Prologue => "prologue",
+            Evidence::AfterPadding => "after padding",
+        }
+    }
+}
+
+/// A function found in the executable but missing from the mapping.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionCandidate {
+    /// Address of the function, relative to the image base of the mapping.
+    pub address: usize,
+    pub size: usize,
+    pub evidence: Evidence,
+}
+
+/// Record a start, keeping the strongest evidence.
+fn add_start(starts: &mut BTreeMap<usize, Evidence>, address: usize, evidence: Evidence) {
+    let entry = starts.entry(address).or_insert(evidence);
+    *entry = (*entry).min(evidence);
+}
+
+fn is_padding(byte: u8) -> bool {
+    byte == INT3 || byte == NOP
+}
+
+/// Targets of the direct calls of a section, found by a linear sweep
+/// resuming after the bytes capstone can't decode.
+fn call_targets(
+    ctx: &Capstone,
+    section: &CodeSection,
+    starts: &mut BTreeMap<usize, Evidence>,
+) -> Result<(), ExecutableError> {
+    let range = section.range();
+    let mut offset = 0;
+
+    while offset < section.data.len() {
+        let instructions =
+            ctx.disasm_all(&section.data[offset..], (section.address + offset) as u64)?;
+        let mut decoded = 0;
+
+        for instruction in instructions.iter() {
+            decoded += instruction.bytes().len();
+
+            if instruction.mnemonic() != Some("call") {
+                continue;
+            }
+
+            if let Some(X86OperandType::Imm(target)) =
+                operands(ctx, instruction)?.first().map(|x| &x.op_type)
+            {
+                if range.contains(&(*target as usize)) {
+                    add_start(starts, *target as usize, Evidence::CallTarget);
+                }
+            }
+        }
+
+        offset += decoded.max(1);
+    }
+
+    Ok(())
+}
+
+/// Prologues and ends of padding in a section.
+fn byte_patterns(section: &CodeSection, starts: &mut BTreeMap<usize, Evidence>) {
+    let data = &section.data[..];
+
+    for offset in 0..data.len() {
+        let address = section.address + offset;
+        let follows_boundary =
+            offset == 0 || is_padding(data[offset - 1]) || data[offset - 1] == 0xc3;
+
+        if follows_boundary && PROLOGUES.iter().any(|x| data[offset..].starts_with(x)) {
+            add_start(starts, address, Evidence::Prologue);
+        }
+
+        // Padding runs end on the alignment of the next function.
+        if offset >= 2
+            && address.is_multiple_of(16)
+            && data[offset] != INT3
+            && data[offset - 1] == INT3
+            && data[offset - 2] == INT3
+        {
+            add_start(starts, address, Evidence::AfterPadding);
+        }
+    }
+}
+
+/// Find the functions of an executable missing from its mapping.
+pub fn detect(
+    ctx: &Capstone,
+    raw_obj: &File,
+    mapping: &Mapping,
+) -> Result<Vec<FunctionCandidate>, ExecutableError> {
+    let code_sections = CodeSection::from_object(raw_obj, mapping.section_name())?;
+    let mut starts = BTreeMap::new();
+
+    let entry = raw_obj.entry() as usize;
+
+    if CodeSection::find(&code_sections, entry).is_some() {
+        add_start(&mut starts, entry, Evidence::EntryPoint);
+    }
+
+    for section in &code_sections {
+        call_targets(ctx, section, &mut starts)?;
+        byte_patterns(section, &mut starts);
+    }
+
+    let known: Vec<Range<usize>> = mapping
+        .function
+        .iter()
+        .flatten()
+        .filter_map(|x| {
+            let address = mapping.rebase(raw_obj, x.address)?;
+
+            Some(address..address.saturating_add(x.size.max(1)))
+        })
+        .collect();
+
+    let bounds: BTreeSet<usize> = starts
+        .keys()
+        .copied()
+        .chain(known.iter().map(|x| x.start))
+        .collect();
+
+    // Mapping addresses are relative to the image base of the mapping.
+    let image_base = mapping.executable.image_base;
+    let unrebase = |address: usize| match image_base {
+        Some(image_base) => address - raw_obj.relative_address_base() as usize + image_base,
+        None => address,
+    };
+
+    let mut res = Vec::new();
+
+    for (&address, &evidence) in &starts {
+        if known.iter().any(|x| x.contains(&address)) {
+            continue;
+        }
+
+        let section = match CodeSection::find(&code_sections, address) {
+            Some(section) => section,
+            None => continue,
+        };
+
+        let end = bounds
+            .range(address + 1..)
+            .next()
+            .map_or(section.range().end, |x| (*x).min(section.range().end));
+
+        let data = &section.data[address - section.address..end - section.address];
+        let size = data.len() - data.iter().rev().take_while(|x| is_padding(**x)).count();
+
+        if size != 0 {
+            res.push(FunctionCandidate {
+                address: unrebase(address),
+                size,
+                evidence,
+            });
+        }
+    }
+
+    Ok(res)
+}
+
+/// Render the candidates as `[[function]]` entries to paste in the mapping.
+pub fn render_toml(candidates: &[FunctionCandidate]) -> Result<String, std::fmt::Error> {
+    let mut res = String::new();
+
+    for candidate in candidates {
+        writeln!(res, "# {}", candidate.evidence.description())?;
+        writeln!(res, "[[function]]")?;
+        writeln!(res, "name = \"sub_{:x}\"", candidate.address)?;
+        writeln!(res, "address = {:#x}", candidate.address)?;
+        writeln!(res, "size = {:#x}", candidate.size)?;
+        writeln!(res)?;
+    }
+
+    Ok(res)
+}
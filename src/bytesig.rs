@@ -0,0 +1,381 @@
+//! Byte signatures identifying functions in other executables.
+//!
+//! A signature is the code of a function of the original executable with
+//! the bytes relocated by the linker turned into wildcards, so that the
+//! same function still matches once linked at another address, the way
+//! FLIRT identifies library functions. Signatures are written one per line,
+//! the hexadecimal pattern followed by the name of the function:
+//!
+//! ```text
+//! 558bec8b4508a1????????5dc3 _get_counter
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt::Write;
+
+use capstone::Capstone;
+
+use crate::diff::{DiffAlgorithm, DiffOptions, RELOCATION_PLACEHOLDER};
+use crate::{Executable, ExecutableError, Function};
+
+/// Text of a wildcard byte in a pattern.
+const WILDCARD: &str = "??";
+
+/// Size of the relocated operands.
+const RELOCATION_SIZE: usize = 4;
+
+/// Number of leading bytes signatures are indexed by when scanning.
+const PREFIX_SIZE: usize = 4;
+
+#[derive(Debug)]
+pub enum ByteSignatureError {
+    InvalidLine { line: usize },
+}
+
+impl std::fmt::Display for ByteSignatureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ByteSignatureError::InvalidLine { line } => {
+                write!(f, "Invalid signature at line {line}!")
+            }
+        }
+    }
+}
+
+impl Error for ByteSignatureError {}
+
+/// Code of a function, `None` for the wildcard bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ByteSignature {
+    pub name: String,
+    pub pattern: Vec<Option<u8>>,
+}
+
+impl ByteSignature {
+    /// Compute the signature of a function, trailing padding excluded.
+    ///
+    /// The operands masked when relocations are ignored, as well as the
+    /// jump tables, become wildcards.
+    pub fn compute(
+        ctx: &Capstone,
+        executable: &Executable,
+        function: &Function,
+    ) -> Result<Self, ExecutableError> {
+        let options = DiffOptions {
+            algorithm: DiffAlgorithm::Instruction,
+            ignore_relocations: true,
+            ..DiffOptions::default()
+        };
+        let data = function.compared_data(ctx, &options)?;
+        let mut pattern = vec![None; data.len()];
+
+        for instruction in executable.decode_function(ctx, function, &options)? {
+            let range = instruction.offset..instruction.offset + instruction.bytes.len();
+            let original = &data[range.clone()];
+            let masked = relocated_bytes(
+                original,
+                &instruction.bytes,
+                instruction.operands == RELOCATION_PLACEHOLDER,
+                executable,
+            );
+
+            for (idx, byte) in original.iter().enumerate() {
+                pattern[range.start + idx] = (!masked[idx]).then_some(*byte);
+            }
+        }
+
+        Ok(Self {
+            name: function.name.clone(),
+            pattern,
+        })
+    }
+
+    /// Number of bytes that aren't wildcards.
+    pub fn fixed_count(&self) -> usize {
+        self.pattern.iter().flatten().count()
+    }
+
+    /// Whether the start of `data` matches the signature.
+    pub fn matches(&self, data: &[u8]) -> bool {
+        data.len() >= self.pattern.len()
+            && self
+                .pattern
+                .iter()
+                .zip(data)
+                .all(|(expected, byte)| expected.is_none_or(|x| x == *byte))
+    }
+
+    /// Leading bytes, when none of them is a wildcard.
+    fn prefix(&self) -> Option<[u8; PREFIX_SIZE]> {
+        let mut res = [0; PREFIX_SIZE];
+
+        for (idx, byte) in self.pattern.get(..PREFIX_SIZE)?.iter().enumerate() {
+            res[idx] = (*byte)?;
+        }
+
+        Some(res)
+    }
+}
+
+impl std::fmt::Display for ByteSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for byte in &self.pattern {
+            match byte {
+                Some(byte) => write!(f, "{byte:02x}")?,
+                None => write!(f, "{WILDCARD}")?,
+            }
+        }
+
+        write!(f, " {}", self.name)
+    }
+}
+
+/// Offsets of the bytes of an instruction masked by normalization.
+///
+/// Masked operands are zeroed, which can't be told apart from operands
+/// containing zeros: the masked bytes are the 4 byte windows holding an
+/// address of the executable, or the target of a branch leaving the
+/// function. Any other difference is masked as is.
+fn relocated_bytes(
+    original: &[u8],
+    masked: &[u8],
+    is_branch: bool,
+    executable: &Executable,
+) -> Vec<bool> {
+    let mut res: Vec<bool> = original.iter().zip(masked).map(|(a, b)| a != b).collect();
+
+    if !res.contains(&true) || original.len() < RELOCATION_SIZE {
+        return res;
+    }
+
+    let is_zeroed = |start: usize| {
+        masked[start..start + RELOCATION_SIZE]
+            .iter()
+            .all(|x| *x == 0)
+    };
+    let is_address = |start: usize| {
+        let value = u32::from_le_bytes([
+            original[start],
+            original[start + 1],
+            original[start + 2],
+            original[start + 3],
+        ]) as usize;
+
+        executable
+            .section_ranges()
+            .iter()
+            .any(|x| x.contains(&value))
+    };
+
+    let windows: Vec<usize> = (0..=original.len() - RELOCATION_SIZE)
+        .filter(|start| is_zeroed(*start))
+        .collect();
+    let mut relocations: Vec<usize> = windows
+        .iter()
+        .copied()
+        .filter(|start| is_address(*start))
+        .collect();
+
+    if relocations.is_empty() {
+        // Branch targets are the last operand, RIP-relative displacements
+        // follow the ModRM byte.
+        let window = if is_branch {
+            windows.last()
+        } else {
+            windows.first()
+        };
+
+        relocations.extend(window);
+    }
+
+    for start in relocations {
+        res[start..start + RELOCATION_SIZE].fill(true);
+    }
+
+    res
+}
+
+/// Compute the signatures of every function of an executable, sorted by
+/// name, leaving out the ones with less than `min_size` fixed bytes.
+pub fn compute_all(
+    ctx: &Capstone,
+    executable: &Executable,
+    min_size: usize,
+) -> Result<Vec<ByteSignature>, ExecutableError> {
+    let mut res = Vec::new();
+
+    for (_, function) in executable.functions_iter() {
+        if executable.is_ignored(&function.name) {
+            continue;
+        }
+
+        let signature = ByteSignature::compute(ctx, executable, function)?;
+
+        if signature.fixed_count() >= min_size {
+            res.push(signature);
+        }
+    }
+
+    res.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(res)
+}
+
+/// Render signatures, one per line.
+pub fn render(signatures: &[ByteSignature]) -> Result<String, std::fmt::Error> {
+    let mut res = String::new();
+
+    for signature in signatures {
+        writeln!(res, "{signature}")?;
+    }
+
+    Ok(res)
+}
+
+/// Parse signatures, ignoring empty lines and `#` comments.
+pub fn parse(content: &str) -> Result<Vec<ByteSignature>, ByteSignatureError> {
+    let mut res = Vec::new();
+
+    for (idx, line) in content.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid = || ByteSignatureError::InvalidLine { line: idx + 1 };
+        let (raw_pattern, name) = line.split_once(char::is_whitespace).ok_or_else(invalid)?;
+
+        if raw_pattern.len() % 2 != 0 || !raw_pattern.is_ascii() {
+            return Err(invalid());
+        }
+
+        let pattern = (0..raw_pattern.len())
+            .step_by(2)
+            .map(|x| match &raw_pattern[x..x + 2] {
+                WILDCARD => Ok(None),
+                byte => u8::from_str_radix(byte, 16).map(Some),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+
+        if pattern.first().copied().flatten().is_none() {
+            return Err(invalid());
+        }
+
+        res.push(ByteSignature {
+            name: name.trim().into(),
+            pattern,
+        });
+    }
+
+    Ok(res)
+}
+
+/// A function identified by its signature.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignatureMatch {
+    pub name: String,
+    pub address: usize,
+    pub size: usize,
+}
+
+/// Result of scanning an executable.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScanResult {
+    /// Functions found at a single address, by address.
+    pub matches: Vec<SignatureMatch>,
+    /// Names of the signatures matching several addresses, or sharing
+    /// their address with another signature.
+    pub ambiguous: Vec<String>,
+}
+
+/// Scan the code sections of an executable for the signatures.
+///
+/// Signatures of functions the executable already knows, by name or by
+/// address, are left out.
+pub fn scan(signatures: &[ByteSignature], executable: &Executable) -> ScanResult {
+    let signatures: Vec<&ByteSignature> = signatures
+        .iter()
+        .filter(|x| executable.get_function(&x.name).is_none())
+        .collect();
+
+    let mut by_prefix: HashMap<[u8; PREFIX_SIZE], Vec<usize>> = HashMap::new();
+    let mut unindexed = Vec::new();
+
+    for (idx, signature) in signatures.iter().enumerate() {
+        match signature.prefix() {
+            Some(prefix) => by_prefix.entry(prefix).or_default().push(idx),
+            None => unindexed.push(idx),
+        }
+    }
+
+    let mut found: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+
+    for range in executable.code_ranges() {
+        let data = executable.code_bytes(range.clone()).unwrap_or_default();
+
+        for offset in 0..data.len() {
+            let address = range.start + offset;
+            let rest = &data[offset..];
+            let indexed = rest
+                .get(..PREFIX_SIZE)
+                .and_then(|x| by_prefix.get(x))
+                .into_iter()
+                .flatten();
+
+            for idx in indexed.chain(&unindexed) {
+                if signatures[*idx].matches(rest) {
+                    found.entry(*idx).or_default().push(address);
+                }
+            }
+        }
+    }
+
+    let mut res = ScanResult::default();
+    let mut by_address: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+
+    for (idx, addresses) in found {
+        match addresses.as_slice() {
+            [address] => by_address.entry(*address).or_default().push(idx),
+            _ => res.ambiguous.push(signatures[idx].name.clone()),
+        }
+    }
+
+    for (address, indices) in by_address {
+        if executable.get_function_by_address(address).is_some() {
+            continue;
+        }
+
+        match indices.as_slice() {
+            [idx] => res.matches.push(SignatureMatch {
+                name: signatures[*idx].name.clone(),
+                address,
+                size: signatures[*idx].pattern.len(),
+            }),
+            _ => res
+                .ambiguous
+                .extend(indices.iter().map(|x| signatures[*x].name.clone())),
+        }
+    }
+
+    res.ambiguous.sort();
+
+    res
+}
+
+/// Render the matches as `[[function]]` entries to paste in the mapping.
+pub fn render_toml(matches: &[SignatureMatch]) -> Result<String, std::fmt::Error> {
+    let mut res = String::new();
+
+    for function in matches {
+        writeln!(res, "[[function]]")?;
+        writeln!(res, "name = \"{}\"", function.name)?;
+        writeln!(res, "address = {:#x}", function.address)?;
+        writeln!(res, "size = {:#x}", function.size)?;
+        writeln!(res)?;
+    }
+
+    Ok(res)
+}
@@ -0,0 +1,331 @@
+//! On-disk cache of disassembly and scores.
+//!
+//! Entries are keyed by a hash of everything the result depends on: the
+//! bytes and address of the functions, the options and the version of
+//! satsuki. Changing any of them changes the key, so stale entries are never
+//! read back and are simply left behind.
+
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use capstone::Capstone;
+
+use crate::diff::DiffOptions;
+use crate::id::Fnv64;
+use crate::limits::FunctionLimits;
+use crate::report::InstructionCounts;
+use crate::{Executable, ExecutableError, Function, GeneratedStats};
+
+/// Key of a cache entry.
+#[derive(Clone, Debug)]
+pub struct CacheKey {
+    hasher: Fnv64,
+}
+
+impl CacheKey {
+    /// Start a key for a kind of entry (`disassembly`, `score`...).
+    pub fn new(kind: &str) -> Self {
+        let mut hasher = Fnv64::default();
+
+        env!("CARGO_PKG_VERSION").hash(&mut hasher);
+        kind.hash(&mut hasher);
+
+        Self { hasher }
+    }
+
+    /// Add a value the entry depends on.
+    pub fn with<T: Hash + ?Sized>(mut self, value: &T) -> Self {
+        value.hash(&mut self.hasher);
+        self
+    }
+
+    /// Add the address and bytes of a function.
+    pub fn with_function(self, function: &Function) -> Self {
+        self.with(&function.address).with(&*function.data)
+    }
+
+    /// Add the names and addresses of the functions of an executable, used
+    /// to resolve call targets.
+    pub fn with_names(self, executable: &Executable) -> Self {
+        let mut names: Vec<(usize, &str)> = executable
+            .functions
+            .values()
+            .map(|x| (x.address, x.name.as_str()))
+            .collect();
+        names.sort();
+
+        self.with(&names)
+    }
+
+    /// Add the address ranges of the sections of an executable, used to
+    /// detect relocated operands.
+    pub fn with_sections(self, executable: &Executable) -> Self {
+        self.with(&executable.section_ranges)
+    }
+
+    fn file_name(&self) -> String {
+        format!("{:016x}", self.hasher.finish())
+    }
+}
+
+/// Directory of cache entries.
+#[derive(Clone, Debug)]
+pub struct DisassemblyCache {
+    dir: PathBuf,
+}
+
+impl DisassemblyCache {
+    /// Use a directory as cache, creating it if needed.
+    pub fn open(dir: &Path) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+
+        Ok(Self { dir: dir.into() })
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<String> {
+        std::fs::read_to_string(self.dir.join(key.file_name())).ok()
+    }
+
+    /// Store an entry. The cache is an optimization, failing to write it isn't an error.
+    pub fn insert(&self, key: &CacheKey, value: &str) {
+        let path = self.dir.join(key.file_name());
+        let temp_path = path.with_extension("tmp");
+
+        // Write then rename so that concurrent runs never read a partial entry.
+        if std::fs::write(&temp_path, value).is_ok() {
+            let _ = std::fs::rename(&temp_path, &path);
+        }
+    }
+
+    /// Read an entry, computing and storing it when missing.
+    pub fn get_or_insert_with<E>(
+        &self,
+        key: &CacheKey,
+        f: impl FnOnce() -> Result<String, E>,
+    ) -> Result<String, E> {
+        if let Some(value) = self.get(key) {
+            return Ok(value);
+        }
+
+        let value = f()?;
+        self.insert(key, &value);
+
+        Ok(value)
+    }
+
+    /// Score of a function against its reimplementation, computed only when
+    /// one of them changed.
+    ///
+    /// `context` identifies the disassembler configuration.
+    pub fn function_diff(
+        &self,
+        ctx: &Capstone,
+        context: &CacheKey,
+        (executable, function): (&Executable, &Function),
+        (other, other_function): (&Executable, &Function),
+        options: &DiffOptions,
+    ) -> Result<f32, ExecutableError> {
+        let mut key = context
+            .clone()
+            .with("score")
+            .with(options)
+            .with_function(function)
+            .with_function(other_function);
+
+        if options.ignore_relocations {
+            key = key.with_sections(executable).with_sections(other);
+        }
+
+        if let Some(score) = self.get(&key).and_then(|x| x.parse().ok()) {
+            return Ok(score);
+        }
+
+        let score =
+            executable.compute_function_diff(ctx, function, other, other_function, options)?;
+        self.insert(&key, &score.to_string());
+
+        Ok(score)
+    }
+
+    /// Same as [`Self::function_diff`], also counting the instructions
+    /// compared. The function may be missing from the reimplementation.
+    pub fn counted_function_diff(
+        &self,
+        ctx: &Capstone,
+        context: &CacheKey,
+        (executable, function): (&Executable, &Function),
+        other: &Executable,
+        options: &DiffOptions,
+    ) -> Result<(Option<f32>, InstructionCounts), ExecutableError> {
+        let other_function = other.paired_function(function, options.pairing);
+        let mut key = context
+            .clone()
+            .with("counted score")
+            .with(options)
+            .with_function(function)
+            .with(&other_function.is_some());
+
+        if let Some(other_function) = other_function {
+            key = key.with_function(other_function);
+        }
+
+        if options.ignore_relocations {
+            key = key.with_sections(executable).with_sections(other);
+        }
+
+        if let Some(entry) = self.get(&key).and_then(|x| serde_json::from_str(&x).ok()) {
+            return Ok(entry);
+        }
+
+        let entry = executable.get_counted_function_stat(ctx, other, function, options)?;
+
+        if let Ok(value) = serde_json::to_string(&entry) {
+            self.insert(&key, &value);
+        }
+
+        Ok(entry)
+    }
+
+    /// Same as [`Executable::generate_stats_with_counts`], reusing the
+    /// cached scores.
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_stats(
+        &self,
+        ctx: &Capstone,
+        context: &CacheKey,
+        executable: &Executable,
+        other: &Executable,
+        options: &DiffOptions,
+        limits: &FunctionLimits,
+        count_instructions: bool,
+    ) -> Result<GeneratedStats, ExecutableError> {
+        executable.generate_stats_by(other, options, limits, |_, function| {
+            if count_instructions {
+                return self
+                    .counted_function_diff(ctx, context, (executable, function), other, options)
+                    .map(|(stat, counts)| (stat, Some(counts)));
+            }
+
+            let stat = match other.paired_function(function, options.pairing) {
+                Some(other_function) => Some(self.function_diff(
+                    ctx,
+                    context,
+                    (executable, function),
+                    (other, other_function),
+                    options,
+                )?),
+                None => None,
+            };
+
+            Ok((stat, None))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_change_with_what_the_entry_depends_on() {
+        let key = |bytes: &[u8]| CacheKey::new("disassembly").with(bytes).file_name();
+
+        assert_eq!(key(&[0x55, 0xc3]), key(&[0x55, 0xc3]));
+        assert_ne!(key(&[0x55, 0xc3]), key(&[0x56, 0xc3]));
+        assert_ne!(
+            CacheKey::new("disassembly").file_name(),
+            CacheKey::new("score").file_name()
+        );
+    }
+
+    #[test]
+    fn entries_are_only_computed_when_missing() {
+        let dir = std::env::temp_dir().join(format!("satsuki-cache-{}", std::process::id()));
+        let cache = DisassemblyCache::open(&dir).unwrap();
+        let key = CacheKey::new("disassembly").with("f");
+
+        let first = cache.get_or_insert_with(&key, || Ok::<_, ()>("push ebp".to_string()));
+        let second = cache.get_or_insert_with(&key, || Err(()));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(first, Ok("push ebp".to_string()));
+        assert_eq!(second, Ok("push ebp".to_string()));
+    }
+
+    #[test]
+    fn cached_stats_match_the_computed_ones() {
+        use capstone::arch::x86::ArchMode;
+        use capstone::arch::BuildsCapstone;
+
+        use crate::FunctionSource;
+
+        let ctx = Capstone::new()
+            .x86()
+            .mode(ArchMode::Mode32)
+            .detail(true)
+            .build()
+            .unwrap();
+        let executable = |functions: &[(&str, usize, Vec<u8>)]| {
+            let mut res = Executable::default();
+
+            for (name, address, data) in functions {
+                res.add_function(
+                    (*name).into(),
+                    *address,
+                    data.clone().into(),
+                    FunctionSource::Pdb,
+                )
+                .unwrap();
+            }
+
+            res
+        };
+        let original = executable(&[
+            ("a", 0x1000, vec![0x55, 0x8b, 0x45, 0x08, 0x5d, 0xc3]),
+            ("b", 0x1010, [vec![0x90; 16], vec![0xc3]].concat()),
+            ("c", 0x1030, vec![0x55, 0x5d, 0xc3]),
+        ]);
+        let reimplementation = executable(&[
+            ("a", 0x2000, vec![0x55, 0x8b, 0x45, 0x0c, 0x5d, 0xc3]),
+            ("b", 0x2010, [vec![0x90; 16], vec![0xc3]].concat()),
+        ]);
+        let options = DiffOptions::default();
+        let limits = FunctionLimits {
+            max_size: Some(8),
+            time_budget: None,
+        };
+
+        let dir = std::env::temp_dir().join(format!("satsuki-stats-{}", std::process::id()));
+        let cache = DisassemblyCache::open(&dir).unwrap();
+        let context = CacheKey::new("satsuki");
+        let cached = |cache: &DisassemblyCache| {
+            cache
+                .generate_stats(
+                    &ctx,
+                    &context,
+                    &original,
+                    &reimplementation,
+                    &options,
+                    &limits,
+                    true,
+                )
+                .unwrap()
+        };
+        let first = cached(&cache);
+        let second = cached(&cache);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let expected = original
+            .generate_stats_with_counts(&ctx, &reimplementation, &options, &limits, true)
+            .unwrap();
+
+        for stats in [first, second] {
+            assert_eq!(stats.stats, expected.stats);
+            assert_eq!(stats.instruction_counts, expected.instruction_counts);
+            assert_eq!(stats.skipped, expected.skipped);
+        }
+
+        assert_eq!(expected.skipped.len(), 1);
+        assert_eq!(expected.instruction_counts.len(), 2);
+    }
+}
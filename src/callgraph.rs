@@ -0,0 +1,294 @@
+//! Whole-program call graph.
+//!
+//! Every call of the mapped functions is resolved to a function or an
+//! import, following jump thunks. Functions calling no other function are
+//! the leaves of the graph, usually the easiest ones to decompile first.
+//! The other way around, [`callers`] lists the call sites of a function.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+use capstone::arch::x86::{X86OperandType, X86Reg};
+use capstone::{Capstone, RegId};
+use serde::Serialize;
+
+use crate::trace::operands;
+use crate::{CallTarget, Executable, ExecutableError, Function};
+
+/// Output format of a call graph.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CallGraphFormat {
+    #[default]
+    Dot,
+    Json,
+}
+
+impl CallGraphFormat {
+    /// Guess the format from the extension of the output file.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().map(|x| x.to_string_lossy().to_lowercase()) {
+            Some(extension) if extension == "json" => CallGraphFormat::Json,
+            _ => CallGraphFormat::Dot,
+        }
+    }
+
+    /// MIME type of graphs in this format.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            CallGraphFormat::Dot => "text/vnd.graphviz",
+            CallGraphFormat::Json => "application/json",
+        }
+    }
+}
+
+impl FromStr for CallGraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(Self::Dot),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "Unknown call graph format \"{s}\", expected one of dot or json"
+            )),
+        }
+    }
+}
+
+/// Callees of a function.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct CallGraphNode {
+    pub address: usize,
+    /// Mapped functions called, by name.
+    pub calls: BTreeSet<String>,
+    /// Imported functions called (`KERNEL32!GetProcAddress`).
+    pub imports: BTreeSet<String>,
+    /// Addresses called that aren't mapped.
+    pub unmapped: BTreeSet<usize>,
+}
+
+/// Calls between the functions of an executable.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct CallGraph {
+    pub functions: BTreeMap<String, CallGraphNode>,
+}
+
+impl CallGraph {
+    /// Resolve the calls of every function of an executable.
+    pub fn build(ctx: &Capstone, executable: &Executable) -> Result<Self, ExecutableError> {
+        let mut functions = BTreeMap::new();
+
+        for (name, function) in executable.functions_iter() {
+            functions.insert(name.clone(), node(ctx, executable, function)?);
+        }
+
+        Ok(Self { functions })
+    }
+
+    /// Whether a function only calls itself or imports.
+    pub fn is_leaf(&self, name: &str) -> bool {
+        self.functions
+            .get(name)
+            .is_some_and(|node| node.unmapped.is_empty() && node.calls.iter().all(|x| x == name))
+    }
+
+    /// Names of the leaf functions, see [`CallGraph::is_leaf`].
+    pub fn leaves(&self) -> impl Iterator<Item = &str> {
+        self.functions
+            .keys()
+            .map(|x| x.as_str())
+            .filter(|x| self.is_leaf(x))
+    }
+
+    /// Names of the functions calling a function.
+    pub fn callers<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.functions
+            .iter()
+            .filter(move |(_, node)| node.calls.contains(name))
+            .map(|(caller, _)| caller.as_str())
+    }
+
+    /// Render the graph in Graphviz DOT format, leaves filled and imports dashed.
+    pub fn to_dot(&self) -> Result<String, std::fmt::Error> {
+        let mut res = String::new();
+        let quote = |name: &str| format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""));
+
+        writeln!(res, "digraph callgraph {{")?;
+        writeln!(res, "    node [shape=box];")?;
+
+        for (name, node) in &self.functions {
+            if self.is_leaf(name) {
+                writeln!(
+                    res,
+                    "    {} [tooltip=\"{:#x}\", style=filled, fillcolor=\"#c8e6c9\"];",
+                    quote(name),
+                    node.address
+                )?;
+            } else {
+                writeln!(
+                    res,
+                    "    {} [tooltip=\"{:#x}\"];",
+                    quote(name),
+                    node.address
+                )?;
+            }
+        }
+
+        let imports: BTreeSet<&String> = self.functions.values().flat_map(|x| &x.imports).collect();
+
+        for import in imports {
+            writeln!(res, "    {} [shape=ellipse, style=dashed];", quote(import))?;
+        }
+
+        for (name, node) in &self.functions {
+            for callee in node.calls.iter().chain(&node.imports) {
+                writeln!(res, "    {} -> {};", quote(name), quote(callee))?;
+            }
+
+            for address in &node.unmapped {
+                writeln!(
+                    res,
+                    "    {} -> {};",
+                    quote(name),
+                    quote(&format!("sub_{address:x}"))
+                )?;
+            }
+        }
+
+        writeln!(res, "}}")?;
+
+        Ok(res)
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        Ok(serde_json::to_string_pretty(self)? + "\n")
+    }
+}
+
+/// Callee of a call instruction.
+#[derive(Clone, Copy, Debug)]
+pub enum Callee<'a> {
+    /// Mapped function or import, see [`Executable::resolve_call_target`].
+    Resolved(CallTarget<'a>),
+    /// Address of a function that isn't mapped.
+    Unmapped(usize),
+}
+
+/// A call instruction of a function.
+#[derive(Clone, Copy, Debug)]
+pub struct CallSite<'a> {
+    /// Offset of the instruction in the calling function.
+    pub offset: usize,
+    pub callee: Callee<'a>,
+}
+
+/// Calls of a function, resolved the same way as the call operands of the
+/// disassembly: direct calls through jump thunks and indirect calls through
+/// the import address table. Other indirect calls are left out.
+pub fn call_sites<'a>(
+    ctx: &Capstone,
+    executable: &'a Executable,
+    function: &Function,
+) -> Result<Vec<CallSite<'a>>, ExecutableError> {
+    let mut res = Vec::new();
+
+    let instructions = ctx.disasm_all(&function.data, function.address as u64)?;
+    let is_invalid = |reg: RegId| u32::from(reg.0) == X86Reg::X86_REG_INVALID;
+
+    for instruction in instructions.iter() {
+        if instruction.mnemonic() != Some("call") {
+            continue;
+        }
+
+        for operand in operands(ctx, instruction)? {
+            let callee = match operand.op_type {
+                X86OperandType::Imm(value) => {
+                    let address = value as usize;
+
+                    match executable.resolve_call_target(address) {
+                        Some(target) => Callee::Resolved(target),
+                        None => Callee::Unmapped(address),
+                    }
+                }
+                // call [slot], through the import address table, absolute in
+                // 32-bit code and RIP-relative in 64-bit code.
+                X86OperandType::Mem(mem) if is_invalid(mem.index()) => {
+                    let slot = if is_invalid(mem.base()) {
+                        mem.disp() as u32 as usize
+                    } else if u32::from(mem.base().0) == X86Reg::X86_REG_RIP {
+                        let next = instruction.address() as usize + instruction.bytes().len();
+
+                        next.wrapping_add_signed(mem.disp() as isize)
+                    } else {
+                        continue;
+                    };
+
+                    match executable.get_data_symbol_by_address(slot) {
+                        Some((symbol, 0)) => Callee::Resolved(CallTarget::Import(symbol)),
+                        _ => continue,
+                    }
+                }
+                _ => continue,
+            };
+
+            res.push(CallSite {
+                offset: instruction.address() as usize - function.address,
+                callee,
+            });
+        }
+    }
+
+    Ok(res)
+}
+
+/// Calls of a function or import named `name` in every function of an
+/// executable, sorted by address.
+pub fn callers<'a>(
+    ctx: &Capstone,
+    executable: &'a Executable,
+    name: &str,
+) -> Result<Vec<(&'a Function, usize)>, ExecutableError> {
+    let mut res = Vec::new();
+
+    for function in executable.functions.values() {
+        for call_site in call_sites(ctx, executable, function)? {
+            if matches!(call_site.callee, Callee::Resolved(target) if target.name() == name) {
+                res.push((function, call_site.offset));
+            }
+        }
+    }
+
+    res.sort_by_key(|(function, offset)| function.address + offset);
+
+    Ok(res)
+}
+
+/// Resolve the calls of a function.
+fn node(
+    ctx: &Capstone,
+    executable: &Executable,
+    function: &Function,
+) -> Result<CallGraphNode, ExecutableError> {
+    let mut res = CallGraphNode {
+        address: function.address,
+        ..CallGraphNode::default()
+    };
+
+    for call_site in call_sites(ctx, executable, function)? {
+        match call_site.callee {
+            Callee::Resolved(CallTarget::Function(callee)) => {
+                res.calls.insert(callee.name.clone());
+            }
+            Callee::Resolved(CallTarget::Import(symbol)) => {
+                res.imports.insert(symbol.name.clone());
+            }
+            Callee::Unmapped(address) => {
+                res.unmapped.insert(address);
+            }
+        }
+    }
+
+    Ok(res)
+}
@@ -0,0 +1,308 @@
+//! Control flow graphs of decoded functions.
+//!
+//! A function is split into basic blocks, runs of instructions only entered
+//! at their first instruction and only left at their last one. Comparing
+//! functions block by block keeps the score of a reimplementation whose
+//! compiler laid out the same blocks in another order, where a linear diff
+//! would see every moved block as removed and inserted.
+//!
+//! Graphs are exported in Graphviz DOT format by [`to_dot`], their blocks
+//! colored after how they match the reimplementation.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::diff::{self, DecodedInstruction};
+
+/// How execution continues after an instruction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Flow {
+    /// Continue with the next instruction, calls included.
+    #[default]
+    Next,
+    /// Unconditional jump, to an offset of the function when known.
+    Jump(Option<usize>),
+    /// Conditional branch, falling through to the next instruction when not taken.
+    Branch(Option<usize>),
+    /// Return from the function, or trap.
+    Return,
+}
+
+impl Flow {
+    /// Same flow without its target, which differs between equivalent blocks.
+    fn kind(self) -> Self {
+        match self {
+            Flow::Jump(_) => Flow::Jump(None),
+            Flow::Branch(_) => Flow::Branch(None),
+            flow => flow,
+        }
+    }
+}
+
+/// A basic block of a function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// Indexes of the instructions of the block.
+    pub range: Range<usize>,
+    /// Indexes of the blocks execution can continue with.
+    pub successors: Vec<usize>,
+}
+
+/// Basic blocks of a function, in the order of their instructions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    pub blocks: Vec<BasicBlock>,
+}
+
+impl ControlFlowGraph {
+    /// Split decoded instructions into basic blocks.
+    ///
+    /// Blocks start at the start of the function, at branch targets and after
+    /// branches. Indirect jumps, such as the ones through jump tables, have
+    /// no known successors.
+    pub fn build(instructions: &[DecodedInstruction]) -> Self {
+        let index_of = |offset: usize| {
+            instructions
+                .binary_search_by_key(&offset, |x| x.offset)
+                .ok()
+        };
+
+        let mut leaders = BTreeSet::new();
+
+        if !instructions.is_empty() {
+            leaders.insert(0);
+        }
+
+        for (idx, instruction) in instructions.iter().enumerate() {
+            match instruction.flow {
+                Flow::Next => continue,
+                Flow::Jump(target) | Flow::Branch(target) => {
+                    leaders.extend(target.and_then(index_of));
+                }
+                Flow::Return => {}
+            }
+
+            if idx + 1 < instructions.len() {
+                leaders.insert(idx + 1);
+            }
+        }
+
+        let starts: Vec<usize> = leaders.into_iter().collect();
+        let block_of = |idx: usize| starts.partition_point(|x| *x <= idx) - 1;
+
+        let blocks = starts
+            .iter()
+            .enumerate()
+            .map(|(block, start)| {
+                let end = starts.get(block + 1).copied().unwrap_or(instructions.len());
+                let fallthrough = (end < instructions.len()).then(|| block + 1);
+                let target = |target: Option<usize>| target.and_then(index_of).map(block_of);
+
+                let mut successors: Vec<usize> = match instructions[end - 1].flow {
+                    Flow::Next => fallthrough.into_iter().collect(),
+                    Flow::Jump(x) => target(x).into_iter().collect(),
+                    Flow::Branch(x) => target(x).into_iter().chain(fallthrough).collect(),
+                    Flow::Return => Vec::new(),
+                };
+                successors.dedup();
+
+                BasicBlock {
+                    range: *start..end,
+                    successors,
+                }
+            })
+            .collect();
+
+        Self { blocks }
+    }
+
+    /// Indexes of the blocks continuing with a block.
+    pub fn predecessors(&self, block: usize) -> Vec<usize> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| x.successors.contains(&block))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Shape of a block, matched before the content of the blocks.
+    fn shape(
+        &self,
+        block: usize,
+        instructions: &[DecodedInstruction],
+    ) -> (bool, usize, usize, Flow) {
+        let range = &self.blocks[block].range;
+
+        (
+            block == 0,
+            self.blocks[block].successors.len(),
+            self.predecessors(block).len(),
+            instructions[range.end - 1].flow.kind(),
+        )
+    }
+}
+
+/// Pair the blocks of two functions.
+///
+/// Blocks of the same shape (entry or not, number of predecessors and
+/// successors, kind of the last instruction) are paired first, then the
+/// remaining ones. Within each pass, the pairs sharing the most instructions
+/// are taken first. Blocks sharing no instruction are left unpaired.
+pub fn match_blocks(
+    (a_cfg, a): (&ControlFlowGraph, &[DecodedInstruction]),
+    (b_cfg, b): (&ControlFlowGraph, &[DecodedInstruction]),
+) -> Vec<(usize, usize)> {
+    let a_keys = diff::keys(a);
+    let b_keys = diff::keys(b);
+    let a_shapes: Vec<_> = (0..a_cfg.blocks.len()).map(|i| a_cfg.shape(i, a)).collect();
+    let b_shapes: Vec<_> = (0..b_cfg.blocks.len()).map(|j| b_cfg.shape(j, b)).collect();
+
+    let mut candidates = Vec::new();
+
+    for (i, a_block) in a_cfg.blocks.iter().enumerate() {
+        for (j, b_block) in b_cfg.blocks.iter().enumerate() {
+            let count = diff::matching_count(
+                &a_keys[a_block.range.clone()],
+                &b_keys[b_block.range.clone()],
+            );
+
+            if count != 0 {
+                let same_shape = a_shapes[i] == b_shapes[j];
+                candidates.push((!same_shape, std::cmp::Reverse(count), i, j));
+            }
+        }
+    }
+
+    candidates.sort();
+
+    let mut a_paired = vec![false; a_cfg.blocks.len()];
+    let mut b_paired = vec![false; b_cfg.blocks.len()];
+    let mut res = Vec::new();
+
+    for (_, _, i, j) in candidates {
+        if !a_paired[i] && !b_paired[j] {
+            a_paired[i] = true;
+            b_paired[j] = true;
+            res.push((i, j));
+        }
+    }
+
+    res.sort();
+    res
+}
+
+/// Compute a match percentage of two decoded functions after pairing their
+/// basic blocks, the instructions of paired blocks being aligned.
+pub fn block_score(a: &[DecodedInstruction], b: &[DecodedInstruction]) -> f32 {
+    let total = a.len().max(b.len());
+
+    if total == 0 {
+        return 0.0;
+    }
+
+    let a_cfg = ControlFlowGraph::build(a);
+    let b_cfg = ControlFlowGraph::build(b);
+
+    let matching_count: usize = match_blocks((&a_cfg, a), (&b_cfg, b))
+        .into_iter()
+        .map(|(i, j)| {
+            diff::matching_count(
+                &diff::keys(&a[a_cfg.blocks[i].range.clone()]),
+                &diff::keys(&b[b_cfg.blocks[j].range.clone()]),
+            )
+        })
+        .sum();
+
+    (matching_count as f32 / total as f32) * 100.0
+}
+
+/// How a block of a function matches the reimplementation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockMatch {
+    /// Paired with a block of the same instructions.
+    Equal,
+    /// Paired with a block of different instructions.
+    Different,
+    /// Not paired with any block.
+    Unpaired,
+}
+
+impl BlockMatch {
+    /// Fill color of the block in DOT graphs.
+    fn color(self) -> &'static str {
+        match self {
+            BlockMatch::Equal => "#c8e6c9",
+            BlockMatch::Different => "#fff3b0",
+            BlockMatch::Unpaired => "#ffcdd2",
+        }
+    }
+}
+
+/// How each block of `a` matches the blocks of `b`.
+pub fn block_matches(
+    (a_cfg, a): (&ControlFlowGraph, &[DecodedInstruction]),
+    (b_cfg, b): (&ControlFlowGraph, &[DecodedInstruction]),
+) -> Vec<BlockMatch> {
+    let mut res = vec![BlockMatch::Unpaired; a_cfg.blocks.len()];
+
+    for (i, j) in match_blocks((a_cfg, a), (b_cfg, b)) {
+        let a_block = &a[a_cfg.blocks[i].range.clone()];
+        let b_block = &b[b_cfg.blocks[j].range.clone()];
+
+        res[i] = if diff::keys(a_block) == diff::keys(b_block) {
+            BlockMatch::Equal
+        } else {
+            BlockMatch::Different
+        };
+    }
+
+    res
+}
+
+/// Escape a line of a DOT label, left-justified.
+fn escape_label_line(line: &str) -> String {
+    line.replace('\\', "\\\\").replace('"', "\\\"") + "\\l"
+}
+
+/// Render the graph of a function in Graphviz DOT format, the blocks filled
+/// after how they match the reimplementation when `matches` are given.
+pub fn to_dot(
+    name: &str,
+    (cfg, instructions): (&ControlFlowGraph, &[DecodedInstruction]),
+    matches: Option<&[BlockMatch]>,
+) -> Result<String, std::fmt::Error> {
+    let mut res = String::new();
+
+    writeln!(res, "digraph \"{}\" {{", name.replace('"', "\\\""))?;
+    writeln!(res, "    node [shape=box, fontname=\"monospace\"];")?;
+
+    for (idx, block) in cfg.blocks.iter().enumerate() {
+        let block_instructions = &instructions[block.range.clone()];
+        let mut label = escape_label_line(&format!("+{:#06x}:", block_instructions[0].offset));
+
+        for instruction in block_instructions {
+            label += &escape_label_line(&format!("    {instruction}"));
+        }
+
+        match matches.and_then(|x| x.get(idx)) {
+            Some(status) => writeln!(
+                res,
+                "    b{idx} [label=\"{label}\", style=filled, fillcolor=\"{}\"];",
+                status.color()
+            )?,
+            None => writeln!(res, "    b{idx} [label=\"{label}\"];")?,
+        }
+    }
+
+    for (idx, block) in cfg.blocks.iter().enumerate() {
+        for successor in &block.successors {
+            writeln!(res, "    b{idx} -> b{successor};")?;
+        }
+    }
+
+    writeln!(res, "}}")?;
+
+    Ok(res)
+}
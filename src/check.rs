@@ -0,0 +1,89 @@
+//! Match thresholds enforced in continuous integration.
+//!
+//! The mapping can set a minimum global percentage (`min_global_percent`)
+//! and a minimum percentage per function (`min_percent`). Any function
+//! falling below its threshold is a regression.
+
+use std::fmt::Write;
+
+use crate::format::PercentageFormat;
+use crate::report::StatsReport;
+use crate::Mapping;
+
+/// A threshold that isn't met.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    /// Name of the function, `None` for the global percentage.
+    pub name: Option<String>,
+    pub expected: f32,
+    /// Match percentage, `None` when the function is missing from the reimplementation.
+    pub actual: Option<f32>,
+}
+
+/// Compare a report with the thresholds of the mapping.
+///
+/// Returns the thresholds that aren't met and the number of thresholds checked.
+pub fn check(report: &StatsReport, mapping: &Mapping) -> (Vec<Violation>, usize) {
+    let mut res = Vec::new();
+    let mut count = 0;
+
+    if let Some(expected) = mapping.min_global_percent {
+        count += 1;
+
+        if report.global.percent < expected {
+            res.push(Violation {
+                name: None,
+                expected,
+                actual: Some(report.global.percent),
+            });
+        }
+    }
+
+    for function in &report.functions {
+        let expected = match mapping
+            .get_function_def(&function.name)
+            .and_then(|x| x.min_percent)
+        {
+            Some(expected) => expected,
+            None => continue,
+        };
+
+        count += 1;
+
+        if function.percent.unwrap_or(0.0) < expected {
+            res.push(Violation {
+                name: Some(function.name.clone()),
+                expected,
+                actual: function.percent,
+            });
+        }
+    }
+
+    res.sort_by(|a, b| a.name.cmp(&b.name));
+
+    (res, count)
+}
+
+/// Render the violations, one per line.
+pub fn render(
+    violations: &[Violation],
+    percentage_format: &PercentageFormat,
+) -> Result<String, std::fmt::Error> {
+    let mut res = String::new();
+
+    for violation in violations {
+        let actual = match violation.actual {
+            Some(actual) => percentage_format.format(actual),
+            None => "MISSING".into(),
+        };
+
+        writeln!(
+            res,
+            "{}: {actual}, expected at least {}",
+            violation.name.as_deref().unwrap_or("GLOBAL"),
+            percentage_format.format(violation.expected)
+        )?;
+    }
+
+    Ok(res)
+}
@@ -0,0 +1,105 @@
+//! ANSI coloring of terminal output.
+//!
+//! Every colored output goes through a [`Colorizer`] built from a
+//! [`ColorChoice`], so `--color`, `NO_COLOR` and terminal detection behave
+//! the same in every subcommand.
+
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+/// Whether to color output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    Always,
+    Never,
+    /// Color when writing to a terminal and `NO_COLOR` is not set.
+    #[default]
+    Auto,
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            "auto" => Ok(Self::Auto),
+            _ => Err(format!(
+                "Unknown color choice \"{s}\", expected always, never or auto"
+            )),
+        }
+    }
+}
+
+impl ColorChoice {
+    /// Whether `NO_COLOR` is set to a non-empty value, see <https://no-color.org>.
+    fn no_color_env() -> bool {
+        std::env::var_os("NO_COLOR").is_some_and(|x| !x.is_empty())
+    }
+
+    /// Resolve the choice for an output, given whether it is a terminal.
+    pub fn enabled(&self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => is_terminal && !Self::no_color_env(),
+        }
+    }
+}
+
+/// Colors used in reports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    Green,
+    Yellow,
+    Red,
+}
+
+impl Color {
+    fn ansi_code(&self) -> &'static str {
+        match self {
+            Color::Green => "\x1b[32m",
+            Color::Yellow => "\x1b[33m",
+            Color::Red => "\x1b[31m",
+        }
+    }
+
+    /// Color associated to a match percentage, `None` meaning missing.
+    pub fn for_percentage(value: Option<f32>) -> Self {
+        match value {
+            Some(value) if value >= 100.0 => Color::Green,
+            Some(value) if value > 0.0 => Color::Yellow,
+            _ => Color::Red,
+        }
+    }
+}
+
+/// Apply colors to text when enabled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Colorizer {
+    enabled: bool,
+}
+
+impl Colorizer {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+
+    /// Enable colors on stdout according to `choice`.
+    pub fn for_stdout(choice: ColorChoice) -> Self {
+        Self::new(choice.enabled(std::io::stdout().is_terminal()))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn paint(&self, color: Color, text: &str) -> String {
+        if self.enabled {
+            format!("{}{text}\x1b[0m", color.ansi_code())
+        } else {
+            text.into()
+        }
+    }
+}
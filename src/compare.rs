@@ -0,0 +1,196 @@
+//! Comparison of two stats reports.
+//!
+//! Functions are matched by their stable identifier, so renaming a function
+//! between two reports isn't seen as one function disappearing and another
+//! one appearing.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::color::{Color, Colorizer};
+use crate::format::PercentageFormat;
+use crate::report::{FunctionReport, StatsReport};
+
+/// How a function changed between two reports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChangeKind {
+    Regressed,
+    Disappeared,
+    Improved,
+    Appeared,
+}
+
+impl ChangeKind {
+    /// Whether the change is a regression.
+    pub fn is_regression(&self) -> bool {
+        matches!(self, ChangeKind::Regressed | ChangeKind::Disappeared)
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            ChangeKind::Regressed => "Regressed",
+            ChangeKind::Disappeared => "Disappeared",
+            ChangeKind::Improved => "Improved",
+            ChangeKind::Appeared => "Appeared",
+        }
+    }
+}
+
+/// Change of a function between two reports.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FunctionChange {
+    pub name: String,
+    pub kind: ChangeKind,
+    /// Match percentage in the old report, `None` when missing.
+    pub before: Option<f32>,
+    /// Match percentage in the new report, `None` when missing.
+    pub after: Option<f32>,
+}
+
+impl FunctionChange {
+    pub fn delta(&self) -> f32 {
+        self.after.unwrap_or(0.0) - self.before.unwrap_or(0.0)
+    }
+}
+
+pub(crate) fn key(function: &FunctionReport) -> &str {
+    // Reports written before identifiers were introduced.
+    if function.id.is_empty() {
+        &function.name
+    } else {
+        &function.id
+    }
+}
+
+/// Functions that changed between two reports, sorted by kind and name.
+pub fn compare(before: &StatsReport, after: &StatsReport) -> Vec<FunctionChange> {
+    let previous: HashMap<&str, &FunctionReport> =
+        before.functions.iter().map(|x| (key(x), x)).collect();
+    let current: HashMap<&str, &FunctionReport> =
+        after.functions.iter().map(|x| (key(x), x)).collect();
+    let mut res = Vec::new();
+
+    for function in &after.functions {
+        let (kind, before) = match previous.get(key(function)) {
+            None => (ChangeKind::Appeared, None),
+            Some(old) => {
+                let (old_percent, new_percent) = (
+                    old.percent.unwrap_or(-1.0),
+                    function.percent.unwrap_or(-1.0),
+                );
+
+                if new_percent > old_percent {
+                    (ChangeKind::Improved, old.percent)
+                } else if new_percent < old_percent {
+                    (ChangeKind::Regressed, old.percent)
+                } else {
+                    continue;
+                }
+            }
+        };
+
+        res.push(FunctionChange {
+            name: function.name.clone(),
+            kind,
+            before,
+            after: function.percent,
+        });
+    }
+
+    for function in &before.functions {
+        if !current.contains_key(key(function)) {
+            res.push(FunctionChange {
+                name: function.name.clone(),
+                kind: ChangeKind::Disappeared,
+                before: function.percent,
+                after: None,
+            });
+        }
+    }
+
+    res.sort_by(|a, b| (a.kind, &a.name).cmp(&(b.kind, &b.name)));
+    res
+}
+
+pub(crate) fn format_percent(percentage_format: &PercentageFormat, value: Option<f32>) -> String {
+    match value {
+        Some(value) => percentage_format.format(value),
+        None => "MISSING".into(),
+    }
+}
+
+pub(crate) fn format_delta(
+    delta: f32,
+    percentage_format: &PercentageFormat,
+    colorizer: &Colorizer,
+) -> String {
+    let color = if delta >= 0.0 {
+        Color::Green
+    } else {
+        Color::Red
+    };
+
+    colorizer.paint(color, &percentage_format.format_delta(delta))
+}
+
+/// Render a change as `name: before -> after (delta)`.
+pub fn render_change(
+    change: &FunctionChange,
+    percentage_format: &PercentageFormat,
+    colorizer: &Colorizer,
+) -> String {
+    format!(
+        "{}: {} -> {} ({})",
+        change.name,
+        format_percent(percentage_format, change.before),
+        format_percent(percentage_format, change.after),
+        format_delta(change.delta(), percentage_format, colorizer)
+    )
+}
+
+/// Render the changes grouped by kind, followed by the global percentages.
+pub fn render(
+    changes: &[FunctionChange],
+    before: &StatsReport,
+    after: &StatsReport,
+    percentage_format: &PercentageFormat,
+    colorizer: &Colorizer,
+) -> Result<String, std::fmt::Error> {
+    let mut res = String::new();
+    let mut current_kind = None;
+
+    for change in changes {
+        if current_kind != Some(change.kind) {
+            if current_kind.is_some() {
+                writeln!(res)?;
+            }
+
+            writeln!(res, "{}:", change.kind.title())?;
+            current_kind = Some(change.kind);
+        }
+
+        writeln!(
+            res,
+            "  {}",
+            render_change(change, percentage_format, colorizer)
+        )?;
+    }
+
+    if changes.is_empty() {
+        writeln!(res, "No function changed")?;
+    }
+
+    writeln!(
+        res,
+        "\nGLOBAL: {} -> {} ({})",
+        percentage_format.format(before.global.percent),
+        percentage_format.format(after.global.percent),
+        format_delta(
+            after.global.percent - before.global.percent,
+            percentage_format,
+            colorizer
+        )
+    )?;
+
+    Ok(res)
+}
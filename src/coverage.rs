@@ -0,0 +1,182 @@
+//! Coverage of the code sections by the known functions.
+//!
+//! The bytes of the code sections claimed by no function of the mapping or
+//! of the symbols are either padding between functions or code nobody has
+//! identified yet. The size of the latter tells how complete the function
+//! inventory is, and their disassembly helps finding the forgotten functions.
+
+use std::fmt::Write;
+use std::ops::Range;
+
+use capstone::Capstone;
+
+use crate::{Executable, ExecutableError};
+
+/// Bytes used by compilers to pad functions to their alignment.
+const PADDING: [u8; 2] = [0xcc, 0x90];
+
+/// Coverage of the code sections of an executable.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Coverage {
+    /// Bytes of the code sections.
+    pub total: usize,
+    /// Bytes of the known functions.
+    pub covered: usize,
+    /// Bytes of padding around the unclaimed ranges.
+    pub padding: usize,
+    pub function_count: usize,
+    /// Unclaimed ranges, padding excluded, by address.
+    pub gaps: Vec<Range<usize>>,
+}
+
+impl Coverage {
+    /// Compute the coverage of the code sections of `executable` by its
+    /// functions and the ones of the other executables loaded from the same
+    /// file, such as the one loaded from its symbols.
+    pub fn compute(executable: &Executable, others: &[&Executable]) -> Self {
+        let mut functions: Vec<Range<usize>> = std::iter::once(executable)
+            .chain(others.iter().copied())
+            .flat_map(|x| x.functions_iter())
+            .map(|(_, function)| function.address..function.address + function.data.len())
+            .filter(|x| !x.is_empty())
+            .collect();
+        functions.sort_by_key(|x| (x.start, x.end));
+        functions.dedup_by_key(|x| x.start);
+
+        let mut res = Self {
+            function_count: functions.len(),
+            ..Self::default()
+        };
+
+        for section in executable.code_ranges() {
+            res.total += section.len();
+
+            let mut address = section.start;
+            let mut unclaimed = Vec::new();
+
+            for function in &functions {
+                let start = function.start.clamp(section.start, section.end);
+                let end = function.end.clamp(section.start, section.end);
+
+                if start > address {
+                    unclaimed.push(address..start);
+                }
+
+                address = address.max(end);
+            }
+
+            if address < section.end {
+                unclaimed.push(address..section.end);
+            }
+
+            res.covered += section.len() - unclaimed.iter().map(|x| x.len()).sum::<usize>();
+
+            for range in unclaimed {
+                let bytes = executable.code_bytes(range.clone()).unwrap_or_default();
+                let is_padding = |x: &&u8| PADDING.contains(x);
+                let leading = bytes.iter().take_while(is_padding).count();
+                let trailing = bytes[leading..].iter().rev().take_while(is_padding).count();
+                let gap = range.start + leading..range.end - trailing;
+
+                res.padding += leading + trailing;
+
+                if !gap.is_empty() {
+                    res.gaps.push(gap);
+                }
+            }
+        }
+
+        res
+    }
+
+    /// Percentage of the code claimed by functions, padding excluded.
+    pub fn percent(&self) -> f32 {
+        match self.total - self.padding {
+            0 => 0.0,
+            code => self.covered as f32 / code as f32 * 100.0,
+        }
+    }
+
+    /// Render the coverage followed by the `count` largest unclaimed ranges.
+    pub fn render(&self, count: usize) -> Result<String, std::fmt::Error> {
+        let mut res = String::new();
+        let unknown: usize = self.gaps.iter().map(|x| x.len()).sum();
+
+        writeln!(
+            res,
+            "Coverage: {:.2}% ({} of {} bytes in {} functions, {} bytes of padding)",
+            self.percent(),
+            self.covered,
+            self.total,
+            self.function_count,
+            self.padding
+        )?;
+        writeln!(
+            res,
+            "{unknown} bytes in {} unknown regions",
+            self.gaps.len()
+        )?;
+
+        let mut largest: Vec<&Range<usize>> = self.gaps.iter().collect();
+        largest.sort_by_key(|x| (std::cmp::Reverse(x.len()), x.start));
+
+        for range in largest.into_iter().take(count) {
+            writeln!(
+                res,
+                "{:#010x}-{:#010x}  {:>8} bytes",
+                range.start,
+                range.end,
+                range.len()
+            )?;
+        }
+
+        Ok(res)
+    }
+}
+
+/// Render unclaimed ranges, each followed by the disassembly of its first
+/// `preview` instructions.
+pub fn render_gaps(
+    ctx: &Capstone,
+    executable: &Executable,
+    gaps: &[Range<usize>],
+    preview: usize,
+) -> Result<String, ExecutableError> {
+    let mut res = String::new();
+
+    for gap in gaps {
+        writeln!(
+            res,
+            "{:#010x}-{:#010x}  {} bytes",
+            gap.start,
+            gap.end,
+            gap.len()
+        )?;
+
+        if preview == 0 {
+            continue;
+        }
+
+        let bytes = executable.code_bytes(gap.clone()).unwrap_or_default();
+        let instructions = ctx.disasm_count(bytes, gap.start as u64, preview)?;
+
+        for instruction in instructions.iter() {
+            writeln!(
+                res,
+                "    {:08x}  {} {}",
+                instruction.address(),
+                instruction.mnemonic().unwrap_or_default(),
+                instruction.op_str().unwrap_or_default()
+            )?;
+        }
+    }
+
+    writeln!(
+        res,
+        "{} gaps, {} bytes",
+        gaps.len(),
+        gaps.iter().map(|x| x.len()).sum::<usize>()
+    )?;
+
+    Ok(res)
+}
@@ -0,0 +1,96 @@
+//! Comparison of global variables.
+//!
+//! Global tables (sprite metadata, stage scripts, lookup tables) are part of
+//! the reimplementation just like functions. The `[[data]]` entries of the
+//! mapping are compared byte by byte with the variables of the same name in
+//! the reimplementation.
+
+use std::fmt::Write;
+
+use crate::{Executable, Mapping};
+
+/// Match of a global variable of the mapping.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataStat {
+    pub name: String,
+    pub address: usize,
+    /// Initialized bytes of the original variable.
+    pub size: usize,
+    /// Percentage of matching bytes, `None` when the reimplementation
+    /// doesn't define the variable.
+    pub percent: Option<f32>,
+}
+
+/// Compare the global variables of the mapping, sorted by address.
+///
+/// Variables without initialized bytes in the original, such as the ones of
+/// `.bss`, are left out.
+pub fn data_stats(
+    original: &Executable,
+    reimplementation: &Executable,
+    mapping: &Mapping,
+) -> Vec<DataStat> {
+    let mut res: Vec<DataStat> = mapping
+        .data
+        .iter()
+        .filter_map(|data| {
+            let symbol = original.get_data_symbol(&data.name)?;
+            let size = original.get_data(&data.name)?.len();
+
+            (size != 0).then(|| DataStat {
+                name: data.name.clone(),
+                address: symbol.address,
+                size,
+                percent: original.compute_data_diff(reimplementation, &data.name),
+            })
+        })
+        .collect();
+
+    res.sort_by_key(|x| x.address);
+    res
+}
+
+/// Percentage of matching bytes over all the variables, missing ones
+/// counting as not matching.
+pub fn global_percent(stats: &[DataStat]) -> f32 {
+    let total: usize = stats.iter().map(|x| x.size).sum();
+    let matching: f32 = stats
+        .iter()
+        .map(|x| x.percent.unwrap_or(0.0) / 100.0 * x.size as f32)
+        .sum();
+
+    match total {
+        0 => 0.0,
+        _ => matching / total as f32 * 100.0,
+    }
+}
+
+/// Render the stats, one variable per line: address, size, percentage and
+/// name, followed by the global percentage.
+pub fn render(stats: &[DataStat]) -> Result<String, std::fmt::Error> {
+    let mut res = String::new();
+
+    for stat in stats {
+        let percent = match stat.percent {
+            Some(percent) => format!("{percent:.2}%"),
+            None => "missing".into(),
+        };
+
+        writeln!(
+            res,
+            "{:#010x}  {:>8}  {percent:>8}  {}",
+            stat.address, stat.size, stat.name
+        )?;
+    }
+
+    let matching = stats.iter().filter(|x| x.percent == Some(100.0)).count();
+
+    writeln!(
+        res,
+        "Global: {:.2}% ({matching}/{} variables matching)",
+        global_percent(stats),
+        stats.len()
+    )?;
+
+    Ok(res)
+}
@@ -0,0 +1,198 @@
+//! Creation of decomp.me scratches.
+
+use serde::{Deserialize, Serialize};
+
+/// Default decomp.me instance.
+pub const DEFAULT_API_URL: &str = "https://decomp.me";
+
+/// Compiler used to build the original game.
+pub const DEFAULT_COMPILER: &str = "msvc7.0";
+
+/// Default compiler flags, usually refined in the scratch afterwards.
+pub const DEFAULT_COMPILER_FLAGS: &str = "/O2";
+
+#[derive(Debug)]
+pub enum UploadError {
+    HttpError { error: Box<ureq::Error> },
+    InvalidResponse { error: std::io::Error },
+}
+
+impl std::fmt::Display for UploadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UploadError::HttpError { error } => write!(f, "Scratch creation failed: {error}"),
+            UploadError::InvalidResponse { error } => {
+                write!(f, "Unexpected response from decomp.me: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for UploadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            UploadError::HttpError { error } => Some(error),
+            UploadError::InvalidResponse { error } => Some(error),
+        }
+    }
+}
+
+impl From<ureq::Error> for UploadError {
+    fn from(error: ureq::Error) -> Self {
+        UploadError::HttpError {
+            error: Box::new(error),
+        }
+    }
+}
+
+/// Parameters of a new scratch.
+#[derive(Clone, Debug, Serialize)]
+pub struct ScratchRequest {
+    pub name: String,
+    pub platform: String,
+    pub compiler: String,
+    pub compiler_flags: String,
+    pub target_asm: String,
+    pub context: String,
+    pub diff_label: String,
+}
+
+impl ScratchRequest {
+    /// Build a request for a win32 function from its disassembly at address zero.
+    pub fn new(name: &str, disassembly: &str) -> Self {
+        Self {
+            name: name.into(),
+            platform: "win32".into(),
+            compiler: DEFAULT_COMPILER.into(),
+            compiler_flags: DEFAULT_COMPILER_FLAGS.into(),
+            target_asm: format_target_asm(name, disassembly),
+            context: String::new(),
+            diff_label: name.into(),
+        }
+    }
+}
+
+/// A scratch created on decomp.me.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Scratch {
+    pub slug: String,
+    pub claim_token: Option<String>,
+}
+
+impl Scratch {
+    /// URL of the scratch.
+    pub fn url(&self, api_url: &str) -> String {
+        format!("{}/scratch/{}", api_url.trim_end_matches('/'), self.slug)
+    }
+
+    /// URL claiming the ownership of an anonymous scratch.
+    pub fn claim_url(&self, api_url: &str) -> Option<String> {
+        self.claim_token
+            .as_ref()
+            .map(|token| format!("{}/claim?token={token}", self.url(api_url)))
+    }
+}
+
+/// String instructions, whose operands are implied by their size suffix.
+const STRING_INSTRUCTIONS: &[&str] = &["movs", "stos", "lods", "scas", "cmps", "ins", "outs"];
+
+/// Instructions whose memory operand size is not implied by their register operands.
+const SIZE_HINT_REQUIRED: &[&str] = &[
+    "movzx", "movsx", "shl", "shr", "sal", "sar", "rol", "ror", "rcl", "rcr",
+];
+
+/// Size hint matching a general purpose register.
+fn register_size_hint(name: &str) -> Option<&'static str> {
+    match name {
+        "al" | "ah" | "bl" | "bh" | "cl" | "ch" | "dl" | "dh" => Some("byte"),
+        "ax" | "bx" | "cx" | "dx" | "si" | "di" | "bp" | "sp" => Some("word"),
+        "eax" | "ebx" | "ecx" | "edx" | "esi" | "edi" | "ebp" | "esp" => Some("dword"),
+        _ => None,
+    }
+}
+
+pub(crate) fn is_string_instruction(mnemonic: &str) -> bool {
+    STRING_INSTRUCTIONS.iter().any(|base| {
+        mnemonic
+            .strip_prefix(base)
+            .map(|suffix| matches!(suffix, "b" | "w" | "d"))
+            .unwrap_or(false)
+    })
+}
+
+/// Rewrite a disassembled line in the dialect accepted by the decomp.me MSVC preset.
+///
+/// String instructions use their short form (`rep movsd`) and size hints
+/// implied by a register operand are dropped.
+pub fn to_msvc_dialect(line: &str) -> String {
+    let trimmed = line.trim_start();
+    let indent = &line[..line.len() - trimmed.len()];
+
+    let mut words: Vec<&str> = trimmed.split(' ').collect();
+    let prefix_count = words
+        .iter()
+        .take_while(|word| matches!(**word, "rep" | "repe" | "repne" | "lock"))
+        .count();
+
+    let mnemonic = match words.get(prefix_count) {
+        Some(mnemonic) if !mnemonic.ends_with(':') => *mnemonic,
+        _ => return line.into(),
+    };
+
+    if is_string_instruction(mnemonic) {
+        words.truncate(prefix_count + 1);
+        return format!("{indent}{}", words.join(" "));
+    }
+
+    let instruction = words[..=prefix_count].join(" ");
+    let operands = words[prefix_count + 1..].join(" ");
+
+    if operands.is_empty() || SIZE_HINT_REQUIRED.contains(&mnemonic) {
+        return line.into();
+    }
+
+    let operands: Vec<&str> = operands.split(", ").collect();
+    let register_sizes: Vec<&str> = operands
+        .iter()
+        .filter_map(|operand| register_size_hint(operand))
+        .collect();
+
+    let operands: Vec<String> = operands
+        .iter()
+        .map(|operand| {
+            for size in &register_sizes {
+                if let Some(stripped) = operand.strip_prefix(&format!("{size} ptr ")) {
+                    return stripped.to_string();
+                }
+            }
+
+            operand.to_string()
+        })
+        .collect();
+
+    format!("{indent}{instruction} {}", operands.join(", "))
+}
+
+/// Format a disassembly as target assembly accepted by the decomp.me assembler.
+///
+/// The function label is quoted as C++ names are not valid GNU as symbols.
+pub fn format_target_asm(name: &str, disassembly: &str) -> String {
+    let mut res = format!(".intel_syntax noprefix\n\"{name}\":\n");
+
+    for line in disassembly.lines() {
+        res.push_str(&to_msvc_dialect(line));
+        res.push('\n');
+    }
+
+    res
+}
+
+/// Create a scratch on the given decomp.me instance.
+pub fn create_scratch(api_url: &str, request: &ScratchRequest) -> Result<Scratch, UploadError> {
+    let url = format!("{}/api/scratch", api_url.trim_end_matches('/'));
+
+    ureq::post(&url)
+        .send_json(request)?
+        .into_json()
+        .map_err(|error| UploadError::InvalidResponse { error })
+}
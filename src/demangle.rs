@@ -0,0 +1,62 @@
+//! Symbol undecoration.
+//!
+//! Public symbols of PDBs keep their decorated names (`?bar@Foo@@QAEXH@Z`).
+//! They are turned back into readable C++ names (`Foo::bar(int)`) for
+//! display only; the decorated name stays the key used for lookups.
+//!
+//! The MSVC scheme is handled by `msvc-demangler`, leaving out the access
+//! specifiers, calling conventions and return types. Names it can't parse
+//! are left decorated.
+//!
+//! Reimplementations built with MinGW use the Itanium scheme instead
+//! (`_ZN6Player6updateEv`), which is handled by `cpp_demangle`.
+
+use std::borrow::Cow;
+
+use msvc_demangler::DemangleFlags;
+
+/// Undecorate an MSVC decorated name, `None` when it isn't one or can't be parsed.
+pub fn demangle(name: &str) -> Option<String> {
+    if !name.starts_with('?') {
+        return None;
+    }
+
+    let flags = DemangleFlags::NO_ACCESS_SPECIFIERS
+        | DemangleFlags::NO_MEMBER_TYPE
+        | DemangleFlags::NO_FUNCTION_RETURNS
+        | DemangleFlags::NO_MS_KEYWORDS
+        | DemangleFlags::SPACE_AFTER_COMMA;
+
+    msvc_demangler::demangle(name, flags).ok()
+}
+
+/// Demangle an Itanium mangled name, `None` when it isn't one or can't be parsed.
+///
+/// The extra underscore prefixed to symbols of 32-bit Windows targets is
+/// accepted.
+pub fn itanium(name: &str) -> Option<String> {
+    let name = match name.strip_prefix('_') {
+        Some(stripped) if stripped.starts_with("_Z") => stripped,
+        _ => name,
+    };
+
+    if !name.starts_with("_Z") {
+        return None;
+    }
+
+    cpp_demangle::Symbol::new(name)
+        .ok()?
+        .demangle(&cpp_demangle::DemangleOptions::default())
+        .ok()
+}
+
+/// Name to display, undecorated when requested and possible.
+pub fn display_name(name: &str, demangle: bool) -> Cow<'_, str> {
+    if demangle {
+        if let Some(res) = self::demangle(name) {
+            return Cow::Owned(res);
+        }
+    }
+
+    Cow::Borrowed(name)
+}
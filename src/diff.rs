@@ -0,0 +1,889 @@
+//! Function comparison algorithms.
+
+use std::cell::Cell;
+use std::ops::Range;
+use std::str::FromStr;
+use std::time::Instant;
+
+use capstone::arch::x86::{X86Operand, X86OperandType, X86Reg};
+use capstone::arch::ArchOperand;
+use capstone::InsnGroupType::{CS_GRP_BRANCH_RELATIVE, CS_GRP_IRET, CS_GRP_JUMP, CS_GRP_RET};
+use capstone::{Capstone, Insn, RegId, RegIdInt};
+use similar::{capture_diff_slices_deadline, Algorithm, DiffOp, DiffTag};
+
+use crate::cfg::{self, Flow};
+use crate::color::{Color, Colorizer};
+use crate::trace::operands;
+use crate::x87::{self, X87Rules};
+use crate::{replace_literal, ExecutableError};
+
+/// Encoding of the one-byte `nop`, used for normalized filler.
+const NOP: u8 = 0x90;
+
+/// Text used in place of an operand masked by normalization.
+pub const RELOCATION_PLACEHOLDER: &str = "<reloc>";
+
+/// Text used in place of an immediate operand masked by fuzzy matching.
+pub const IMMEDIATE_PLACEHOLDER: &str = "<imm>";
+
+/// Algorithm used to compare two functions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DiffAlgorithm {
+    /// Compare bytes one to one.
+    #[default]
+    Raw,
+    /// Compare decoded instructions one to one.
+    Instruction,
+    /// Align decoded instructions using Myers diff before comparing them.
+    Aligned,
+    /// Pair the basic blocks of the functions before aligning their
+    /// instructions, so that reordered blocks still match.
+    Blocks,
+}
+
+impl FromStr for DiffAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raw" => Ok(Self::Raw),
+            "instruction" => Ok(Self::Instruction),
+            "aligned" => Ok(Self::Aligned),
+            "blocks" => Ok(Self::Blocks),
+            _ => Err(format!(
+                "Unknown diff algorithm \"{s}\", expected one of raw, instruction, aligned or blocks"
+            )),
+        }
+    }
+}
+
+/// How the functions of the original executable are paired with the
+/// functions of the reimplementation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Pairing {
+    /// Functions with the same name.
+    #[default]
+    Name,
+    /// Functions at the same address, for reimplementations linked at the
+    /// addresses of the original. Names don't need to match, so the mapping
+    /// can use plain names while the PDB has decorated ones.
+    Address,
+}
+
+impl FromStr for Pairing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(Self::Name),
+            "address" => Ok(Self::Address),
+            _ => Err(format!(
+                "Unknown pairing \"{s}\", expected one of name or address"
+            )),
+        }
+    }
+}
+
+/// Options controlling how two functions are compared.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DiffOptions {
+    pub algorithm: DiffAlgorithm,
+    /// Mask operands that are relocated by the linker (call targets outside
+    /// of the function and addresses pointing into the executable sections).
+    pub ignore_relocations: bool,
+    pub pairing: Pairing,
+    /// Compare the `int3`/`nop` padding at the end of the functions, which is
+    /// trimmed from both sides by default.
+    pub keep_padding: bool,
+    /// Merge runs of nop-equivalent instructions (`nop dword ptr [eax]`,
+    /// `lea esi, [esi]`, `mov edi, edi`...) into a single `nop`, so that
+    /// assemblers using different filler encodings still match.
+    pub normalize_nops: bool,
+    /// Mask every immediate operand, so that instructions only differing by
+    /// their constants or addresses still match.
+    pub fuzzy_immediates: bool,
+    /// Rewrite equivalent x87 sequences to a single form.
+    pub x87: X87Rules,
+}
+
+impl DiffOptions {
+    /// Options comparing the mnemonics and operands of aligned instructions,
+    /// relocated operands masked, so that different encodings of the same
+    /// instruction still match.
+    pub fn instruction_match(pairing: Pairing) -> Self {
+        Self {
+            algorithm: DiffAlgorithm::Aligned,
+            ignore_relocations: true,
+            pairing,
+            keep_padding: false,
+            normalize_nops: true,
+            fuzzy_immediates: false,
+            x87: X87Rules::default(),
+        }
+    }
+
+    /// Whether the functions need to be decoded before being compared.
+    pub fn needs_decoding(&self) -> bool {
+        self.algorithm != DiffAlgorithm::Raw
+            || self.ignore_relocations
+            || self.normalize_nops
+            || self.fuzzy_immediates
+            || !self.x87.is_empty()
+    }
+}
+
+/// A decoded instruction.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DecodedInstruction {
+    /// Offset relative to the start of the function.
+    pub offset: usize,
+    /// Encoding of the instruction, with masked operands zeroed.
+    pub bytes: Vec<u8>,
+    pub mnemonic: String,
+    pub operands: String,
+    /// How execution continues, not part of the comparison key.
+    pub flow: Flow,
+}
+
+impl std::fmt::Display for DecodedInstruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.operands.is_empty() {
+            write!(f, "{}", self.mnemonic)
+        } else {
+            write!(f, "{} {}", self.mnemonic, self.operands)
+        }
+    }
+}
+
+impl DecodedInstruction {
+    /// Key used when comparing instructions.
+    pub fn key(&self) -> (&str, &str) {
+        (&self.mnemonic, &self.operands)
+    }
+
+    fn mask_encoded(&mut self, encoded: [u8; 4]) {
+        if let Some(position) = self
+            .bytes
+            .windows(encoded.len())
+            .rposition(|window| window == encoded)
+        {
+            self.bytes[position..position + encoded.len()].fill(0);
+        }
+    }
+
+    fn mask_value(&mut self, value: i64) {
+        let value = value as u32;
+
+        self.operands = replace_literal(
+            &self.operands,
+            &format!("{value:#x}"),
+            RELOCATION_PLACEHOLDER,
+        );
+        self.mask_encoded(value.to_le_bytes());
+    }
+
+    /// Mask the displacement of a RIP-relative operand, which always depends
+    /// on where the function and its target were placed.
+    fn mask_rip_relative(&mut self, disp: i64) {
+        let text = if disp < 0 {
+            format!("rip - {:#x}", disp.unsigned_abs())
+        } else {
+            format!("rip + {disp:#x}")
+        };
+
+        self.operands = replace_literal(
+            &self.operands,
+            &text,
+            &format!("rip + {RELOCATION_PLACEHOLDER}"),
+        );
+        self.mask_encoded((disp as i32).to_le_bytes());
+    }
+
+    /// Mask an immediate operand, which is always encoded at the end of the
+    /// instruction.
+    fn mask_immediate(&mut self, value: i64, is_branch_relative: bool) {
+        let operands: Vec<String> = split_operands(&self.operands)
+            .into_iter()
+            .map(|x| {
+                if is_literal(x) {
+                    IMMEDIATE_PLACEHOLDER.into()
+                } else {
+                    x.into()
+                }
+            })
+            .collect();
+        self.operands = operands.join(", ");
+
+        let len = self.bytes.len();
+        let encoded = (value as u32).to_le_bytes();
+        let size = if is_branch_relative {
+            // The encoding holds the distance to the target, not the target.
+            if len >= 5 {
+                4
+            } else {
+                1
+            }
+        } else {
+            [4, 2]
+                .into_iter()
+                .find(|size| len > *size && self.bytes.ends_with(&encoded[..*size]))
+                .unwrap_or(1)
+        };
+
+        self.bytes[len.saturating_sub(size)..].fill(0);
+    }
+
+    fn mask_branch_target(&mut self) {
+        self.operands = RELOCATION_PLACEHOLDER.into();
+
+        // Only rel32 branches can reach outside of the function.
+        if self.bytes.len() >= 5 {
+            let len = self.bytes.len();
+            self.bytes[len - 4..].fill(0);
+        }
+    }
+}
+
+/// Split the operands of an instruction, keeping the commas of AT&T memory
+/// operands (`(%eax,%ecx,4)`).
+fn split_operands(operands: &str) -> Vec<&str> {
+    let mut res = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (idx, c) in operands.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                res.push(operands[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+
+    if !operands.is_empty() {
+        res.push(operands[start..].trim());
+    }
+
+    res
+}
+
+/// Whether an operand is a number (`0x10`, `-4`, `$0x10`...).
+fn is_literal(operand: &str) -> bool {
+    let operand = operand.trim_start_matches('$').trim_start_matches('-');
+
+    match operand.strip_prefix("0x") {
+        Some(digits) => !digits.is_empty() && digits.chars().all(|x| x.is_ascii_hexdigit()),
+        None => !operand.is_empty() && operand.chars().all(|x| x.is_ascii_digit()),
+    }
+}
+
+/// Decode the given function data at address zero.
+///
+/// `relocatable_ranges` are the address ranges of the executable the data
+/// comes from; they are only used when relocations are ignored.
+pub fn decode(
+    ctx: &Capstone,
+    data: &[u8],
+    relocatable_ranges: &[Range<usize>],
+    options: &DiffOptions,
+) -> Result<Vec<DecodedInstruction>, ExecutableError> {
+    let mut res = Vec::new();
+    decode_range(
+        ctx,
+        data,
+        0..data.len(),
+        relocatable_ranges,
+        options,
+        &mut res,
+    )?;
+
+    x87::normalize(&mut res, &options.x87);
+
+    Ok(res)
+}
+
+/// Same as [`decode`], only decoding the given ranges of the data, such as
+/// the code around jump tables (see [`crate::jumptable::code_ranges`]).
+pub fn decode_ranges(
+    ctx: &Capstone,
+    data: &[u8],
+    code_ranges: &[Range<usize>],
+    relocatable_ranges: &[Range<usize>],
+    options: &DiffOptions,
+) -> Result<Vec<DecodedInstruction>, ExecutableError> {
+    let mut res = Vec::new();
+
+    for range in code_ranges {
+        decode_range(
+            ctx,
+            data,
+            range.clone(),
+            relocatable_ranges,
+            options,
+            &mut res,
+        )?;
+    }
+
+    x87::normalize(&mut res, &options.x87);
+
+    Ok(res)
+}
+
+fn decode_range(
+    ctx: &Capstone,
+    data: &[u8],
+    range: Range<usize>,
+    relocatable_ranges: &[Range<usize>],
+    options: &DiffOptions,
+    res: &mut Vec<DecodedInstruction>,
+) -> Result<(), ExecutableError> {
+    let instructions = ctx.disasm_all(&data[range.clone()], range.start as u64)?;
+
+    for instruction in instructions.iter() {
+        let mut decoded = DecodedInstruction {
+            offset: instruction.address() as usize,
+            bytes: instruction.bytes().to_vec(),
+            mnemonic: instruction.mnemonic().unwrap_or_default().into(),
+            operands: instruction.op_str().unwrap_or_default().into(),
+            flow: flow(ctx, instruction, data.len())?,
+        };
+
+        if options.ignore_relocations {
+            let detail = ctx.insn_detail(instruction)?;
+            let is_branch_relative = detail
+                .groups()
+                .iter()
+                .any(|v| u32::from(v.0) == CS_GRP_BRANCH_RELATIVE);
+            let is_relocatable = |value: i64| {
+                relocatable_ranges
+                    .iter()
+                    .any(|range| range.contains(&(value as u32 as usize)))
+            };
+
+            for operand in detail.arch_detail().operands() {
+                if let ArchOperand::X86Operand(X86Operand { op_type, .. }) = operand {
+                    match op_type {
+                        X86OperandType::Imm(value)
+                            if is_branch_relative
+                                && (value < 0 || value as usize >= data.len()) =>
+                        {
+                            decoded.mask_branch_target();
+                        }
+                        X86OperandType::Imm(_) if is_branch_relative => {}
+                        X86OperandType::Imm(value) if is_relocatable(value) => {
+                            decoded.mask_value(value);
+                        }
+                        X86OperandType::Mem(mem)
+                            if mem.base() == RegId(X86Reg::X86_REG_RIP as RegIdInt) =>
+                        {
+                            decoded.mask_rip_relative(mem.disp());
+                        }
+                        X86OperandType::Mem(mem) if is_relocatable(mem.disp()) => {
+                            decoded.mask_value(mem.disp());
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        if options.fuzzy_immediates {
+            let detail = ctx.insn_detail(instruction)?;
+            let is_branch_relative = detail
+                .groups()
+                .iter()
+                .any(|v| u32::from(v.0) == CS_GRP_BRANCH_RELATIVE);
+
+            for operand in operands(ctx, instruction)? {
+                if let X86OperandType::Imm(value) = operand.op_type {
+                    decoded.mask_immediate(value, is_branch_relative);
+                }
+            }
+        }
+
+        if options.normalize_nops && is_nop_equivalent(ctx, instruction)? {
+            decoded.mnemonic = "nop".into();
+            decoded.operands.clear();
+            decoded.bytes.fill(NOP);
+
+            // Extend the filler run ending right before this instruction.
+            if let Some(previous) = res.last_mut().filter(|x| {
+                x.mnemonic == "nop"
+                    && x.operands.is_empty()
+                    && x.offset + x.bytes.len() == decoded.offset
+            }) {
+                previous.bytes.extend_from_slice(&decoded.bytes);
+                continue;
+            }
+        }
+
+        res.push(decoded);
+    }
+
+    Ok(())
+}
+
+/// How execution continues after an instruction of a function of `len` bytes
+/// decoded at address zero.
+fn flow(ctx: &Capstone, instruction: &Insn<'_>, len: usize) -> Result<Flow, ExecutableError> {
+    let detail = ctx.insn_detail(instruction)?;
+    let has_group = |group: u32| detail.groups().iter().any(|v| u32::from(v.0) == group);
+
+    if has_group(CS_GRP_RET) || has_group(CS_GRP_IRET) || instruction.mnemonic() == Some("hlt") {
+        return Ok(Flow::Return);
+    }
+
+    if !has_group(CS_GRP_JUMP) {
+        return Ok(Flow::Next);
+    }
+
+    let target = operands(ctx, instruction)?
+        .into_iter()
+        .find_map(|operand| match operand.op_type {
+            X86OperandType::Imm(value) if has_group(CS_GRP_BRANCH_RELATIVE) => Some(value),
+            _ => None,
+        })
+        .filter(|value| (0..len as i64).contains(value))
+        .map(|value| value as usize);
+
+    match instruction.mnemonic() {
+        Some("jmp") | Some("ljmp") => Ok(Flow::Jump(target)),
+        _ => Ok(Flow::Branch(target)),
+    }
+}
+
+/// Whether an instruction does nothing: a `nop` of any length, or an
+/// instruction moving a register into itself (`mov edi, edi`,
+/// `xchg ax, ax`, `lea esi, [esi]`).
+fn is_nop_equivalent(ctx: &Capstone, instruction: &Insn<'_>) -> Result<bool, ExecutableError> {
+    let mnemonic = instruction.mnemonic().unwrap_or_default();
+
+    if mnemonic == "nop" {
+        return Ok(true);
+    }
+
+    if !matches!(mnemonic, "mov" | "xchg" | "lea") {
+        return Ok(false);
+    }
+
+    let operands = operands(ctx, instruction)?;
+    let is_same_register = match operands.as_slice() {
+        [a, b] => match (&a.op_type, &b.op_type) {
+            (X86OperandType::Reg(a), X86OperandType::Reg(b)) => mnemonic != "lea" && a == b,
+            (X86OperandType::Reg(a), X86OperandType::Mem(mem)) => {
+                mnemonic == "lea"
+                    && mem.base() == *a
+                    && mem.index() == RegId(X86Reg::X86_REG_INVALID as RegIdInt)
+                    && mem.disp() == 0
+            }
+            _ => false,
+        },
+        _ => false,
+    };
+
+    Ok(is_same_register)
+}
+
+/// Rebuild the function data from decoded instructions, keeping masked bytes zeroed.
+///
+/// Bytes that were not decoded are kept unchanged.
+pub fn normalized_bytes(instructions: &[DecodedInstruction], data: &[u8]) -> Vec<u8> {
+    let mut res = data.to_vec();
+
+    for instruction in instructions {
+        let end = (instruction.offset + instruction.bytes.len()).min(res.len());
+
+        res[instruction.offset..end]
+            .copy_from_slice(&instruction.bytes[..end - instruction.offset]);
+    }
+
+    res
+}
+
+/// Strip the `int3` and `nop` instructions padding a function to its
+/// alignment, found after its last other instruction.
+///
+/// The data is kept whole when its end doesn't decode, as the trailing bytes
+/// then can't be told apart from the operands of a truncated instruction.
+pub fn trim_padding<'a>(ctx: &Capstone, data: &'a [u8]) -> Result<&'a [u8], ExecutableError> {
+    let instructions = ctx.disasm_all(data, 0)?;
+    let mut decoded_len = 0;
+    let mut len = 0;
+
+    for instruction in instructions.iter() {
+        decoded_len = instruction.address() as usize + instruction.len();
+
+        if !matches!(instruction.mnemonic(), Some("int3" | "nop")) {
+            len = decoded_len;
+        }
+    }
+
+    if decoded_len != data.len() {
+        return Ok(data);
+    }
+
+    Ok(&data[..len])
+}
+
+/// Compute a match percentage of two byte sequences compared one to one.
+///
+/// Like [`aligned_score`], extra bytes on either side count as mismatches.
+pub fn raw_score(a: &[u8], b: &[u8]) -> f32 {
+    if a.is_empty() {
+        return 0.0;
+    }
+
+    let matching_count = a.iter().zip(b).filter(|(x, y)| x == y).count();
+
+    (matching_count as f32 / a.len().max(b.len()) as f32) * 100.0
+}
+
+/// Compute a match percentage of two instruction sequences compared one to one.
+///
+/// Like [`aligned_score`], extra instructions on either side count as
+/// mismatches.
+pub fn instruction_score(a: &[DecodedInstruction], b: &[DecodedInstruction]) -> f32 {
+    let total = a.len().max(b.len());
+
+    if total == 0 {
+        return 0.0;
+    }
+
+    let matching_count = a
+        .iter()
+        .zip(b.iter())
+        .filter(|(x, y)| x.key() == y.key())
+        .count();
+
+    (matching_count as f32 / total as f32) * 100.0
+}
+
+thread_local! {
+    /// Deadline of the alignments of the thread, see [`with_deadline`].
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+    /// Whether an alignment gave up since the deadline was set.
+    static IS_DEADLINE_HIT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f`, alignments giving up once `deadline` is passed.
+///
+/// Returns `None` when an alignment gave up, as the result of `f` is then
+/// based on an approximate alignment.
+pub fn with_deadline<T>(deadline: Option<Instant>, f: impl FnOnce() -> T) -> Option<T> {
+    let previous = DEADLINE.replace(deadline);
+    IS_DEADLINE_HIT.set(false);
+
+    let res = f();
+
+    DEADLINE.set(previous);
+
+    (!IS_DEADLINE_HIT.take()).then_some(res)
+}
+
+/// Align two sequences and return the operations needed to go from `a` to `b`.
+///
+/// Past the deadline set by [`with_deadline`], the alignment is approximate.
+pub fn align<T>(a: &[T], b: &[T]) -> Vec<DiffOp>
+where
+    T: Eq + std::hash::Hash + Ord,
+{
+    let deadline = DEADLINE.get();
+    let res = capture_diff_slices_deadline(Algorithm::Myers, a, b, deadline);
+
+    if deadline.is_some_and(|x| Instant::now() > x) {
+        IS_DEADLINE_HIT.set(true);
+    }
+
+    res
+}
+
+/// Comparison keys of a sequence of instructions.
+pub fn keys(instructions: &[DecodedInstruction]) -> Vec<(&str, &str)> {
+    instructions.iter().map(|x| x.key()).collect()
+}
+
+/// Render the aligned difference of two instruction sequences.
+///
+/// Lines only present in `a` are prefixed by `-`, lines only present in `b`
+/// by `+`, and matching lines by a space.
+pub fn unified_diff(a: &[DecodedInstruction], b: &[DecodedInstruction]) -> String {
+    let mut res = String::new();
+
+    for op in align(&keys(a), &keys(b)) {
+        let (tag, a_range, b_range) = op.as_tag_tuple();
+
+        if tag == DiffTag::Equal {
+            for instruction in &a[a_range] {
+                res.push_str(&format!("     {instruction}\n"));
+            }
+
+            continue;
+        }
+
+        for instruction in &a[a_range] {
+            res.push_str(&format!("-    {instruction}\n"));
+        }
+
+        for instruction in &b[b_range] {
+            res.push_str(&format!("+    {instruction}\n"));
+        }
+    }
+
+    res
+}
+
+/// Kind of a row of a side by side diff.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RowKind {
+    Equal,
+    Changed,
+    Deleted,
+    Inserted,
+}
+
+impl RowKind {
+    pub fn marker(&self) -> char {
+        match self {
+            RowKind::Equal => ' ',
+            RowKind::Changed => '|',
+            RowKind::Deleted => '<',
+            RowKind::Inserted => '>',
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            RowKind::Equal => Color::Green,
+            RowKind::Changed => Color::Yellow,
+            RowKind::Deleted | RowKind::Inserted => Color::Red,
+        }
+    }
+}
+
+/// A row of a side by side diff.
+#[derive(Clone, Debug)]
+pub struct DiffRow<'a> {
+    pub kind: RowKind,
+    pub left: Option<&'a DecodedInstruction>,
+    pub right: Option<&'a DecodedInstruction>,
+}
+
+/// Align two instruction sequences into side by side rows.
+pub fn side_by_side_rows<'a>(
+    a: &'a [DecodedInstruction],
+    b: &'a [DecodedInstruction],
+) -> Vec<DiffRow<'a>> {
+    let mut res = Vec::new();
+
+    for op in align(&keys(a), &keys(b)) {
+        let (tag, a_range, b_range) = op.as_tag_tuple();
+        let a_len = a_range.len();
+        let b_len = b_range.len();
+
+        for idx in 0..a_len.max(b_len) {
+            let left = (idx < a_len).then(|| &a[a_range.start + idx]);
+            let right = (idx < b_len).then(|| &b[b_range.start + idx]);
+
+            let kind = match (tag, left, right) {
+                (DiffTag::Equal, _, _) => RowKind::Equal,
+                (_, Some(_), Some(_)) => RowKind::Changed,
+                (_, Some(_), None) => RowKind::Deleted,
+                _ => RowKind::Inserted,
+            };
+
+            res.push(DiffRow { kind, left, right });
+        }
+    }
+
+    res
+}
+
+/// Render two instruction sequences side by side, marking mismatching lines.
+pub fn side_by_side(
+    a: &[DecodedInstruction],
+    b: &[DecodedInstruction],
+    colorizer: &Colorizer,
+) -> String {
+    let rows = side_by_side_rows(a, b);
+    let width = a
+        .iter()
+        .map(|instruction| instruction.to_string().len())
+        .max()
+        .unwrap_or(0);
+
+    let mut res = String::new();
+
+    for row in rows {
+        let left = row.left.map(|x| x.to_string()).unwrap_or_default();
+        let right = row.right.map(|x| x.to_string()).unwrap_or_default();
+
+        let line = format!("    {left:width$}  {}  {right}", row.kind.marker());
+        res.push_str(&colorizer.paint(row.kind.color(), line.trim_end()));
+        res.push('\n');
+    }
+
+    res
+}
+
+/// Compute a match percentage of two sequences after aligning them.
+///
+/// Every inserted, deleted or replaced element counts as a single mismatch.
+pub fn aligned_score<T>(a: &[T], b: &[T]) -> f32
+where
+    T: Eq + std::hash::Hash + Ord,
+{
+    let total = a.len().max(b.len());
+
+    if total == 0 {
+        return 0.0;
+    }
+
+    (matching_count(a, b) as f32 / total as f32) * 100.0
+}
+
+/// Number of elements of two sequences left unchanged once aligned.
+pub fn matching_count<T>(a: &[T], b: &[T]) -> usize
+where
+    T: Eq + std::hash::Hash + Ord,
+{
+    align(a, b)
+        .iter()
+        .map(|op| match op {
+            DiffOp::Equal { len, .. } => *len,
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Compute a match percentage of two decoded functions.
+pub fn score(
+    algorithm: DiffAlgorithm,
+    (a, a_data): (&[DecodedInstruction], &[u8]),
+    (b, b_data): (&[DecodedInstruction], &[u8]),
+) -> f32 {
+    match algorithm {
+        DiffAlgorithm::Raw => raw_score(&normalized_bytes(a, a_data), &normalized_bytes(b, b_data)),
+        DiffAlgorithm::Instruction => instruction_score(a, b),
+        DiffAlgorithm::Aligned => aligned_score(&keys(a), &keys(b)),
+        DiffAlgorithm::Blocks => cfg::block_score(a, b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use capstone::arch::x86::ArchMode;
+    use capstone::arch::BuildsCapstone;
+
+    use super::*;
+
+    #[test]
+    fn alignments_give_up_past_the_deadline() {
+        let deadline = Instant::now();
+        std::thread::sleep(std::time::Duration::from_millis(1));
+
+        assert_eq!(
+            with_deadline(Some(deadline), || aligned_score(&[1, 2, 3], &[1, 3])),
+            None
+        );
+        assert!(with_deadline(None, || aligned_score(&[1, 2, 3], &[1, 3])).is_some());
+    }
+
+    fn capstone() -> Capstone {
+        Capstone::new()
+            .x86()
+            .mode(ArchMode::Mode32)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn trims_padding_instructions_after_the_last_instruction() {
+        let ctx = capstone();
+
+        assert_eq!(
+            trim_padding(&ctx, &[0x55, 0xc3, 0xcc, 0x90, 0xcc]).unwrap(),
+            [0x55, 0xc3]
+        );
+        assert_eq!(trim_padding(&ctx, &[0xcc, 0xcc]).unwrap(), [0u8; 0]);
+    }
+
+    #[test]
+    fn keeps_padding_bytes_belonging_to_an_instruction() {
+        let ctx = capstone();
+        // mov eax, 0x90cc90cc
+        let data = [0xb8, 0xcc, 0x90, 0xcc, 0x90];
+
+        assert_eq!(trim_padding(&ctx, &data).unwrap(), data);
+        // Truncated mov eax, imm32.
+        assert_eq!(trim_padding(&ctx, &data[..3]).unwrap(), &data[..3]);
+    }
+
+    #[test]
+    fn raw_scores_count_extra_bytes_as_mismatches() {
+        assert_eq!(raw_score(&[1, 2, 3, 4], &[1, 2, 3, 4]), 100.0);
+        assert_eq!(raw_score(&[1, 2, 3, 4], &[1, 2, 3, 4, 5, 6, 7, 8]), 50.0);
+        assert_eq!(raw_score(&[1, 2, 3, 4], &[1, 2]), 50.0);
+        assert_eq!(raw_score(&[1, 2, 3, 4], &[1, 0, 3, 0]), 50.0);
+        assert_eq!(raw_score(&[], &[1]), 0.0);
+        assert_eq!(raw_score(&[], &[]), 0.0);
+    }
+
+    #[test]
+    fn aligned_scores_count_every_edit_once() {
+        assert_eq!(aligned_score(&[1, 2, 3, 4], &[1, 2, 3, 4]), 100.0);
+        assert_eq!(aligned_score(&[1, 2, 3, 4], &[1, 3, 4]), 75.0);
+        assert_eq!(aligned_score(&[1, 2, 3, 4], &[0, 1, 2, 3, 4]), 80.0);
+        assert_eq!(aligned_score::<u8>(&[], &[]), 0.0);
+    }
+
+    fn instruction(mnemonic: &str, operands: &str) -> DecodedInstruction {
+        DecodedInstruction {
+            offset: 0,
+            bytes: Vec::new(),
+            mnemonic: mnemonic.into(),
+            operands: operands.into(),
+            flow: Flow::Next,
+        }
+    }
+
+    #[test]
+    fn masked_values_leave_longer_literals_untouched() {
+        let mut decoded = instruction("mov", "dword ptr [0x4010], 0x40100");
+        decoded.mask_value(0x4010);
+        assert_eq!(decoded.operands, "dword ptr [<reloc>], 0x40100");
+
+        let mut decoded = instruction("lea", "rax, [rip + 0x100]");
+        decoded.mask_rip_relative(0x10);
+        assert_eq!(decoded.operands, "rax, [rip + 0x100]");
+    }
+
+    #[test]
+    fn side_by_side_diffs_mark_mismatching_lines() {
+        let a = [
+            instruction("push", "ebp"),
+            instruction("mov", "eax, 1"),
+            instruction("ret", ""),
+        ];
+        let b = [
+            instruction("push", "ebp"),
+            instruction("mov", "eax, 2"),
+            instruction("nop", ""),
+            instruction("ret", ""),
+        ];
+
+        assert_eq!(
+            side_by_side(&a, &b, &Colorizer::new(false))
+                .lines()
+                .collect::<Vec<_>>(),
+            [
+                "    push ebp       push ebp",
+                "    mov eax, 1  |  mov eax, 2",
+                "                >  nop",
+                "    ret            ret",
+            ]
+        );
+    }
+}
@@ -0,0 +1,308 @@
+//! Memory dumps of the running game.
+//!
+//! Minidumps capture the memory of a process as a list of regions along with
+//! the modules loaded in it. The sections of a module are read from its PE
+//! headers as mapped in memory, so code patched at runtime or unpacked by a
+//! protection can be compared with the reimplementation. Dumps usually miss
+//! some pages: the parts of the sections that weren't captured are gaps,
+//! filled with zeros, and the functions overlapping them are left out.
+
+use std::ops::Range;
+
+use object::pe::{ImageDosHeader, ImageNtHeaders32, ImageNtHeaders64, IMAGE_SCN_CNT_CODE};
+use object::pe::{IMAGE_SCN_CNT_INITIALIZED_DATA, IMAGE_SCN_MEM_EXECUTE};
+use object::read::pe::ImageNtHeaders;
+use object::{FileKind, LittleEndian as LE};
+
+use crate::ExecutableError;
+
+/// `MDMP`, signature of minidumps.
+const MINIDUMP_SIGNATURE: &[u8] = b"MDMP";
+
+/// Streams of a minidump.
+const MODULE_LIST_STREAM: u32 = 4;
+const MEMORY_LIST_STREAM: u32 = 5;
+const MEMORY64_LIST_STREAM: u32 = 9;
+
+/// Size of a `MINIDUMP_MODULE`.
+const MODULE_SIZE: usize = 108;
+
+/// Bytes of the PE headers read at the base of a module.
+const HEADERS_SIZE: usize = 0x1000;
+
+/// A range of memory captured by the dump.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub address: usize,
+    pub data: Vec<u8>,
+}
+
+impl MemoryRegion {
+    fn range(&self) -> Range<usize> {
+        self.address..self.address + self.data.len()
+    }
+}
+
+/// A module loaded in the dumped process.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DumpModule {
+    /// Path of the module (`C:\Games\th06\th06.exe`).
+    pub path: String,
+    pub base: usize,
+    pub size: usize,
+}
+
+impl DumpModule {
+    /// File name of the module, without its directory.
+    pub fn file_name(&self) -> &str {
+        self.path.rsplit(['\\', '/']).next().unwrap_or(&self.path)
+    }
+}
+
+/// A section of a module, reconstructed from the captured memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DumpSection {
+    pub name: String,
+    pub address: usize,
+    /// Content of the section, zeros in the gaps.
+    pub data: Vec<u8>,
+    pub is_code: bool,
+    /// Ranges of addresses that weren't captured.
+    pub gaps: Vec<Range<usize>>,
+}
+
+/// Content of a memory dump.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryDump {
+    /// Captured memory, sorted by address.
+    pub regions: Vec<MemoryRegion>,
+    /// Loaded modules, the executable of the process first.
+    pub modules: Vec<DumpModule>,
+}
+
+/// Whether a file is a minidump.
+pub fn is_minidump(data: &[u8]) -> bool {
+    data.starts_with(MINIDUMP_SIGNATURE)
+}
+
+fn invalid(reason: &'static str) -> ExecutableError {
+    ExecutableError::InvalidDump { reason }
+}
+
+fn read_bytes(data: &[u8], offset: usize, len: usize) -> Result<&[u8], ExecutableError> {
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or_else(|| invalid("truncated file"))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, ExecutableError> {
+    let bytes = read_bytes(data, offset, 4)?;
+
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, ExecutableError> {
+    Ok(read_u32(data, offset)? as u64 | (read_u32(data, offset + 4)? as u64) << 32)
+}
+
+/// Read a `MINIDUMP_STRING`, UTF-16 prefixed by its size in bytes.
+fn read_string(data: &[u8], offset: usize) -> Result<String, ExecutableError> {
+    let len = read_u32(data, offset)? as usize;
+    let units: Vec<u16> = read_bytes(data, offset + 4, len)?
+        .chunks_exact(2)
+        .map(|x| u16::from_le_bytes([x[0], x[1]]))
+        .collect();
+
+    Ok(String::from_utf16_lossy(&units))
+}
+
+impl MemoryDump {
+    /// Parse a minidump, as written by `MiniDumpWriteDump` or a debugger.
+    pub fn parse_minidump(data: &[u8]) -> Result<Self, ExecutableError> {
+        if !is_minidump(data) {
+            return Err(invalid("missing MDMP signature"));
+        }
+
+        let stream_count = read_u32(data, 8)? as usize;
+        let directory = read_u32(data, 12)? as usize;
+        let mut res = Self::default();
+
+        for idx in 0..stream_count {
+            let entry = directory + idx * 12;
+            let stream_type = read_u32(data, entry)?;
+            let stream = read_u32(data, entry + 8)? as usize;
+
+            match stream_type {
+                MODULE_LIST_STREAM => {
+                    let count = read_u32(data, stream)? as usize;
+
+                    for module in (0..count).map(|x| stream + 4 + x * MODULE_SIZE) {
+                        res.modules.push(DumpModule {
+                            base: read_u64(data, module)? as usize,
+                            size: read_u32(data, module + 8)? as usize,
+                            path: read_string(data, read_u32(data, module + 20)? as usize)?,
+                        });
+                    }
+                }
+                MEMORY_LIST_STREAM => {
+                    let count = read_u32(data, stream)? as usize;
+
+                    for descriptor in (0..count).map(|x| stream + 4 + x * 16) {
+                        let len = read_u32(data, descriptor + 8)? as usize;
+                        let rva = read_u32(data, descriptor + 12)? as usize;
+
+                        res.regions.push(MemoryRegion {
+                            address: read_u64(data, descriptor)? as usize,
+                            data: read_bytes(data, rva, len)?.to_vec(),
+                        });
+                    }
+                }
+                // Full memory dumps, the content of the ranges following each other.
+                MEMORY64_LIST_STREAM => {
+                    let count = read_u64(data, stream)? as usize;
+                    let mut rva = read_u64(data, stream + 8)? as usize;
+
+                    for descriptor in (0..count).map(|x| stream + 16 + x * 16) {
+                        let len = read_u64(data, descriptor + 8)? as usize;
+
+                        res.regions.push(MemoryRegion {
+                            address: read_u64(data, descriptor)? as usize,
+                            data: read_bytes(data, rva, len)?.to_vec(),
+                        });
+                        rva += len;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        res.regions.sort_by_key(|x| x.address);
+
+        Ok(res)
+    }
+
+    /// Find a module by file name, ignoring case, or the executable of the
+    /// process when no name is given.
+    pub fn module(&self, name: Option<&str>) -> Result<&DumpModule, ExecutableError> {
+        let module = match name {
+            Some(name) => self
+                .modules
+                .iter()
+                .find(|x| x.file_name().eq_ignore_ascii_case(name)),
+            None => self.modules.first(),
+        };
+
+        module.ok_or_else(|| ExecutableError::ModuleNotFound {
+            module_name: name.unwrap_or("<executable>").into(),
+        })
+    }
+
+    /// Captured bytes at an address, when a single region contains them.
+    pub fn read(&self, address: usize, len: usize) -> Option<&[u8]> {
+        let region = self.regions.iter().find(|x| x.range().contains(&address))?;
+        let offset = address - region.address;
+
+        region.data.get(offset..offset.checked_add(len)?)
+    }
+
+    /// Copy the captured bytes of a range, returning the gaps left as zeros.
+    fn copy(&self, range: Range<usize>) -> (Vec<u8>, Vec<Range<usize>>) {
+        let mut data = vec![0; range.len()];
+        let mut gaps = Vec::new();
+        let mut address = range.start;
+
+        for region in &self.regions {
+            let start = region.address.max(address);
+            let end = region.range().end.min(range.end);
+
+            if start >= end {
+                continue;
+            }
+
+            if start > address {
+                gaps.push(address..start);
+            }
+
+            data[start - range.start..end - range.start]
+                .copy_from_slice(&region.data[start - region.address..end - region.address]);
+            address = end;
+        }
+
+        if address < range.end {
+            gaps.push(address..range.end);
+        }
+
+        (data, gaps)
+    }
+
+    /// Reconstruct the sections of a module from its PE headers.
+    ///
+    /// When the headers weren't captured, the whole module is a single code
+    /// section.
+    pub fn sections(&self, module: &DumpModule) -> Result<Vec<DumpSection>, ExecutableError> {
+        let headers = self
+            .read(module.base, HEADERS_SIZE.min(module.size))
+            .unwrap_or_default();
+        let layout = match FileKind::parse(headers) {
+            Ok(FileKind::Pe32) => section_layout::<ImageNtHeaders32>(headers)?,
+            Ok(FileKind::Pe64) => section_layout::<ImageNtHeaders64>(headers)?,
+            _ => vec![(String::from("dump"), 0..module.size, true)],
+        };
+
+        Ok(layout
+            .into_iter()
+            .map(|(name, range, is_code)| {
+                let address = module.base + range.start;
+                let (data, gaps) = self.copy(address..module.base + range.end);
+
+                DumpSection {
+                    name,
+                    address,
+                    data,
+                    is_code,
+                    gaps,
+                }
+            })
+            .collect())
+    }
+}
+
+/// Names, relative ranges and kinds of the sections containing code or
+/// initialized data.
+fn section_layout<Pe: ImageNtHeaders>(
+    headers: &[u8],
+) -> Result<Vec<(String, Range<usize>, bool)>, ExecutableError> {
+    let dos_header = ImageDosHeader::parse(headers)?;
+    let mut offset = dos_header.nt_headers_offset().into();
+    let (nt_headers, _) = Pe::parse(headers, &mut offset)?;
+    let mut res = Vec::new();
+
+    for section in nt_headers.sections(headers, offset)?.iter() {
+        let characteristics = section.characteristics.get(LE);
+        let is_code = characteristics & (IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE) != 0;
+
+        if !is_code && characteristics & IMAGE_SCN_CNT_INITIALIZED_DATA == 0 {
+            continue;
+        }
+
+        let start = section.virtual_address.get(LE) as usize;
+        let size = section.virtual_size.get(LE) as usize;
+
+        res.push((
+            String::from_utf8_lossy(section.raw_name()).into_owned(),
+            start..start + size,
+            is_code,
+        ));
+    }
+
+    Ok(res)
+}
+
+/// Whether a range of addresses overlaps a gap of the sections.
+pub fn overlaps_gap(sections: &[DumpSection], range: &Range<usize>) -> bool {
+    sections
+        .iter()
+        .flat_map(|x| &x.gaps)
+        .any(|gap| gap.start < range.end && range.start < gap.end)
+}
@@ -0,0 +1,181 @@
+//! DWARF debug information.
+//!
+//! Reimplementations built with clang or MinGW carry DWARF instead of a PDB.
+//! Only the subprograms with code are extracted, named after their enclosing
+//! namespaces and classes (`Foo::bar`) like the PDB procedures are.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use gimli::{AttributeValue, DebuggingInformationEntry, Dwarf, EndianSlice, RunTimeEndian, Unit};
+use object::{File, Object, ObjectSection};
+
+use crate::ExecutableError;
+
+type Reader<'a> = EndianSlice<'a, RunTimeEndian>;
+
+/// A function described by the debug information.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DwarfFunction {
+    pub name: String,
+    pub address: usize,
+    /// Size of the function, `None` when only its start is known.
+    pub size: Option<usize>,
+}
+
+fn attribute_string<'a>(
+    dwarf: &Dwarf<Reader<'a>>,
+    unit: &Unit<Reader<'a>>,
+    entry: &DebuggingInformationEntry<'_, '_, Reader<'a>>,
+    attribute: gimli::DwAt,
+) -> Result<Option<String>, gimli::Error> {
+    match entry.attr_value(attribute)? {
+        Some(value) => Ok(Some(
+            dwarf
+                .attr_string(unit, value)?
+                .to_string_lossy()
+                .into_owned(),
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Offset of the declaration a definition refers to, within the same unit.
+fn declaration_offset(
+    entry: &DebuggingInformationEntry<'_, '_, Reader<'_>>,
+) -> Result<Option<gimli::UnitOffset>, gimli::Error> {
+    for attribute in [gimli::DW_AT_specification, gimli::DW_AT_abstract_origin] {
+        if let Some(AttributeValue::UnitRef(offset)) = entry.attr_value(attribute)? {
+            return Ok(Some(offset));
+        }
+    }
+
+    Ok(None)
+}
+
+fn unit_functions<'a>(
+    dwarf: &Dwarf<Reader<'a>>,
+    unit: &Unit<Reader<'a>>,
+) -> Result<Vec<DwarfFunction>, gimli::Error> {
+    let mut qualified_names: HashMap<gimli::UnitOffset, String> = HashMap::new();
+    let mut declarations: HashMap<gimli::UnitOffset, gimli::UnitOffset> = HashMap::new();
+    let mut pending = Vec::new();
+    let mut scopes: Vec<(isize, String)> = Vec::new();
+    let mut depth = 0isize;
+    let mut entries = unit.entries();
+
+    while let Some((delta, entry)) = entries.next_dfs()? {
+        depth += delta;
+
+        while scopes.last().map(|(x, _)| *x >= depth).unwrap_or(false) {
+            scopes.pop();
+        }
+
+        let name = attribute_string(dwarf, unit, entry, gimli::DW_AT_name)?;
+        let qualified_name = name.as_ref().map(|name| {
+            let mut parts: Vec<&str> = scopes.iter().map(|(_, x)| x.as_str()).collect();
+            parts.push(name);
+            parts.join("::")
+        });
+
+        if let Some(qualified_name) = &qualified_name {
+            qualified_names.insert(entry.offset(), qualified_name.clone());
+        }
+
+        let declaration = declaration_offset(entry)?;
+
+        if let Some(declaration) = declaration {
+            declarations.insert(entry.offset(), declaration);
+        }
+
+        match entry.tag() {
+            gimli::DW_TAG_namespace
+            | gimli::DW_TAG_class_type
+            | gimli::DW_TAG_structure_type
+            | gimli::DW_TAG_union_type => {
+                let name = name.unwrap_or_else(|| "(anonymous namespace)".into());
+                scopes.push((depth, name));
+            }
+            gimli::DW_TAG_subprogram => {
+                let low_pc = match entry.attr_value(gimli::DW_AT_low_pc)? {
+                    Some(AttributeValue::Addr(address)) => address,
+                    Some(AttributeValue::DebugAddrIndex(index)) => dwarf.address(unit, index)?,
+                    _ => continue,
+                };
+
+                let size = match entry.attr_value(gimli::DW_AT_high_pc)? {
+                    Some(AttributeValue::Addr(address)) => address.checked_sub(low_pc),
+                    Some(AttributeValue::Udata(size)) => Some(size),
+                    Some(AttributeValue::DebugAddrIndex(index)) => {
+                        dwarf.address(unit, index)?.checked_sub(low_pc)
+                    }
+                    _ => None,
+                };
+
+                pending.push((
+                    qualified_name,
+                    declaration,
+                    low_pc as usize,
+                    size.map(|x| x as usize),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    // Definitions of methods refer to the declaration inside their class,
+    // possibly through an abstract instance.
+    let resolve = |mut offset: gimli::UnitOffset| {
+        for _ in 0..declarations.len() + 1 {
+            if let Some(name) = qualified_names.get(&offset) {
+                return Some(name.clone());
+            }
+
+            offset = *declarations.get(&offset)?;
+        }
+
+        None
+    };
+
+    Ok(pending
+        .into_iter()
+        .filter_map(|(name, declaration, address, size)| {
+            let name = declaration.and_then(resolve).or(name)?;
+
+            Some(DwarfFunction {
+                name,
+                address,
+                size,
+            })
+        })
+        .collect())
+}
+
+/// Collect the functions described by the DWARF sections of an object.
+pub fn functions(raw_obj: &File) -> Result<Vec<DwarfFunction>, ExecutableError> {
+    let endian = if raw_obj.is_little_endian() {
+        RunTimeEndian::Little
+    } else {
+        RunTimeEndian::Big
+    };
+
+    let load_section = |id: gimli::SectionId| -> Result<Cow<'_, [u8]>, ExecutableError> {
+        match raw_obj.section_by_name(id.name()) {
+            Some(section) => Ok(section.uncompressed_data()?),
+            None => Ok(Cow::Borrowed(&[])),
+        }
+    };
+
+    let sections = Dwarf::load(load_section)?;
+    let dwarf = sections.borrow(|section| EndianSlice::new(section, endian));
+
+    let mut res = Vec::new();
+    let mut units = dwarf.units();
+
+    while let Some(header) = units.next()? {
+        let unit = dwarf.unit(header)?;
+        res.extend(unit_functions(&dwarf, &unit)?);
+    }
+
+    Ok(res)
+}
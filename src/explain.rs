@@ -0,0 +1,491 @@
+//! Step by step explanation of a function score.
+//!
+//! Surprising percentages are hard to trust without knowing what was
+//! compared. This replays the comparison of a single function and reports
+//! the normalization applied to each instruction and every matching and
+//! mismatching range.
+//!
+//! [`mismatches`] only lists the ranges that differ, with offsets relative to
+//! the start of both functions, to jump straight to them in a disassembler.
+
+use std::fmt::Write;
+use std::ops::Range;
+
+use capstone::Capstone;
+use similar::DiffTag;
+
+use crate::cfg::{self, ControlFlowGraph};
+use crate::diff::{self, DecodedInstruction, DiffAlgorithm, DiffOptions};
+use crate::format::PercentageFormat;
+use crate::{Executable, ExecutableError, Function};
+
+fn algorithm_name(algorithm: DiffAlgorithm) -> &'static str {
+    match algorithm {
+        DiffAlgorithm::Raw => "raw (bytes compared one to one)",
+        DiffAlgorithm::Instruction => "instruction (instructions compared one to one)",
+        DiffAlgorithm::Aligned => "aligned (instructions aligned before being compared)",
+        DiffAlgorithm::Blocks => {
+            "blocks (basic blocks paired before their instructions are aligned)"
+        }
+    }
+}
+
+/// List the operands masked by normalization.
+fn explain_masking(
+    res: &mut String,
+    side: &str,
+    unmasked: &[DecodedInstruction],
+    masked: &[DecodedInstruction],
+) -> Result<usize, std::fmt::Error> {
+    let mut count = 0;
+
+    for (before, after) in unmasked.iter().zip(masked) {
+        if before.operands != after.operands || before.bytes != after.bytes {
+            writeln!(res, "  {side} +{:#06x}: {before} -> {after}", before.offset)?;
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+/// Describe the runs of matching and mismatching bytes.
+fn explain_bytes(res: &mut String, a: &[u8], b: &[u8]) -> Result<(usize, usize), std::fmt::Error> {
+    let mut matching_count = 0;
+    let mut start = 0;
+
+    while start < a.len() {
+        let is_match = |idx: usize| b.get(idx) == Some(&a[idx]);
+        let current = is_match(start);
+        let end = (start..a.len())
+            .find(|idx| is_match(*idx) != current || (*idx >= b.len()) != (start >= b.len()))
+            .unwrap_or(a.len());
+
+        let status = match (current, start >= b.len()) {
+            (true, _) => "match",
+            (false, true) => "missing from reimplementation",
+            (false, false) => "differ",
+        };
+
+        writeln!(
+            res,
+            "  +{start:#06x}..+{end:#06x} {status} ({} bytes)",
+            end - start
+        )?;
+
+        if current {
+            matching_count += end - start;
+        }
+
+        start = end;
+    }
+
+    if b.len() > a.len() {
+        writeln!(
+            res,
+            "  {} extra bytes in the reimplementation are not counted",
+            b.len() - a.len()
+        )?;
+    }
+
+    Ok((matching_count, a.len()))
+}
+
+/// Describe the instructions compared one to one.
+fn explain_instructions(
+    res: &mut String,
+    a: &[DecodedInstruction],
+    b: &[DecodedInstruction],
+) -> Result<(usize, usize), std::fmt::Error> {
+    let mut matching_count = 0;
+
+    for idx in 0..a.len().max(b.len()) {
+        match (a.get(idx), b.get(idx)) {
+            (Some(x), Some(y)) if x.key() == y.key() => {
+                writeln!(res, "  = +{:#06x} {x}", x.offset)?;
+                matching_count += 1;
+            }
+            (Some(x), Some(y)) => writeln!(res, "  ! +{:#06x} {x} | {y}", x.offset)?,
+            (Some(x), None) => writeln!(res, "  - +{:#06x} {x}", x.offset)?,
+            (None, Some(y)) => writeln!(res, "  + +{:#06x} {y} (not counted)", y.offset)?,
+            (None, None) => {}
+        }
+    }
+
+    Ok((matching_count, a.len()))
+}
+
+/// Describe the alignment of two instruction sequences.
+fn explain_alignment(
+    res: &mut String,
+    a: &[DecodedInstruction],
+    b: &[DecodedInstruction],
+) -> Result<(usize, usize), std::fmt::Error> {
+    let mut matching_count = 0;
+
+    for op in diff::align(&diff::keys(a), &diff::keys(b)) {
+        let (tag, a_range, b_range) = op.as_tag_tuple();
+
+        match tag {
+            DiffTag::Equal => {
+                writeln!(
+                    res,
+                    "  = {} instructions equal, from original +{:#06x}",
+                    a_range.len(),
+                    a[a_range.start].offset
+                )?;
+                matching_count += a_range.len();
+            }
+            _ => {
+                for x in &a[a_range] {
+                    writeln!(res, "  - +{:#06x} {x}", x.offset)?;
+                }
+
+                for y in &b[b_range] {
+                    writeln!(res, "  + +{:#06x} {y}", y.offset)?;
+                }
+            }
+        }
+    }
+
+    Ok((matching_count, a.len().max(b.len())))
+}
+
+/// Describe the pairs of basic blocks and the blocks left unpaired.
+fn explain_blocks(
+    res: &mut String,
+    a: &[DecodedInstruction],
+    b: &[DecodedInstruction],
+) -> Result<(usize, usize), std::fmt::Error> {
+    let a_cfg = ControlFlowGraph::build(a);
+    let b_cfg = ControlFlowGraph::build(b);
+    let pairs = cfg::match_blocks((&a_cfg, a), (&b_cfg, b));
+    let mut matching_count = 0;
+
+    for (i, j) in &pairs {
+        let a_block = &a[a_cfg.blocks[*i].range.clone()];
+        let b_block = &b[b_cfg.blocks[*j].range.clone()];
+        let count = diff::matching_count(&diff::keys(a_block), &diff::keys(b_block));
+
+        writeln!(
+            res,
+            "  = original +{:#06x} <-> reimplementation +{:#06x}: {count} / {} instructions equal",
+            a_block[0].offset,
+            b_block[0].offset,
+            a_block.len().max(b_block.len())
+        )?;
+        matching_count += count;
+    }
+
+    for (idx, block) in a_cfg.blocks.iter().enumerate() {
+        if !pairs.iter().any(|(i, _)| *i == idx) {
+            writeln!(
+                res,
+                "  - original +{:#06x}: {} instructions unpaired",
+                a[block.range.start].offset,
+                block.range.len()
+            )?;
+        }
+    }
+
+    for (idx, block) in b_cfg.blocks.iter().enumerate() {
+        if !pairs.iter().any(|(_, j)| *j == idx) {
+            writeln!(
+                res,
+                "  + reimplementation +{:#06x}: {} instructions unpaired",
+                b[block.range.start].offset,
+                block.range.len()
+            )?;
+        }
+    }
+
+    Ok((matching_count, a.len().max(b.len())))
+}
+
+/// Explain how the score of a function is computed.
+pub fn explain(
+    ctx: &Capstone,
+    (executable, function): (&Executable, &Function),
+    (other, other_function): (&Executable, &Function),
+    options: &DiffOptions,
+) -> Result<String, ExecutableError> {
+    let mut res = String::new();
+
+    writeln!(res, "Function: {}", function.name)?;
+    writeln!(res, "Algorithm: {}", algorithm_name(options.algorithm))?;
+    writeln!(
+        res,
+        "Original: {} bytes at {:#x}",
+        function.data.len(),
+        function.address
+    )?;
+    writeln!(
+        res,
+        "Reimplementation: {} bytes at {:#x}",
+        other_function.data.len(),
+        other_function.address
+    )?;
+
+    let (matching_count, total) = if options.needs_decoding() {
+        let instructions = executable.decode_function(ctx, function, options)?;
+        let other_instructions = other.decode_function(ctx, other_function, options)?;
+
+        writeln!(res, "\nNormalization:")?;
+
+        if options.ignore_relocations {
+            let unmasked_options = DiffOptions {
+                ignore_relocations: false,
+                ..*options
+            };
+            let unmasked = executable.decode_function(ctx, function, &unmasked_options)?;
+            let other_unmasked = other.decode_function(ctx, other_function, &unmasked_options)?;
+
+            let count = explain_masking(&mut res, "original", &unmasked, &instructions)?
+                + explain_masking(
+                    &mut res,
+                    "reimplementation",
+                    &other_unmasked,
+                    &other_instructions,
+                )?;
+
+            if count == 0 {
+                writeln!(res, "  relocations ignored, no operand masked")?;
+            }
+        }
+
+        if options.normalize_nops {
+            writeln!(res, "  nop-equivalent filler merged into nop")?;
+        }
+
+        if options.fuzzy_immediates {
+            writeln!(res, "  immediate operands ignored")?;
+        }
+
+        if !options.x87.is_empty() {
+            writeln!(res, "  x87 rules: {}", options.x87.names().join(", "))?;
+        }
+
+        if !options.ignore_relocations
+            && !options.normalize_nops
+            && !options.fuzzy_immediates
+            && options.x87.is_empty()
+        {
+            writeln!(res, "  none")?;
+        }
+
+        writeln!(res, "\nComparison:")?;
+
+        match options.algorithm {
+            DiffAlgorithm::Raw => explain_bytes(
+                &mut res,
+                &diff::normalized_bytes(&instructions, function.compared_data(ctx, options)?),
+                &diff::normalized_bytes(
+                    &other_instructions,
+                    other_function.compared_data(ctx, options)?,
+                ),
+            )?,
+            DiffAlgorithm::Instruction => {
+                explain_instructions(&mut res, &instructions, &other_instructions)?
+            }
+            DiffAlgorithm::Aligned => {
+                explain_alignment(&mut res, &instructions, &other_instructions)?
+            }
+            DiffAlgorithm::Blocks => explain_blocks(&mut res, &instructions, &other_instructions)?,
+        }
+    } else {
+        writeln!(res, "\nNormalization:\n  none")?;
+        writeln!(res, "\nComparison:")?;
+
+        explain_bytes(
+            &mut res,
+            function.compared_data(ctx, options)?,
+            other_function.compared_data(ctx, options)?,
+        )?
+    };
+
+    let score = executable.compute_function_diff(ctx, function, other, other_function, options)?;
+
+    writeln!(
+        res,
+        "\nScore: {matching_count} / {total} = {}",
+        PercentageFormat::default().format(score)
+    )?;
+
+    Ok(res)
+}
+
+/// A range of a function differing from its reimplementation.
+///
+/// Offsets are relative to the start of each function. One of the ranges is
+/// empty when bytes or instructions are only present on one side.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MismatchRange {
+    pub original: Range<usize>,
+    pub reimplementation: Range<usize>,
+}
+
+/// Runs of bytes differing when compared one to one.
+pub fn byte_mismatches(a: &[u8], b: &[u8]) -> Vec<MismatchRange> {
+    let mut res = Vec::new();
+    let len = a.len().max(b.len());
+    let mut start = 0;
+
+    while start < len {
+        if a.get(start) == b.get(start) {
+            start += 1;
+            continue;
+        }
+
+        let end = (start..len)
+            .find(|idx| a.get(*idx) == b.get(*idx))
+            .unwrap_or(len);
+
+        res.push(MismatchRange {
+            original: start.min(a.len())..end.min(a.len()),
+            reimplementation: start.min(b.len())..end.min(b.len()),
+        });
+
+        start = end;
+    }
+
+    res
+}
+
+/// Byte range covered by a run of instructions, empty at `insertion_point`
+/// when there is none.
+fn instruction_range(instructions: &[DecodedInstruction], insertion_point: usize) -> Range<usize> {
+    match (instructions.first(), instructions.last()) {
+        (Some(first), Some(last)) => first.offset..last.offset + last.bytes.len(),
+        _ => insertion_point..insertion_point,
+    }
+}
+
+/// Runs of instructions left over once both sides are aligned.
+pub fn instruction_mismatches(
+    a: &[DecodedInstruction],
+    b: &[DecodedInstruction],
+) -> Vec<MismatchRange> {
+    let end_of = |instructions: &[DecodedInstruction]| {
+        instructions.last().map_or(0, |x| x.offset + x.bytes.len())
+    };
+    let offset_of = |instructions: &[DecodedInstruction], idx: usize| {
+        instructions
+            .get(idx)
+            .map_or_else(|| end_of(instructions), |x| x.offset)
+    };
+
+    diff::align(&diff::keys(a), &diff::keys(b))
+        .into_iter()
+        .filter_map(|op| {
+            let (tag, a_range, b_range) = op.as_tag_tuple();
+
+            if tag == DiffTag::Equal {
+                return None;
+            }
+
+            Some(MismatchRange {
+                original: instruction_range(&a[a_range.clone()], offset_of(a, a_range.start)),
+                reimplementation: instruction_range(
+                    &b[b_range.clone()],
+                    offset_of(b, b_range.start),
+                ),
+            })
+        })
+        .collect()
+}
+
+fn format_range(range: &Range<usize>) -> String {
+    format!(
+        "+{:#06x}..+{:#06x} ({} bytes)",
+        range.start,
+        range.end,
+        range.len()
+    )
+}
+
+/// List the byte and instruction ranges of a function differing from its
+/// reimplementation.
+///
+/// Bytes are compared after normalization, so masked operands only show up
+/// when relocations aren't ignored.
+pub fn mismatches(
+    ctx: &Capstone,
+    (executable, function): (&Executable, &Function),
+    (other, other_function): (&Executable, &Function),
+    options: &DiffOptions,
+) -> Result<String, ExecutableError> {
+    let mut res = String::new();
+
+    let instructions = executable.decode_function(ctx, function, options)?;
+    let other_instructions = other.decode_function(ctx, other_function, options)?;
+
+    writeln!(res, "Function: {}", function.name)?;
+    writeln!(
+        res,
+        "Original: {} bytes at {:#x}",
+        function.data.len(),
+        function.address
+    )?;
+    writeln!(
+        res,
+        "Reimplementation: {} bytes at {:#x}",
+        other_function.data.len(),
+        other_function.address
+    )?;
+
+    let byte_ranges = byte_mismatches(
+        &diff::normalized_bytes(&instructions, function.compared_data(ctx, options)?),
+        &diff::normalized_bytes(
+            &other_instructions,
+            other_function.compared_data(ctx, options)?,
+        ),
+    );
+
+    writeln!(res, "\nByte ranges:")?;
+
+    if byte_ranges.is_empty() {
+        writeln!(res, "  none")?;
+    }
+
+    for range in &byte_ranges {
+        writeln!(
+            res,
+            "  original {} | reimplementation {}",
+            format_range(&range.original),
+            format_range(&range.reimplementation)
+        )?;
+    }
+
+    let instruction_ranges = instruction_mismatches(&instructions, &other_instructions);
+
+    writeln!(res, "\nInstruction ranges:")?;
+
+    if instruction_ranges.is_empty() {
+        writeln!(res, "  none")?;
+    }
+
+    for range in &instruction_ranges {
+        writeln!(
+            res,
+            "  original {} | reimplementation {}",
+            format_range(&range.original),
+            format_range(&range.reimplementation)
+        )?;
+
+        for x in instructions
+            .iter()
+            .filter(|x| range.original.contains(&x.offset))
+        {
+            writeln!(res, "    - +{:#06x} {x}", x.offset)?;
+        }
+
+        for y in other_instructions
+            .iter()
+            .filter(|y| range.reimplementation.contains(&y.offset))
+        {
+            writeln!(res, "    + +{:#06x} {y}", y.offset)?;
+        }
+    }
+
+    Ok(res)
+}
@@ -0,0 +1,57 @@
+//! Number formatting shared by every report writer.
+//!
+//! Reports are committed to git by downstream projects, so the output must be
+//! byte-for-byte reproducible: a fixed precision and always `.` as the decimal
+//! separator, whatever the machine producing it.
+
+/// Formatter for match percentages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PercentageFormat {
+    /// Number of digits after the decimal separator.
+    pub precision: usize,
+}
+
+impl Default for PercentageFormat {
+    fn default() -> Self {
+        Self { precision: 2 }
+    }
+}
+
+impl PercentageFormat {
+    pub fn new(precision: usize) -> Self {
+        Self { precision }
+    }
+
+    /// Format a value without the percent sign.
+    pub fn format_number(&self, value: f32) -> String {
+        // Avoid emitting "NaN" or "-0.00" in reports.
+        let value = if value.is_finite() { value } else { 0.0 };
+        let res = format!("{:.*}", self.precision, value);
+
+        match res.strip_prefix('-') {
+            Some(abs) if abs.chars().all(|c| c == '0' || c == '.') => abs.into(),
+            _ => res,
+        }
+    }
+
+    /// Round a value to the configured precision, as it would be printed.
+    pub fn round(&self, value: f32) -> f32 {
+        self.format_number(value).parse().unwrap_or(0.0)
+    }
+
+    /// Format a value followed by a percent sign.
+    pub fn format(&self, value: f32) -> String {
+        format!("{}%", self.format_number(value))
+    }
+
+    /// Format a difference of percentages, always signed (`+1.50%`).
+    pub fn format_delta(&self, value: f32) -> String {
+        let res = self.format(value);
+
+        if res.starts_with('-') {
+            res
+        } else {
+            format!("+{res}")
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Stack frames of functions.
+//!
+//! The size of the frame is read from the prologue: the registers pushed,
+//! the `sub esp, N` reserving the local variables and the `mov eax, N` fed
+//! to the stack probe (`__chkstk`) for frames bigger than a page. A
+//! reimplementation reserving a different size usually declares its local
+//! variables differently, which shifts every access to them.
+
+use std::collections::HashMap;
+
+use capstone::arch::x86::X86OperandType;
+use capstone::Capstone;
+use serde::{Deserialize, Serialize};
+
+use crate::diff::Pairing;
+use crate::trace::operands;
+use crate::{Executable, ExecutableError, Function};
+
+/// Stack frame set up by the prologue of a function.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StackFrame {
+    /// Bytes reserved for the local variables.
+    pub locals_size: usize,
+    /// Registers pushed by the prologue, frame pointer included, in push order.
+    pub saved_registers: Vec<String>,
+    /// Bytes of the pushed registers.
+    pub saved_size: usize,
+    /// Whether the frame is addressed through `ebp`.
+    pub has_frame_pointer: bool,
+}
+
+impl StackFrame {
+    /// Bytes below the return address, locals and saved registers.
+    pub fn size(&self) -> usize {
+        self.locals_size + self.saved_size
+    }
+}
+
+/// Size of the stack frame of a function and of its reimplementation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrameSizes {
+    pub original: usize,
+    pub reimplementation: usize,
+}
+
+impl FrameSizes {
+    pub fn is_matching(&self) -> bool {
+        self.original == self.reimplementation
+    }
+}
+
+impl std::fmt::Display for FrameSizes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.original, self.reimplementation)
+    }
+}
+
+/// Read the stack frame from the prologue of a function.
+pub fn analyze(ctx: &Capstone, function: &Function) -> Result<StackFrame, ExecutableError> {
+    let mut res = StackFrame::default();
+    let mut probe_size = None;
+
+    let instructions = ctx.disasm_all(&function.data, 0)?;
+
+    for instruction in instructions.iter() {
+        let operands = operands(ctx, instruction)?;
+        let name = |op_type: &X86OperandType| match op_type {
+            X86OperandType::Reg(reg) => ctx.reg_name(*reg),
+            _ => None,
+        };
+        let register_names: Vec<Option<String>> =
+            operands.iter().map(|x| name(&x.op_type)).collect();
+        let immediate = operands.iter().find_map(|x| match x.op_type {
+            X86OperandType::Imm(value) => Some(value as usize),
+            _ => None,
+        });
+
+        match (
+            instruction.mnemonic().unwrap_or_default(),
+            register_names.as_slice(),
+        ) {
+            // Hot patching point.
+            ("mov", [Some(a), Some(b)]) if a == "edi" && b == "edi" => {}
+            ("push", [Some(register)]) => {
+                res.saved_registers.push(register.clone());
+                res.saved_size += operands[0].size as usize;
+            }
+            ("mov", [Some(a), Some(b)])
+                if matches!(a.as_str(), "ebp" | "rbp") && matches!(b.as_str(), "esp" | "rsp") =>
+            {
+                res.has_frame_pointer = true;
+            }
+            ("sub", [Some(register), None]) if matches!(register.as_str(), "esp" | "rsp") => {
+                res.locals_size += immediate.unwrap_or_default();
+            }
+            // Alignment of the frame.
+            ("and", [Some(register), None]) if matches!(register.as_str(), "esp" | "rsp") => {}
+            ("mov", [Some(register), None]) if register == "eax" && immediate.is_some() => {
+                probe_size = immediate;
+            }
+            ("call", _) if probe_size.is_some() => {
+                res.locals_size += probe_size.take().unwrap_or_default();
+            }
+            _ => break,
+        }
+    }
+
+    Ok(res)
+}
+
+/// Frame sizes of the functions of an executable paired with another one.
+pub fn frame_sizes(
+    ctx: &Capstone,
+    executable: &Executable,
+    other: &Executable,
+    pairing: Pairing,
+) -> Result<HashMap<String, FrameSizes>, ExecutableError> {
+    let mut res = HashMap::new();
+
+    for (name, function) in executable.functions_iter() {
+        if executable.is_ignored(name) {
+            continue;
+        }
+
+        if let Some(other_function) = other.paired_function(function, pairing) {
+            res.insert(
+                name.clone(),
+                FrameSizes {
+                    original: analyze(ctx, function)?.size(),
+                    reimplementation: analyze(ctx, other_function)?.size(),
+                },
+            );
+        }
+    }
+
+    Ok(res)
+}
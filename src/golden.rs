@@ -0,0 +1,91 @@
+//! Golden disassembly files.
+//!
+//! The reimplementation can commit the disassembly of its functions and check
+//! in CI that it does not drift, catching codegen changes that keep the byte
+//! percentages identical.
+
+use std::hash::Hasher;
+
+use similar::TextDiff;
+
+use crate::id::Fnv64;
+
+/// Extension of golden files.
+pub const GOLDEN_EXTENSION: &str = "asm";
+
+/// Turn a function name into a file name that is valid on every platform.
+///
+/// Names with characters replaced get a hash of the full name appended, so
+/// that decorated names only differing by those characters (`?Foo@@YAXH@Z`
+/// and `?Foo@@YAXM@Z`) don't share a file.
+pub fn function_file_name(name: &str) -> String {
+    let res: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if res == name {
+        return res;
+    }
+
+    let mut hasher = Fnv64::default();
+    hasher.write(name.as_bytes());
+
+    format!("{res}-{:08x}", hasher.finish() as u32)
+}
+
+/// Compare a golden disassembly with the current one.
+///
+/// Returns a unified diff when they differ. Line endings are normalized so
+/// that goldens checked out with CRLF do not report drift.
+pub fn compare(name: &str, expected: &str, actual: &str) -> Option<String> {
+    let expected = expected.replace("\r\n", "\n");
+    let actual = actual.replace("\r\n", "\n");
+
+    if expected == actual {
+        return None;
+    }
+
+    Some(
+        TextDiff::from_lines(&expected, &actual)
+            .unified_diff()
+            .header(&format!("{name} (golden)"), &format!("{name} (current)"))
+            .to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_portable_names() {
+        assert_eq!(function_file_name("_sub_401000"), "_sub_401000");
+        assert_eq!(function_file_name("ecl-run.part1"), "ecl-run.part1");
+    }
+
+    #[test]
+    fn replaces_other_characters() {
+        let res = function_file_name("Player::Update");
+
+        assert!(res.starts_with("Player__Update-"), "{res}");
+        assert!(res
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-.".contains(c)));
+    }
+
+    #[test]
+    fn keeps_decorated_names_apart() {
+        assert_ne!(
+            function_file_name("?Foo@@YAXH@Z"),
+            function_file_name("?Foo@@YAXM@Z")
+        );
+        assert_ne!(function_file_name("a::b"), function_file_name("a__b"));
+    }
+}
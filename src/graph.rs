@@ -0,0 +1,171 @@
+//! SVG charts of the progress over time.
+//!
+//! The chart is a plain SVG document without scripts or external resources
+//! so it can be embedded in a README next to the badge.
+
+use std::fmt::Write;
+
+use crate::history::HistoryEntry;
+use crate::report::escape_html;
+
+/// Colors of the successive series.
+const PALETTE: [&str; 8] = [
+    "#4c1", "#007ec6", "#e05d44", "#dfb317", "#9f5fbf", "#fe7d37", "#555", "#2bb5a0",
+];
+
+const MARGIN_LEFT: f32 = 48.0;
+const MARGIN_RIGHT: f32 = 16.0;
+const MARGIN_TOP: f32 = 32.0;
+const MARGIN_BOTTOM: f32 = 32.0;
+
+/// A line of the chart.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Series {
+    pub name: String,
+    pub entries: Vec<HistoryEntry>,
+}
+
+/// Size of the chart, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChartSize {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Default for ChartSize {
+    fn default() -> Self {
+        Self {
+            width: 640,
+            height: 320,
+        }
+    }
+}
+
+/// Render the series as an SVG line chart, the percentage ranging from 0 to 100%.
+pub fn render_svg(
+    series: &[Series],
+    title: &str,
+    size: ChartSize,
+) -> Result<String, std::fmt::Error> {
+    let mut res = String::new();
+
+    let width = size.width as f32;
+    let height = size.height as f32;
+    let plot_width = (width - MARGIN_LEFT - MARGIN_RIGHT).max(1.0);
+    let plot_height = (height - MARGIN_TOP - MARGIN_BOTTOM).max(1.0);
+
+    let entries = series.iter().flat_map(|x| x.entries.iter());
+    let first = entries.clone().min_by_key(|x| x.timestamp);
+    let last = entries.clone().max_by_key(|x| x.timestamp);
+    let (start, end) = match (first, last) {
+        (Some(first), Some(last)) => (first.timestamp, last.timestamp),
+        _ => (0, 0),
+    };
+
+    let x = |timestamp: i64| {
+        if end == start {
+            MARGIN_LEFT + plot_width / 2.0
+        } else {
+            MARGIN_LEFT + (timestamp - start) as f32 / (end - start) as f32 * plot_width
+        }
+    };
+    let y = |percent: f32| MARGIN_TOP + (1.0 - percent.clamp(0.0, 100.0) / 100.0) * plot_height;
+
+    writeln!(
+        res,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"Verdana,DejaVu Sans,sans-serif\" font-size=\"11\">",
+        size.width, size.height, size.width, size.height
+    )?;
+    writeln!(
+        res,
+        "<rect width=\"{}\" height=\"{}\" fill=\"#fff\"/>",
+        size.width, size.height
+    )?;
+    writeln!(
+        res,
+        "<text x=\"{MARGIN_LEFT}\" y=\"20\" font-size=\"14\">{}</text>",
+        escape_html(title)
+    )?;
+
+    for percent in [0.0, 25.0, 50.0, 75.0, 100.0] {
+        let y = y(percent);
+
+        writeln!(
+            res,
+            "<line x1=\"{MARGIN_LEFT}\" y1=\"{y:.1}\" x2=\"{:.1}\" y2=\"{y:.1}\" stroke=\"#ddd\"/>",
+            MARGIN_LEFT + plot_width
+        )?;
+        writeln!(
+            res,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\" fill=\"#555\">{percent}%</text>",
+            MARGIN_LEFT - 6.0,
+            y + 4.0
+        )?;
+    }
+
+    if let (Some(first), Some(last)) = (first, last) {
+        let label_y = height - MARGIN_BOTTOM + 16.0;
+        let date = |entry: &HistoryEntry| entry.date.split(' ').next().unwrap_or("").to_string();
+
+        writeln!(
+            res,
+            "<text x=\"{MARGIN_LEFT}\" y=\"{label_y:.1}\" fill=\"#555\">{}</text>",
+            escape_html(&date(first))
+        )?;
+
+        if end != start {
+            writeln!(
+                res,
+                "<text x=\"{:.1}\" y=\"{label_y:.1}\" text-anchor=\"end\" fill=\"#555\">{}</text>",
+                MARGIN_LEFT + plot_width,
+                escape_html(&date(last))
+            )?;
+        }
+    }
+
+    for (idx, series) in series.iter().enumerate() {
+        let color = PALETTE[idx % PALETTE.len()];
+        let points: Vec<(f32, f32)> = series
+            .entries
+            .iter()
+            .map(|entry| (x(entry.timestamp), y(entry.percent.unwrap_or(0.0))))
+            .collect();
+        let polyline: Vec<String> = points
+            .iter()
+            .map(|(x, y)| format!("{x:.1},{y:.1}"))
+            .collect();
+
+        writeln!(
+            res,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{color}\" stroke-width=\"2\"/>",
+            polyline.join(" ")
+        )?;
+
+        // Lone snapshots would be invisible as a line.
+        if let [(cx, cy)] = points.as_slice() {
+            writeln!(
+                res,
+                "<circle cx=\"{cx:.1}\" cy=\"{cy:.1}\" r=\"3\" fill=\"{color}\"/>"
+            )?;
+        }
+
+        let legend_x = width - MARGIN_RIGHT - 120.0;
+        let legend_y = MARGIN_TOP + 8.0 + idx as f32 * 16.0;
+
+        writeln!(
+            res,
+            "<rect x=\"{legend_x:.1}\" y=\"{:.1}\" width=\"10\" height=\"10\" fill=\"{color}\"/>",
+            legend_y - 9.0
+        )?;
+        writeln!(
+            res,
+            "<text x=\"{:.1}\" y=\"{legend_y:.1}\">{}</text>",
+            legend_x + 14.0,
+            escape_html(&series.name)
+        )?;
+    }
+
+    writeln!(res, "</svg>")?;
+
+    Ok(res)
+}
@@ -0,0 +1,254 @@
+//! Factoring of sequences shared by a group of functions.
+//!
+//! Families of similar functions (ECL instruction handlers, functions sharing
+//! an SEH prolog or an inlined helper...) contain the same runs of
+//! instructions, at their start or anywhere else. Detecting those shared
+//! sequences allows printing them once instead of once per function.
+
+use std::collections::HashMap;
+
+use similar::DiffTag;
+
+use crate::diff::{self, DecodedInstruction};
+
+/// Comparison keys of consecutive instructions.
+type Window<'a> = [(&'a str, &'a str)];
+
+/// A sequence of instructions found in several functions.
+#[derive(Clone, Debug)]
+pub struct SharedSequence {
+    /// Identifier used by the reference markers.
+    pub id: usize,
+    pub instructions: Vec<DecodedInstruction>,
+    /// Index of the functions containing the sequence and the index of its
+    /// first instruction in each of them.
+    pub uses: Vec<(usize, usize)>,
+}
+
+impl SharedSequence {
+    pub fn marker(&self) -> String {
+        format!(
+            "; shared sequence S{} ({} instructions)",
+            self.id,
+            self.instructions.len()
+        )
+    }
+}
+
+/// Find sequences of at least `min_len` instructions shared by two or more
+/// functions, or found twice in the same one.
+///
+/// Sequences never overlap: each instruction belongs to at most one of them,
+/// the earliest found when scanning the functions in order.
+pub fn find_shared_sequences(
+    functions: &[Vec<DecodedInstruction>],
+    min_len: usize,
+) -> Vec<SharedSequence> {
+    let min_len = min_len.max(1);
+    let keys: Vec<Vec<(&str, &str)>> = functions.iter().map(|x| diff::keys(x)).collect();
+    let mut windows: HashMap<&Window<'_>, Vec<(usize, usize)>> = HashMap::new();
+
+    for (function, function_keys) in keys.iter().enumerate() {
+        for (offset, window) in function_keys.windows(min_len).enumerate() {
+            windows.entry(window).or_default().push((function, offset));
+        }
+    }
+
+    let mut covered: Vec<Vec<bool>> = functions.iter().map(|x| vec![false; x.len()]).collect();
+    let mut res: Vec<SharedSequence> = Vec::new();
+
+    for (function, function_keys) in keys.iter().enumerate() {
+        let mut offset = 0;
+
+        while offset + min_len <= function_keys.len() {
+            let is_free = |covered: &[Vec<bool>], (function, offset): (usize, usize)| {
+                !covered[function][offset..offset + min_len].contains(&true)
+            };
+
+            if !is_free(&covered, (function, offset)) {
+                offset += 1;
+                continue;
+            }
+
+            // Occurrences of the window, without overlapping each other.
+            let mut uses = vec![(function, offset)];
+
+            for candidate in &windows[&function_keys[offset..offset + min_len]] {
+                let overlaps = uses
+                    .iter()
+                    .any(|(f, o)| *f == candidate.0 && o.abs_diff(candidate.1) < min_len);
+
+                if !overlaps && is_free(&covered, *candidate) {
+                    uses.push(*candidate);
+                }
+            }
+
+            if uses.len() < 2 {
+                offset += 1;
+                continue;
+            }
+
+            // Extend the sequence as long as every occurrence agrees, stopping
+            // before the next occurrence in the same function.
+            let mut len = min_len;
+
+            loop {
+                let extends = uses.iter().all(|(f, o)| {
+                    let next = o + len;
+
+                    next < keys[*f].len()
+                        && !covered[*f][next]
+                        && keys[*f][next] == function_keys[offset + len]
+                        && !uses.iter().any(|(g, p)| g == f && *p == next)
+                });
+
+                if !extends {
+                    break;
+                }
+
+                len += 1;
+            }
+
+            for (f, o) in &uses {
+                covered[*f][*o..*o + len].fill(true);
+            }
+
+            uses.sort();
+
+            res.push(SharedSequence {
+                id: res.len() + 1,
+                instructions: functions[function][offset..offset + len].to_vec(),
+                uses,
+            });
+
+            offset += len;
+        }
+    }
+
+    res
+}
+
+/// Sequences used by the given function, with the index of their first
+/// instruction, sorted by index.
+pub fn sequences_of(
+    sequences: &[SharedSequence],
+    function_index: usize,
+) -> Vec<(usize, &SharedSequence)> {
+    let mut res: Vec<(usize, &SharedSequence)> = sequences
+        .iter()
+        .flat_map(|sequence| {
+            sequence
+                .uses
+                .iter()
+                .filter(|(function, _)| *function == function_index)
+                .map(move |(_, offset)| (*offset, sequence))
+        })
+        .collect();
+
+    res.sort_by_key(|(offset, _)| *offset);
+    res
+}
+
+/// Render the aligned difference of two instruction sequences like
+/// [`diff::unified_diff`], the shared sequences of `a` matched as a whole by
+/// `b` replaced by their marker.
+pub fn unified_diff(
+    a: &[DecodedInstruction],
+    b: &[DecodedInstruction],
+    sequences: &[(usize, &SharedSequence)],
+) -> String {
+    let mut res = String::new();
+
+    for op in diff::align(&diff::keys(a), &diff::keys(b)) {
+        let (tag, a_range, b_range) = op.as_tag_tuple();
+
+        if tag == DiffTag::Equal {
+            let mut idx = a_range.start;
+
+            while idx < a_range.end {
+                let sequence = sequences.iter().find(|(offset, sequence)| {
+                    *offset == idx && idx + sequence.instructions.len() <= a_range.end
+                });
+
+                match sequence {
+                    Some((_, sequence)) => {
+                        res.push_str(&format!("     {}\n", sequence.marker()));
+                        idx += sequence.instructions.len();
+                    }
+                    None => {
+                        res.push_str(&format!("     {}\n", a[idx]));
+                        idx += 1;
+                    }
+                }
+            }
+
+            continue;
+        }
+
+        for instruction in &a[a_range] {
+            res.push_str(&format!("-    {instruction}\n"));
+        }
+
+        for instruction in &b[b_range] {
+            res.push_str(&format!("+    {instruction}\n"));
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instructions(mnemonics: &str) -> Vec<DecodedInstruction> {
+        mnemonics
+            .split_whitespace()
+            .enumerate()
+            .map(|(offset, mnemonic)| DecodedInstruction {
+                offset,
+                bytes: Vec::new(),
+                mnemonic: mnemonic.into(),
+                operands: String::new(),
+                flow: Default::default(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_sequences_in_the_middle_of_functions() {
+        let functions = [
+            instructions("push mov sub call add pop ret"),
+            instructions("xor test call add pop jmp"),
+        ];
+        let sequences = find_shared_sequences(&functions, 3);
+
+        assert_eq!(sequences.len(), 1);
+        assert_eq!(sequences[0].uses, vec![(0, 3), (1, 2)]);
+        assert_eq!(sequences[0].instructions.len(), 3);
+    }
+
+    #[test]
+    fn ignores_sequences_shorter_than_the_minimum() {
+        let functions = [instructions("push mov ret"), instructions("push mov nop")];
+
+        assert!(find_shared_sequences(&functions, 3).is_empty());
+        assert_eq!(find_shared_sequences(&functions, 2).len(), 1);
+    }
+
+    #[test]
+    fn collapses_matching_sequences_in_the_diff() {
+        let functions = [
+            instructions("push mov sub ret"),
+            instructions("push mov sub nop"),
+        ];
+        let sequences = find_shared_sequences(&functions, 3);
+        let used = sequences_of(&sequences, 0);
+        let res = unified_diff(&functions[0], &instructions("push mov sub leave"), &used);
+
+        assert_eq!(
+            res,
+            "     ; shared sequence S1 (3 instructions)\n-    ret\n+    leave\n"
+        );
+    }
+}
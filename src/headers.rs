@@ -0,0 +1,152 @@
+//! PE header inspection.
+//!
+//! A mismatch in the section layout, entry point or imports usually explains
+//! why nothing matches between the original and the reimplementation, so this
+//! module exposes a quick way to compare them.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use object::pe::ImageNtHeaders32;
+use object::pe::ImageNtHeaders64;
+use object::read::pe::{ImageNtHeaders, ImageOptionalHeader, PeFile};
+use object::{FileKind, Object, ObjectSection, SectionFlags};
+
+use crate::ExecutableError;
+
+/// Summary of a section header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SectionHeader {
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+    pub characteristics: u32,
+}
+
+/// Summary of the headers of a PE executable.
+#[derive(Clone, Debug, Default)]
+pub struct ExecutableHeaders {
+    pub image_base: u64,
+    pub entry_point: u64,
+    pub subsystem: u16,
+    pub sections: Vec<SectionHeader>,
+    /// Number of imported symbols per library.
+    pub imports: BTreeMap<String, usize>,
+}
+
+impl ExecutableHeaders {
+    pub fn parse(data: &[u8]) -> Result<Self, ExecutableError> {
+        match FileKind::parse(data)? {
+            FileKind::Pe32 => Self::from_pe(&PeFile::<ImageNtHeaders32>::parse(data)?),
+            FileKind::Pe64 => Self::from_pe(&PeFile::<ImageNtHeaders64>::parse(data)?),
+            _ => Err(ExecutableError::UnsupportedFormat),
+        }
+    }
+
+    fn from_pe<Pe: ImageNtHeaders>(pe: &PeFile<'_, Pe>) -> Result<Self, ExecutableError> {
+        let optional_header = pe.nt_headers().optional_header();
+
+        let mut res = Self {
+            image_base: optional_header.image_base(),
+            entry_point: pe.entry(),
+            subsystem: optional_header.subsystem(),
+            ..Default::default()
+        };
+
+        for section in pe.sections() {
+            let characteristics = match section.flags() {
+                SectionFlags::Coff { characteristics } => characteristics,
+                _ => 0,
+            };
+
+            res.sections.push(SectionHeader {
+                name: section.name()?.into(),
+                address: section.address(),
+                size: section.size(),
+                characteristics,
+            });
+        }
+
+        for import in pe.imports()? {
+            let library = String::from_utf8_lossy(import.library()).to_lowercase();
+
+            *res.imports.entry(library).or_default() += 1;
+        }
+
+        Ok(res)
+    }
+
+    /// Produce a human readable report of the differences with another executable.
+    ///
+    /// Returns an empty string when both headers are equivalent.
+    pub fn diff(&self, other: &Self) -> Result<String, ExecutableError> {
+        let mut res = String::new();
+
+        if self.image_base != other.image_base {
+            writeln!(
+                res,
+                "image base: {:#x} != {:#x}",
+                self.image_base, other.image_base
+            )?;
+        }
+
+        if self.entry_point != other.entry_point {
+            writeln!(
+                res,
+                "entry point: {:#x} != {:#x}",
+                self.entry_point, other.entry_point
+            )?;
+        }
+
+        if self.subsystem != other.subsystem {
+            writeln!(res, "subsystem: {} != {}", self.subsystem, other.subsystem)?;
+        }
+
+        for section in &self.sections {
+            match other.sections.iter().find(|x| x.name == section.name) {
+                Some(other_section) => {
+                    if section.size != other_section.size {
+                        writeln!(
+                            res,
+                            "section {}: size {:#x} != {:#x}",
+                            section.name, section.size, other_section.size
+                        )?;
+                    }
+
+                    if section.characteristics != other_section.characteristics {
+                        writeln!(
+                            res,
+                            "section {}: characteristics {:#010x} != {:#010x}",
+                            section.name, section.characteristics, other_section.characteristics
+                        )?;
+                    }
+                }
+                None => writeln!(res, "section {}: missing", section.name)?,
+            }
+        }
+
+        for section in &other.sections {
+            if !self.sections.iter().any(|x| x.name == section.name) {
+                writeln!(res, "section {}: unexpected", section.name)?;
+            }
+        }
+
+        for (library, count) in &self.imports {
+            match other.imports.get(library) {
+                Some(other_count) if other_count != count => {
+                    writeln!(res, "imports {library}: {count} != {other_count}")?;
+                }
+                Some(_) => {}
+                None => writeln!(res, "imports {library}: missing")?,
+            }
+        }
+
+        for library in other.imports.keys() {
+            if !self.imports.contains_key(library) {
+                writeln!(res, "imports {library}: unexpected")?;
+            }
+        }
+
+        Ok(res)
+    }
+}
@@ -0,0 +1,223 @@
+//! History of the match percentages.
+//!
+//! Every `stats` run given `--history progress.db` appends a timestamped
+//! snapshot of the global percentage and of every function percentage to a
+//! SQLite database, which the `history` subcommand queries afterwards.
+
+use std::fmt::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection};
+
+use crate::color::Colorizer;
+use crate::compare;
+use crate::format::PercentageFormat;
+use crate::report::StatsReport;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS snapshot (
+    id INTEGER PRIMARY KEY,
+    timestamp INTEGER NOT NULL,
+    percent REAL NOT NULL
+);
+CREATE TABLE IF NOT EXISTS function_snapshot (
+    snapshot_id INTEGER NOT NULL REFERENCES snapshot(id),
+    name TEXT NOT NULL,
+    id TEXT NOT NULL,
+    percent REAL
+);
+CREATE INDEX IF NOT EXISTS function_snapshot_name ON function_snapshot(name);
+";
+
+#[derive(Debug)]
+pub enum HistoryError {
+    SqliteError { error: rusqlite::Error },
+    FmtError { error: std::fmt::Error },
+}
+
+impl std::fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+impl std::error::Error for HistoryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HistoryError::SqliteError { error } => Some(error),
+            HistoryError::FmtError { error } => Some(error),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for HistoryError {
+    fn from(error: rusqlite::Error) -> Self {
+        Self::SqliteError { error }
+    }
+}
+
+impl From<std::fmt::Error> for HistoryError {
+    fn from(error: std::fmt::Error) -> Self {
+        Self::FmtError { error }
+    }
+}
+
+/// Percentage recorded by a snapshot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    /// Seconds since the Unix epoch.
+    pub timestamp: i64,
+    /// UTC date of the snapshot (`YYYY-MM-DD HH:MM:SS`).
+    pub date: String,
+    /// Match percentage, `None` when the function was missing.
+    pub percent: Option<f32>,
+}
+
+/// Read an entry from a `timestamp, date, percent` row.
+fn entry_from_row(row: &rusqlite::Row) -> rusqlite::Result<HistoryEntry> {
+    Ok(HistoryEntry {
+        timestamp: row.get(0)?,
+        date: row.get(1)?,
+        percent: row.get(2)?,
+    })
+}
+
+/// Database of snapshots.
+pub struct History {
+    connection: Connection,
+}
+
+impl History {
+    /// Open a history database, creating it if needed.
+    pub fn open(path: &Path) -> Result<Self, HistoryError> {
+        let connection = Connection::open(path)?;
+
+        connection.execute_batch(SCHEMA)?;
+
+        Ok(Self { connection })
+    }
+
+    /// Append a snapshot of a report, timestamped with the current time.
+    ///
+    /// Only the functions kept by the report are recorded, so it must be
+    /// recorded before [`StatsReport::filtered`].
+    pub fn record(&mut self, report: &StatsReport) -> Result<(), HistoryError> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.as_secs())
+            .unwrap_or(0);
+
+        let transaction = self.connection.transaction()?;
+
+        transaction.execute(
+            "INSERT INTO snapshot (timestamp, percent) VALUES (?1, ?2)",
+            params![timestamp, report.global.percent],
+        )?;
+
+        let snapshot_id = transaction.last_insert_rowid();
+
+        {
+            let mut statement = transaction.prepare(
+                "INSERT INTO function_snapshot (snapshot_id, name, id, percent) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+
+            for function in &report.functions {
+                statement.execute(params![
+                    snapshot_id,
+                    function.name,
+                    function.id,
+                    function.percent
+                ])?;
+            }
+        }
+
+        transaction.commit()?;
+
+        Ok(())
+    }
+
+    /// Global percentage of every snapshot, oldest first.
+    pub fn global(&self) -> Result<Vec<HistoryEntry>, HistoryError> {
+        let mut statement = self.connection.prepare(
+            "SELECT timestamp, datetime(timestamp, 'unixepoch'), percent FROM snapshot ORDER BY timestamp, id",
+        )?;
+
+        let res = statement
+            .query_map([], entry_from_row)?
+            .collect::<Result<_, _>>()?;
+
+        Ok(res)
+    }
+
+    /// Percentage of a function in every snapshot it appears in, oldest first.
+    ///
+    /// The function is looked up by name or by mapping id, so that renamed
+    /// functions keep their history.
+    pub fn function(&self, name: &str) -> Result<Vec<HistoryEntry>, HistoryError> {
+        let mut statement = self.connection.prepare(
+            "SELECT snapshot.timestamp, datetime(snapshot.timestamp, 'unixepoch'), function_snapshot.percent
+             FROM function_snapshot JOIN snapshot ON snapshot.id = function_snapshot.snapshot_id
+             WHERE function_snapshot.name = ?1 OR (function_snapshot.id != '' AND function_snapshot.id = ?1)
+             ORDER BY snapshot.timestamp, snapshot.id",
+        )?;
+
+        let res = statement
+            .query_map([name], entry_from_row)?
+            .collect::<Result<_, _>>()?;
+
+        Ok(res)
+    }
+
+    /// Average percentage of the functions whose name starts with a prefix
+    /// (`Player::`) in every snapshot, oldest first.
+    pub fn category(&self, prefix: &str) -> Result<Vec<HistoryEntry>, HistoryError> {
+        let mut statement = self.connection.prepare(
+            "SELECT snapshot.timestamp, datetime(snapshot.timestamp, 'unixepoch'), AVG(COALESCE(function_snapshot.percent, 0))
+             FROM function_snapshot JOIN snapshot ON snapshot.id = function_snapshot.snapshot_id
+             WHERE substr(function_snapshot.name, 1, length(?1)) = ?1
+             GROUP BY snapshot.id
+             ORDER BY snapshot.timestamp, snapshot.id",
+        )?;
+
+        let res = statement
+            .query_map([prefix], entry_from_row)?
+            .collect::<Result<_, _>>()?;
+
+        Ok(res)
+    }
+}
+
+/// Render the entries as `date: percent (delta)`, the delta being relative to the previous entry.
+pub fn render(
+    entries: &[HistoryEntry],
+    percentage_format: &PercentageFormat,
+    colorizer: &Colorizer,
+) -> Result<String, std::fmt::Error> {
+    let mut res = String::new();
+    let mut previous = None;
+
+    for entry in entries {
+        write!(
+            res,
+            "{}: {}",
+            entry.date,
+            compare::format_percent(percentage_format, entry.percent)
+        )?;
+
+        let delta = previous.map(|x: f32| entry.percent.unwrap_or(0.0) - x);
+
+        match delta.filter(|x| percentage_format.round(*x) != 0.0) {
+            Some(delta) => writeln!(
+                res,
+                " ({})",
+                compare::format_delta(delta, percentage_format, colorizer)
+            )?,
+            None => writeln!(res)?,
+        }
+
+        previous = Some(entry.percent.unwrap_or(0.0));
+    }
+
+    Ok(res)
+}
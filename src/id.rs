@@ -0,0 +1,45 @@
+//! Stable identifiers of functions.
+//!
+//! Names in the mapping change as the reverse engineering progresses, so
+//! dashboards and history tracking key functions by an identifier derived
+//! from the name and address they had when first seen. The mapping can pin
+//! an identifier to keep it across renames.
+
+use std::hash::Hasher;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// 64-bit FNV-1a hasher, identical on every run unlike the default hasher.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Fnv64(u64);
+
+impl Default for Fnv64 {
+    fn default() -> Self {
+        Self(FNV_OFFSET_BASIS)
+    }
+}
+
+impl std::hash::Hasher for Fnv64 {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Compute the identifier of a function, identical on every run and platform.
+pub fn stable_id(name: &str, address: usize) -> String {
+    let mut hasher = Fnv64::default();
+
+    hasher.write(name.as_bytes());
+    hasher.write(&[0]);
+    hasher.write(&(address as u64).to_le_bytes());
+
+    format!("{:016x}", hasher.finish())
+}
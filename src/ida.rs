@@ -0,0 +1,147 @@
+//! IDA names exports.
+//!
+//! Users who annotated the original executable in IDA can export their work
+//! either as an IDC script (`File > Produce file > Dump database to IDC
+//! file`) or as a plain list of `address name` lines. Both are accepted.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// Names and function bounds found in an IDA export.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IdaNames {
+    /// Names by address.
+    pub names: BTreeMap<usize, String>,
+    /// Function bounds by start address.
+    pub functions: BTreeMap<usize, Range<usize>>,
+}
+
+fn parse_number(value: &str) -> Option<usize> {
+    let value = value.trim();
+    let value = value.rsplit(':').next().unwrap_or(value);
+
+    match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        // IDA writes bare addresses in hexadecimal.
+        None => usize::from_str_radix(value.trim_end_matches(['h', 'H']), 16).ok(),
+    }
+}
+
+/// Split the arguments of an IDC call: `MakeName(0x401000, "main");`.
+fn parse_call(line: &str) -> Option<(&str, Vec<&str>)> {
+    let open = line.find('(')?;
+    let close = line.rfind(')')?;
+
+    if close < open {
+        return None;
+    }
+
+    let name = line[..open].trim();
+    let arguments = line[open + 1..close].split(',').map(|x| x.trim()).collect();
+
+    Some((name, arguments))
+}
+
+fn parse_string(value: &str) -> Option<String> {
+    let value = value.strip_prefix('"')?.strip_suffix('"')?;
+
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.into())
+    }
+}
+
+impl IdaNames {
+    /// Parse an IDC script or a plain names list.
+    ///
+    /// Unknown statements and lines are ignored.
+    pub fn parse(content: &str) -> Self {
+        let mut res = Self::default();
+
+        for line in content.lines() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with("//") || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some((call, arguments)) = parse_call(line) {
+                match (call, arguments.as_slice()) {
+                    ("MakeName" | "MakeNameEx" | "set_name", [address, name, ..]) => {
+                        if let (Some(address), Some(name)) =
+                            (parse_number(address), parse_string(name))
+                        {
+                            res.names.insert(address, name);
+                        }
+                    }
+                    ("MakeFunction" | "add_func", [start, end, ..]) => {
+                        if let (Some(start), Some(end)) = (parse_number(start), parse_number(end)) {
+                            if end > start {
+                                res.functions.insert(start, start..end);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+
+                continue;
+            }
+
+            // Plain list: `0x401000 main` or `.text:00401000 main`.
+            let mut words = line.split_whitespace();
+
+            if let (Some(address), Some(name), None) = (words.next(), words.next(), words.next()) {
+                if let Some(address) = parse_number(address) {
+                    res.names.insert(address, name.into());
+                }
+            }
+        }
+
+        res
+    }
+
+    /// Named functions with their address and size.
+    ///
+    /// When the export records function bounds, only names at the start of a
+    /// function are kept. Otherwise every name is a function extending up to
+    /// the next name, and the last one is dropped as its size is unknown.
+    pub fn named_functions(&self) -> Vec<(String, usize, usize)> {
+        let mut res = Vec::new();
+
+        if !self.functions.is_empty() {
+            for (address, name) in &self.names {
+                if let Some(range) = self.functions.get(address) {
+                    res.push((name.clone(), *address, range.len()));
+                }
+            }
+
+            return res;
+        }
+
+        let addresses: Vec<&usize> = self.names.keys().collect();
+
+        for (address, next) in addresses.iter().zip(addresses.iter().skip(1)) {
+            res.push((self.names[address].clone(), **address, **next - **address));
+        }
+
+        res
+    }
+
+    /// Names outside of every function, only known when the export records
+    /// function bounds.
+    pub fn named_data(&self) -> Vec<(String, usize)> {
+        if self.functions.is_empty() {
+            return Vec::new();
+        }
+
+        self.names
+            .iter()
+            .filter(|(address, _)| !self.functions.values().any(|x| x.contains(address)))
+            .map(|(address, name)| (name.clone(), *address))
+            .collect()
+    }
+}
@@ -0,0 +1,89 @@
+//! Import address table of PE executables.
+//!
+//! Calls to imported functions go through a slot of the import address
+//! table (`call dword ptr [0x40e0c4]`). Slots are named after the function
+//! they point to (`KERNEL32!GetProcAddress`) so the disassembly shows the
+//! actual callee.
+
+use object::pe::{ImageNtHeaders32, ImageNtHeaders64};
+use object::read::pe::{ImageNtHeaders, Import, PeFile};
+use object::{FileKind, LittleEndian as LE, Object};
+
+use crate::ExecutableError;
+
+/// A slot of the import address table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportSlot {
+    /// Virtual address of the slot.
+    pub address: usize,
+    pub size: usize,
+    /// Name of the library, without extension (`KERNEL32`).
+    pub library: String,
+    /// Name of the function, `#<ordinal>` for imports by ordinal.
+    pub name: String,
+}
+
+impl ImportSlot {
+    /// Name of the slot in the disassembly, `library!function`.
+    pub fn symbol_name(&self) -> String {
+        format!("{}!{}", self.library, self.name)
+    }
+}
+
+/// Slots of the import address table, empty for other executables than PE.
+pub fn import_slots(data: &[u8]) -> Result<Vec<ImportSlot>, ExecutableError> {
+    match FileKind::parse(data)? {
+        FileKind::Pe32 => slots(&PeFile::<ImageNtHeaders32>::parse(data)?),
+        FileKind::Pe64 => slots(&PeFile::<ImageNtHeaders64>::parse(data)?),
+        _ => Ok(Vec::new()),
+    }
+}
+
+fn slots<Pe: ImageNtHeaders>(pe: &PeFile<'_, Pe>) -> Result<Vec<ImportSlot>, ExecutableError> {
+    let mut res = Vec::new();
+
+    let table = match pe.import_table()? {
+        Some(table) => table,
+        None => return Ok(res),
+    };
+
+    let image_base = pe.relative_address_base() as usize;
+    let size = std::mem::size_of::<Pe::ImageThunkData>();
+    let mut descriptors = table.descriptors()?;
+
+    while let Some(descriptor) = descriptors.next()? {
+        let library = String::from_utf8_lossy(table.name(descriptor.name.get(LE))?);
+        let library = match library.rsplit_once('.') {
+            Some((stem, extension)) if extension.eq_ignore_ascii_case("dll") => stem.to_string(),
+            _ => library.into_owned(),
+        };
+
+        // The lookup table keeps the names once the loader overwrote the address table.
+        let first_thunk = descriptor.first_thunk.get(LE);
+        let lookup = match descriptor.original_first_thunk.get(LE) {
+            0 => first_thunk,
+            original_first_thunk => original_first_thunk,
+        };
+
+        let mut thunks = table.thunks(lookup)?;
+        let mut address = image_base + first_thunk as usize;
+
+        while let Some(thunk) = thunks.next::<Pe>()? {
+            let name = match table.import::<Pe>(thunk)? {
+                Import::Name(_, name) => String::from_utf8_lossy(name).into_owned(),
+                Import::Ordinal(ordinal) => format!("#{ordinal}"),
+            };
+
+            res.push(ImportSlot {
+                address,
+                size,
+                library: library.clone(),
+                name,
+            });
+
+            address += size;
+        }
+    }
+
+    Ok(res)
+}
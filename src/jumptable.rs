@@ -0,0 +1,145 @@
+//! Jump tables embedded in functions.
+//!
+//! MSVC compiles dense switch statements to an indirect jump through a table
+//! of case addresses (`jmp dword ptr [eax*4 + table]`), sometimes preceded by
+//! a table of case indexes (`movzx eax, byte ptr [eax + indexes]`). Both are
+//! placed after the code of the function, where capstone would decode them
+//! as garbage instructions.
+
+use std::ops::Range;
+
+use capstone::arch::x86::X86OperandType;
+use capstone::Capstone;
+
+use crate::trace::operands;
+use crate::ExecutableError;
+
+/// Byte used by MSVC to pad functions to their alignment.
+const INT3: u8 = 0xcc;
+
+/// Content of a jump table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TableKind {
+    /// Addresses of the cases, 4 bytes each.
+    Addresses,
+    /// Indexes into the table of addresses, 1 byte each.
+    Indexes,
+}
+
+/// A jump table of a function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JumpTable {
+    pub kind: TableKind,
+    /// Offsets of the table in the function.
+    pub range: Range<usize>,
+}
+
+impl JumpTable {
+    /// Values of the table: addresses or indexes.
+    pub fn entries(&self, data: &[u8]) -> Vec<usize> {
+        let bytes = &data[self.range.clone()];
+
+        match self.kind {
+            TableKind::Addresses => bytes
+                .chunks_exact(4)
+                .map(|x| u32::from_le_bytes([x[0], x[1], x[2], x[3]]) as usize)
+                .collect(),
+            TableKind::Indexes => bytes.iter().map(|x| *x as usize).collect(),
+        }
+    }
+}
+
+/// Find the jump tables of a function loaded at `address`, sorted by offset.
+pub fn find(
+    ctx: &Capstone,
+    address: usize,
+    data: &[u8],
+) -> Result<Vec<JumpTable>, ExecutableError> {
+    let function_range = address..address + data.len();
+    let mut starts: Vec<(usize, TableKind)> = Vec::new();
+
+    let instructions = ctx.disasm_all(data, address as u64)?;
+
+    for instruction in instructions.iter() {
+        let offset = instruction.address() as usize - address;
+
+        // Everything after the first table is data.
+        if starts.iter().any(|(start, _)| *start <= offset) {
+            break;
+        }
+
+        let kind = match instruction.mnemonic() {
+            Some("jmp") => TableKind::Addresses,
+            Some("movzx") => TableKind::Indexes,
+            _ => continue,
+        };
+
+        for operand in operands(ctx, instruction)? {
+            if let X86OperandType::Mem(mem) = operand.op_type {
+                let is_table_access = match kind {
+                    TableKind::Addresses => mem.scale() == 4,
+                    TableKind::Indexes => operand.size == 1,
+                };
+                let table = mem.disp() as usize;
+
+                if is_table_access && mem.disp() > 0 && function_range.contains(&table) {
+                    starts.push((table - address, kind));
+                }
+            }
+        }
+    }
+
+    starts.sort();
+    starts.dedup_by_key(|(start, _)| *start);
+
+    let mut res = Vec::new();
+
+    for (idx, (start, kind)) in starts.iter().enumerate() {
+        let end = starts.get(idx + 1).map_or(data.len(), |(next, _)| *next);
+        let bytes = &data[*start..end];
+
+        let len = match kind {
+            TableKind::Addresses => {
+                4 * bytes
+                    .chunks_exact(4)
+                    .take_while(|x| {
+                        let entry = u32::from_le_bytes([x[0], x[1], x[2], x[3]]) as usize;
+                        function_range.contains(&entry)
+                    })
+                    .count()
+            }
+            TableKind::Indexes => {
+                bytes.len() - bytes.iter().rev().take_while(|x| **x == INT3).count()
+            }
+        };
+
+        if len != 0 {
+            res.push(JumpTable {
+                kind: *kind,
+                range: *start..*start + len,
+            });
+        }
+    }
+
+    Ok(res)
+}
+
+/// Ranges of a function of `len` bytes outside of its jump tables.
+pub fn code_ranges(tables: &[JumpTable], len: usize) -> Vec<Range<usize>> {
+    let mut res = Vec::new();
+    let mut start = 0;
+
+    for table in tables {
+        if table.range.start > start {
+            res.push(start..table.range.start);
+        }
+
+        start = start.max(table.range.end);
+    }
+
+    if start < len {
+        res.push(start..len);
+    }
+
+    res
+}
@@ -3,25 +3,211 @@
 //! Simple binary comparison helper tool for Touhou 06.
 
 use std::collections::hash_map::Iter;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
 use std::fmt::Write;
-use std::{collections::HashMap, error::Error};
+use std::ops::{Deref, Range};
+use std::sync::Arc;
+use std::time::Instant;
 
-use capstone::arch::x86::{X86Operand, X86OperandType};
 use capstone::arch::x86::X86InsnGroup::*;
+use capstone::arch::x86::{X86Operand, X86OperandType};
 use capstone::arch::ArchOperand;
-use capstone::{Capstone, Insn, InsnDetail, Instructions};
 use capstone::InsnGroupType::*;
-use object::{File, Object, ObjectSection, ObjectSymbol, SymbolKind};
+use capstone::{Capstone, Insn, InsnDetail};
+use object::{File, Object, ObjectSection, ObjectSymbol, SectionIndex, SectionKind, SymbolKind};
 use pdb::{FallibleIterator, ProcedureSymbol, PublicSymbol, Source, SymbolData, PDB};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+pub mod arch;
+pub mod archive;
+pub mod badge;
+pub mod boundaries;
+pub mod bytesig;
+pub mod cache;
+pub mod callgraph;
+pub mod cfg;
+pub mod check;
+pub mod color;
+pub mod compare;
+pub mod coverage;
+pub mod data;
+pub mod decompme;
+pub mod demangle;
+pub mod diff;
+pub mod dump;
+pub mod dwarf;
+pub mod explain;
+pub mod format;
+pub mod frame;
+pub mod golden;
+pub mod graph;
+pub mod group;
+pub mod headers;
+pub mod history;
+pub mod id;
+pub mod ida;
+pub mod imports;
+pub mod jumptable;
+pub mod limits;
+pub mod list;
+pub mod map;
+pub mod masm;
+pub mod objdiff;
+pub mod profile;
+pub mod rename;
+pub mod report;
+pub mod signature;
+pub mod sink;
+pub mod strings;
+pub mod stubs;
+pub mod suggest;
+pub mod tables;
+pub mod trace;
+pub mod validate;
+pub mod vtable;
+pub mod watch;
+pub mod workspace;
+pub mod x87;
+
+use diff::{DecodedInstruction, DiffAlgorithm, DiffOptions, Pairing};
+use jumptable::{JumpTable, TableKind};
+use limits::{FunctionLimits, SkippedFunction};
+use map::MapSymbol;
+use profile::{Dialect, FormattingProfile};
+use report::InstructionCounts;
+use signature::Signatures;
+
+/// Match percentage of each function, `None` when missing from the reimplementation.
+///
+/// Sorted by name, so that iterating it gives the same order on every run.
+pub type FunctionStats = BTreeMap<String, Option<f32>>;
 
 #[derive(Debug)]
 pub enum ExecutableError {
-    ObjectError { error: object::Error },
-    PdbError { error: pdb::Error },
-    CapstoneError { error: capstone::Error },
-    WriteError { error: std::fmt::Error },
-    FunctionNameConflict { function_name: String },
+    ObjectError {
+        error: object::Error,
+    },
+    PdbError {
+        error: pdb::Error,
+    },
+    DwarfError {
+        error: gimli::Error,
+    },
+    CapstoneError {
+        error: capstone::Error,
+    },
+    WriteError {
+        error: std::fmt::Error,
+    },
+    FunctionNameConflict {
+        function_name: String,
+    },
+    UnsupportedFormat,
+    InstructionNotFound {
+        offset: usize,
+    },
+    /// The section selected by the mapping doesn't exist in the executable.
+    SectionNotFound {
+        section_name: String,
+    },
+    /// A function of the mapping extends beyond the section containing it.
+    FunctionOutOfRange {
+        name: String,
+        address: usize,
+        size: usize,
+        section_len: usize,
+    },
+    /// A function of the mapping isn't located in any code section.
+    FunctionOutsideSections {
+        name: String,
+        address: usize,
+    },
+    /// A memory dump is malformed.
+    InvalidDump {
+        reason: &'static str,
+    },
+    /// The module selected by the mapping isn't loaded in the dumped process.
+    ModuleNotFound {
+        module_name: String,
+    },
+    /// No function of the mapping is located in the selected version.
+    VersionNotFound {
+        version: String,
+    },
+    /// An error with the location it happened at.
+    Context {
+        context: ErrorContext,
+        error: Box<ExecutableError>,
+    },
+}
+
+/// Location of an error: the operation that failed and the file, function
+/// and offset it was working on.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// Operation that failed, such as `loading` or `disassembling`.
+    pub operation: &'static str,
+    pub file: Option<std::path::PathBuf>,
+    pub function: Option<String>,
+    /// Offset relative to the start of the function.
+    pub offset: Option<usize>,
+}
+
+impl ErrorContext {
+    pub fn new(operation: &'static str) -> Self {
+        Self {
+            operation,
+            ..Default::default()
+        }
+    }
+
+    pub fn file(mut self, file: impl Into<std::path::PathBuf>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    pub fn function(mut self, function: impl Into<String>) -> Self {
+        self.function = Some(function.into());
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+}
+
+impl std::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "while {}", self.operation)?;
+
+        if let Some(function) = &self.function {
+            write!(f, " {function}")?;
+        }
+
+        if let Some(offset) = self.offset {
+            write!(f, " at +{offset:#x}")?;
+        }
+
+        match (&self.file, &self.function) {
+            (Some(file), Some(_)) => write!(f, " in {}", file.display())?,
+            (Some(file), None) => write!(f, " {}", file.display())?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl ExecutableError {
+    /// Attach the location the error happened at.
+    pub fn with_context(self, context: ErrorContext) -> Self {
+        Self::Context {
+            context,
+            error: Box::new(self),
+        }
+    }
 }
 
 impl std::fmt::Display for ExecutableError {
@@ -30,6 +216,38 @@ impl std::fmt::Display for ExecutableError {
             ExecutableError::FunctionNameConflict { function_name } => {
                 write!(f, "Function \"{function_name}\" already exist!")
             }
+            ExecutableError::UnsupportedFormat => {
+                write!(f, "Executable format is not supported!")
+            }
+            ExecutableError::InstructionNotFound { offset } => {
+                write!(f, "No instruction starts at offset {offset:#x}!")
+            }
+            ExecutableError::SectionNotFound { section_name } => {
+                write!(f, "Section \"{section_name}\" not found!")
+            }
+            ExecutableError::FunctionOutOfRange {
+                name,
+                address,
+                size,
+                section_len,
+            } => write!(
+                f,
+                "Function \"{name}\" at {address:#x} ({size:#x} bytes) extends beyond its section ({section_len:#x} bytes)!"
+            ),
+            ExecutableError::FunctionOutsideSections { name, address } => write!(
+                f,
+                "Function \"{name}\" at {address:#x} isn't located in a code section!"
+            ),
+            ExecutableError::InvalidDump { reason } => {
+                write!(f, "Invalid memory dump: {reason}!")
+            }
+            ExecutableError::ModuleNotFound { module_name } => {
+                write!(f, "Module \"{module_name}\" not found in the dump!")
+            }
+            ExecutableError::VersionNotFound { version } => {
+                write!(f, "Version \"{version}\" not found in the mapping!")
+            }
+            ExecutableError::Context { context, error } => write!(f, "{error} ({context})"),
             _ => std::fmt::Debug::fmt(self, f),
         }
     }
@@ -40,7 +258,9 @@ impl Error for ExecutableError {
         match self {
             ExecutableError::ObjectError { error } => Some(error),
             ExecutableError::PdbError { error } => Some(error),
+            ExecutableError::DwarfError { error } => Some(error),
             ExecutableError::WriteError { error } => Some(error),
+            ExecutableError::Context { error, .. } => Some(error.as_ref()),
             _ => None,
         }
     }
@@ -58,6 +278,12 @@ impl From<pdb::Error> for ExecutableError {
     }
 }
 
+impl From<gimli::Error> for ExecutableError {
+    fn from(error: gimli::Error) -> Self {
+        Self::DwarfError { error }
+    }
+}
+
 impl From<capstone::Error> for ExecutableError {
     fn from(error: capstone::Error) -> Self {
         Self::CapstoneError { error }
@@ -70,38 +296,421 @@ impl From<std::fmt::Error> for ExecutableError {
     }
 }
 
+/// Progress of the reimplementation of a function, declared in the mapping.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WorkStatus {
+    Matching,
+    Wip,
+    /// Placeholder implementation, to be rewritten.
+    Stub,
+    NotStarted,
+}
+
+impl WorkStatus {
+    pub const ALL: [WorkStatus; 4] = [
+        WorkStatus::Matching,
+        WorkStatus::Wip,
+        WorkStatus::Stub,
+        WorkStatus::NotStarted,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WorkStatus::Matching => "matching",
+            WorkStatus::Wip => "wip",
+            WorkStatus::Stub => "stub",
+            WorkStatus::NotStarted => "not-started",
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct FunctionDef {
     pub name: Option<String>,
     pub address: usize,
     pub size: usize,
+    /// Stable identifier pinned in the mapping, kept when the function is renamed.
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Minimum match percentage enforced by `check`.
+    #[serde(default)]
+    pub min_percent: Option<f32>,
+    /// Other names of the function, such as its decorated name in the
+    /// reimplementation symbols (`?Foo@@YAXXZ`).
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Leave the function out of the stats.
+    #[serde(default)]
+    pub ignored: bool,
+    /// Progress of the reimplementation, guessed from the match percentage when unset.
+    #[serde(default)]
+    pub status: Option<WorkStatus>,
+    /// Location of the function in the other versions of the executable, by
+    /// version name (`[function.versions."1.02d"]`).
+    #[serde(default)]
+    pub versions: BTreeMap<String, VersionDef>,
+}
+
+impl FunctionDef {
+    /// Whether the function is known under a name or alias.
+    pub fn has_name(&self, name: &str) -> bool {
+        self.name.as_deref() == Some(name) || self.aliases.iter().any(|x| x == name)
+    }
+}
+
+/// Location of a function in another version of the executable.
+#[derive(Clone, Debug, Deserialize)]
+pub struct VersionDef {
+    pub address: usize,
+    /// Size in bytes, the one of the default version when unset.
+    pub size: Option<usize>,
+}
+
+/// A global variable of the original executable, the `[[data]]` entries of
+/// the mapping.
+#[derive(Clone, Debug, Deserialize)]
+pub struct DataDef {
+    pub name: String,
+    pub address: usize,
+    /// Size in bytes, the variable extends up to the next one when unset.
+    #[serde(default)]
+    pub size: usize,
+    /// Name of the section containing the variable (`.data`), checked by
+    /// `validate-mapping`.
+    pub section: Option<String>,
 }
 
+/// A vtable of the original executable, the `[[vtable]]` entries of the
+/// mapping, compared slot by slot with the reimplementation.
 #[derive(Clone, Debug, Deserialize)]
+pub struct VtableDef {
+    /// Name of the vtable symbol in the reimplementation (`??_7Player@@6B@`).
+    pub name: String,
+    pub address: usize,
+    /// Size in bytes, a pointer per virtual method.
+    pub size: usize,
+}
+
+/// Functions left out of the stats, the `[ignore]` table of the mapping.
+///
+/// Data blobs, compiler thunks and CRT functions aren't meant to be
+/// reimplemented and would otherwise drag the global percentage down.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct IgnoreConfig {
+    /// Names of the ignored functions.
+    #[serde(default)]
+    pub functions: Vec<String>,
+    /// Prefixes of the names of the ignored functions (`__crt`).
+    #[serde(default)]
+    pub prefixes: Vec<String>,
+}
+
+/// Settings of the executables, the `[executable]` table of the mapping.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ExecutableConfig {
+    /// Architecture of the executables, x86 by default.
+    pub arch: Option<arch::Arch>,
+    /// Either 32 or 64, used when `arch` isn't set.
+    pub bitness: Option<u8>,
+    /// Syntax of the disassembly, Intel by default.
+    pub syntax: Option<arch::Syntax>,
+    /// Image base the mapping addresses are based on. Functions are rebased
+    /// when the original executable is loaded at another address.
+    pub image_base: Option<usize>,
+    /// Demangle the Itanium (GCC, MinGW) names of the symbol tables.
+    #[serde(default)]
+    pub demangle_symbols: bool,
+    /// Address the original executable is loaded at when it is a flat binary
+    /// without headers, such as a memory dump, see
+    /// [`Executable::from_raw_with_mapping`].
+    pub raw_base: Option<usize>,
+    /// File name of the module compared when the original executable is a
+    /// memory dump, the executable of the process by default.
+    pub dump_module: Option<String>,
+    /// Version of the executable the addresses of the functions are given
+    /// for, the other versions being listed by each function.
+    pub version: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
 pub struct Mapping {
+    /// Name of the only section containing code, every executable section
+    /// is used by default.
+    pub section: Option<String>,
+    #[serde(default)]
+    pub executable: ExecutableConfig,
+    /// Minimum global match percentage enforced by `check`.
+    pub min_global_percent: Option<f32>,
+    #[serde(default)]
+    pub badge: badge::BadgeConfig,
+    #[serde(default)]
+    pub ignore: IgnoreConfig,
     pub function: Option<Vec<FunctionDef>>,
+    #[serde(default)]
+    pub data: Vec<DataDef>,
+    #[serde(default)]
+    pub vtable: Vec<VtableDef>,
 }
 
 impl Mapping {
+    /// Name of the only section containing code, if configured.
+    pub fn section_name(&self) -> Option<&str> {
+        self.section.as_deref()
+    }
+
+    /// Architecture of the executables.
+    pub fn arch(&self) -> arch::Arch {
+        match (self.executable.arch, self.executable.bitness) {
+            (Some(arch), _) => arch,
+            (None, Some(64)) => arch::Arch::X86_64,
+            (None, _) => arch::Arch::X86,
+        }
+    }
+
+    /// Syntax of the disassembly.
+    pub fn syntax(&self) -> arch::Syntax {
+        self.executable.syntax.unwrap_or_default()
+    }
+
+    /// Address of a mapping function in an executable, rebased when the
+    /// executable isn't loaded at the image base of the mapping.
+    pub fn rebase(&self, raw_obj: &File, address: usize) -> Option<usize> {
+        self.rebase_to(raw_obj.relative_address_base() as usize, address)
+    }
+
+    /// Address of a mapping function in an executable loaded at `base`.
+    pub fn rebase_to(&self, base: usize, address: usize) -> Option<usize> {
+        match self.executable.image_base {
+            Some(image_base) => address.checked_sub(image_base).map(|x| x + base),
+            None => Some(address),
+        }
+    }
+
+    /// Definition of a function, looked up by name or alias.
     pub fn get_function_def(&self, name: &str) -> Option<&FunctionDef> {
         if let Some(function) = &self.function {
             for f in function {
-                if let Some(f_name) = &f.name {
-                    if f_name == name {
-                        return Some(f);
-                    }
+                if f.has_name(name) {
+                    return Some(f);
                 }
             }
         }
 
         None
     }
+
+    /// Whether a function is left out of the stats, by its definition or the `[ignore]` table.
+    pub fn is_ignored(&self, name: &str) -> bool {
+        self.get_function_def(name).is_some_and(|x| x.ignored)
+            || self.ignore.functions.iter().any(|x| x == name)
+            || self
+                .ignore
+                .prefixes
+                .iter()
+                .any(|x| name.starts_with(x.as_str()))
+    }
+
+    /// Names of the versions of the executable the mapping describes.
+    pub fn versions(&self) -> Vec<&str> {
+        let versions = self
+            .function
+            .iter()
+            .flatten()
+            .flat_map(|x| x.versions.keys().map(String::as_str));
+        let mut res: Vec<&str> = self
+            .executable
+            .version
+            .as_deref()
+            .into_iter()
+            .chain(versions)
+            .collect();
+
+        res.sort();
+        res.dedup();
+        res
+    }
+
+    /// Use the addresses of the functions in another version of the
+    /// executable, leaving out the functions not located in that version.
+    pub fn select_version(&mut self, version: &str) -> Result<(), ExecutableError> {
+        if self.executable.version.as_deref() == Some(version) {
+            return Ok(());
+        }
+
+        if !self.versions().contains(&version) {
+            return Err(ExecutableError::VersionNotFound {
+                version: version.into(),
+            });
+        }
+
+        if let Some(functions) = &mut self.function {
+            functions.retain_mut(|function| match function.versions.get(version) {
+                Some(location) => {
+                    function.address = location.address;
+                    function.size = location.size.unwrap_or(function.size);
+                    true
+                }
+                None => false,
+            });
+        }
+
+        self.executable.version = Some(version.into());
+
+        Ok(())
+    }
+
+    /// Stable identifier of a function, pinned in the mapping or derived from
+    /// its name and address.
+    pub fn function_id(&self, name: &str, address: usize) -> String {
+        self.get_function_def(name)
+            .and_then(|x| x.id.clone())
+            .unwrap_or_else(|| id::stable_id(name, address))
+    }
+
+    /// Build a mapping from an IDA names export (IDC script or names list).
+    pub fn from_ida_names(content: &str) -> Self {
+        let names = ida::IdaNames::parse(content);
+        let function = names
+            .named_functions()
+            .into_iter()
+            .map(|(name, address, size)| FunctionDef {
+                name: Some(name),
+                address,
+                size,
+                id: None,
+                min_percent: None,
+                aliases: Vec::new(),
+                ignored: false,
+                status: None,
+                versions: BTreeMap::new(),
+            })
+            .collect();
+
+        Self {
+            function: Some(function),
+            data: names
+                .named_data()
+                .into_iter()
+                .map(|(name, address)| DataDef {
+                    name,
+                    address,
+                    size: 0,
+                    section: None,
+                })
+                .collect(),
+            ..Self::default()
+        }
+    }
+}
+
+/// A section of an object containing code, or data for
+/// [`Executable::string_at`].
+///
+/// The content is shared with the functions of the section, see [`FunctionData`].
+#[derive(Clone, Debug)]
+struct CodeSection {
+    name: String,
+    address: usize,
+    data: Arc<[u8]>,
+}
+
+impl CodeSection {
+    /// Collect the code sections of an object, or only the one named `section_name`.
+    fn from_object(
+        raw_obj: &File,
+        section_name: Option<&str>,
+    ) -> Result<Vec<Self>, ExecutableError> {
+        let mut res = Vec::new();
+
+        for section in raw_obj.sections() {
+            let name = section.name()?;
+            let is_selected = match section_name {
+                Some(section_name) => name == section_name,
+                None => section.kind() == SectionKind::Text,
+            };
+
+            if is_selected {
+                res.push(Self {
+                    name: name.into(),
+                    address: section.address() as usize,
+                    data: section.data()?.into(),
+                });
+            }
+        }
+
+        if let (Some(section_name), true) = (section_name, res.is_empty()) {
+            return Err(ExecutableError::SectionNotFound {
+                section_name: section_name.into(),
+            });
+        }
+
+        Ok(res)
+    }
+
+    fn range(&self) -> Range<usize> {
+        self.address..self.address + self.data.len()
+    }
+
+    /// Bytes of the section at the given offsets.
+    fn slice(&self, range: Range<usize>) -> FunctionData {
+        FunctionData::new(self.data.clone(), range)
+    }
+
+    /// Collect the sections of an object containing initialized data.
+    fn data_sections(raw_obj: &File) -> Result<Vec<Self>, ExecutableError> {
+        let mut res = Vec::new();
+
+        for section in raw_obj.sections().filter(|x| {
+            matches!(
+                x.kind(),
+                SectionKind::Data | SectionKind::ReadOnlyData | SectionKind::ReadOnlyString
+            )
+        }) {
+            res.push(Self {
+                name: section.name()?.into(),
+                address: section.address() as usize,
+                data: section.data()?.into(),
+            });
+        }
+
+        Ok(res)
+    }
+
+    /// Find the section containing an address.
+    fn find(sections: &[Self], address: usize) -> Option<&Self> {
+        sections.iter().find(|x| x.range().contains(&address))
+    }
+}
+
+/// Name of the code section of flat binaries, see [`Executable::from_raw_with_mapping`].
+pub const RAW_SECTION_NAME: &str = "raw";
+
+/// Stats of the functions of an executable compared to another one.
+#[derive(Clone, Debug, Default)]
+pub struct GeneratedStats {
+    pub stats: FunctionStats,
+    /// Instructions compared of each function, when they were counted.
+    pub instruction_counts: HashMap<String, InstructionCounts>,
+    /// Functions left out because they exceeded the limits.
+    pub skipped: Vec<SkippedFunction>,
 }
 
 /// Represent some executable
 #[derive(Clone, Default, Debug)]
 pub struct Executable {
     functions: HashMap<String, Function>,
+    section_ranges: Vec<Range<usize>>,
+    /// Sections containing code, shared with the functions.
+    code_sections: Vec<CodeSection>,
+    /// Names of the functions by alias.
+    aliases: HashMap<String, String>,
+    /// Names of the functions left out of the stats.
+    ignored: HashSet<String>,
+    /// Global variables by address.
+    data_symbols: BTreeMap<usize, DataSymbol>,
+    /// Sections containing initialized data, searched for strings.
+    data_sections: Vec<CodeSection>,
 }
 
 impl Executable {
@@ -109,7 +718,8 @@ impl Executable {
         &mut self,
         name: String,
         address: usize,
-        data: Vec<u8>,
+        data: FunctionData,
+        source: FunctionSource,
     ) -> Result<(), ExecutableError> {
         if self.functions.contains_key(&name) {
             return Err(ExecutableError::FunctionNameConflict {
@@ -117,12 +727,21 @@ impl Executable {
             });
         }
 
+        let section = self
+            .code_sections
+            .iter()
+            .find(|x| x.range().contains(&address))
+            .map(|x| x.name.clone());
+
         self.functions.insert(
             name.clone(),
             Function {
                 name,
                 address,
                 data,
+                section,
+                source,
+                module: None,
             },
         );
 
@@ -133,12 +752,236 @@ impl Executable {
         self.functions.len()
     }
 
+    /// Number of functions counted in the stats, ignored ones excluded.
+    pub fn stats_functions_count(&self) -> usize {
+        self.functions.len() - self.ignored.len()
+    }
+
+    /// Total size of the functions counted in the stats, ignored ones excluded.
+    pub fn stats_functions_size(&self) -> usize {
+        self.functions
+            .iter()
+            .filter(|(name, _)| !self.is_ignored(name))
+            .map(|(_, x)| x.data.len())
+            .sum()
+    }
+
+    /// Whether a function is ignored by the mapping and left out of the stats.
+    pub fn is_ignored(&self, name: &str) -> bool {
+        self.ignored.contains(name)
+    }
+
     pub fn functions_iter(&self) -> Iter<'_, String, Function> {
         self.functions.iter()
     }
 
+    /// Find a function by name or by one of its aliases in the mapping.
     pub fn get_function(&self, name: &String) -> Option<&Function> {
-        self.functions.get(name)
+        self.functions
+            .get(name)
+            .or_else(|| self.functions.get(self.aliases.get(name)?))
+    }
+
+    /// Register the aliases of the mapping, renaming the functions loaded
+    /// under an alias to their name in the mapping.
+    fn apply_aliases(&mut self, mapping: &Mapping) {
+        for function in mapping.function.iter().flatten() {
+            let name = match &function.name {
+                Some(name) => name,
+                None => continue,
+            };
+
+            for alias in &function.aliases {
+                self.aliases.insert(alias.clone(), name.clone());
+
+                if self.functions.contains_key(name) {
+                    continue;
+                }
+
+                if let Some(mut aliased) = self.functions.remove(alias) {
+                    aliased.name = name.clone();
+                    self.functions.insert(name.clone(), aliased);
+                }
+            }
+        }
+    }
+
+    /// Function of this executable paired with a function of another one.
+    ///
+    /// When paired by address, a function with the same name is preferred
+    /// among the functions at that address.
+    pub fn paired_function(&self, function: &Function, pairing: Pairing) -> Option<&Function> {
+        self.paired_function_at(&function.name, function.address, pairing)
+    }
+
+    /// Same as [`Self::paired_function`], for the function with the given
+    /// name and address.
+    pub fn paired_function_at(
+        &self,
+        name: &String,
+        address: usize,
+        pairing: Pairing,
+    ) -> Option<&Function> {
+        match pairing {
+            Pairing::Name => self.get_function(name),
+            Pairing::Address => self
+                .get_function(name)
+                .filter(|x| x.address == address)
+                .or_else(|| self.get_function_by_address(address)),
+        }
+    }
+
+    /// Register a global variable, the first one defined at an address is kept.
+    pub fn add_data_symbol(&mut self, name: String, address: usize, size: usize) {
+        self.data_symbols.entry(address).or_insert(DataSymbol {
+            name,
+            address,
+            size,
+        });
+    }
+
+    /// Name the slots of the import address table after the functions they
+    /// point to, replacing the other symbols at their address.
+    pub fn add_imports(&mut self, slots: &[imports::ImportSlot]) {
+        for slot in slots {
+            self.data_symbols.insert(
+                slot.address,
+                DataSymbol {
+                    name: slot.symbol_name(),
+                    address: slot.address,
+                    size: slot.size,
+                },
+            );
+        }
+    }
+
+    /// Global variables, sorted by address.
+    pub fn data_symbols(&self) -> impl Iterator<Item = &DataSymbol> {
+        self.data_symbols.values()
+    }
+
+    /// Global variable containing an address, with the offset of the address in it.
+    ///
+    /// Variables of unknown size extend up to the next one of their section.
+    pub fn get_data_symbol_by_address(&self, address: usize) -> Option<(&DataSymbol, usize)> {
+        let (_, symbol) = self.data_symbols.range(..=address).next_back()?;
+        let offset = address - symbol.address;
+
+        let is_contained = if symbol.size != 0 {
+            offset < symbol.size
+        } else {
+            self.section_ranges
+                .iter()
+                .any(|x| x.contains(&symbol.address) && x.contains(&address))
+        };
+
+        is_contained.then_some((symbol, offset))
+    }
+
+    /// Find a global variable by name.
+    pub fn get_data_symbol(&self, name: &str) -> Option<&DataSymbol> {
+        self.data_symbols.values().find(|x| x.name == name)
+    }
+
+    /// Initialized bytes of a global variable, by name.
+    ///
+    /// Variables of unknown size extend up to the next one of their section.
+    /// Bytes past the initialized part of the section are left out.
+    pub fn get_data(&self, name: &str) -> Option<&[u8]> {
+        let symbol = self.get_data_symbol(name)?;
+        let section = CodeSection::find(&self.data_sections, symbol.address)?;
+        let end = match symbol.size {
+            0 => self
+                .data_symbols
+                .range(symbol.address + 1..)
+                .next()
+                .map_or(usize::MAX, |(address, _)| *address),
+            size => symbol.address + size,
+        };
+
+        let start = symbol.address - section.address;
+        let end = end.min(section.range().end) - section.address;
+
+        Some(&section.data[start..end])
+    }
+
+    /// Compare a global variable with the one of the same name in another
+    /// executable, byte by byte over the size of this one.
+    pub fn compute_data_diff(&self, other: &Self, name: &str) -> Option<f32> {
+        let data = self.get_data(name).filter(|x| !x.is_empty())?;
+        let other_data = other.get_data(name)?;
+
+        Some(diff::raw_score(
+            data,
+            &other_data[..other_data.len().min(data.len())],
+        ))
+    }
+
+    /// String literal stored at an address of a data section, quoted, see
+    /// [`strings::decode`].
+    pub fn string_at(&self, address: usize) -> Option<String> {
+        let section = CodeSection::find(&self.data_sections, address)?;
+
+        strings::decode(&section.data[address - section.address..])
+    }
+
+    /// String literals of the data sections with their address, see
+    /// [`strings::extract`].
+    pub fn strings(&self) -> Vec<(usize, String)> {
+        self.data_sections
+            .iter()
+            .flat_map(|section| strings::extract(&section.data, section.address))
+            .collect()
+    }
+
+    /// Jump of the one-instruction thunk at an address, if any.
+    fn thunk_at(&self, address: usize) -> Option<Thunk<'_>> {
+        let section = CodeSection::find(&self.code_sections, address)?;
+        let bytes = &section.data[address - section.address..];
+
+        match *bytes {
+            // jmp rel32, as emitted for incremental linking.
+            [0xe9, a, b, c, d, ..] => {
+                let displacement = i32::from_le_bytes([a, b, c, d]) as isize;
+
+                Some(Thunk::Jump(address.wrapping_add_signed(5 + displacement)))
+            }
+            // jmp [slot], absolute in 32-bit code and RIP-relative in 64-bit code.
+            [0xff, 0x25, a, b, c, d, ..] => {
+                let value = u32::from_le_bytes([a, b, c, d]);
+                let slots = [
+                    value as usize,
+                    address.wrapping_add_signed(6 + value as i32 as isize),
+                ];
+
+                slots.into_iter().find_map(|slot| {
+                    let (symbol, offset) = self.get_data_symbol_by_address(slot)?;
+
+                    (offset == 0).then_some(Thunk::Import(symbol))
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Function or import called at an address, following the jump thunks
+    /// to the actual callee.
+    ///
+    /// The thunk itself is returned when the function it jumps to is unknown.
+    pub fn resolve_call_target(&self, address: usize) -> Option<CallTarget<'_>> {
+        let mut current = address;
+
+        for _ in 0..MAX_THUNK_DEPTH {
+            match self.thunk_at(current) {
+                Some(Thunk::Jump(target)) => current = target,
+                Some(Thunk::Import(symbol)) => return Some(CallTarget::Import(symbol)),
+                None => break,
+            }
+        }
+
+        self.get_function_by_address(current)
+            .or_else(|| self.get_function_by_address(address))
+            .map(CallTarget::Function)
     }
 
     pub fn get_function_by_address(&self, address: usize) -> Option<&Function> {
@@ -147,176 +990,834 @@ impl Executable {
             .find(|&function| function.address == address)
     }
 
-    pub fn get_function_stat(&self, other: &Self, name: &String) -> Option<f32> {
-        match (self.get_function(name), other.get_function(name)) {
-            (Some(a), Some(b)) => Some(a.compute_raw_diff(b)),
-            _ => None,
+    /// Address ranges of the code sections of the executable.
+    pub fn code_ranges(&self) -> Vec<Range<usize>> {
+        self.code_sections.iter().map(|x| x.range()).collect()
+    }
+
+    /// Bytes of the code sections at an address range, when a single
+    /// section contains it.
+    pub fn code_bytes(&self, range: Range<usize>) -> Option<&[u8]> {
+        let section = CodeSection::find(&self.code_sections, range.start)?;
+
+        section
+            .data
+            .get(range.start - section.address..range.end.checked_sub(section.address)?)
+    }
+
+    /// Address ranges of the sections of the executable.
+    pub fn section_ranges(&self) -> &[Range<usize>] {
+        &self.section_ranges
+    }
+
+    /// Decode a function of this executable for comparison.
+    pub fn decode_function(
+        &self,
+        ctx: &Capstone,
+        function: &Function,
+        options: &DiffOptions,
+    ) -> Result<Vec<DecodedInstruction>, ExecutableError> {
+        function
+            .compared_data(ctx, options)
+            .and_then(|data| {
+                diff::decode_ranges(
+                    ctx,
+                    data,
+                    &function.compared_ranges(ctx, data.len())?,
+                    &self.section_ranges,
+                    options,
+                )
+            })
+            .map_err(|err| err.with_context(ErrorContext::new("decoding").function(&function.name)))
+    }
+
+    /// Compare a function of this executable with a function of another executable.
+    pub fn compute_function_diff(
+        &self,
+        ctx: &Capstone,
+        function: &Function,
+        other: &Self,
+        other_function: &Function,
+        options: &DiffOptions,
+    ) -> Result<f32, ExecutableError> {
+        if !options.needs_decoding() {
+            return function.compute_raw_diff(ctx, other_function, options);
         }
+
+        let instructions = self.decode_function(ctx, function, options)?;
+        let other_instructions = other.decode_function(ctx, other_function, options)?;
+
+        Ok(diff::score(
+            options.algorithm,
+            (&instructions, function.compared_data(ctx, options)?),
+            (
+                &other_instructions,
+                other_function.compared_data(ctx, options)?,
+            ),
+        ))
     }
 
-    pub fn generate_stats(&self, other: &Self) -> HashMap<String, Option<f32>> {
-        let mut res = HashMap::new();
+    pub fn get_function_stat(
+        &self,
+        ctx: &Capstone,
+        other: &Self,
+        name: &String,
+        options: &DiffOptions,
+    ) -> Result<Option<f32>, ExecutableError> {
+        let function = self.get_function(name);
+        let other_function = function.and_then(|x| other.paired_function(x, options.pairing));
+
+        match (function, other_function) {
+            (Some(a), Some(b)) => Ok(Some(self.compute_function_diff(ctx, a, other, b, options)?)),
+            _ => Ok(None),
+        }
+    }
 
-        for function_name in self.functions.keys() {
-            res.insert(
-                function_name.clone(),
-                self.get_function_stat(other, function_name),
-            );
+    /// Same as [`Self::get_function_stat`], the functions being decoded even
+    /// when the options don't need it, so that the instructions compared are
+    /// counted.
+    pub(crate) fn get_counted_function_stat(
+        &self,
+        ctx: &Capstone,
+        other: &Self,
+        function: &Function,
+        options: &DiffOptions,
+    ) -> Result<(Option<f32>, InstructionCounts), ExecutableError> {
+        let instructions = self.decode_function(ctx, function, options)?;
+        let mut counts = InstructionCounts {
+            original: instructions.len(),
+            reimplementation: None,
+        };
+
+        let other_function = match other.paired_function(function, options.pairing) {
+            Some(other_function) => other_function,
+            None => return Ok((None, counts)),
+        };
+
+        let other_instructions = other.decode_function(ctx, other_function, options)?;
+        counts.reimplementation = Some(other_instructions.len());
+
+        let percent = diff::score(
+            options.algorithm,
+            (&instructions, function.compared_data(ctx, options)?),
+            (
+                &other_instructions,
+                other_function.compared_data(ctx, options)?,
+            ),
+        );
+
+        Ok((Some(percent), counts))
+    }
+
+    pub fn generate_stats(
+        &self,
+        ctx: &Capstone,
+        other: &Self,
+        options: &DiffOptions,
+    ) -> Result<FunctionStats, ExecutableError> {
+        let (res, _) =
+            self.generate_stats_with_limits(ctx, other, options, &FunctionLimits::default())?;
+
+        Ok(res)
+    }
+
+    /// Generate stats, leaving out the functions exceeding the given limits.
+    ///
+    /// Skipped functions are returned separately and absent from the stats.
+    pub fn generate_stats_with_limits(
+        &self,
+        ctx: &Capstone,
+        other: &Self,
+        options: &DiffOptions,
+        limits: &FunctionLimits,
+    ) -> Result<(FunctionStats, Vec<SkippedFunction>), ExecutableError> {
+        let stats = self.generate_stats_with_counts(ctx, other, options, limits, false)?;
+
+        Ok((stats.stats, stats.skipped))
+    }
+
+    /// Same as [`Self::generate_stats_with_limits`], also counting the
+    /// instructions compared when `count_instructions` is set.
+    pub fn generate_stats_with_counts(
+        &self,
+        ctx: &Capstone,
+        other: &Self,
+        options: &DiffOptions,
+        limits: &FunctionLimits,
+        count_instructions: bool,
+    ) -> Result<GeneratedStats, ExecutableError> {
+        self.generate_stats_by(other, options, limits, |function_name, function| {
+            match count_instructions {
+                true => self
+                    .get_counted_function_stat(ctx, other, function, options)
+                    .map(|(stat, counts)| (stat, Some(counts))),
+                false => self
+                    .get_function_stat(ctx, other, function_name, options)
+                    .map(|stat| (stat, None)),
+            }
+        })
+    }
+
+    /// Generate stats with `stat` scoring each function, leaving out the
+    /// ignored functions and the ones exceeding the limits.
+    ///
+    /// Shared by [`Self::generate_stats_with_counts`] and
+    /// [`cache::DisassemblyCache::generate_stats`].
+    pub(crate) fn generate_stats_by(
+        &self,
+        other: &Self,
+        options: &DiffOptions,
+        limits: &FunctionLimits,
+        mut stat: impl FnMut(
+            &String,
+            &Function,
+        )
+            -> Result<(Option<f32>, Option<InstructionCounts>), ExecutableError>,
+    ) -> Result<GeneratedStats, ExecutableError> {
+        let mut res = FunctionStats::new();
+        let mut instruction_counts = HashMap::new();
+        let mut skipped = Vec::new();
+
+        for (function_name, function) in &self.functions {
+            if self.is_ignored(function_name) {
+                continue;
+            }
+
+            let other_size = other
+                .paired_function(function, options.pairing)
+                .map(|x| x.data.len())
+                .unwrap_or(0);
+
+            if let Some(reason) = limits.check_size(function.data.len().max(other_size)) {
+                skipped.push(SkippedFunction {
+                    name: function_name.clone(),
+                    reason,
+                });
+                continue;
+            }
+
+            let start = Instant::now();
+            let deadline = limits.time_budget.map(|x| start + x);
+            let stat =
+                diff::with_deadline(deadline, || stat(function_name, function)).transpose()?;
+
+            let reason = match stat {
+                Some(_) => limits.check_time(start.elapsed()),
+                None => limits.over_budget(start.elapsed()),
+            };
+
+            if let Some(reason) = reason {
+                skipped.push(SkippedFunction {
+                    name: function_name.clone(),
+                    reason,
+                });
+                continue;
+            }
+
+            if let Some((stat, counts)) = stat {
+                res.insert(function_name.clone(), stat);
+
+                if let Some(counts) = counts {
+                    instruction_counts.insert(function_name.clone(), counts);
+                }
+            }
         }
 
-        res
+        skipped.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(GeneratedStats {
+            stats: res,
+            instruction_counts,
+            skipped,
+        })
     }
 
+    /// Load the functions of the symbol table, from every code section.
     pub fn from_object(raw_obj: &File) -> Result<Self, ExecutableError> {
+        Self::from_object_in_sections(raw_obj, None, false)
+    }
+
+    /// Load the functions of the symbol table located in the given code section.
+    pub fn from_object_with_section(
+        raw_obj: &File,
+        section_name: &str,
+    ) -> Result<Self, ExecutableError> {
+        Self::from_object_in_sections(raw_obj, Some(section_name), false)
+    }
+
+    /// Load the functions of the symbol table, Itanium mangled names being
+    /// demangled when `demangle_symbols` is set.
+    fn from_object_in_sections(
+        raw_obj: &File,
+        section_name: Option<&str>,
+        demangle_symbols: bool,
+    ) -> Result<Self, ExecutableError> {
         let mut res: Executable = Self::default();
 
-        if let Some(text_sec) = raw_obj.section_by_name(".text") {
-            let text_section_address = text_sec.address() as usize;
-            let text_data = text_sec.data()?;
+        for section in raw_obj.sections() {
+            let address = section.address() as usize;
 
-            for sym in raw_obj
-                .symbols()
-                .filter(|x| x.kind() == SymbolKind::Text && x.size() != 0)
-            {
-                let name = sym.name()?;
+            if address != 0 {
+                res.section_ranges
+                    .push(address..address + section.size() as usize);
+            }
+        }
+
+        res.code_sections = CodeSection::from_object(raw_obj, section_name)?;
+        res.data_sections = CodeSection::data_sections(raw_obj)?;
+        let code_sections = res.code_sections.clone();
+
+        for sym in raw_obj.symbols().filter(|x| x.kind() == SymbolKind::Data) {
+            if let Ok(name) = sym.name() {
+                res.add_data_symbol(name.into(), sym.address() as usize, sym.size() as usize);
+            }
+        }
+
+        for sym in raw_obj
+            .symbols()
+            .filter(|x| x.kind() == SymbolKind::Text && x.size() != 0)
+        {
+            let name = sym.name()?;
+            let name = match demangle_symbols {
+                true => demangle::itanium(name).unwrap_or_else(|| name.into()),
+                false => name.into(),
+            };
+            let address = sym.address() as usize;
+
+            if let Some(section) = CodeSection::find(&code_sections, address) {
+                res.add_function_from_section(
+                    section,
+                    FunctionSource::SymbolTable,
+                    name,
+                    address - section.address,
+                    sym.size() as usize,
+                )?;
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn add_function_from_section(
+        &mut self,
+        section: &CodeSection,
+        source: FunctionSource,
+        name: String,
+        offset: usize,
+        len: usize,
+    ) -> Result<(), ExecutableError> {
+        if len == 0 {
+            return Ok(());
+        }
+
+        if offset >= section.data.len() {
+            return Err(ExecutableError::FunctionOutOfRange {
+                name,
+                address: section.address + offset,
+                size: len,
+                section_len: section.data.len(),
+            });
+        }
+
+        let len = len.min(section.data.len() - offset);
+        let data = section.slice(offset..offset + len);
+
+        match self.add_function(name, section.address + offset, data, source) {
+            Ok(()) | Err(ExecutableError::FunctionNameConflict { .. }) => {}
+            Err(err) => return Err(err),
+        }
+
+        Ok(())
+    }
+
+    pub fn from_object_with_pdb<'s, S>(
+        raw_obj: &File,
+        mapping: Mapping,
+        mut pdb_file: PDB<'s, S>,
+    ) -> Result<Self, ExecutableError>
+    where
+        S: Source<'s> + 's,
+    {
+        let mut res = Self::from_object_in_sections(
+            raw_obj,
+            mapping.section_name(),
+            mapping.executable.demangle_symbols,
+        )?;
+        let code_sections = res.code_sections.clone();
+
+        // PDB offsets are relative to the section they belong to.
+        let section_by_index = |index: u16| {
+            let section = raw_obj
+                .section_by_index(SectionIndex(index as usize))
+                .ok()?;
+            CodeSection::find(&code_sections, section.address() as usize)
+        };
 
-                let address = sym.address() as usize;
-                let size = sym.size() as usize;
-                let offset = address - text_section_address;
-                let data = text_data[offset..offset + size].to_vec();
+        let dbi = pdb_file.debug_information()?;
+        let mut modules = dbi.modules()?;
+
+        while let Some(module) = modules.next()? {
+            let module_name = module_file_name(&module.module_name());
+
+            let module_context = || ErrorContext::new("reading the symbols").file(&module_name);
+            let module_info = pdb_file
+                .module_info(&module)
+                .map_err(|err| ExecutableError::from(err).with_context(module_context()))?;
+
+            if let Some(module_info) = module_info {
+                let mut iter = module_info
+                    .symbols()
+                    .map_err(|err| ExecutableError::from(err).with_context(module_context()))?;
+
+                while let Some(symbol) = iter
+                    .next()
+                    .map_err(|err| ExecutableError::from(err).with_context(module_context()))?
+                {
+                    if let Ok(SymbolData::Procedure(ProcedureSymbol {
+                        name, offset, len, ..
+                    })) = symbol.parse()
+                    {
+                        if let Some(section) = section_by_index(offset.section) {
+                            let name: String = name.to_string().into();
+
+                            res.add_function_from_section(
+                                section,
+                                FunctionSource::Pdb,
+                                name.clone(),
+                                offset.offset as usize,
+                                len as usize,
+                            )?;
+
+                            if let Some(function) = res.functions.get_mut(&name) {
+                                function.module.get_or_insert_with(|| module_name.clone());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let symbol_table = pdb_file.global_symbols()?;
+
+        let data_address = |offset: pdb::PdbInternalSectionOffset| {
+            let section = raw_obj
+                .section_by_index(SectionIndex(offset.section as usize))
+                .ok()?;
+            Some(section.address() as usize + offset.offset as usize)
+        };
+
+        let mut symbols = symbol_table.iter();
+        while let Some(symbol) = symbols.next()? {
+            match symbol.parse() {
+                Ok(pdb::SymbolData::Public(PublicSymbol {
+                    code: false,
+                    function: false,
+                    offset,
+                    name,
+                    ..
+                }))
+                | Ok(pdb::SymbolData::Data(pdb::DataSymbol { offset, name, .. })) => {
+                    if let Some(address) = data_address(offset) {
+                        res.add_data_symbol(name.to_string().into(), address, 0);
+                    }
 
-                if size == 0 {
                     continue;
                 }
+                _ => {}
+            }
+
+            if let Ok(pdb::SymbolData::Public(PublicSymbol {
+                function: true,
+                offset,
+                name,
+                ..
+            })) = symbol.parse()
+            {
+                let name = name.to_string();
+                let len = mapping.get_function_def(&name).map(|x| x.size).unwrap_or(0);
+
+                if let Some(section) = section_by_index(offset.section) {
+                    res.add_function_from_section(
+                        section,
+                        FunctionSource::Pdb,
+                        name.into(),
+                        offset.offset as usize,
+                        len,
+                    )?;
+                }
+            }
+        }
+
+        res.apply_aliases(&mapping);
+
+        Ok(res)
+    }
+
+    /// Load the functions of an executable from its DWARF debug information,
+    /// as produced by clang or MinGW.
+    ///
+    /// Functions without a known size use the size defined in the mapping.
+    pub fn from_object_with_dwarf(
+        raw_obj: &File,
+        mapping: Mapping,
+    ) -> Result<Self, ExecutableError> {
+        let mut res = Self::from_object_in_sections(
+            raw_obj,
+            mapping.section_name(),
+            mapping.executable.demangle_symbols,
+        )?;
+        let code_sections = res.code_sections.clone();
+
+        for function in dwarf::functions(raw_obj)? {
+            let section = match CodeSection::find(&code_sections, function.address) {
+                Some(section) => section,
+                None => continue,
+            };
+
+            let len = function
+                .size
+                .or_else(|| mapping.get_function_def(&function.name).map(|x| x.size))
+                .unwrap_or(0);
+
+            res.add_function_from_section(
+                section,
+                FunctionSource::Dwarf,
+                function.name,
+                function.address - section.address,
+                len,
+            )?;
+        }
+
+        res.apply_aliases(&mapping);
+
+        Ok(res)
+    }
+
+    /// Load the functions of an executable from the MSVC linker map file
+    /// produced alongside it.
+    ///
+    /// Map files do not record sizes: a function extends up to the next
+    /// symbol of its section, unless the mapping defines its size.
+    pub fn from_object_with_map(
+        raw_obj: &File,
+        mapping: Mapping,
+        map_content: &str,
+    ) -> Result<Self, ExecutableError> {
+        let mut res = Self::from_object_in_sections(
+            raw_obj,
+            mapping.section_name(),
+            mapping.executable.demangle_symbols,
+        )?;
+        let code_sections = res.code_sections.clone();
+
+        let mut symbols: Vec<MapSymbol> = map::parse_map(map_content);
+        symbols.sort_by_key(|x| x.address);
+
+        for (idx, symbol) in symbols.iter().enumerate() {
+            if !symbol.is_function {
+                res.add_data_symbol(symbol.name.clone(), symbol.address, 0);
+                continue;
+            }
+
+            let section = match CodeSection::find(&code_sections, symbol.address) {
+                Some(section) => section,
+                None => continue,
+            };
+
+            let end = symbols[idx + 1..]
+                .iter()
+                .map(|x| x.address)
+                .find(|address| *address > symbol.address)
+                .unwrap_or(usize::MAX)
+                .min(section.range().end);
+            let len = mapping
+                .get_function_def(&symbol.name)
+                .map(|x| x.size)
+                .unwrap_or(end - symbol.address);
+
+            res.add_function_from_section(
+                section,
+                FunctionSource::Map,
+                symbol.name.clone(),
+                symbol.address - section.address,
+                len,
+            )?;
+        }
+
+        res.apply_aliases(&mapping);
+
+        Ok(res)
+    }
+
+    pub fn from_object_with_mapping(
+        raw_obj: &File,
+        mapping: Mapping,
+    ) -> Result<Self, ExecutableError> {
+        let mut res = Self::from_object_in_sections(
+            raw_obj,
+            mapping.section_name(),
+            mapping.executable.demangle_symbols,
+        )?;
+
+        res.add_mapping(&mapping, |address| mapping.rebase(raw_obj, address))?;
+
+        Ok(res)
+    }
+
+    /// Load a flat binary without headers, such as a memory dump or an
+    /// extracted segment, with the functions of the mapping.
+    ///
+    /// The whole file is a single code section loaded at `base`, also
+    /// searched for strings. The mapping addresses are not rebased.
+    pub fn from_raw_with_mapping(
+        data: &[u8],
+        base: usize,
+        mapping: Mapping,
+    ) -> Result<Self, ExecutableError> {
+        let section = CodeSection {
+            name: RAW_SECTION_NAME.into(),
+            address: base,
+            data: data.into(),
+        };
+
+        let mut res = Self {
+            section_ranges: vec![section.range()],
+            code_sections: vec![section.clone()],
+            data_sections: vec![section],
+            ..Self::default()
+        };
+
+        res.add_mapping(&mapping, Some)?;
+
+        Ok(res)
+    }
+
+    /// Load a module of a memory dump with the functions of the mapping,
+    /// rebased to the `base` the module is loaded at.
+    ///
+    /// The functions overlapping the gaps of the dump are expected to be
+    /// removed from the mapping, see [`dump::overlaps_gap`].
+    pub fn from_dump_with_mapping(
+        sections: &[dump::DumpSection],
+        base: usize,
+        mapping: Mapping,
+    ) -> Result<Self, ExecutableError> {
+        let mut res = Self::default();
+
+        for section in sections {
+            let code_section = CodeSection {
+                name: section.name.clone(),
+                address: section.address,
+                data: section.data.as_slice().into(),
+            };
+
+            res.section_ranges.push(code_section.range());
 
-                res.add_function(name.into(), sym.address() as usize, data)?;
+            if section.is_code {
+                res.code_sections.push(code_section);
+            } else {
+                res.data_sections.push(code_section);
             }
         }
 
+        res.add_mapping(&mapping, |address| mapping.rebase_to(base, address))?;
+
         Ok(res)
     }
 
-    fn add_function_from_pdb(
+    /// Add the functions and global variables defined by the mapping, their
+    /// addresses translated by `rebase`.
+    fn add_mapping(
         &mut self,
-        text_section_address: usize,
-        text_data: &[u8],
-        name: String,
-        offset: usize,
-        len: usize,
+        mapping: &Mapping,
+        rebase: impl Fn(usize) -> Option<usize>,
     ) -> Result<(), ExecutableError> {
-        if len == 0 {
-            return Ok(());
-        }
+        let code_sections = self.code_sections.clone();
+
+        if let Some(functions) = &mapping.function {
+            for function in functions {
+                if let Some(name) = &function.name {
+                    let section = rebase(function.address).and_then(|address| {
+                        CodeSection::find(&code_sections, address).map(|x| (address, x))
+                    });
+
+                    let (address, section) = match section {
+                        Some(section) => section,
+                        None => {
+                            return Err(ExecutableError::FunctionOutsideSections {
+                                name: name.clone(),
+                                address: function.address,
+                            })
+                        }
+                    };
 
-        let data = text_data[offset..offset + len].to_vec();
+                    let offset = address - section.address;
 
-        match self.add_function(name, text_section_address + offset, data) {
-            Ok(()) | Err(ExecutableError::FunctionNameConflict { .. }) => {}
-            Err(err) => return Err(err),
-        }
+                    if offset
+                        .checked_add(function.size)
+                        .is_none_or(|end| end > section.data.len())
+                    {
+                        return Err(ExecutableError::FunctionOutOfRange {
+                            name: name.clone(),
+                            address: function.address,
+                            size: function.size,
+                            section_len: section.data.len(),
+                        });
+                    }
 
-        Ok(())
-    }
+                    let data = section.slice(offset..offset + function.size);
 
-    pub fn from_object_with_pdb<'s, S>(
-        raw_obj: &File,
-        mapping: Mapping,
-        mut pdb_file: PDB<'s, S>,
-    ) -> Result<Self, ExecutableError>
-    where
-        S: Source<'s> + 's,
-    {
-        let mut res = Self::from_object(raw_obj)?;
-
-        if let Some(text_sec) = raw_obj.section_by_name(".text") {
-            let text_section_address = text_sec.address() as usize;
-            let text_data = text_sec.data()?;
-
-            let dbi = pdb_file.debug_information()?;
-            let mut modules = dbi.modules()?;
-
-            while let Some(module) = modules.next()? {
-                if let Some(module_info) = pdb_file.module_info(&module)? {
-                    let mut iter = module_info.symbols()?;
-
-                    while let Some(symbol) = iter.next()? {
-                        if let Ok(SymbolData::Procedure(ProcedureSymbol {
-                            name,
-                            offset,
-                            len,
-                            ..
-                        })) = symbol.parse()
-                        {
-                            let name = name.to_string();
-                            let offset = offset.offset as usize;
-                            let len = len as usize;
-
-                            res.add_function_from_pdb(
-                                text_section_address,
-                                text_data,
-                                name.into(),
-                                offset,
-                                len,
-                            )?;
-                        }
+                    match self.add_function(name.clone(), address, data, FunctionSource::Mapping) {
+                        Ok(()) | Err(ExecutableError::FunctionNameConflict { .. }) => {}
+                        Err(err) => return Err(err),
                     }
                 }
             }
+        }
 
-            let symbol_table = pdb_file.global_symbols()?;
+        for data in &mapping.data {
+            if let Some(address) = rebase(data.address) {
+                self.add_data_symbol(data.name.clone(), address, data.size);
+            }
+        }
 
-            let mut symbols = symbol_table.iter();
-            while let Some(symbol) = symbols.next()? {
-                if let Ok(pdb::SymbolData::Public(PublicSymbol {
-                    function: true,
-                    offset,
-                    name,
-                    ..
-                })) = symbol.parse()
-                {
-                    let name = name.to_string();
-                    let offset = offset.offset as usize;
-                    let len = mapping.get_function_def(&name).map(|x| x.size).unwrap_or(0);
-                    res.add_function_from_pdb(
-                        text_section_address,
-                        text_data,
-                        name.into(),
-                        offset,
-                        len,
-                    )?;
-                }
+        for vtable in &mapping.vtable {
+            if let Some(address) = rebase(vtable.address) {
+                self.add_data_symbol(vtable.name.clone(), address, vtable.size);
             }
         }
 
-        Ok(res)
+        self.apply_aliases(mapping);
+        self.ignored = self
+            .functions
+            .keys()
+            .filter(|x| mapping.is_ignored(x))
+            .cloned()
+            .collect();
+
+        Ok(())
     }
+}
 
-    pub fn from_object_with_mapping(
-        raw_obj: &File,
-        mapping: Mapping,
-    ) -> Result<Self, ExecutableError> {
-        let mut res = Self::from_object(raw_obj)?;
+/// Number of thunks followed when resolving a call, guarding against cycles.
+const MAX_THUNK_DEPTH: usize = 8;
 
-        if let Some(text_sec) = raw_obj.section_by_name(".text") {
-            let text_section_address = text_sec.address() as usize;
-            let text_data = text_sec.data()?;
+/// Jump of a one-instruction thunk.
+enum Thunk<'a> {
+    Jump(usize),
+    Import(&'a DataSymbol),
+}
 
-            if let Some(functions) = mapping.function {
-                for function in functions {
-                    if let Some(name) = function.name {
-                        let offset = function.address - text_section_address;
-                        let data = text_data[offset..offset + function.size].to_vec();
+/// Callee of a call instruction.
+#[derive(Clone, Copy, Debug)]
+pub enum CallTarget<'a> {
+    Function(&'a Function),
+    /// Slot of the import address table, see [`Executable::add_imports`].
+    Import(&'a DataSymbol),
+}
 
-                        match res.add_function(name, function.address, data) {
-                            Ok(()) | Err(ExecutableError::FunctionNameConflict { .. }) => {}
-                            Err(err) => return Err(err),
-                        }
-                    }
-                }
-            }
+impl CallTarget<'_> {
+    pub fn name(&self) -> &str {
+        match self {
+            CallTarget::Function(function) => &function.name,
+            CallTarget::Import(symbol) => &symbol.name,
         }
+    }
+}
 
-        Ok(res)
+/// A global variable of an executable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DataSymbol {
+    pub name: String,
+    pub address: usize,
+    /// Size in bytes, zero when unknown.
+    pub size: usize,
+}
+
+/// Bytes of a function, a range of the content of its section.
+///
+/// Sections are loaded once and shared by all their functions instead of
+/// every function owning a copy of its bytes.
+#[derive(Clone)]
+pub struct FunctionData {
+    buffer: Arc<[u8]>,
+    range: Range<usize>,
+}
+
+impl FunctionData {
+    /// Bytes of `buffer` at the given range, which must be in bounds.
+    pub fn new(buffer: Arc<[u8]>, range: Range<usize>) -> Self {
+        assert!(
+            range.start <= range.end && range.end <= buffer.len(),
+            "range {range:?} out of bounds of a buffer of {} bytes",
+            buffer.len()
+        );
+
+        Self { buffer, range }
+    }
+}
+
+impl Deref for FunctionData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buffer[self.range.clone()]
+    }
+}
+
+impl PartialEq for FunctionData {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for FunctionData {}
+
+impl From<Vec<u8>> for FunctionData {
+    fn from(data: Vec<u8>) -> Self {
+        let len = data.len();
+
+        Self::new(data.into(), 0..len)
+    }
+}
+
+impl std::fmt::Debug for FunctionData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// File name of a PDB module, without the directories of the build machine.
+fn module_file_name(module_name: &str) -> String {
+    module_name
+        .rsplit(['\\', '/'])
+        .next()
+        .unwrap_or(module_name)
+        .to_string()
+}
+
+/// Where the definition of a function comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FunctionSource {
+    /// Symbol table of the executable.
+    SymbolTable,
+    Pdb,
+    Dwarf,
+    /// Linker map file.
+    Map,
+    /// Mapping file of the original executable.
+    Mapping,
+}
+
+impl std::fmt::Display for FunctionSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FunctionSource::SymbolTable => "symbols",
+            FunctionSource::Pdb => "pdb",
+            FunctionSource::Dwarf => "dwarf",
+            FunctionSource::Map => "map",
+            FunctionSource::Mapping => "mapping",
+        })
     }
 }
 
@@ -324,15 +1825,94 @@ impl Executable {
 pub struct Function {
     pub name: String,
     pub address: usize,
-    pub data: Vec<u8>,
+    pub data: FunctionData,
+    /// Name of the code section containing the function, if known.
+    pub section: Option<String>,
+    pub source: FunctionSource,
+    /// File name of the PDB module (compiland) defining the function, if known.
+    pub module: Option<String>,
+}
+
+/// Replace the displacements of memory operands pointing into a known global
+/// variable by `name+offset`.
+fn resolve_data_references(
+    executable: &Executable,
+    detail: &InsnDetail<'_>,
+    op_str: &str,
+    demangle: bool,
+) -> String {
+    let mut res = op_str.to_string();
+
+    for operand in detail.arch_detail().operands() {
+        if let ArchOperand::X86Operand(X86Operand {
+            op_type: X86OperandType::Mem(mem),
+            ..
+        }) = operand
+        {
+            let disp = mem.disp();
+
+            if disp <= 0 {
+                continue;
+            }
+
+            if let Some((symbol, offset)) = executable.get_data_symbol_by_address(disp as usize) {
+                let name = demangle::display_name(&symbol.name, demangle);
+                let reference = match offset {
+                    0 => name.to_string(),
+                    _ => format!("{name}+{offset:#x}"),
+                };
+
+                res = replace_literal(&res, &format!("{disp:#x}"), &reference);
+            }
+        }
+    }
+
+    res
+}
+
+/// Replace the occurrences of a literal, leaving longer literals starting
+/// with the same digits untouched.
+pub(crate) fn replace_literal(text: &str, literal: &str, replacement: &str) -> String {
+    let mut res = String::new();
+    let mut rest = text;
+
+    while let Some(position) = rest.find(literal) {
+        let end = position + literal.len();
+        let is_whole = !rest[end..].starts_with(|c: char| c.is_ascii_alphanumeric());
+
+        res.push_str(&rest[..position]);
+        res.push_str(if is_whole { replacement } else { literal });
+        rest = &rest[end..];
+    }
+
+    res.push_str(rest);
+    res
 }
 
 impl Function {
-    fn find_labels(&self, ctx: &Capstone, force_address_zero: bool, instructions: &Instructions<'_>) -> Result<HashMap<u64, String>, ExecutableError> {
+    /// Find the targets of the jumps inside the function, labelled
+    /// `loc_<offset>` after their offset from the start of the function.
+    fn find_labels(
+        &self,
+        ctx: &Capstone,
+        force_address_zero: bool,
+        instructions: &[&Insn<'_>],
+    ) -> Result<HashMap<u64, String>, ExecutableError> {
         let mut labels = HashMap::new();
-        let mut idx = 0;
+        let base = if force_address_zero {
+            0
+        } else {
+            self.address as u64
+        };
+
         for instruction in instructions.iter() {
-            let detail = ctx.insn_detail(instruction)?;
+            let detail = ctx.insn_detail(instruction).map_err(|err| {
+                ExecutableError::from(err).with_context(
+                    ErrorContext::new("decoding")
+                        .function(&self.name)
+                        .offset((instruction.address() - base) as usize),
+                )
+            })?;
             let groups = detail.groups().iter().map(|v| u32::from(v.0));
             let is_jump = groups.clone().any(|v| v == CS_GRP_JUMP);
             let is_32bit = groups.clone().any(|v| v == X86_GRP_NOT64BITMODE);
@@ -348,32 +1928,79 @@ impl Function {
                         let target_address = if force_address_zero {
                             if is_32bit {
                                 (self.address as i32 + immediate as i32) as usize
-
                             } else {
                                 (self.address as i64 + immediate) as usize
                             }
                         } else {
                             immediate as usize
                         };
-                        if (self.address..self.address + self.data.len()).contains(&target_address) {
+                        if (self.address..self.address + self.data.len()).contains(&target_address)
+                        {
                             let addr = (target_address - self.address) as u64;
-                            if !labels.contains_key(&addr) {
-                                idx += 1;
-                                labels.insert(addr, format!("L_{idx}"));
-                            }
+                            labels
+                                .entry(addr)
+                                .or_insert_with(|| format!("loc_{addr:x}"));
                         }
                     }
                 }
             }
         }
+
+        // A label in the middle of an instruction would never be printed.
+        let starts: HashSet<u64> = instructions.iter().map(|x| x.address() - base).collect();
+        labels.retain(|addr, _| starts.contains(addr));
+
         Ok(labels)
     }
+    /// Find the string literals referenced by the operands of the
+    /// instructions, by offset of the instruction.
+    fn find_strings(
+        &self,
+        ctx: &Capstone,
+        executable: &Executable,
+        base: u64,
+        instructions: &[&Insn<'_>],
+    ) -> Result<Vec<(u64, String)>, ExecutableError> {
+        let mut res = Vec::new();
+
+        for instruction in instructions.iter() {
+            let detail = ctx.insn_detail(instruction)?;
+
+            for operand in detail.arch_detail().operands() {
+                let address = match operand {
+                    ArchOperand::X86Operand(X86Operand {
+                        op_type: X86OperandType::Imm(value),
+                        ..
+                    }) => value,
+                    ArchOperand::X86Operand(X86Operand {
+                        op_type: X86OperandType::Mem(mem),
+                        ..
+                    }) => mem.disp(),
+                    _ => continue,
+                };
+
+                if address <= 0 {
+                    continue;
+                }
+
+                if let Some(string) = executable.string_at(address as usize) {
+                    res.push((instruction.address() - base, string));
+                    break;
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn format_instruction(
         &self,
         ctx: &Capstone,
         executable: &Executable,
         force_address_zero: bool,
         resolve_names: bool,
+        profile: &FormattingProfile,
         labels: &HashMap<u64, String>,
         instruction: &Insn<'_>,
     ) -> Result<String, ExecutableError> {
@@ -389,7 +2016,12 @@ impl Function {
         let mut has_custom_format = false;
 
         // Handle relative call
-        fn get_imm(fn_address: usize, detail: &InsnDetail<'_>, is_32bit: bool, force_address_zero: bool) -> Option<usize> {
+        fn get_imm(
+            fn_address: usize,
+            detail: &InsnDetail<'_>,
+            is_32bit: bool,
+            force_address_zero: bool,
+        ) -> Option<usize> {
             let arch_detail = detail.arch_detail();
             let ops = arch_detail.operands();
 
@@ -402,7 +2034,6 @@ impl Function {
                     let target_address = if force_address_zero {
                         if is_32bit {
                             (fn_address as i32 + immediate as i32) as usize
-
                         } else {
                             (fn_address as i64 + immediate) as usize
                         }
@@ -417,15 +2048,20 @@ impl Function {
             None
         }
 
-
         if resolve_names {
             // Handle relative call
             if is_call && is_branch_relative {
-                if let Some(target_address) = get_imm(self.address, &detail, is_32bit, force_address_zero) {
-                    if let Some(target_function) = executable.get_function_by_address(target_address)
-                    {
+                if let Some(target_address) =
+                    get_imm(self.address, &detail, is_32bit, force_address_zero)
+                {
+                    if let Some(target) = executable.resolve_call_target(target_address) {
                         if let Some(mnemonic) = instruction.mnemonic() {
-                            writeln!(res, "    {} {}", mnemonic, target_function.name)?;
+                            writeln!(
+                                res,
+                                "    {} {}",
+                                mnemonic,
+                                demangle::display_name(target.name(), profile.demangle)
+                            )?;
 
                             has_custom_format = true;
                         }
@@ -435,9 +2071,15 @@ impl Function {
         }
 
         if !has_custom_format && is_jump {
-            if let Some(target_address) = get_imm(self.address, &detail, is_32bit, force_address_zero) {
-                let relative_addr = target_address - self.address;
-                if let Some(label) = labels.get(&(relative_addr as u64)) {
+            if let Some(target_address) =
+                get_imm(self.address, &detail, is_32bit, force_address_zero)
+            {
+                // Jumps before the start of the function have no label.
+                let label = target_address
+                    .checked_sub(self.address)
+                    .and_then(|relative_addr| labels.get(&(relative_addr as u64)));
+
+                if let Some(label) = label {
                     if let Some(mnemonic) = instruction.mnemonic() {
                         writeln!(res, "    {} {}", mnemonic, label)?;
 
@@ -449,12 +2091,28 @@ impl Function {
 
         if !has_custom_format {
             if let Some(mnemonic) = instruction.mnemonic() {
-                write!(res, "    {} ", mnemonic)?;
-                if let Some(op_str) = instruction.op_str() {
-                    write!(res, "{}", op_str)?;
+                let op_str = instruction.op_str().unwrap_or_default();
+                let operands = if resolve_names {
+                    resolve_data_references(executable, &detail, op_str, profile.demangle)
+                } else {
+                    op_str.to_string()
+                };
+
+                match profile.dialect {
+                    Dialect::Capstone => writeln!(res, "    {} {}", mnemonic, operands)?,
+                    Dialect::Masm => writeln!(
+                        res,
+                        "    {}",
+                        masm::format_instruction(
+                            executable,
+                            &detail,
+                            mnemonic,
+                            &operands,
+                            resolve_names && !is_call && !is_jump,
+                            profile.demangle,
+                        )
+                    )?,
                 }
-
-                res.push('\n');
             }
         }
 
@@ -467,6 +2125,45 @@ impl Function {
         executable: &Executable,
         force_address_zero: bool,
         resolve_names: bool,
+    ) -> Result<String, ExecutableError> {
+        self.disassemble_with_profile(
+            ctx,
+            executable,
+            force_address_zero,
+            resolve_names,
+            &FormattingProfile::default(),
+        )
+    }
+
+    /// Disassemble the function, formatting operands with the given profile.
+    pub fn disassemble_with_profile(
+        &self,
+        ctx: &Capstone,
+        executable: &Executable,
+        force_address_zero: bool,
+        resolve_names: bool,
+        profile: &FormattingProfile,
+    ) -> Result<String, ExecutableError> {
+        self.disassemble_annotated(
+            ctx,
+            executable,
+            force_address_zero,
+            resolve_names,
+            profile,
+            &Signatures::new(),
+        )
+    }
+
+    /// Disassemble the function, annotating the arguments pushed before calls
+    /// to functions with a known signature.
+    pub fn disassemble_annotated(
+        &self,
+        ctx: &Capstone,
+        executable: &Executable,
+        force_address_zero: bool,
+        resolve_names: bool,
+        profile: &FormattingProfile,
+        signatures: &Signatures,
     ) -> Result<String, ExecutableError> {
         let address = if force_address_zero {
             0
@@ -474,48 +2171,400 @@ impl Function {
             self.address as u64
         };
 
-        let instructions = ctx.disasm_all(&self.data, address)?;
+        let disassembling_error = |err: ExecutableError| {
+            err.with_context(ErrorContext::new("disassembling").function(&self.name))
+        };
+
+        // Jump tables would be decoded as garbage, only the code around them is disassembled.
+        let tables = self.jump_tables(ctx).map_err(disassembling_error)?;
+        let segments = jumptable::code_ranges(&tables, self.data.len())
+            .into_iter()
+            .map(|range| ctx.disasm_all(&self.data[range.clone()], address + range.start as u64))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| disassembling_error(err.into()))?;
+        let instructions: Vec<&Insn<'_>> = segments.iter().flat_map(|x| x.iter()).collect();
 
         let mut res = String::new();
 
         // First, find the labels
-        let labels = self.find_labels(ctx, force_address_zero, &instructions)?;
+        let mut labels = self.find_labels(ctx, force_address_zero, &instructions)?;
+        let starts: HashSet<u64> = instructions.iter().map(|x| x.address() - address).collect();
+
+        for table in tables.iter().filter(|x| x.kind == TableKind::Addresses) {
+            for entry in table.entries(&self.data) {
+                let offset = entry.wrapping_sub(self.address) as u64;
+
+                if starts.contains(&offset) {
+                    labels
+                        .entry(offset)
+                        .or_insert_with(|| format!("loc_{offset:x}"));
+                }
+            }
+        }
+
+        let mut comments = signature::annotate_arguments(ctx, executable, self, signatures)?;
+
+        if resolve_names {
+            for (offset, string) in self.find_strings(ctx, executable, address, &instructions)? {
+                comments
+                    .entry(offset)
+                    .and_modify(|x| *x = format!("{x} {string}"))
+                    .or_insert(string);
+            }
+        }
+
+        // Encodings are padded to the longest one so that instructions stay aligned.
+        let bytes_width = instructions
+            .iter()
+            .map(|x| x.bytes().len() * 3)
+            .max()
+            .unwrap_or(0);
+
+        let mut tables = tables.iter().peekable();
 
         for instruction in instructions.iter() {
-            let insn_addr = instruction.address() as u64 - address;
+            let insn_addr = instruction.address() - address;
+
+            while let Some(table) = tables.next_if(|x| x.range.start as u64 <= insn_addr) {
+                res.push_str(&self.format_table(table, &labels));
+            }
 
             if let Some(label) = labels.get(&insn_addr) {
                 res.push_str(&format!("{label}:\n"));
             }
-            res.push_str(&self.format_instruction(
+
+            let mut line = profile.format_line(&self.format_instruction(
                 ctx,
                 executable,
                 force_address_zero,
                 resolve_names,
+                profile,
                 &labels,
                 instruction,
             )?);
+
+            if profile.show_bytes {
+                let bytes: Vec<String> = instruction
+                    .bytes()
+                    .iter()
+                    .map(|x| format!("{x:02x}"))
+                    .collect();
+
+                line = format!("    {:<bytes_width$}{line}", bytes.join(" "));
+            }
+
+            if profile.show_addresses {
+                line = format!("{:08x}:{line}", instruction.address());
+            }
+
+            match comments.get(&insn_addr) {
+                Some(comment) => {
+                    res.push_str(line.trim_end());
+                    res.push_str(&format!(" ; {comment}\n"));
+                }
+                None => res.push_str(&line),
+            }
+        }
+
+        for table in tables {
+            res.push_str(&self.format_table(table, &labels));
         }
 
         Ok(res)
     }
 
-    pub fn compute_raw_diff(&self, other: &Function) -> f32 {
-        let expected_function_size = self.data.len();
-        let mut matching_count = 0;
+    /// Render a jump table as data directives, case addresses as labels.
+    fn format_table(&self, table: &JumpTable, labels: &HashMap<u64, String>) -> String {
+        let entries = table.entries(&self.data);
+
+        match table.kind {
+            TableKind::Addresses => entries
+                .iter()
+                .map(|entry| {
+                    let offset = entry.wrapping_sub(self.address) as u64;
+
+                    match labels.get(&offset) {
+                        Some(label) => format!("    dd {label}\n"),
+                        None => format!("    dd {entry:#x}\n"),
+                    }
+                })
+                .collect(),
+            TableKind::Indexes => entries
+                .chunks(16)
+                .map(|x| {
+                    let values: Vec<String> = x.iter().map(|x| x.to_string()).collect();
+
+                    format!("    db {}\n", values.join(", "))
+                })
+                .collect(),
+        }
+    }
+
+    /// Dump the bytes of the function, 16 per line: address, hexadecimal
+    /// and printable ASCII characters.
+    pub fn hexdump(&self, force_address_zero: bool) -> Result<String, std::fmt::Error> {
+        let address = if force_address_zero { 0 } else { self.address };
+        let mut res = String::new();
+
+        for (idx, chunk) in self.data.chunks(16).enumerate() {
+            write!(res, "{:08x} ", address + idx * 16)?;
+
+            for column in 0..16 {
+                if column == 8 {
+                    res.push(' ');
+                }
 
-        for (idx, a) in self.data.iter().enumerate() {
-            if let Some(b) = other.data.get(idx) {
-                if a == b {
-                    matching_count += 1;
+                match chunk.get(column) {
+                    Some(byte) => write!(res, " {byte:02x}")?,
+                    None => res.push_str("   "),
                 }
             }
+
+            let text: String = chunk
+                .iter()
+                .map(|x| {
+                    if x.is_ascii_graphic() || *x == b' ' {
+                        *x as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+
+            writeln!(res, "  |{text}|")?;
+        }
+
+        Ok(res)
+    }
+
+    /// Jump tables embedded in the function.
+    pub fn jump_tables(&self, ctx: &Capstone) -> Result<Vec<JumpTable>, ExecutableError> {
+        jumptable::find(ctx, self.address, &self.data)
+    }
+
+    /// Offsets of the code of the function, jump tables excluded.
+    pub fn code_ranges(&self, ctx: &Capstone) -> Result<Vec<Range<usize>>, ExecutableError> {
+        Ok(jumptable::code_ranges(
+            &self.jump_tables(ctx)?,
+            self.data.len(),
+        ))
+    }
+
+    /// Bytes of the function compared with the given options, trailing
+    /// padding stripped unless it is kept.
+    pub fn compared_data(
+        &self,
+        ctx: &Capstone,
+        options: &DiffOptions,
+    ) -> Result<&[u8], ExecutableError> {
+        if options.keep_padding {
+            Ok(&self.data)
+        } else {
+            diff::trim_padding(ctx, &self.data)
+        }
+    }
+
+    /// Offsets of the code of the function compared, `len` being the length
+    /// of its [compared data](Self::compared_data).
+    fn compared_ranges(
+        &self,
+        ctx: &Capstone,
+        len: usize,
+    ) -> Result<Vec<Range<usize>>, ExecutableError> {
+        Ok(self
+            .code_ranges(ctx)?
+            .into_iter()
+            .map(|x| x.start..x.end.min(len))
+            .filter(|x| !x.is_empty())
+            .collect())
+    }
+
+    pub fn compute_raw_diff(
+        &self,
+        ctx: &Capstone,
+        other: &Function,
+        options: &DiffOptions,
+    ) -> Result<f32, ExecutableError> {
+        Ok(diff::raw_score(
+            self.compared_data(ctx, options)?,
+            other.compared_data(ctx, options)?,
+        ))
+    }
+
+    /// Compare two functions instruction by instruction.
+    ///
+    /// Both functions are disassembled at address zero and each instruction is
+    /// compared on its mnemonic and operands, so a differing displacement only
+    /// affects the instruction it belongs to.
+    pub fn compute_instruction_diff(
+        &self,
+        ctx: &Capstone,
+        other: &Function,
+    ) -> Result<f32, ExecutableError> {
+        let options = DiffOptions::default();
+        let instructions =
+            diff::decode_ranges(ctx, &self.data, &self.code_ranges(ctx)?, &[], &options)?;
+        let other_instructions =
+            diff::decode_ranges(ctx, &other.data, &other.code_ranges(ctx)?, &[], &options)?;
+
+        Ok(diff::instruction_score(&instructions, &other_instructions))
+    }
+
+    /// Compare two functions after aligning their instructions.
+    ///
+    /// An inserted or dropped instruction only counts as a single mismatch
+    /// instead of shifting every following instruction.
+    pub fn compute_aligned_diff(
+        &self,
+        ctx: &Capstone,
+        other: &Function,
+    ) -> Result<f32, ExecutableError> {
+        let options = DiffOptions::default();
+        let instructions =
+            diff::decode_ranges(ctx, &self.data, &self.code_ranges(ctx)?, &[], &options)?;
+        let other_instructions =
+            diff::decode_ranges(ctx, &other.data, &other.code_ranges(ctx)?, &[], &options)?;
+
+        Ok(diff::score(
+            DiffAlgorithm::Aligned,
+            (&instructions, &self.data),
+            (&other_instructions, &other.data),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use capstone::arch::x86::ArchMode;
+    use capstone::arch::BuildsCapstone;
+
+    use super::*;
+
+    fn capstone() -> Capstone {
+        Capstone::new()
+            .x86()
+            .mode(ArchMode::Mode32)
+            .detail(true)
+            .build()
+            .unwrap()
+    }
+
+    fn function(data: &[u8]) -> Function {
+        Function {
+            name: "f".into(),
+            address: 0x401000,
+            data: data.to_vec().into(),
+            section: None,
+            source: FunctionSource::Mapping,
+            module: None,
         }
+    }
+
+    #[test]
+    fn instruction_diffs_only_count_the_differing_instruction() {
+        let ctx = capstone();
+        // push ebp; mov eax, [ebp + 8]; pop ebp; ret
+        let a = function(&[0x55, 0x8b, 0x45, 0x08, 0x5d, 0xc3]);
+        // push ebp; mov eax, [ebp + 0xc]; pop ebp; ret
+        let b = function(&[0x55, 0x8b, 0x45, 0x0c, 0x5d, 0xc3]);
+
+        assert_eq!(a.compute_instruction_diff(&ctx, &a).unwrap(), 100.0);
+        assert_eq!(a.compute_instruction_diff(&ctx, &b).unwrap(), 75.0);
+    }
+
+    #[test]
+    fn aligned_diffs_count_an_inserted_instruction_once() {
+        let ctx = capstone();
+        // push ebp; mov eax, [ebp + 8]; pop ebp; ret
+        let a = function(&[0x55, 0x8b, 0x45, 0x08, 0x5d, 0xc3]);
+        // push ebp; nop; mov eax, [ebp + 8]; pop ebp; ret
+        let b = function(&[0x55, 0x90, 0x8b, 0x45, 0x08, 0x5d, 0xc3]);
+
+        assert_eq!(a.compute_aligned_diff(&ctx, &b).unwrap(), 80.0);
+
+        let executable = Executable::default();
+        let options = DiffOptions {
+            algorithm: DiffAlgorithm::Aligned,
+            ..Default::default()
+        };
+        assert_eq!(
+            executable
+                .compute_function_diff(&ctx, &a, &executable, &b, &options)
+                .unwrap(),
+            80.0
+        );
+    }
 
-        let result = (matching_count as f32 / expected_function_size as f32) * 100.0;
+    fn mapping(address: usize, size: usize) -> Mapping {
+        Mapping {
+            function: Some(vec![FunctionDef {
+                name: Some("f".into()),
+                address,
+                size,
+                id: None,
+                min_percent: None,
+                aliases: Vec::new(),
+                ignored: false,
+                status: None,
+                versions: BTreeMap::new(),
+            }]),
+            ..Mapping::default()
+        }
+    }
 
-        assert!(result != 100.0 || expected_function_size == other.data.len());
+    #[test]
+    fn mapping_functions_must_be_in_a_code_section() {
+        let data = [0x55, 0x5d, 0xc3];
+
+        let executable = Executable::from_raw_with_mapping(&data, 0x1000, mapping(0x1000, 3));
+        assert!(executable.unwrap().get_function(&"f".into()).is_some());
+
+        let executable = Executable::from_raw_with_mapping(&data, 0x1000, mapping(0x2000, 3));
+        assert!(matches!(
+            executable,
+            Err(ExecutableError::FunctionOutsideSections {
+                address: 0x2000,
+                ..
+            })
+        ));
+
+        let executable = Executable::from_raw_with_mapping(&data, 0x1000, mapping(0x1001, 3));
+        assert!(matches!(
+            executable,
+            Err(ExecutableError::FunctionOutOfRange {
+                address: 0x1001,
+                ..
+            })
+        ));
+    }
 
-        result
+    #[test]
+    fn symbols_past_their_section_are_rejected() {
+        let section = CodeSection {
+            name: ".text".into(),
+            address: 0x1000,
+            data: [0x55, 0x5d, 0xc3].into(),
+        };
+        let mut executable = Executable::default();
+
+        executable
+            .add_function_from_section(&section, FunctionSource::SymbolTable, "f".into(), 1, 8)
+            .unwrap();
+        assert_eq!(executable.get_function(&"f".into()).unwrap().data.len(), 2);
+
+        let res = executable.add_function_from_section(
+            &section,
+            FunctionSource::SymbolTable,
+            "g".into(),
+            3,
+            1,
+        );
+        assert!(matches!(
+            res,
+            Err(ExecutableError::FunctionOutOfRange {
+                address: 0x1003,
+                ..
+            })
+        ));
     }
 }
@@ -0,0 +1,76 @@
+//! Limits protecting stats runs from pathological functions.
+//!
+//! A bogus mapping entry can describe a "function" spanning megabytes of
+//! code, which takes forever to disassemble and align. Such functions are
+//! skipped and listed separately in reports instead.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Per-function limits applied when generating stats.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FunctionLimits {
+    /// Functions bigger than this number of bytes are not compared.
+    pub max_size: Option<usize>,
+    /// Comparisons taking longer than this are discarded.
+    ///
+    /// Alignments give up once the budget is spent, the disassembly isn't
+    /// interrupted but only takes time proportional to `max_size`.
+    pub time_budget: Option<Duration>,
+}
+
+/// Why a function was skipped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SkipReason {
+    TooLarge { size: usize, limit: usize },
+    OverBudget { elapsed_ms: u64, budget_ms: u64 },
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::TooLarge { size, limit } => {
+                write!(f, "size of {size} bytes exceeds the {limit} bytes limit")
+            }
+            SkipReason::OverBudget {
+                elapsed_ms,
+                budget_ms,
+            } => write!(f, "took {elapsed_ms}ms, over the {budget_ms}ms budget"),
+        }
+    }
+}
+
+/// A function left out of the stats.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedFunction {
+    pub name: String,
+    pub reason: SkipReason,
+}
+
+impl FunctionLimits {
+    /// Check the size of a function before comparing it.
+    pub fn check_size(&self, size: usize) -> Option<SkipReason> {
+        match self.max_size {
+            Some(limit) if size > limit => Some(SkipReason::TooLarge { size, limit }),
+            _ => None,
+        }
+    }
+
+    /// Check the time a comparison took.
+    pub fn check_time(&self, elapsed: Duration) -> Option<SkipReason> {
+        match self.time_budget {
+            Some(budget) if elapsed > budget => self.over_budget(elapsed),
+            _ => None,
+        }
+    }
+
+    /// Reason of a comparison interrupted after spending the time budget.
+    pub fn over_budget(&self, elapsed: Duration) -> Option<SkipReason> {
+        self.time_budget.map(|budget| SkipReason::OverBudget {
+            elapsed_ms: elapsed.as_millis() as u64,
+            budget_ms: budget.as_millis() as u64,
+        })
+    }
+}
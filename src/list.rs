@@ -0,0 +1,108 @@
+//! Listing of the functions known in an executable.
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use regex::Regex;
+
+use crate::{demangle, Executable, Function};
+
+/// Filter over function names.
+#[derive(Clone, Debug)]
+pub struct NameFilter {
+    regex: Regex,
+}
+
+impl NameFilter {
+    /// Glob pattern matching the whole name: `*` matches any sequence of
+    /// characters and `?` a single one.
+    pub fn glob(pattern: &str) -> Result<Self, regex::Error> {
+        let mut res = String::from("^");
+
+        for c in pattern.chars() {
+            match c {
+                '*' => res.push_str(".*"),
+                '?' => res.push('.'),
+                _ => res.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+
+        res.push('$');
+
+        Self::regex(&res)
+    }
+
+    /// Regular expression matching anywhere in the name.
+    pub fn regex(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            regex: Regex::new(pattern)?,
+        })
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        self.regex.is_match(name)
+    }
+}
+
+/// Order of a function listing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FunctionOrder {
+    #[default]
+    Name,
+    Address,
+    /// Largest functions first.
+    Size,
+}
+
+impl FromStr for FunctionOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(Self::Name),
+            "address" => Ok(Self::Address),
+            "size" => Ok(Self::Size),
+            _ => Err(format!(
+                "Unknown order \"{s}\", expected one of name, address or size"
+            )),
+        }
+    }
+}
+
+/// Functions of an executable in the given order.
+pub fn functions(executable: &Executable, order: FunctionOrder) -> Vec<&Function> {
+    let mut res: Vec<&Function> = executable.functions_iter().map(|(_, x)| x).collect();
+
+    match order {
+        FunctionOrder::Name => res.sort_by(|a, b| a.name.cmp(&b.name)),
+        FunctionOrder::Address => {
+            res.sort_by(|a, b| (a.address, &a.name).cmp(&(b.address, &b.name)))
+        }
+        FunctionOrder::Size => res.sort_by(|a, b| {
+            b.data
+                .len()
+                .cmp(&a.data.len())
+                .then_with(|| a.name.cmp(&b.name))
+        }),
+    }
+
+    res
+}
+
+/// Render one function per line: address, size, source and name.
+pub fn render(functions: &[&Function], demangle: bool) -> Result<String, std::fmt::Error> {
+    let mut res = String::new();
+
+    for function in functions {
+        writeln!(
+            res,
+            "{:#010x} {:>6} {:<8} {}",
+            function.address,
+            function.data.len(),
+            function.source,
+            demangle::display_name(&function.name, demangle)
+        )?;
+    }
+
+    Ok(res)
+}
@@ -0,0 +1,88 @@
+//! MSVC linker map files.
+//!
+//! The reimplementation build produces a `.map` file next to the executable,
+//! which is enough to locate its functions when no PDB is available.
+
+/// A symbol listed in a map file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MapSymbol {
+    /// Index of the section, starting at one.
+    pub section: u16,
+    /// Offset from the start of the section.
+    pub offset: usize,
+    /// Decorated name of the symbol.
+    pub name: String,
+    /// Virtual address of the symbol.
+    pub address: usize,
+    pub is_function: bool,
+    /// Object file the symbol comes from.
+    pub object: String,
+}
+
+/// Parse a symbol line: `0001:00000000  _main  00401000 f  main.obj`.
+fn parse_symbol(line: &str) -> Option<MapSymbol> {
+    let mut words = line.split_whitespace();
+
+    let (section, offset) = words.next()?.split_once(':')?;
+    let section = u16::from_str_radix(section, 16).ok()?;
+    let offset = usize::from_str_radix(offset, 16).ok()?;
+    let name = words.next()?.to_string();
+    let address = usize::from_str_radix(words.next()?, 16).ok()?;
+
+    let mut rest: Vec<&str> = words.collect();
+    let is_function = rest.first() == Some(&"f");
+
+    if is_function {
+        rest.remove(0);
+    }
+
+    // The "i" flag marks symbols imported from a DLL.
+    if rest.first() == Some(&"i") {
+        rest.remove(0);
+    }
+
+    Some(MapSymbol {
+        section,
+        offset,
+        name,
+        address,
+        is_function,
+        object: rest.join(" "),
+    })
+}
+
+/// Parse the public and static symbols of a map file.
+///
+/// Lines that do not describe a symbol (headers, section table, entry
+/// point...) are ignored.
+pub fn parse_map(content: &str) -> Vec<MapSymbol> {
+    let mut res = Vec::new();
+    let mut in_symbols = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("Address") && trimmed.contains("Publics by Value") {
+            in_symbols = true;
+            continue;
+        }
+
+        if trimmed.starts_with("Static symbols") {
+            in_symbols = true;
+            continue;
+        }
+
+        if trimmed.starts_with("entry point at") || trimmed.starts_with("Exports") {
+            in_symbols = false;
+            continue;
+        }
+
+        if in_symbols {
+            if let Some(symbol) = parse_symbol(trimmed) {
+                res.push(symbol);
+            }
+        }
+    }
+
+    res
+}
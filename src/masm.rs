@@ -0,0 +1,136 @@
+//! MASM dialect of the disassembly.
+//!
+//! Capstone writes Intel syntax the way GNU as reads it, which MASM
+//! assembles differently: `[0x403000]` without a segment is an immediate,
+//! addresses pushed as immediates need `OFFSET` to be relocated and string
+//! instructions don't take operands. Functions are exported as procedures
+//! that can be included in the existing MASM stubs.
+
+use capstone::arch::x86::{X86Operand, X86OperandType};
+use capstone::arch::ArchOperand;
+use capstone::InsnDetail;
+
+use crate::{decompme, demangle, Executable};
+
+/// Size keywords of memory operands.
+const SIZE_KEYWORDS: &[&str] = &[
+    "byte", "word", "dword", "fword", "qword", "tbyte", "xmmword", "ymmword",
+];
+
+/// Rewrite the mnemonic and operands of a disassembled instruction for MASM.
+///
+/// Immediates pointing to a data symbol or a function are written as
+/// `OFFSET name` when `resolve_offsets` is set, which branches must not.
+pub fn format_instruction(
+    executable: &Executable,
+    detail: &InsnDetail<'_>,
+    mnemonic: &str,
+    operands: &str,
+    resolve_offsets: bool,
+    demangle: bool,
+) -> String {
+    let instruction = mnemonic.rsplit(' ').next().unwrap_or(mnemonic);
+
+    // movsd dword ptr es:[edi], dword ptr [esi]
+    if decompme::is_string_instruction(instruction) && !operands.contains("xmm") {
+        return mnemonic.to_string();
+    }
+
+    let mut res = operands.to_string();
+
+    if resolve_offsets {
+        res = offsets(executable, detail, &res, demangle);
+    }
+
+    format!("{mnemonic} {}", uppercase_sizes(&add_segments(&res)))
+}
+
+/// Replace the immediates pointing to a symbol with `OFFSET name`.
+fn offsets(
+    executable: &Executable,
+    detail: &InsnDetail<'_>,
+    operands: &str,
+    demangle: bool,
+) -> String {
+    let mut res = operands.to_string();
+
+    for operand in detail.arch_detail().operands() {
+        let ArchOperand::X86Operand(X86Operand {
+            op_type: X86OperandType::Imm(value),
+            ..
+        }) = operand
+        else {
+            continue;
+        };
+
+        if value <= 0 {
+            continue;
+        }
+
+        let address = value as usize;
+        let reference = match executable.get_function_by_address(address) {
+            Some(function) => demangle::display_name(&function.name, demangle).to_string(),
+            None => match executable.get_data_symbol_by_address(address) {
+                Some((symbol, 0)) => demangle::display_name(&symbol.name, demangle).to_string(),
+                Some((symbol, offset)) => format!(
+                    "{}+{offset:#x}",
+                    demangle::display_name(&symbol.name, demangle)
+                ),
+                None => continue,
+            },
+        };
+
+        res = crate::replace_literal(&res, &format!("{value:#x}"), &format!("OFFSET {reference}"));
+    }
+
+    res
+}
+
+/// Prefix absolute memory operands with the `ds` segment, MASM reading
+/// `[0x403000]` as the immediate `0x403000`.
+fn add_segments(operands: &str) -> String {
+    let mut res = String::with_capacity(operands.len());
+    let mut rest = operands;
+
+    while let Some(start) = rest.find('[') {
+        let end = rest[start..]
+            .find(']')
+            .map_or(rest.len(), |x| start + x + 1);
+        let is_absolute = rest[start + 1..].starts_with(|c: char| c.is_ascii_digit());
+        let has_segment = rest[..start].ends_with(':');
+
+        res.push_str(&rest[..start]);
+
+        if is_absolute && !has_segment {
+            res.push_str("ds:");
+        }
+
+        res.push_str(&rest[start..end]);
+        rest = &rest[end..];
+    }
+
+    res.push_str(rest);
+    res
+}
+
+/// Write the size keywords of memory operands in uppercase, `DWORD PTR`.
+fn uppercase_sizes(operands: &str) -> String {
+    operands
+        .split(", ")
+        .map(|operand| {
+            for size in SIZE_KEYWORDS {
+                if let Some(rest) = operand.strip_prefix(&format!("{size} ptr ")) {
+                    return format!("{} PTR {rest}", size.to_uppercase());
+                }
+            }
+
+            operand.to_string()
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Wrap the disassembly of a function in a MASM procedure.
+pub fn format_procedure(name: &str, disassembly: &str) -> String {
+    format!("{name} PROC\n{disassembly}{name} ENDP\n")
+}
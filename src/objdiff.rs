@@ -0,0 +1,173 @@
+//! Reports in the format consumed by objdiff.
+//!
+//! This follows the JSON encoding of objdiff's `Report` message, so projects
+//! already using the objdiff frontend or its progress website can display
+//! satsuki numbers. Satsuki has no notion of translation units: every
+//! function of a binary is reported in a single unit, and workspaces report
+//! one unit per binary.
+
+use serde::Serialize;
+
+use crate::report::{MatchStatus, StatsReport};
+
+/// Version of the objdiff report format produced.
+pub const REPORT_VERSION: u32 = 1;
+
+/// Name of the unit containing all functions.
+pub const DEFAULT_UNIT_NAME: &str = "main";
+
+/// Aggregated measures of a report or unit.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Measures {
+    pub fuzzy_match_percent: f32,
+    pub total_code: u64,
+    pub matched_code: u64,
+    pub matched_code_percent: f32,
+    pub total_data: u64,
+    pub matched_data: u64,
+    pub matched_data_percent: f32,
+    pub total_functions: u32,
+    pub matched_functions: u32,
+    pub matched_functions_percent: f32,
+    pub complete_code: u64,
+    pub complete_code_percent: f32,
+    pub complete_data: u64,
+    pub complete_data_percent: f32,
+    pub total_units: u32,
+    pub complete_units: u32,
+}
+
+/// Metadata of a function.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ReportItemMetadata {
+    pub virtual_address: u64,
+}
+
+/// A function of a unit.
+#[derive(Clone, Debug, Serialize)]
+pub struct ReportItem {
+    pub name: String,
+    pub size: u64,
+    pub fuzzy_match_percent: f32,
+    pub metadata: ReportItemMetadata,
+}
+
+/// A translation unit.
+#[derive(Clone, Debug, Serialize)]
+pub struct ReportUnit {
+    pub name: String,
+    pub measures: Measures,
+    pub sections: Vec<ReportItem>,
+    pub functions: Vec<ReportItem>,
+}
+
+/// An objdiff report.
+#[derive(Clone, Debug, Serialize)]
+pub struct Report {
+    pub measures: Measures,
+    pub units: Vec<ReportUnit>,
+    pub version: u32,
+}
+
+fn percent(part: u64, total: u64) -> f32 {
+    if total == 0 {
+        return 0.0;
+    }
+
+    (part as f32 / total as f32) * 100.0
+}
+
+impl Measures {
+    fn update_percentages(&mut self) {
+        self.matched_code_percent = percent(self.matched_code, self.total_code);
+        self.matched_functions_percent =
+            percent(self.matched_functions as u64, self.total_functions as u64);
+    }
+}
+
+impl ReportUnit {
+    /// Convert a stats report, weighting the fuzzy match by function size as objdiff does.
+    pub fn from_stats(report: &StatsReport, unit_name: &str) -> Self {
+        let mut functions: Vec<_> = report.functions.iter().collect();
+        functions.sort_by_key(|x| x.address);
+
+        let mut measures = Measures {
+            total_units: 1,
+            ..Default::default()
+        };
+        let mut weighted_match = 0.0;
+
+        for function in &functions {
+            let size = function.size as u64;
+
+            measures.total_code += size;
+            measures.total_functions += 1;
+            weighted_match += function.percent.unwrap_or(0.0) as f64 * size as f64;
+
+            if function.status == MatchStatus::Matching {
+                measures.matched_code += size;
+                measures.matched_functions += 1;
+            }
+        }
+
+        if measures.total_code != 0 {
+            measures.fuzzy_match_percent = (weighted_match / measures.total_code as f64) as f32;
+        }
+
+        measures.update_percentages();
+
+        Self {
+            name: unit_name.into(),
+            measures,
+            sections: Vec::new(),
+            functions: functions
+                .iter()
+                .map(|function| ReportItem {
+                    name: function.name.clone(),
+                    size: function.size as u64,
+                    fuzzy_match_percent: function.percent.unwrap_or(0.0),
+                    metadata: ReportItemMetadata {
+                        virtual_address: function.address as u64,
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Report {
+    /// Convert a stats report to a report with a single unit.
+    pub fn from_stats(report: &StatsReport, unit_name: &str) -> Self {
+        Self::from_units(vec![ReportUnit::from_stats(report, unit_name)])
+    }
+
+    /// Aggregate the measures of several units.
+    pub fn from_units(units: Vec<ReportUnit>) -> Self {
+        let mut measures = Measures::default();
+        let mut weighted_match = 0.0;
+
+        for unit in &units {
+            let unit_measures = &unit.measures;
+
+            measures.total_code += unit_measures.total_code;
+            measures.matched_code += unit_measures.matched_code;
+            measures.total_functions += unit_measures.total_functions;
+            measures.matched_functions += unit_measures.matched_functions;
+            measures.total_units += 1;
+            weighted_match +=
+                unit_measures.fuzzy_match_percent as f64 * unit_measures.total_code as f64;
+        }
+
+        if measures.total_code != 0 {
+            measures.fuzzy_match_percent = (weighted_match / measures.total_code as f64) as f32;
+        }
+
+        measures.update_percentages();
+
+        Self {
+            measures,
+            units,
+            version: REPORT_VERSION,
+        }
+    }
+}
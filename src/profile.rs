@@ -0,0 +1,228 @@
+//! Formatting profiles applied to disassembly output.
+//!
+//! Downstream assemblers and decomp.me presets disagree on how numbers must
+//! be written. A profile describes the expected style so dumps can be pasted
+//! as is. The default profile leaves the capstone output untouched.
+
+use std::str::FromStr;
+
+/// Base used to write numeric literals.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Radix {
+    #[default]
+    Hex,
+    Decimal,
+}
+
+impl FromStr for Radix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hex" => Ok(Self::Hex),
+            "dec" | "decimal" => Ok(Self::Decimal),
+            _ => Err(format!("Unknown radix \"{s}\", expected hex or dec")),
+        }
+    }
+}
+
+/// Notation of hexadecimal literals.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HexNotation {
+    /// `0x1f`
+    #[default]
+    Prefix,
+    /// `1Fh`, as used by MASM.
+    Suffix,
+}
+
+impl FromStr for HexNotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "prefix" => Ok(Self::Prefix),
+            "suffix" => Ok(Self::Suffix),
+            _ => Err(format!(
+                "Unknown hex notation \"{s}\", expected prefix or suffix"
+            )),
+        }
+    }
+}
+
+/// Assembler dialect of the disassembly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dialect {
+    /// Operands as written by capstone.
+    #[default]
+    Capstone,
+    /// Operands accepted by MASM, see [`crate::masm`]. Hexadecimal literals
+    /// always use the suffix notation.
+    Masm,
+}
+
+impl FromStr for Dialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "capstone" => Ok(Self::Capstone),
+            "masm" => Ok(Self::Masm),
+            _ => Err(format!(
+                "Unknown dialect \"{s}\", expected capstone or masm"
+            )),
+        }
+    }
+}
+
+/// Style of numeric literals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LiteralStyle {
+    pub radix: Radix,
+    pub hex_notation: HexNotation,
+    /// Minimum number of digits, padded with zeros.
+    pub min_width: usize,
+    /// Render negative values with a minus sign (`[ebp - 0x10]`) instead of
+    /// their 32-bit two's complement (`[ebp + 0xfffffff0]`).
+    pub signed: bool,
+}
+
+impl Default for LiteralStyle {
+    fn default() -> Self {
+        Self {
+            radix: Radix::Hex,
+            hex_notation: HexNotation::Prefix,
+            min_width: 0,
+            signed: true,
+        }
+    }
+}
+
+impl LiteralStyle {
+    /// Format the magnitude of a literal.
+    fn format_magnitude(&self, value: u64) -> String {
+        let width = self.min_width;
+
+        match (self.radix, self.hex_notation) {
+            (Radix::Decimal, _) => format!("{value:0width$}"),
+            (Radix::Hex, HexNotation::Prefix) => format!("0x{value:0width$x}"),
+            (Radix::Hex, HexNotation::Suffix) => {
+                let digits = format!("{value:0width$X}");
+
+                // MASM requires literals to start with a digit.
+                if digits.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                    format!("0{digits}h")
+                } else {
+                    format!("{digits}h")
+                }
+            }
+        }
+    }
+}
+
+/// Formatting options of a disassembly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FormattingProfile {
+    pub literals: LiteralStyle,
+    /// Undecorate the names of called functions, see [`crate::demangle`].
+    pub demangle: bool,
+    /// Prefix each instruction with its encoding, like objdump.
+    pub show_bytes: bool,
+    /// Prefix each instruction with its address, zero based when the
+    /// function is disassembled at address zero.
+    pub show_addresses: bool,
+    pub dialect: Dialect,
+}
+
+/// Parse a literal as written by capstone.
+fn parse_literal(literal: &str) -> Option<u64> {
+    match literal.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => literal.parse().ok(),
+    }
+}
+
+impl FormattingProfile {
+    /// Style of the literals, MASM only accepting the suffix notation.
+    fn literal_style(&self) -> LiteralStyle {
+        match self.dialect {
+            Dialect::Capstone => self.literals,
+            Dialect::Masm => LiteralStyle {
+                hex_notation: HexNotation::Suffix,
+                ..self.literals
+            },
+        }
+    }
+
+    /// Rewrite the numeric literals of formatted instructions with this profile.
+    ///
+    /// Digits that are part of identifiers (mnemonics, labels, symbol names)
+    /// are left untouched.
+    pub fn format_line(&self, line: &str) -> String {
+        let style = &self.literal_style();
+
+        if *style == LiteralStyle::default() {
+            return line.into();
+        }
+
+        let mut res = String::with_capacity(line.len());
+        let mut idx = 0;
+
+        while let Some(c) = line[idx..].chars().next() {
+            let previous = res.chars().last();
+
+            // Digits part of identifiers, `st(0)` or scales are not literals.
+            let starts_literal = c.is_ascii_digit()
+                && !matches!(previous, Some(p) if p.is_ascii_alphanumeric() || matches!(p, '_' | '(' | '*'));
+
+            if !starts_literal {
+                res.push(c);
+                idx += c.len_utf8();
+                continue;
+            }
+
+            let end = line[idx..]
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .map(|x| idx + x)
+                .unwrap_or(line.len());
+
+            let Some(magnitude) = parse_literal(&line[idx..end]) else {
+                res.push_str(&line[idx..end]);
+                idx = end;
+                continue;
+            };
+
+            let negative = if res.ends_with(" - ") {
+                res.truncate(res.len() - 3);
+                res.push_str(" + ");
+                true
+            } else if res.ends_with('-') {
+                res.pop();
+                true
+            } else {
+                false
+            };
+
+            match (negative, style.signed) {
+                (false, _) => res.push_str(&style.format_magnitude(magnitude)),
+                (true, true) if res.ends_with(" + ") => {
+                    res.truncate(res.len() - 3);
+                    res.push_str(" - ");
+                    res.push_str(&style.format_magnitude(magnitude));
+                }
+                (true, true) => {
+                    res.push('-');
+                    res.push_str(&style.format_magnitude(magnitude));
+                }
+                (true, false) => {
+                    let value = (magnitude as u32).wrapping_neg() as u64;
+                    res.push_str(&style.format_magnitude(value));
+                }
+            }
+
+            idx = end;
+        }
+
+        res
+    }
+}
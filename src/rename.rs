@@ -0,0 +1,300 @@
+//! Bulk application of externally sourced function names to a mapping.
+//!
+//! Names usually come from spreadsheets or community databases as a list of
+//! address/name pairs. The mapping is edited in place so that comments and
+//! formatting of the TOML file are preserved.
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use toml_edit::{value, Document};
+
+use crate::id;
+
+#[derive(Debug)]
+pub enum RenameError {
+    CsvError { error: csv::Error },
+    TomlError { error: toml_edit::TomlError },
+    InvalidAddress { line: usize, address: String },
+    InvalidMapping,
+}
+
+impl std::fmt::Display for RenameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenameError::InvalidAddress { line, address } => {
+                write!(f, "Invalid address \"{address}\" at line {line}!")
+            }
+            RenameError::InvalidMapping => {
+                write!(f, "Mapping \"function\" entry is not an array of tables!")
+            }
+            _ => std::fmt::Debug::fmt(self, f),
+        }
+    }
+}
+
+impl Error for RenameError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RenameError::CsvError { error } => Some(error),
+            RenameError::TomlError { error } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<csv::Error> for RenameError {
+    fn from(error: csv::Error) -> Self {
+        Self::CsvError { error }
+    }
+}
+
+impl From<toml_edit::TomlError> for RenameError {
+    fn from(error: toml_edit::TomlError) -> Self {
+        Self::TomlError { error }
+    }
+}
+
+/// Parse an address written either in hexadecimal (`0x` prefix) or in decimal.
+pub fn parse_address(raw: &str) -> Option<usize> {
+    let raw = raw.trim();
+
+    match raw.strip_prefix("0x").or_else(|| raw.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => raw.parse().ok(),
+    }
+}
+
+/// A name to assign to the function at the given address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameAssignment {
+    pub address: usize,
+    pub name: String,
+}
+
+/// Parse a CSV or TSV list of address/name pairs.
+///
+/// The delimiter is detected from the content and an optional header line is
+/// skipped.
+pub fn parse_name_list(raw: &str) -> Result<Vec<NameAssignment>, RenameError> {
+    let delimiter = if raw.contains('\t') { b'\t' } else { b',' };
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .delimiter(delimiter)
+        .from_reader(raw.as_bytes());
+
+    let mut res = Vec::new();
+
+    for (idx, record) in rdr.records().enumerate() {
+        let record = record?;
+
+        let (raw_address, name) = match (record.get(0), record.get(1)) {
+            (Some(address), Some(name)) if !name.is_empty() => (address, name),
+            _ => continue,
+        };
+
+        match parse_address(raw_address) {
+            Some(address) => res.push(NameAssignment {
+                address,
+                name: name.into(),
+            }),
+            None if idx == 0 => {}
+            None => {
+                return Err(RenameError::InvalidAddress {
+                    line: idx + 1,
+                    address: raw_address.into(),
+                })
+            }
+        }
+    }
+
+    Ok(res)
+}
+
+/// Result of applying a single name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RenameOutcome {
+    /// The function had no name and got one.
+    Named { address: usize, name: String },
+    /// The function already had another name and it was replaced.
+    Renamed {
+        address: usize,
+        old_name: String,
+        name: String,
+    },
+    /// The function already has this name.
+    Unchanged { address: usize, name: String },
+    /// The function already has another name and it was kept.
+    Conflict {
+        address: usize,
+        existing_name: String,
+        name: String,
+    },
+    /// The name is already used by a function at another address.
+    DuplicateName {
+        address: usize,
+        name: String,
+        other_address: usize,
+    },
+    /// No function of the mapping starts at this address.
+    UnknownAddress { address: usize, name: String },
+}
+
+impl RenameOutcome {
+    pub fn is_conflict(&self) -> bool {
+        matches!(
+            self,
+            RenameOutcome::Conflict { .. }
+                | RenameOutcome::DuplicateName { .. }
+                | RenameOutcome::UnknownAddress { .. }
+        )
+    }
+}
+
+impl std::fmt::Display for RenameOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenameOutcome::Named { address, name } => write!(f, "{address:#x}: named {name}"),
+            RenameOutcome::Renamed {
+                address,
+                old_name,
+                name,
+            } => write!(f, "{address:#x}: renamed {old_name} to {name}"),
+            RenameOutcome::Unchanged { address, name } => {
+                write!(f, "{address:#x}: already named {name}")
+            }
+            RenameOutcome::Conflict {
+                address,
+                existing_name,
+                name,
+            } => write!(
+                f,
+                "{address:#x}: conflict, named {existing_name}, not {name}"
+            ),
+            RenameOutcome::DuplicateName {
+                address,
+                name,
+                other_address,
+            } => write!(
+                f,
+                "{address:#x}: conflict, {name} is already used by {other_address:#x}"
+            ),
+            RenameOutcome::UnknownAddress { address, name } => {
+                write!(f, "{address:#x}: no function in mapping for {name}")
+            }
+        }
+    }
+}
+
+/// Apply names to the functions of a mapping TOML document.
+///
+/// Existing names are only replaced when `force` is set, in which case the
+/// stable identifier of the function is pinned in the mapping.
+pub fn apply_names(
+    mapping: &mut Document,
+    names: &[NameAssignment],
+    force: bool,
+) -> Result<Vec<RenameOutcome>, RenameError> {
+    let functions = match mapping.get_mut("function") {
+        Some(item) => item
+            .as_array_of_tables_mut()
+            .ok_or(RenameError::InvalidMapping)?,
+        None => {
+            return Ok(names
+                .iter()
+                .map(|x| RenameOutcome::UnknownAddress {
+                    address: x.address,
+                    name: x.name.clone(),
+                })
+                .collect())
+        }
+    };
+
+    let mut addresses = HashMap::new();
+    let mut name_owners = HashMap::new();
+
+    for (idx, function) in functions.iter().enumerate() {
+        if let Some(address) = function.get("address").and_then(|x| x.as_integer()) {
+            addresses.insert(address as usize, idx);
+
+            if let Some(name) = function.get("name").and_then(|x| x.as_str()) {
+                name_owners.insert(name.to_string(), address as usize);
+            }
+        }
+    }
+
+    let mut res = Vec::new();
+
+    for assignment in names {
+        let address = assignment.address;
+        let name = assignment.name.clone();
+
+        let function = match addresses.get(&address) {
+            Some(idx) => functions.get_mut(*idx).unwrap(),
+            None => {
+                res.push(RenameOutcome::UnknownAddress { address, name });
+                continue;
+            }
+        };
+
+        if let Some(other_address) = name_owners.get(&name) {
+            if *other_address != address {
+                res.push(RenameOutcome::DuplicateName {
+                    address,
+                    name,
+                    other_address: *other_address,
+                });
+                continue;
+            }
+        }
+
+        let existing_name = function
+            .get("name")
+            .and_then(|x| x.as_str())
+            .map(String::from);
+
+        let outcome = match existing_name {
+            None => RenameOutcome::Named {
+                address,
+                name: name.clone(),
+            },
+            Some(existing_name) if existing_name == name => {
+                res.push(RenameOutcome::Unchanged { address, name });
+                continue;
+            }
+            Some(existing_name) if force => {
+                name_owners.remove(&existing_name);
+
+                RenameOutcome::Renamed {
+                    address,
+                    old_name: existing_name,
+                    name: name.clone(),
+                }
+            }
+            Some(existing_name) => {
+                res.push(RenameOutcome::Conflict {
+                    address,
+                    existing_name,
+                    name,
+                });
+                continue;
+            }
+        };
+
+        // Pin the identifier derived from the old name so its history is kept.
+        if let RenameOutcome::Renamed { old_name, .. } = &outcome {
+            if !function.contains_key("id") {
+                function["id"] = value(id::stable_id(old_name, address));
+            }
+        }
+
+        function["name"] = value(name.clone());
+        name_owners.insert(name, address);
+        res.push(outcome);
+    }
+
+    Ok(res)
+}
@@ -0,0 +1,1538 @@
+//! Structured stats reports and their renderers.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::{Color, Colorizer};
+use crate::compare::{self, ChangeKind};
+use crate::diff::Pairing;
+use crate::format::PercentageFormat;
+use crate::frame::FrameSizes;
+use crate::limits::SkippedFunction;
+use crate::list::FunctionOrder;
+use crate::objdiff;
+use crate::sink::{ReportSink, SinkError};
+use crate::{demangle, id, Executable, FunctionStats, Mapping, WorkStatus};
+
+#[derive(Debug)]
+pub enum ReportError {
+    FormatError { error: std::fmt::Error },
+    CsvError { error: csv::Error },
+    JsonError { error: serde_json::Error },
+    SinkError { error: SinkError },
+    UnsupportedFormat { format: ReportFormat },
+}
+
+impl std::fmt::Display for ReportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportError::SinkError { error } => write!(f, "{error}"),
+            ReportError::UnsupportedFormat { format } => {
+                write!(f, "Report format {format:?} is not supported here!")
+            }
+            _ => std::fmt::Debug::fmt(self, f),
+        }
+    }
+}
+
+impl std::error::Error for ReportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReportError::FormatError { error } => Some(error),
+            ReportError::CsvError { error } => Some(error),
+            ReportError::JsonError { error } => Some(error),
+            ReportError::SinkError { error } => Some(error),
+            ReportError::UnsupportedFormat { .. } => None,
+        }
+    }
+}
+
+impl From<std::fmt::Error> for ReportError {
+    fn from(error: std::fmt::Error) -> Self {
+        ReportError::FormatError { error }
+    }
+}
+
+impl From<csv::Error> for ReportError {
+    fn from(error: csv::Error) -> Self {
+        ReportError::CsvError { error }
+    }
+}
+
+impl From<serde_json::Error> for ReportError {
+    fn from(error: serde_json::Error) -> Self {
+        ReportError::JsonError { error }
+    }
+}
+
+impl From<SinkError> for ReportError {
+    fn from(error: SinkError) -> Self {
+        ReportError::SinkError { error }
+    }
+}
+
+/// Stylesheet embedded in HTML reports.
+const HTML_STYLE: &str = r#"body { font-family: sans-serif; margin: 2em auto; max-width: 960px; }
+table { border-collapse: collapse; width: 100%; }
+th, td { padding: 4px 8px; text-align: left; border-bottom: 1px solid #ddd; }
+th { cursor: pointer; user-select: none; }
+td.number { font-family: monospace; }
+.bar { background: #eee; width: 200px; height: 12px; }
+.bar > div { height: 100%; }
+.green { background: #4c1; }
+.yellow { background: #dfb317; }
+.red { background: #e05d44; }"#;
+
+/// Script embedded in HTML reports to sort the table by clicking its headers.
+const HTML_SCRIPT: &str = r#"document.querySelectorAll("th").forEach((th, column) => {
+  th.addEventListener("click", () => {
+    const body = th.closest("table").tBodies[0];
+    const ascending = th.dataset.order !== "asc";
+    th.dataset.order = ascending ? "asc" : "desc";
+    const key = (row) => row.cells[column].dataset.value;
+    const numeric = th.dataset.type === "number";
+    Array.from(body.rows)
+      .sort((a, b) => {
+        const res = numeric ? key(a) - key(b) : key(a).localeCompare(key(b));
+        return ascending ? res : -res;
+      })
+      .forEach((row) => body.appendChild(row));
+  });
+});"#;
+
+/// Escape text to be embedded in HTML.
+pub(crate) fn escape_html(value: &str) -> String {
+    let mut res = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&#39;"),
+            _ => res.push(c),
+        }
+    }
+
+    res
+}
+
+fn css_class(color: Color) -> &'static str {
+    match color {
+        Color::Green => "green",
+        Color::Yellow => "yellow",
+        Color::Red => "red",
+    }
+}
+
+/// Write CSV records, every field quoted like the reports always were.
+pub(crate) fn write_csv(header: &[String], rows: &[Vec<String>]) -> Result<String, ReportError> {
+    let mut writer = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::Always)
+        .from_writer(Vec::new());
+
+    writer.write_record(header)?;
+
+    for row in rows {
+        writer.write_record(row)?;
+    }
+
+    let data = writer
+        .into_inner()
+        .map_err(|err| csv::Error::from(err.into_error()))?;
+
+    Ok(String::from_utf8_lossy(&data).into_owned())
+}
+
+/// Match status of a function.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchStatus {
+    Matching,
+    Partial,
+    Missing,
+}
+
+impl MatchStatus {
+    pub fn from_percentage(value: Option<f32>) -> Self {
+        match value {
+            Some(value) if value >= 100.0 => MatchStatus::Matching,
+            Some(_) => MatchStatus::Partial,
+            None => MatchStatus::Missing,
+        }
+    }
+
+    /// Progress of a function without a status in the mapping.
+    pub fn work_status(&self) -> WorkStatus {
+        match self {
+            MatchStatus::Matching => WorkStatus::Matching,
+            MatchStatus::Partial => WorkStatus::Wip,
+            MatchStatus::Missing => WorkStatus::NotStarted,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MatchStatus::Matching => "matching",
+            MatchStatus::Partial => "partial",
+            MatchStatus::Missing => "missing",
+        }
+    }
+}
+
+/// Number of instructions of a function and of its reimplementation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstructionCounts {
+    pub original: usize,
+    /// `None` when the function is missing from the reimplementation.
+    pub reimplementation: Option<usize>,
+}
+
+/// Range of match percentages the functions are grouped by in the summary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PercentBucket {
+    Matching,
+    /// 90% to 99%.
+    Close,
+    /// 50% to 89%.
+    Partial,
+    /// Below 50%.
+    Low,
+    Missing,
+}
+
+impl PercentBucket {
+    pub const ALL: [PercentBucket; 5] = [
+        PercentBucket::Matching,
+        PercentBucket::Close,
+        PercentBucket::Partial,
+        PercentBucket::Low,
+        PercentBucket::Missing,
+    ];
+
+    pub fn from_percentage(value: Option<f32>) -> Self {
+        match value {
+            Some(value) if value >= 100.0 => PercentBucket::Matching,
+            Some(value) if value >= 90.0 => PercentBucket::Close,
+            Some(value) if value >= 50.0 => PercentBucket::Partial,
+            Some(_) => PercentBucket::Low,
+            None => PercentBucket::Missing,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PercentBucket::Matching => "100%",
+            PercentBucket::Close => "90-99%",
+            PercentBucket::Partial => "50-89%",
+            PercentBucket::Low => "<50%",
+            PercentBucket::Missing => "missing",
+        }
+    }
+}
+
+/// Stats of a single function.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FunctionReport {
+    /// Stable identifier, see [`crate::id`].
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub address: usize,
+    pub size: usize,
+    /// Match percentage, `None` when the function is missing from the reimplementation.
+    pub percent: Option<f32>,
+    pub status: MatchStatus,
+    /// Change of the match percentage since the baseline, `None` without baseline.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta: Option<f32>,
+    /// Progress declared in the mapping, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub work_status: Option<WorkStatus>,
+    /// Share of matching instructions, see [`crate::diff::DiffOptions::instruction_match`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instruction_percent: Option<f32>,
+    /// Stack frame sizes of the function and its reimplementation, see [`crate::frame`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame_sizes: Option<FrameSizes>,
+    /// Number of instructions of the function and its reimplementation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instruction_counts: Option<InstructionCounts>,
+}
+
+impl FunctionReport {
+    /// Progress declared in the mapping, or guessed from the match status.
+    pub fn effective_work_status(&self) -> WorkStatus {
+        self.work_status.unwrap_or(self.status.work_status())
+    }
+}
+
+/// Summary of the stats of all functions.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GlobalReport {
+    pub percent: f32,
+    pub function_count: usize,
+    pub matching_count: usize,
+    pub missing_count: usize,
+}
+
+impl GlobalReport {
+    /// Summary of some functions, missing functions counting as 0%.
+    pub(crate) fn summarize(functions: &[&FunctionReport]) -> Self {
+        let total_match: f32 = functions.iter().map(|x| x.percent.unwrap_or(0.0)).sum();
+        let count_status = |status| functions.iter().filter(|x| x.status == status).count();
+
+        Self {
+            percent: if functions.is_empty() {
+                0.0
+            } else {
+                total_match / functions.len() as f32
+            },
+            function_count: functions.len(),
+            matching_count: count_status(MatchStatus::Matching),
+            missing_count: count_status(MatchStatus::Missing),
+        }
+    }
+}
+
+/// Name of the group of the functions without a known module, such as the
+/// ones missing from the reimplementation.
+pub const UNKNOWN_MODULE: &str = "(unknown)";
+
+/// Stats of the functions defined by a PDB module (compiland).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ModuleReport {
+    pub name: String,
+    #[serde(flatten)]
+    pub summary: GlobalReport,
+}
+
+/// Grouping of the functions of a report.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupBy {
+    /// PDB module (compiland) of the reimplementation defining the function.
+    Module,
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "module" => Ok(Self::Module),
+            _ => Err(format!("Unknown grouping \"{s}\", expected module")),
+        }
+    }
+}
+
+/// Stats of an executable compared to its reimplementation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StatsReport {
+    pub functions: Vec<FunctionReport>,
+    pub global: GlobalReport,
+    /// Functions left out because they exceeded the configured limits.
+    #[serde(default)]
+    pub skipped: Vec<SkippedFunction>,
+    /// Progress since the baseline, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<Progress>,
+    /// Stats per PDB module, listed instead of the functions when present.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modules: Vec<ModuleReport>,
+    /// Breakdowns of every function, kept when [`Self::filtered`] leaves some
+    /// of them out.
+    #[serde(skip)]
+    breakdowns: Option<Breakdowns>,
+}
+
+/// Breakdowns of the functions of a report by percentage and by progress.
+#[derive(Clone, Debug)]
+struct Breakdowns {
+    buckets: Vec<(PercentBucket, usize, usize)>,
+    work_statuses: Vec<(WorkStatus, f32)>,
+}
+
+/// Net progress since a baseline report.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Progress {
+    /// Change of the global match percentage.
+    pub global_delta: f32,
+    pub improved: usize,
+    pub regressed: usize,
+    pub appeared: usize,
+    pub disappeared: usize,
+}
+
+impl Progress {
+    fn summary(&self, percentage_format: &PercentageFormat) -> String {
+        format!(
+            "{} since baseline ({} improved, {} regressed, {} appeared, {} disappeared)",
+            percentage_format.format_delta(self.global_delta),
+            self.improved,
+            self.regressed,
+            self.appeared,
+            self.disappeared
+        )
+    }
+}
+
+impl StatsReport {
+    /// Build a report from the stats of the original executable.
+    ///
+    /// The global percentage only covers the functions of the stats, leaving
+    /// out the ignored and skipped ones.
+    pub fn new(executable: &Executable, stats: &FunctionStats) -> Self {
+        let mut functions = Vec::new();
+
+        for (name, percent) in stats {
+            let (address, size) = executable
+                .get_function(name)
+                .map(|x| (x.address, x.data.len()))
+                .unwrap_or_default();
+
+            functions.push(FunctionReport {
+                id: id::stable_id(name, address),
+                name: name.clone(),
+                address,
+                size,
+                percent: *percent,
+                status: MatchStatus::from_percentage(*percent),
+                delta: None,
+                work_status: None,
+                instruction_percent: None,
+                frame_sizes: None,
+                instruction_counts: None,
+            });
+        }
+
+        functions.sort_by(|a, b| (a.address, &a.name).cmp(&(b.address, &b.name)));
+
+        let global = GlobalReport::summarize(&functions.iter().collect::<Vec<_>>());
+
+        Self {
+            functions,
+            global,
+            skipped: Vec::new(),
+            progress: None,
+            modules: Vec::new(),
+            breakdowns: None,
+        }
+    }
+
+    /// Reorder the functions, ties being broken by name.
+    ///
+    /// Functions are sorted by address by default.
+    pub fn sorted(mut self, order: FunctionOrder) -> Self {
+        match order {
+            FunctionOrder::Name => self.functions.sort_by(|a, b| a.name.cmp(&b.name)),
+            FunctionOrder::Address => self
+                .functions
+                .sort_by(|a, b| (a.address, &a.name).cmp(&(b.address, &b.name))),
+            FunctionOrder::Size => self
+                .functions
+                .sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name))),
+        }
+
+        self
+    }
+
+    /// Weigh the global percentage by the size of the functions, so that a
+    /// large function counts more than a small stub.
+    ///
+    /// Covers the functions kept so far, so must be called before
+    /// [`Self::filtered`] for the global percentage to cover every function.
+    pub fn with_weighted_global(mut self) -> Self {
+        let total_size: usize = self.functions.iter().map(|x| x.size).sum();
+        let matched_size: f32 = self
+            .functions
+            .iter()
+            .map(|x| x.percent.unwrap_or(0.0) * x.size as f32)
+            .sum();
+
+        self.global.percent = if total_size == 0 {
+            0.0
+        } else {
+            matched_size / total_size as f32
+        };
+
+        self
+    }
+
+    /// Use the identifiers pinned in the mapping.
+    pub fn with_mapping_ids(mut self, mapping: &Mapping) -> Self {
+        for function in &mut self.functions {
+            function.id = mapping.function_id(&function.name, function.address);
+        }
+
+        self
+    }
+
+    /// Attach the share of matching instructions of each function.
+    ///
+    /// Must be called before the names are demangled.
+    pub fn with_instruction_stats(mut self, stats: &FunctionStats) -> Self {
+        for function in &mut self.functions {
+            function.instruction_percent = stats.get(&function.name).copied().flatten();
+        }
+
+        self
+    }
+
+    fn has_instruction_stats(&self) -> bool {
+        self.functions
+            .iter()
+            .any(|x| x.instruction_percent.is_some())
+    }
+
+    /// Attach the stack frame sizes of each function and its reimplementation.
+    pub fn with_frame_sizes(mut self, frame_sizes: &HashMap<String, FrameSizes>) -> Self {
+        for function in &mut self.functions {
+            function.frame_sizes = frame_sizes.get(&function.name).copied();
+        }
+
+        self
+    }
+
+    fn has_frame_sizes(&self) -> bool {
+        self.functions.iter().any(|x| x.frame_sizes.is_some())
+    }
+
+    /// Attach the instruction counts of each function and its
+    /// reimplementation.
+    ///
+    /// Must be called before the names are demangled.
+    pub fn with_instruction_counts(
+        mut self,
+        instruction_counts: &HashMap<String, InstructionCounts>,
+    ) -> Self {
+        for function in &mut self.functions {
+            function.instruction_counts = instruction_counts.get(&function.name).copied();
+        }
+
+        self
+    }
+
+    /// Use the progress declared in the mapping.
+    pub fn with_work_statuses(mut self, mapping: &Mapping) -> Self {
+        for function in &mut self.functions {
+            function.work_status = mapping
+                .get_function_def(&function.name)
+                .and_then(|x| x.status);
+        }
+
+        self
+    }
+
+    /// Share of the functions in each progress status, leaving out the empty ones.
+    pub fn work_status_breakdown(&self) -> Vec<(WorkStatus, f32)> {
+        if let Some(breakdowns) = &self.breakdowns {
+            return breakdowns.work_statuses.clone();
+        }
+
+        if self.functions.is_empty() {
+            return Vec::new();
+        }
+
+        WorkStatus::ALL
+            .iter()
+            .map(|status| {
+                let count = self
+                    .functions
+                    .iter()
+                    .filter(|x| x.effective_work_status() == *status)
+                    .count();
+
+                (*status, count as f32 * 100.0 / self.functions.len() as f32)
+            })
+            .filter(|(_, share)| *share > 0.0)
+            .collect()
+    }
+
+    /// Breakdown of the progress as `37% matching, 12% wip`.
+    pub fn format_work_status_breakdown(&self, percentage_format: &PercentageFormat) -> String {
+        let parts: Vec<String> = self
+            .work_status_breakdown()
+            .iter()
+            .map(|(status, share)| {
+                format!("{} {}", percentage_format.format(*share), status.name())
+            })
+            .collect();
+
+        parts.join(", ")
+    }
+
+    /// Only keep the functions missing from the reimplementation, or the
+    /// ones matching below `below` percents, missing ones included.
+    ///
+    /// The global stats, the modules, the progress since the baseline and
+    /// the breakdowns still cover every function, the rendered functions
+    /// and the rows of the CSV only the kept ones. Must be called after
+    /// [`Self::with_weighted_global`], [`Self::with_baseline`] and recording
+    /// the report in the history, which cover the functions kept so far.
+    pub fn filtered(mut self, only_missing: bool, below: Option<f32>) -> Self {
+        self.breakdowns = Some(Breakdowns {
+            buckets: self.bucket_breakdown(),
+            work_statuses: self.work_status_breakdown(),
+        });
+
+        self.functions.retain(|function| {
+            let is_missing = function.percent.is_none();
+            let is_below = match (function.percent, below) {
+                (_, None) | (None, _) => true,
+                (Some(percent), Some(below)) => percent < below,
+            };
+
+            (is_missing || !only_missing) && is_below
+        });
+
+        self
+    }
+
+    /// Number of functions and their total size in each percentage bucket.
+    pub fn bucket_breakdown(&self) -> Vec<(PercentBucket, usize, usize)> {
+        if let Some(breakdowns) = &self.breakdowns {
+            return breakdowns.buckets.clone();
+        }
+
+        PercentBucket::ALL
+            .iter()
+            .map(|bucket| {
+                let functions = self
+                    .functions
+                    .iter()
+                    .filter(|x| PercentBucket::from_percentage(x.percent) == *bucket);
+
+                (
+                    *bucket,
+                    functions.clone().count(),
+                    functions.map(|x| x.size).sum(),
+                )
+            })
+            .collect()
+    }
+
+    /// Undecorate the names of the functions, identifiers are kept.
+    pub fn with_demangled_names(mut self) -> Self {
+        for function in &mut self.functions {
+            if let Some(name) = demangle::demangle(&function.name) {
+                function.name = name;
+            }
+        }
+
+        self
+    }
+
+    /// Annotate the functions with their change since a baseline report.
+    ///
+    /// The progress covers the functions kept so far, so must be called
+    /// before [`Self::filtered`] to cover every function.
+    pub fn with_baseline(mut self, baseline: &StatsReport) -> Self {
+        let mut progress = Progress {
+            global_delta: self.global.percent - baseline.global.percent,
+            ..Default::default()
+        };
+
+        for change in compare::compare(baseline, &self) {
+            match change.kind {
+                ChangeKind::Improved => progress.improved += 1,
+                ChangeKind::Regressed => progress.regressed += 1,
+                ChangeKind::Appeared => progress.appeared += 1,
+                ChangeKind::Disappeared => progress.disappeared += 1,
+            }
+        }
+
+        for function in &mut self.functions {
+            let previous = baseline
+                .functions
+                .iter()
+                .find(|x| compare::key(x) == compare::key(function))
+                .and_then(|x| x.percent);
+
+            function.delta = Some(function.percent.unwrap_or(0.0) - previous.unwrap_or(0.0));
+        }
+
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Change of a function since the baseline, when it isn't null once rounded.
+    fn format_delta(
+        percentage_format: &PercentageFormat,
+        function: &FunctionReport,
+    ) -> Option<String> {
+        function
+            .delta
+            .filter(|x| percentage_format.round(*x) != 0.0)
+            .map(|x| percentage_format.format_delta(x))
+    }
+
+    /// Aggregate the functions by the PDB module of the reimplementation
+    /// defining them, the functions being paired as when they were compared.
+    ///
+    /// Must be called before the names are demangled.
+    pub fn with_modules(mut self, reimplementation: &Executable, pairing: Pairing) -> Self {
+        let mut groups: BTreeMap<String, Vec<&FunctionReport>> = BTreeMap::new();
+
+        for function in &self.functions {
+            let module = reimplementation
+                .paired_function_at(&function.name, function.address, pairing)
+                .and_then(|x| x.module.clone())
+                .unwrap_or_else(|| UNKNOWN_MODULE.into());
+
+            groups.entry(module).or_default().push(function);
+        }
+
+        let modules = groups
+            .into_iter()
+            .map(|(name, functions)| ModuleReport {
+                name,
+                summary: GlobalReport::summarize(&functions),
+            })
+            .collect();
+
+        self.modules = modules;
+        self
+    }
+
+    /// Attach the functions skipped while generating the stats.
+    pub fn with_skipped(mut self, skipped: Vec<SkippedFunction>) -> Self {
+        self.skipped = skipped;
+        self
+    }
+
+    fn format_percent(percentage_format: &PercentageFormat, value: Option<f32>) -> String {
+        match value {
+            Some(value) => percentage_format.format(value),
+            None => "MISSING".into(),
+        }
+    }
+
+    /// Render the report as `name: percent` lines.
+    pub fn render_text(
+        &self,
+        percentage_format: &PercentageFormat,
+        colorizer: &Colorizer,
+    ) -> Result<String, std::fmt::Error> {
+        let mut res = String::new();
+
+        for module in &self.modules {
+            let summary = &module.summary;
+
+            writeln!(
+                res,
+                "{}: {} ({} functions, {} matching, {} missing)",
+                module.name,
+                colorizer.paint(
+                    Color::for_percentage(Some(summary.percent)),
+                    &percentage_format.format(summary.percent)
+                ),
+                summary.function_count,
+                summary.matching_count,
+                summary.missing_count
+            )?;
+        }
+
+        let functions = if self.modules.is_empty() {
+            self.functions.as_slice()
+        } else {
+            &[]
+        };
+
+        for function in functions {
+            let value = Self::format_percent(percentage_format, function.percent);
+
+            write!(
+                res,
+                "{}: {}",
+                function.name,
+                colorizer.paint(Color::for_percentage(function.percent), &value)
+            )?;
+
+            if let Some(instruction_percent) = function.instruction_percent {
+                write!(
+                    res,
+                    " [{} instructions]",
+                    percentage_format.format(instruction_percent)
+                )?;
+            }
+
+            if let Some(frame_sizes) = function.frame_sizes {
+                write!(res, " [frame {frame_sizes} bytes]")?;
+            }
+
+            match Self::format_delta(percentage_format, function) {
+                Some(delta) => writeln!(res, " ({delta})")?,
+                None => writeln!(res)?,
+            }
+        }
+
+        if self.global.function_count > 0 {
+            writeln!(res, "\nBREAKDOWN:")?;
+
+            for (bucket, count, size) in self.bucket_breakdown() {
+                writeln!(
+                    res,
+                    "{:<8} {count:>6} functions {size:>9} bytes",
+                    bucket.name()
+                )?;
+            }
+
+            writeln!(
+                res,
+                "\nSTATUS: {}",
+                self.format_work_status_breakdown(percentage_format)
+            )?;
+        }
+
+        if let Some(progress) = &self.progress {
+            writeln!(res, "\nPROGRESS: {}", progress.summary(percentage_format))?;
+        }
+
+        if !self.skipped.is_empty() {
+            writeln!(res, "\nSkipped functions:")?;
+
+            for function in &self.skipped {
+                writeln!(res, "{}: {}", function.name, function.reason)?;
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Header and rows of the CSV rendering: a row per module when grouped
+    /// by module, a row per function otherwise.
+    pub(crate) fn csv_records(
+        &self,
+        percentage_format: &PercentageFormat,
+    ) -> (Vec<String>, Vec<Vec<String>>) {
+        if !self.modules.is_empty() {
+            let header = ["Module", "Match %", "Functions", "Matching", "Missing"];
+            let rows = self
+                .modules
+                .iter()
+                .map(|module| {
+                    let summary = &module.summary;
+
+                    vec![
+                        module.name.clone(),
+                        percentage_format.format(summary.percent),
+                        summary.function_count.to_string(),
+                        summary.matching_count.to_string(),
+                        summary.missing_count.to_string(),
+                    ]
+                })
+                .collect();
+
+            return (header.map(String::from).to_vec(), rows);
+        }
+
+        let has_baseline = self.progress.is_some();
+        let has_instruction_stats = self.has_instruction_stats();
+        let has_frame_sizes = self.has_frame_sizes();
+
+        let mut header: Vec<String> = [
+            "Function name",
+            "Match %",
+            "Id",
+            "Address",
+            "Size",
+            "Status",
+            "Original instructions",
+            "Reimplementation instructions",
+        ]
+        .map(String::from)
+        .to_vec();
+
+        if has_instruction_stats {
+            header.push("Instructions".into());
+        }
+
+        if has_frame_sizes {
+            header.push("Frame".into());
+        }
+
+        if has_baseline {
+            header.push("Change".into());
+        }
+
+        let mut rows = Vec::new();
+
+        for function in &self.functions {
+            let counts = function.instruction_counts;
+            let mut row = vec![
+                function.name.clone(),
+                Self::format_percent(percentage_format, function.percent),
+                function.id.clone(),
+                format!("{:#x}", function.address),
+                function.size.to_string(),
+                function.status.name().into(),
+                counts.map(|x| x.original.to_string()).unwrap_or_default(),
+                counts
+                    .and_then(|x| x.reimplementation)
+                    .map(|x| x.to_string())
+                    .unwrap_or_default(),
+            ];
+
+            if has_instruction_stats {
+                row.push(Self::format_percent(
+                    percentage_format,
+                    function.instruction_percent,
+                ));
+            }
+
+            if has_frame_sizes {
+                row.push(
+                    function
+                        .frame_sizes
+                        .map(|x| x.to_string())
+                        .unwrap_or_default(),
+                );
+            }
+
+            if has_baseline {
+                row.push(
+                    function
+                        .delta
+                        .map(|x| percentage_format.format_delta(x))
+                        .unwrap_or_default(),
+                );
+            }
+
+            rows.push(row);
+        }
+
+        for function in &self.skipped {
+            let mut row = vec![String::new(); header.len()];
+            row[0] = function.name.clone();
+            row[1] = "SKIPPED".into();
+
+            rows.push(row);
+        }
+
+        (header, rows)
+    }
+
+    /// Render the report as CSV.
+    pub fn render_csv(&self, percentage_format: &PercentageFormat) -> Result<String, ReportError> {
+        let (header, rows) = self.csv_records(percentage_format);
+
+        write_csv(&header, &rows)
+    }
+
+    /// Copy of the report with percentages rounded to the configured precision.
+    pub fn rounded(&self, percentage_format: &PercentageFormat) -> Self {
+        let mut report = self.clone();
+
+        for function in &mut report.functions {
+            function.percent = function.percent.map(|x| percentage_format.round(x));
+            function.delta = function.delta.map(|x| percentage_format.round(x));
+            function.instruction_percent = function
+                .instruction_percent
+                .map(|x| percentage_format.round(x));
+        }
+
+        report.global.percent = percentage_format.round(report.global.percent);
+
+        for module in &mut report.modules {
+            module.summary.percent = percentage_format.round(module.summary.percent);
+        }
+
+        if let Some(progress) = &mut report.progress {
+            progress.global_delta = percentage_format.round(progress.global_delta);
+        }
+
+        report
+    }
+
+    /// Load a report previously rendered as JSON.
+    pub fn from_json(content: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(content)
+    }
+
+    /// Render the report as JSON, rounding percentages to the configured precision.
+    pub fn render_json(&self, percentage_format: &PercentageFormat) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&self.rounded(percentage_format))
+    }
+
+    /// Render the report as a GitHub-flavored Markdown table followed by a summary line.
+    pub fn render_markdown(
+        &self,
+        percentage_format: &PercentageFormat,
+    ) -> Result<String, std::fmt::Error> {
+        let mut res = String::new();
+        let global = &self.global;
+
+        let mut functions: Vec<_> = self.functions.iter().collect();
+
+        let has_baseline = self.progress.is_some();
+        let has_instruction_stats = self.has_instruction_stats();
+        let has_frame_sizes = self.has_frame_sizes();
+
+        if !self.modules.is_empty() {
+            writeln!(res, "| Module | Functions | Matching | Missing | Match |")?;
+            writeln!(res, "| --- | ---: | ---: | ---: | ---: |")?;
+
+            for module in &self.modules {
+                let summary = &module.summary;
+
+                writeln!(
+                    res,
+                    "| `{}` | {} | {} | {} | {} |",
+                    module.name.replace('|', "\\|"),
+                    summary.function_count,
+                    summary.matching_count,
+                    summary.missing_count,
+                    percentage_format.format(summary.percent)
+                )?;
+            }
+
+            functions.clear();
+        } else {
+            let mut header = String::from("| Function | Size | Match |");
+            let mut alignment = String::from("| --- | ---: | ---: |");
+
+            if has_instruction_stats {
+                header.push_str(" Instructions |");
+                alignment.push_str(" ---: |");
+            }
+
+            if has_frame_sizes {
+                header.push_str(" Frame |");
+                alignment.push_str(" ---: |");
+            }
+
+            if has_baseline {
+                header.push_str(" Change |");
+                alignment.push_str(" ---: |");
+            }
+
+            writeln!(res, "{header}")?;
+            writeln!(res, "{alignment}")?;
+        }
+
+        for function in functions {
+            write!(
+                res,
+                "| `{}` | {} | {} |",
+                function.name.replace('|', "\\|"),
+                function.size,
+                Self::format_percent(percentage_format, function.percent)
+            )?;
+
+            if has_instruction_stats {
+                let instruction_value =
+                    Self::format_percent(percentage_format, function.instruction_percent);
+
+                write!(res, " {instruction_value} |")?;
+            }
+
+            if has_frame_sizes {
+                let frame_value = function
+                    .frame_sizes
+                    .map(|x| x.to_string())
+                    .unwrap_or_default();
+
+                write!(res, " {frame_value} |")?;
+            }
+
+            if has_baseline {
+                let delta = Self::format_delta(percentage_format, function).unwrap_or_default();
+                write!(res, " {delta} |")?;
+            }
+
+            writeln!(res)?;
+        }
+
+        writeln!(res)?;
+        writeln!(
+            res,
+            "**Global: {}** ({} functions, {} matching, {} missing)",
+            percentage_format.format(global.percent),
+            global.function_count,
+            global.matching_count,
+            global.missing_count
+        )?;
+
+        if self.global.function_count > 0 {
+            writeln!(res, "\n| Match | Functions | Bytes |")?;
+            writeln!(res, "| --- | ---: | ---: |")?;
+
+            for (bucket, count, size) in self.bucket_breakdown() {
+                writeln!(res, "| {} | {count} | {size} |", bucket.name())?;
+            }
+
+            writeln!(
+                res,
+                "\n**Status: {}**",
+                self.format_work_status_breakdown(percentage_format)
+            )?;
+        }
+
+        if let Some(progress) = &self.progress {
+            writeln!(
+                res,
+                "\n**Progress: {}**",
+                progress.summary(percentage_format)
+            )?;
+        }
+
+        if !self.skipped.is_empty() {
+            writeln!(res, "\n### Skipped functions\n")?;
+
+            for function in &self.skipped {
+                writeln!(res, "- `{}`: {}", function.name, function.reason)?;
+            }
+        }
+
+        Ok(res)
+    }
+
+    fn html_progress_bar(percent: Option<f32>) -> String {
+        format!(
+            "<div class=\"bar\"><div class=\"{}\" style=\"width: {}%\"></div></div>",
+            css_class(Color::for_percentage(percent)),
+            PercentageFormat::new(2).format_number(percent.unwrap_or(0.0).clamp(0.0, 100.0))
+        )
+    }
+
+    /// Render the report as a self-contained HTML page with a sortable table.
+    pub fn render_html(
+        &self,
+        percentage_format: &PercentageFormat,
+    ) -> Result<String, std::fmt::Error> {
+        let mut res = String::new();
+        let global = &self.global;
+
+        let functions = &self.functions;
+
+        writeln!(res, "<!DOCTYPE html>")?;
+        writeln!(res, "<html>")?;
+        writeln!(res, "<head>")?;
+        writeln!(res, "<meta charset=\"utf-8\">")?;
+        writeln!(res, "<title>Progress report</title>")?;
+        writeln!(res, "<style>\n{HTML_STYLE}\n</style>")?;
+        writeln!(res, "</head>")?;
+        writeln!(res, "<body>")?;
+        writeln!(
+            res,
+            "<h1>Progress: {}</h1>",
+            percentage_format.format(global.percent)
+        )?;
+        writeln!(res, "{}", Self::html_progress_bar(Some(global.percent)))?;
+        writeln!(
+            res,
+            "<p>{} functions, {} matching, {} missing</p>",
+            global.function_count, global.matching_count, global.missing_count
+        )?;
+
+        if self.global.function_count > 0 {
+            writeln!(
+                res,
+                "<p>Status: {}</p>",
+                self.format_work_status_breakdown(percentage_format)
+            )?;
+        }
+
+        if let Some(progress) = &self.progress {
+            writeln!(
+                res,
+                "<p>{}</p>",
+                escape_html(&progress.summary(percentage_format))
+            )?;
+        }
+
+        let has_instruction_stats = self.has_instruction_stats();
+        let instruction_header = if has_instruction_stats {
+            "<th data-type=\"number\">Instructions</th>"
+        } else {
+            ""
+        };
+        let has_frame_sizes = self.has_frame_sizes();
+        let frame_header = if has_frame_sizes {
+            "<th>Frame</th>"
+        } else {
+            ""
+        };
+
+        writeln!(res, "<table>")?;
+        writeln!(res, "<thead><tr><th>Function</th><th data-type=\"number\">Address</th><th data-type=\"number\">Size</th><th data-type=\"number\">Match</th>{instruction_header}{frame_header}<th data-type=\"number\">Progress</th></tr></thead>")?;
+        writeln!(res, "<tbody>")?;
+
+        for function in functions {
+            let name = escape_html(&function.name);
+            let sort_value = function.percent.unwrap_or(-1.0);
+            let instruction_cell = if has_instruction_stats {
+                format!(
+                    "<td class=\"number\" data-value=\"{}\">{}</td>",
+                    function.instruction_percent.unwrap_or(-1.0),
+                    Self::format_percent(percentage_format, function.instruction_percent)
+                )
+            } else {
+                String::new()
+            };
+            let frame_cell = if has_frame_sizes {
+                let frame_value = function
+                    .frame_sizes
+                    .map(|x| x.to_string())
+                    .unwrap_or_default();
+
+                format!("<td class=\"number\">{frame_value}</td>")
+            } else {
+                String::new()
+            };
+
+            writeln!(
+                res,
+                "<tr data-id=\"{id}\"><td data-value=\"{name}\">{name}</td><td class=\"number\" data-value=\"{address}\">{address:#x}</td><td class=\"number\" data-value=\"{size}\">{size}</td><td class=\"number\" data-value=\"{sort_value}\">{}</td>{instruction_cell}{frame_cell}<td data-value=\"{sort_value}\">{}</td></tr>",
+                Self::format_percent(percentage_format, function.percent),
+                Self::html_progress_bar(function.percent),
+                id = function.id,
+                address = function.address,
+                size = function.size,
+            )?;
+        }
+
+        writeln!(res, "</tbody>")?;
+        writeln!(res, "</table>")?;
+
+        if !self.skipped.is_empty() {
+            writeln!(res, "<h2>Skipped functions</h2>")?;
+            writeln!(res, "<ul>")?;
+
+            for function in &self.skipped {
+                writeln!(
+                    res,
+                    "<li>{}: {}</li>",
+                    escape_html(&function.name),
+                    function.reason
+                )?;
+            }
+
+            writeln!(res, "</ul>")?;
+        }
+        writeln!(res, "<script>\n{HTML_SCRIPT}\n</script>")?;
+        writeln!(res, "</body>")?;
+        writeln!(res, "</html>")?;
+
+        Ok(res)
+    }
+
+    /// Render the report as an objdiff report with a single unit.
+    pub fn render_objdiff(&self, unit_name: &str) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&objdiff::Report::from_stats(self, unit_name))
+    }
+
+    /// Render the report in the given format.
+    ///
+    /// The colorizer is only used by the text format.
+    pub fn render(
+        &self,
+        format: ReportFormat,
+        percentage_format: &PercentageFormat,
+        colorizer: &Colorizer,
+    ) -> Result<String, ReportError> {
+        Ok(match format {
+            ReportFormat::Text => self.render_text(percentage_format, colorizer)?,
+            ReportFormat::Csv => self.render_csv(percentage_format)?,
+            ReportFormat::Json => self.render_json(percentage_format)? + "\n",
+            ReportFormat::Html => self.render_html(percentage_format)?,
+            ReportFormat::Markdown => self.render_markdown(percentage_format)?,
+            ReportFormat::Objdiff => self.render_objdiff(objdiff::DEFAULT_UNIT_NAME)? + "\n",
+        })
+    }
+
+    /// Render the report in the given format and write it to a sink.
+    pub fn write_to(
+        &self,
+        sink: &mut dyn ReportSink,
+        format: ReportFormat,
+        percentage_format: &PercentageFormat,
+        colorizer: &Colorizer,
+    ) -> Result<(), ReportError> {
+        let res = self.render(format, percentage_format, colorizer)?;
+
+        sink.write_report(&res, format.content_type())?;
+
+        Ok(())
+    }
+}
+
+/// Output format of a stats report.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Text,
+    Csv,
+    Json,
+    Html,
+    Markdown,
+    Objdiff,
+}
+
+impl ReportFormat {
+    /// Guess the format from the extension of the output file.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().map(|x| x.to_string_lossy().to_lowercase()) {
+            Some(extension) if extension == "csv" => ReportFormat::Csv,
+            Some(extension) if extension == "json" => ReportFormat::Json,
+            Some(extension) if extension == "html" || extension == "htm" => ReportFormat::Html,
+            Some(extension) if extension == "md" => ReportFormat::Markdown,
+            _ => ReportFormat::Text,
+        }
+    }
+
+    /// MIME type of reports in this format.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ReportFormat::Text => "text/plain; charset=utf-8",
+            ReportFormat::Csv => "text/csv; charset=utf-8",
+            ReportFormat::Json | ReportFormat::Objdiff => "application/json",
+            ReportFormat::Html => "text/html; charset=utf-8",
+            ReportFormat::Markdown => "text/markdown; charset=utf-8",
+        }
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            "html" => Ok(Self::Html),
+            "markdown" => Ok(Self::Markdown),
+            "objdiff" => Ok(Self::Objdiff),
+            _ => Err(format!(
+                "Unknown report format \"{s}\", expected one of text, csv, json, html, markdown or objdiff"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FunctionSource, FunctionStats};
+
+    #[test]
+    fn modules_aggregate_the_functions_they_define() {
+        let mut reimplementation = Executable::default();
+
+        for (name, address) in [("a", 0x1000), ("b", 0x2000)] {
+            reimplementation
+                .add_function(name.into(), address, vec![0xc3].into(), FunctionSource::Pdb)
+                .unwrap();
+            reimplementation.functions.get_mut(name).unwrap().module = Some("player.cpp".into());
+        }
+
+        let stats: FunctionStats = [("a", Some(100.0)), ("b", Some(50.0)), ("c", None)]
+            .into_iter()
+            .map(|(name, percent)| (name.to_string(), percent))
+            .collect();
+        let report = StatsReport::new(&Executable::default(), &stats)
+            .with_modules(&reimplementation, Pairing::Name);
+
+        let modules: Vec<_> = report
+            .modules
+            .iter()
+            .map(|x| {
+                (
+                    x.name.as_str(),
+                    x.summary.percent,
+                    x.summary.function_count,
+                    x.summary.matching_count,
+                    x.summary.missing_count,
+                )
+            })
+            .collect();
+        assert_eq!(
+            modules,
+            vec![
+                (UNKNOWN_MODULE, 0.0, 1, 0, 1),
+                ("player.cpp", 75.0, 2, 1, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn modules_follow_the_pairing_of_the_functions() {
+        let mut original = Executable::default();
+        original
+            .add_function(
+                "a".into(),
+                0x1000,
+                vec![0xc3].into(),
+                FunctionSource::Mapping,
+            )
+            .unwrap();
+
+        let mut reimplementation = Executable::default();
+        reimplementation
+            .add_function(
+                "?a@@YAXXZ".into(),
+                0x1000,
+                vec![0xc3].into(),
+                FunctionSource::Pdb,
+            )
+            .unwrap();
+        reimplementation
+            .functions
+            .get_mut("?a@@YAXXZ")
+            .unwrap()
+            .module = Some("player.cpp".into());
+
+        let stats: FunctionStats = [("a".to_string(), Some(100.0))].into_iter().collect();
+        let module = |pairing| {
+            StatsReport::new(&original, &stats)
+                .with_modules(&reimplementation, pairing)
+                .modules[0]
+                .name
+                .clone()
+        };
+
+        assert_eq!(module(Pairing::Address), "player.cpp");
+        assert_eq!(module(Pairing::Name), UNKNOWN_MODULE);
+    }
+
+    fn report() -> StatsReport {
+        let stats: FunctionStats = [
+            ("a".to_string(), Some(100.0)),
+            ("b".to_string(), Some(95.0)),
+            ("c".to_string(), Some(20.0)),
+            ("d".to_string(), None),
+        ]
+        .into_iter()
+        .collect();
+
+        StatsReport::new(&Executable::default(), &stats)
+    }
+
+    #[test]
+    fn buckets_count_every_function() {
+        let counts: Vec<usize> = report()
+            .bucket_breakdown()
+            .iter()
+            .map(|(_, count, _)| *count)
+            .collect();
+
+        assert_eq!(counts, vec![1, 1, 0, 1, 1]);
+    }
+
+    #[test]
+    fn filtering_keeps_the_global_stats_and_breakdowns() {
+        let report = report();
+        let filtered = report.clone().filtered(false, Some(50.0));
+
+        let names: Vec<&str> = filtered.functions.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, vec!["c", "d"]);
+        assert_eq!(filtered.global.function_count, 4);
+        assert_eq!(filtered.bucket_breakdown(), report.bucket_breakdown());
+        assert_eq!(
+            filtered.work_status_breakdown(),
+            report.work_status_breakdown()
+        );
+
+        let missing = report.filtered(true, None);
+        assert_eq!(missing.functions.len(), 1);
+        assert_eq!(missing.functions[0].name, "d");
+    }
+
+    #[test]
+    fn filtering_keeps_the_global_stats_breakdowns_and_history() {
+        let mut executable = Executable::default();
+
+        for (name, address, size) in [("a", 0x1000, 16), ("b", 0x2000, 4), ("c", 0x3000, 8)] {
+            executable
+                .add_function(
+                    name.into(),
+                    address,
+                    vec![0xc3; size].into(),
+                    FunctionSource::Mapping,
+                )
+                .unwrap();
+        }
+
+        let stats: FunctionStats = [("a", Some(100.0)), ("b", Some(20.0)), ("c", None)]
+            .into_iter()
+            .map(|(name, percent)| (name.to_string(), percent))
+            .collect();
+        let baseline_stats: FunctionStats = [("a", Some(50.0)), ("b", Some(10.0)), ("c", None)]
+            .into_iter()
+            .map(|(name, percent)| (name.to_string(), percent))
+            .collect();
+        let baseline = StatsReport::new(&executable, &baseline_stats);
+
+        // Same order as the stats command.
+        let report = StatsReport::new(&executable, &stats)
+            .with_weighted_global()
+            .with_baseline(&baseline);
+        let mut history = crate::history::History::open(Path::new(":memory:")).unwrap();
+        history.record(&report).unwrap();
+
+        for (only_missing, below) in [(true, None), (false, Some(50.0))] {
+            let filtered = report.clone().filtered(only_missing, below);
+
+            assert!(filtered.functions.len() < report.functions.len());
+            assert_eq!(
+                filtered.global.percent,
+                16.0 * 100.0 / 28.0 + 4.0 * 20.0 / 28.0
+            );
+            assert_eq!(filtered.global.function_count, 3);
+            assert_eq!(filtered.bucket_breakdown(), report.bucket_breakdown());
+            assert_eq!(
+                filtered.work_status_breakdown(),
+                report.work_status_breakdown()
+            );
+            assert_eq!(filtered.progress.as_ref().unwrap().improved, 2);
+        }
+
+        let snapshots = history.global().unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].percent, Some(report.global.percent));
+        assert_eq!(history.function("c").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn csv_records_follow_the_header() {
+        let mut executable = Executable::default();
+        executable
+            .add_function(
+                "a".into(),
+                0x401000,
+                vec![0x55, 0xc3].into(),
+                FunctionSource::Pdb,
+            )
+            .unwrap();
+
+        let stats: FunctionStats = [("a".to_string(), Some(50.0))].into_iter().collect();
+        let counts = HashMap::from([(
+            "a".to_string(),
+            InstructionCounts {
+                original: 2,
+                reimplementation: Some(3),
+            },
+        )]);
+        let report = StatsReport::new(&executable, &stats).with_instruction_counts(&counts);
+
+        let csv = report.render_csv(&PercentageFormat::default()).unwrap();
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        let header = reader.headers().unwrap().clone();
+        let row = reader.records().next().unwrap().unwrap();
+        let record: HashMap<&str, &str> = header.iter().zip(&row).collect();
+
+        assert_eq!(record["Function name"], "a");
+        assert_eq!(record["Match %"], "50.00%");
+        assert_eq!(record["Address"], "0x401000");
+        assert_eq!(record["Size"], "2");
+        assert_eq!(record["Status"], "partial");
+        assert_eq!(record["Original instructions"], "2");
+        assert_eq!(record["Reimplementation instructions"], "3");
+    }
+}
@@ -0,0 +1,352 @@
+//! Callee signatures used to annotate call arguments.
+//!
+//! Signatures come either from a declarations file containing C prototypes
+//! or from the parameters recorded in a PDB. Only the names of the stack
+//! parameters matter: they are matched against the `push` instructions
+//! preceding a call.
+
+use std::collections::HashMap;
+
+use capstone::arch::x86::X86OperandType;
+use capstone::arch::ArchOperand;
+use capstone::{Capstone, InsnGroupType::CS_GRP_CALL};
+use pdb::{FallibleIterator, RegisterRelativeSymbol, Source, SymbolData, PDB};
+
+use crate::{Executable, ExecutableError, Function};
+
+/// CodeView identifier of the `ebp` register.
+const CV_REG_EBP: u16 = 22;
+
+/// Kind of the `S_BPREL32` symbol, not decoded by the pdb crate.
+const S_BPREL32: u16 = 0x110b;
+
+/// Calling convention of a function.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CallingConvention {
+    #[default]
+    Cdecl,
+    Stdcall,
+    /// `this` is passed in `ecx`.
+    Thiscall,
+    /// The first two parameters are passed in `ecx` and `edx`.
+    Fastcall,
+}
+
+impl CallingConvention {
+    /// Number of parameters passed in registers.
+    fn register_parameter_count(&self) -> usize {
+        match self {
+            CallingConvention::Fastcall => 2,
+            _ => 0,
+        }
+    }
+}
+
+/// Signature of a function.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Signature {
+    pub calling_convention: CallingConvention,
+    /// Names of the parameters, `this` excluded.
+    pub parameters: Vec<String>,
+}
+
+impl Signature {
+    /// Names of the parameters passed on the stack, first pushed last.
+    pub fn stack_parameters(&self) -> &[String] {
+        let skip = self
+            .calling_convention
+            .register_parameter_count()
+            .min(self.parameters.len());
+
+        &self.parameters[skip..]
+    }
+}
+
+/// Signatures by function name.
+pub type Signatures = HashMap<String, Signature>;
+
+/// Extract the name of a parameter from its declaration (`const char *name`).
+fn parameter_name(declaration: &str, idx: usize) -> String {
+    let declaration = declaration.split('=').next().unwrap_or_default().trim();
+    let declaration = declaration.split('[').next().unwrap_or_default().trim();
+
+    // Function pointers: `void (*callback)(int)`.
+    if let Some(start) = declaration.find("(*") {
+        let rest = &declaration[start + 2..];
+        if let Some(end) = rest.find(')') {
+            return rest[..end].trim().into();
+        }
+    }
+
+    let words: Vec<&str> = declaration
+        .split(|c: char| c.is_whitespace() || c == '*' || c == '&')
+        .filter(|x| !x.is_empty())
+        .collect();
+
+    match words.as_slice() {
+        // Unnamed parameter, only a type.
+        [_] | [] => format!("arg{idx}"),
+        [.., name] => (*name).into(),
+    }
+}
+
+/// Parse a single C prototype, returning the function name and its signature.
+fn parse_declaration(declaration: &str) -> Option<(String, Signature)> {
+    let open = declaration.find('(')?;
+    let close = declaration.rfind(')')?;
+
+    if close < open {
+        return None;
+    }
+
+    let head: Vec<&str> = declaration[..open].split_whitespace().collect();
+    let name = head.last()?.trim_start_matches(['*', '&']).to_string();
+
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut calling_convention = if name.contains("::") && !head.contains(&"static") {
+        CallingConvention::Thiscall
+    } else {
+        CallingConvention::Cdecl
+    };
+
+    for word in &head {
+        calling_convention = match *word {
+            "__cdecl" => CallingConvention::Cdecl,
+            "__stdcall" | "WINAPI" | "CALLBACK" => CallingConvention::Stdcall,
+            "__thiscall" => CallingConvention::Thiscall,
+            "__fastcall" => CallingConvention::Fastcall,
+            _ => continue,
+        };
+    }
+
+    let arguments = declaration[open + 1..close].trim();
+    let mut parameters = Vec::new();
+
+    if !arguments.is_empty() && arguments != "void" {
+        // Split on top-level commas only, function pointer types have their own.
+        let mut depth = 0usize;
+        let mut start = 0;
+
+        for (idx, c) in arguments.char_indices() {
+            match c {
+                '(' | '<' => depth += 1,
+                ')' | '>' => depth -= 1,
+                ',' if depth == 0 => {
+                    parameters.push(&arguments[start..idx]);
+                    start = idx + 1;
+                }
+                _ => {}
+            }
+        }
+
+        parameters.push(&arguments[start..]);
+    }
+
+    let parameters = parameters
+        .iter()
+        .map(|x| x.trim())
+        .filter(|x| *x != "...")
+        .enumerate()
+        .map(|(idx, x)| parameter_name(x, idx))
+        .collect();
+
+    Some((
+        name,
+        Signature {
+            calling_convention,
+            parameters,
+        },
+    ))
+}
+
+/// Parse a declarations file containing C prototypes separated by `;`.
+///
+/// Comments and preprocessor lines are ignored, as are declarations that are
+/// not function prototypes.
+pub fn parse_declarations(content: &str) -> Signatures {
+    let content: String = content
+        .lines()
+        .map(|line| line.split("//").next().unwrap_or_default())
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    content
+        .split(';')
+        .filter_map(|declaration| parse_declaration(declaration.trim()))
+        .collect()
+}
+
+/// Collect the parameters of every procedure of a PDB.
+///
+/// Parameters are the variables stored above the frame pointer, in the
+/// order of their offsets.
+pub fn signatures_from_pdb<'s, S>(pdb_file: &mut PDB<'s, S>) -> Result<Signatures, ExecutableError>
+where
+    S: Source<'s> + 's,
+{
+    let mut res = Signatures::new();
+    let dbi = pdb_file.debug_information()?;
+    let mut modules = dbi.modules()?;
+
+    while let Some(module) = modules.next()? {
+        let module_info = match pdb_file.module_info(&module)? {
+            Some(module_info) => module_info,
+            None => continue,
+        };
+
+        let mut iter = module_info.symbols()?;
+        let mut current: Option<(String, Vec<(i32, String)>)> = None;
+        let mut depth = 0usize;
+
+        while let Some(symbol) = iter.next()? {
+            let parameter = match symbol.parse() {
+                Ok(SymbolData::Procedure(procedure)) => {
+                    if depth == 0 {
+                        current = Some((procedure.name.to_string().into(), Vec::new()));
+                    }
+
+                    depth += 1;
+                    continue;
+                }
+                Ok(SymbolData::Block(_))
+                | Ok(SymbolData::InlineSite(_))
+                | Ok(SymbolData::Thunk(_)) => {
+                    depth += 1;
+                    continue;
+                }
+                Ok(SymbolData::ScopeEnd) | Ok(SymbolData::InlineSiteEnd) => {
+                    depth = depth.saturating_sub(1);
+
+                    if depth == 0 {
+                        if let Some((name, mut parameters)) = current.take() {
+                            parameters.sort_by_key(|(offset, _)| *offset);
+
+                            res.insert(
+                                name,
+                                Signature {
+                                    calling_convention: CallingConvention::default(),
+                                    parameters: parameters.into_iter().map(|(_, x)| x).collect(),
+                                },
+                            );
+                        }
+                    }
+
+                    continue;
+                }
+                Ok(SymbolData::RegisterRelative(RegisterRelativeSymbol {
+                    offset,
+                    register,
+                    name,
+                    ..
+                })) if register.0 == CV_REG_EBP => Some((offset, name.to_string().into())),
+                _ if symbol.raw_kind() == S_BPREL32 => {
+                    let data = symbol.raw_bytes();
+
+                    // Record length and kind, then offset, type index and name.
+                    data.get(4..8).map(|x| {
+                        let offset = i32::from_le_bytes(x.try_into().unwrap_or_default());
+                        let name = data.get(12..).unwrap_or_default();
+                        let name = name.split(|x| *x == 0).next().unwrap_or_default();
+
+                        (offset, String::from_utf8_lossy(name).into_owned())
+                    })
+                }
+                _ => None,
+            };
+
+            if let (Some((offset, name)), Some((_, parameters)), 1) =
+                (parameter, current.as_mut(), depth)
+            {
+                // Return address and saved frame pointer sit below the parameters.
+                if offset >= 8 {
+                    parameters.push((offset, name));
+                }
+            }
+        }
+    }
+
+    Ok(res)
+}
+
+/// Find the parameter each `push` preceding a call corresponds to.
+///
+/// Returns comments by instruction offset relative to the start of the function.
+pub fn annotate_arguments(
+    ctx: &Capstone,
+    executable: &Executable,
+    function: &Function,
+    signatures: &Signatures,
+) -> Result<HashMap<u64, String>, ExecutableError> {
+    let mut res = HashMap::new();
+
+    if signatures.is_empty() {
+        return Ok(res);
+    }
+
+    let instructions = ctx.disasm_all(&function.data, function.address as u64)?;
+    let instructions: Vec<_> = instructions.iter().collect();
+
+    for (idx, instruction) in instructions.iter().enumerate() {
+        let detail = ctx.insn_detail(instruction)?;
+
+        if !detail
+            .groups()
+            .iter()
+            .any(|x| u32::from(x.0) == CS_GRP_CALL)
+        {
+            continue;
+        }
+
+        let target =
+            detail
+                .arch_detail()
+                .operands()
+                .into_iter()
+                .find_map(|operand| match operand {
+                    ArchOperand::X86Operand(operand) => match operand.op_type {
+                        X86OperandType::Imm(value) => Some(value as usize),
+                        _ => None,
+                    },
+                    _ => None,
+                });
+
+        let signature = match target
+            .and_then(|x| executable.resolve_call_target(x))
+            .and_then(|x| signatures.get(x.name()))
+        {
+            Some(signature) => signature,
+            None => continue,
+        };
+
+        let mut parameters = signature.stack_parameters().iter();
+
+        for previous in instructions[..idx].iter().rev() {
+            let mnemonic = previous.mnemonic().unwrap_or_default();
+
+            // Stop at anything that could consume or reset the pushed values.
+            if mnemonic == "call" || mnemonic == "ret" || mnemonic.starts_with('j') {
+                break;
+            }
+
+            if mnemonic != "push" {
+                continue;
+            }
+
+            match parameters.next() {
+                Some(parameter) => {
+                    res.insert(
+                        previous.address() - function.address as u64,
+                        format!("arg: {parameter}"),
+                    );
+                }
+                None => break,
+            }
+        }
+    }
+
+    Ok(res)
+}
@@ -0,0 +1,125 @@
+//! Destinations of rendered reports.
+//!
+//! Renderers produce text and hand it to a [`ReportSink`], so programs
+//! embedding satsuki can collect reports without capturing stdout.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum SinkError {
+    IoError { error: std::io::Error },
+    HttpError { error: Box<ureq::Error> },
+}
+
+impl std::fmt::Display for SinkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SinkError::HttpError { error } => write!(f, "HTTP request failed: {error}"),
+            _ => std::fmt::Debug::fmt(self, f),
+        }
+    }
+}
+
+impl std::error::Error for SinkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SinkError::IoError { error } => Some(error),
+            SinkError::HttpError { error } => Some(error),
+        }
+    }
+}
+
+impl From<std::io::Error> for SinkError {
+    fn from(error: std::io::Error) -> Self {
+        SinkError::IoError { error }
+    }
+}
+
+impl From<ureq::Error> for SinkError {
+    fn from(error: ureq::Error) -> Self {
+        SinkError::HttpError {
+            error: Box::new(error),
+        }
+    }
+}
+
+/// Destination of a rendered report.
+pub trait ReportSink {
+    /// Write a rendered report.
+    ///
+    /// `content_type` is the MIME type of the report, used by sinks that
+    /// need to describe their payload.
+    fn write_report(&mut self, report: &str, content_type: &str) -> Result<(), SinkError>;
+}
+
+/// Print reports on the standard output.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StdoutSink;
+
+impl ReportSink for StdoutSink {
+    fn write_report(&mut self, report: &str, _content_type: &str) -> Result<(), SinkError> {
+        let mut stdout = std::io::stdout().lock();
+
+        stdout.write_all(report.as_bytes())?;
+        stdout.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Write reports to a file, replacing its content.
+#[derive(Clone, Debug)]
+pub struct FileSink {
+    pub path: PathBuf,
+}
+
+impl FileSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl ReportSink for FileSink {
+    fn write_report(&mut self, report: &str, _content_type: &str) -> Result<(), SinkError> {
+        std::fs::write(&self.path, report)?;
+
+        Ok(())
+    }
+}
+
+/// Accumulate reports in memory.
+#[derive(Clone, Debug, Default)]
+pub struct StringSink {
+    pub content: String,
+}
+
+impl ReportSink for StringSink {
+    fn write_report(&mut self, report: &str, _content_type: &str) -> Result<(), SinkError> {
+        self.content.push_str(report);
+
+        Ok(())
+    }
+}
+
+/// POST reports to an HTTP endpoint.
+#[derive(Clone, Debug)]
+pub struct HttpSink {
+    pub url: String,
+}
+
+impl HttpSink {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self { url: url.into() }
+    }
+}
+
+impl ReportSink for HttpSink {
+    fn write_report(&mut self, report: &str, content_type: &str) -> Result<(), SinkError> {
+        ureq::post(&self.url)
+            .set("Content-Type", content_type)
+            .send_string(report)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+//! Detection of the string literals referenced by instructions.
+//!
+//! The literals of the data sections are also compared as a whole, a cheap
+//! way to find the functions whose reimplementation misses or mistypes one.
+//!
+//! Touhou games store their text as NUL-terminated ASCII or Shift-JIS
+//! strings. Without a conversion table, only the parts of Shift-JIS mapping
+//! to Unicode by a fixed offset are decoded: the ideographic space, the
+//! full-width digits and letters and the kana. Kanji are kept as `\x..`
+//! escapes of their two bytes.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Shortest string reported, in bytes.
+const MIN_LEN: usize = 2;
+/// Longest string reported, in bytes.
+const MAX_LEN: usize = 256;
+/// Shortest string extracted from a whole section, in bytes. Pointers to
+/// the data of 32-bit executables (`0x0040f6bc`) would pass for 3 bytes
+/// strings.
+const EXTRACT_MIN_LEN: usize = 4;
+
+/// Whether a byte starts a double-byte Shift-JIS character.
+fn is_lead_byte(byte: u8) -> bool {
+    matches!(byte, 0x81..=0x9f | 0xe0..=0xfc)
+}
+
+fn is_trail_byte(byte: u8) -> bool {
+    matches!(byte, 0x40..=0x7e | 0x80..=0xfc)
+}
+
+/// Character of a double-byte Shift-JIS sequence, when it maps to Unicode by an offset.
+fn decode_double_byte(lead: u8, trail: u8) -> Option<char> {
+    let code = u16::from_be_bytes([lead, trail]);
+    let offset =
+        |start: u16, unicode_start: u32| char::from_u32(unicode_start + (code - start) as u32);
+
+    match code {
+        0x8140 => Some('\u{3000}'),
+        0x824f..=0x8258 => offset(0x824f, 0xff10),
+        0x8260..=0x8279 => offset(0x8260, 0xff21),
+        0x8281..=0x829a => offset(0x8281, 0xff41),
+        0x829f..=0x82f1 => offset(0x829f, 0x3041),
+        0x8340..=0x837e => offset(0x8340, 0x30a1),
+        // 0x837f is unused, the katakana resume after it.
+        0x8380..=0x8396 => offset(0x8380, 0x30e0),
+        _ => None,
+    }
+}
+
+/// Decode the string at the start of `data`, quoted and escaped.
+///
+/// Returns `None` unless `data` starts with at least [`MIN_LEN`] bytes of
+/// text followed by a NUL byte.
+pub fn decode(data: &[u8]) -> Option<String> {
+    let len = data.iter().take(MAX_LEN + 1).position(|x| *x == 0)?;
+
+    if len < MIN_LEN {
+        return None;
+    }
+
+    let bytes = &data[..len];
+    let mut res = String::from("\"");
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        let byte = bytes[idx];
+
+        match byte {
+            b'"' => res.push_str("\\\""),
+            b'\\' => res.push_str("\\\\"),
+            b'\n' => res.push_str("\\n"),
+            b'\r' => res.push_str("\\r"),
+            b'\t' => res.push_str("\\t"),
+            0x20..=0x7e => res.push(byte as char),
+            // Half-width katakana.
+            0xa1..=0xdf => res.push(char::from_u32(0xff61 + (byte - 0xa1) as u32)?),
+            _ if is_lead_byte(byte) => {
+                let trail = *bytes.get(idx + 1).filter(|x| is_trail_byte(**x))?;
+
+                match decode_double_byte(byte, trail) {
+                    Some(c) => res.push(c),
+                    None => write!(res, "\\x{byte:02x}\\x{trail:02x}").ok()?,
+                }
+
+                idx += 1;
+            }
+            _ => return None,
+        }
+
+        idx += 1;
+    }
+
+    res.push('"');
+
+    Some(res)
+}
+
+/// Strings of a data section loaded at `address`, with their address.
+///
+/// Only strings of at least [`EXTRACT_MIN_LEN`] bytes starting after a NUL
+/// byte are extracted, the tails of longer strings being left out.
+pub fn extract(data: &[u8], address: usize) -> Vec<(usize, String)> {
+    let mut res = Vec::new();
+    let mut idx = 0;
+
+    while idx < data.len() {
+        let is_start = idx == 0 || data[idx - 1] == 0;
+        let len = data[idx..].iter().position(|x| *x == 0);
+
+        match decode(&data[idx..]).filter(|_| is_start && len >= Some(EXTRACT_MIN_LEN)) {
+            Some(string) => {
+                res.push((address + idx, string));
+                idx += len.unwrap_or(0) + 1;
+            }
+            None => idx += 1,
+        }
+    }
+
+    res
+}
+
+/// Difference between the strings of an executable and its reimplementation.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StringDiff {
+    /// Number of distinct strings of the original.
+    pub original_count: usize,
+    /// Strings of the original absent from the reimplementation.
+    pub missing: Vec<(usize, String)>,
+    /// Strings of the original absent from the reimplementation, which has
+    /// a close one instead.
+    pub different: Vec<(usize, String, String)>,
+    /// Strings present a different number of times on each side, with both
+    /// counts.
+    pub duplicated: Vec<(usize, String, usize, usize)>,
+}
+
+/// Whether two strings only differ in the middle: their common prefix and
+/// suffix cover three quarters of the longest one.
+fn is_close(a: &str, b: &str) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let len = a.len().max(b.len());
+
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a
+        .iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+
+    (prefix + suffix).min(a.len().min(b.len())) * 4 >= len * 3
+}
+
+/// Compare the strings of an executable with the ones of its reimplementation.
+pub fn compare(original: &[(usize, String)], reimplementation: &[(usize, String)]) -> StringDiff {
+    let count = |strings: &[(usize, String)]| {
+        let mut res: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+
+        for (address, string) in strings {
+            res.entry(string.clone()).or_insert((*address, 0)).1 += 1;
+        }
+
+        res
+    };
+
+    let original_counts = count(original);
+    let other_counts = count(reimplementation);
+    let extra: Vec<&String> = other_counts
+        .keys()
+        .filter(|x| !original_counts.contains_key(*x))
+        .collect();
+
+    let mut res = StringDiff {
+        original_count: original_counts.len(),
+        ..StringDiff::default()
+    };
+
+    for (string, (address, count)) in &original_counts {
+        match other_counts.get(string) {
+            Some((_, other_count)) if other_count != count => {
+                res.duplicated
+                    .push((*address, string.clone(), *count, *other_count));
+            }
+            Some(_) => {}
+            None => match extra.iter().find(|x| is_close(string, x)) {
+                Some(close) => res
+                    .different
+                    .push((*address, string.clone(), close.to_string())),
+                None => res.missing.push((*address, string.clone())),
+            },
+        }
+    }
+
+    res.missing.sort();
+    res.different.sort();
+    res.duplicated.sort();
+
+    res
+}
+
+impl StringDiff {
+    /// Render the differences, a line per string, followed by a summary.
+    pub fn render(&self) -> Result<String, std::fmt::Error> {
+        let mut res = String::new();
+
+        for (address, string) in &self.missing {
+            writeln!(res, "missing     {address:#010x}  {string}")?;
+        }
+
+        for (address, string, other) in &self.different {
+            writeln!(res, "different   {address:#010x}  {string} -> {other}")?;
+        }
+
+        for (address, string, count, other_count) in &self.duplicated {
+            writeln!(
+                res,
+                "duplicated  {address:#010x}  {string} ({count} in original, {other_count} in reimplementation)"
+            )?;
+        }
+
+        writeln!(
+            res,
+            "{} strings in original: {} missing, {} different, {} duplicated",
+            self.original_count,
+            self.missing.len(),
+            self.different.len(),
+            self.duplicated.len()
+        )?;
+
+        Ok(res)
+    }
+}
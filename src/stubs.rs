@@ -0,0 +1,260 @@
+//! Declaration stubs of functions not reimplemented yet.
+//!
+//! The calling convention and the number of stack parameters are guessed
+//! from the original code, the types are left as placeholders.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use capstone::arch::x86::{X86OperandType, X86Reg};
+use capstone::{Capstone, RegId};
+
+use crate::signature::CallingConvention;
+use crate::trace::{full_register, operands};
+use crate::{ExecutableError, Function};
+
+/// Declaration stub of a function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionStub {
+    pub name: String,
+    pub address: usize,
+    pub calling_convention: CallingConvention,
+    /// Number of parameters passed on the stack.
+    pub stack_parameter_count: usize,
+}
+
+fn is_register(reg: RegId, id: u32) -> bool {
+    u32::from(reg.0) == id
+}
+
+/// Registers read before being written, among `ecx` and `edx`.
+fn entry_registers(ctx: &Capstone, function: &Function) -> Result<(bool, bool), ExecutableError> {
+    let instructions = ctx.disasm_all(&function.data, 0)?;
+    let mut written: Vec<String> = Vec::new();
+    let mut reads = (false, false);
+
+    for instruction in instructions.iter() {
+        let detail = ctx.insn_detail(instruction)?;
+        let name = |reg: RegId| full_register(&ctx.reg_name(reg).unwrap_or_default());
+
+        // Calls clobber both registers.
+        if instruction.mnemonic() == Some("call") {
+            break;
+        }
+
+        let operands = operands(ctx, instruction)?;
+        let is_zeroing = instruction.mnemonic() == Some("xor")
+            && operands.len() == 2
+            && operands[0].op_type == operands[1].op_type;
+
+        let mut read: Vec<String> = detail.regs_read().iter().map(|x| name(*x)).collect();
+        let mut write: Vec<String> = detail.regs_write().iter().map(|x| name(*x)).collect();
+
+        for operand in &operands {
+            match operand.op_type {
+                X86OperandType::Reg(reg) => {
+                    let access = operand.access;
+
+                    if !is_zeroing && access.map(|x| x.is_readable()).unwrap_or(true) {
+                        read.push(name(reg));
+                    }
+
+                    if access.map(|x| x.is_writable()).unwrap_or(false) {
+                        write.push(name(reg));
+                    }
+                }
+                X86OperandType::Mem(mem) => {
+                    read.push(name(mem.base()));
+                    read.push(name(mem.index()));
+                }
+                _ => {}
+            }
+        }
+
+        for register in read.iter().filter(|x| !written.contains(x)) {
+            match register.as_str() {
+                "ecx" => reads.0 = true,
+                "edx" => reads.1 = true,
+                _ => {}
+            }
+        }
+
+        written.extend(write);
+    }
+
+    Ok(reads)
+}
+
+/// Estimate the number of stack parameters from the accesses above the return address.
+fn stack_parameter_count(ctx: &Capstone, function: &Function) -> Result<usize, ExecutableError> {
+    let instructions = ctx.disasm_all(&function.data, 0)?;
+    let mut esp_delta: i64 = 0;
+    let mut count = 0;
+
+    for instruction in instructions.iter() {
+        let mnemonic = instruction.mnemonic().unwrap_or_default();
+        let operands = operands(ctx, instruction)?;
+
+        for operand in &operands {
+            if let X86OperandType::Mem(mem) = &operand.op_type {
+                let first_parameter = if is_register(mem.base(), X86Reg::X86_REG_EBP) {
+                    8
+                } else if is_register(mem.base(), X86Reg::X86_REG_ESP) {
+                    esp_delta + 4
+                } else {
+                    continue;
+                };
+
+                if mem.disp() >= first_parameter {
+                    count = count.max(((mem.disp() - first_parameter) / 4 + 1) as usize);
+                }
+            }
+        }
+
+        match (mnemonic, operands.as_slice()) {
+            ("push", _) => esp_delta += 4,
+            ("pop", _) => esp_delta -= 4,
+            ("sub", [dst, src]) | ("add", [dst, src]) => {
+                if let (X86OperandType::Reg(reg), X86OperandType::Imm(value)) =
+                    (&dst.op_type, &src.op_type)
+                {
+                    if is_register(*reg, X86Reg::X86_REG_ESP) {
+                        esp_delta += if mnemonic == "sub" { *value } else { -value };
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(count)
+}
+
+/// Guess the declaration of a function from its code.
+pub fn analyze(ctx: &Capstone, function: &Function) -> Result<FunctionStub, ExecutableError> {
+    let instructions = ctx.disasm_all(&function.data, 0)?;
+
+    // `ret N` means the callee cleans up N bytes of parameters.
+    let callee_cleanup = instructions.iter().find_map(|instruction| {
+        if instruction.mnemonic() != Some("ret") {
+            return None;
+        }
+
+        operands(ctx, instruction)
+            .ok()?
+            .first()
+            .and_then(|operand| match operand.op_type {
+                X86OperandType::Imm(value) => Some(value as usize),
+                _ => None,
+            })
+    });
+
+    let (reads_ecx, reads_edx) = entry_registers(ctx, function)?;
+
+    let calling_convention = match (reads_ecx, reads_edx) {
+        (true, true) => CallingConvention::Fastcall,
+        (true, false) if function.name.contains("::") => CallingConvention::Thiscall,
+        _ if callee_cleanup.is_some() => CallingConvention::Stdcall,
+        _ => CallingConvention::Cdecl,
+    };
+
+    let stack_parameter_count = match callee_cleanup {
+        Some(size) => size / 4,
+        None => stack_parameter_count(ctx, function)?,
+    };
+
+    Ok(FunctionStub {
+        name: function.name.clone(),
+        address: function.address,
+        calling_convention,
+        stack_parameter_count,
+    })
+}
+
+impl FunctionStub {
+    fn parameters(&self) -> String {
+        let mut parameters: Vec<String> = Vec::new();
+
+        if self.calling_convention == CallingConvention::Fastcall {
+            parameters.push("int ecx_arg".into());
+            parameters.push("int edx_arg".into());
+        }
+
+        for idx in 0..self.stack_parameter_count {
+            parameters.push(format!("int arg{idx}"));
+        }
+
+        if parameters.is_empty() {
+            "void".into()
+        } else {
+            parameters.join(", ")
+        }
+    }
+
+    fn calling_convention_keyword(&self) -> &'static str {
+        match self.calling_convention {
+            CallingConvention::Cdecl => "__cdecl",
+            CallingConvention::Stdcall => "__stdcall",
+            CallingConvention::Thiscall => "__thiscall",
+            CallingConvention::Fastcall => "__fastcall",
+        }
+    }
+}
+
+/// Render stubs as declarations, grouping methods in their class.
+pub fn render(stubs: &[FunctionStub]) -> Result<String, std::fmt::Error> {
+    let mut free_functions = Vec::new();
+    let mut classes: BTreeMap<&str, Vec<(&str, &FunctionStub)>> = BTreeMap::new();
+
+    for stub in stubs {
+        match stub.name.rsplit_once("::") {
+            Some((class, method)) => classes.entry(class).or_default().push((method, stub)),
+            None => free_functions.push(stub),
+        }
+    }
+
+    let mut res = String::new();
+
+    for stub in free_functions {
+        writeln!(
+            res,
+            "void {} {}({}); // {:#x}",
+            stub.calling_convention_keyword(),
+            stub.name,
+            stub.parameters(),
+            stub.address
+        )?;
+    }
+
+    for (class, methods) in classes {
+        if !res.is_empty() {
+            writeln!(res)?;
+        }
+
+        writeln!(res, "struct {class}")?;
+        writeln!(res, "{{")?;
+
+        for (method, stub) in methods {
+            // Methods not using `this` are assumed to be static.
+            let prefix = match stub.calling_convention {
+                CallingConvention::Thiscall => "",
+                _ => "static ",
+            };
+            let calling_convention = match stub.calling_convention {
+                CallingConvention::Thiscall => String::new(),
+                _ => format!("{} ", stub.calling_convention_keyword()),
+            };
+
+            writeln!(
+                res,
+                "    {prefix}void {calling_convention}{method}({}); // {:#x}",
+                stub.parameters(),
+                stub.address
+            )?;
+        }
+
+        writeln!(res, "}};")?;
+    }
+
+    Ok(res)
+}
@@ -0,0 +1,282 @@
+//! Suggestion of names for the unnamed functions of the mapping.
+//!
+//! Every function of the mapping without a name, or with a placeholder one
+//! such as `sub_401000`, is compared with every function of the
+//! reimplementation the mapping doesn't know yet. The comparison only looks
+//! at how many times each mnemonic is used, which is cheap enough to compare
+//! all the pairs; only pairs that are each other's best candidate are kept.
+//!
+//! A single function can also be compared with the whole reimplementation
+//! using the aligned diff, to find its code under a wrong name.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use capstone::Capstone;
+
+use crate::diff::{self, DiffOptions, Pairing};
+use crate::{Executable, ExecutableError, Function, Mapping};
+
+/// Prefixes of the placeholder names given by disassemblers.
+const PLACEHOLDER_PREFIXES: &[&str] = &["sub_", "FUN_", "fcn."];
+
+/// Functions with fewer instructions look like too many others to be
+/// suggested.
+const MIN_INSTRUCTIONS: usize = 5;
+
+/// Whether a name is a placeholder made of a prefix and an address.
+pub fn is_placeholder_name(name: &str) -> bool {
+    PLACEHOLDER_PREFIXES.iter().any(|prefix| {
+        name.strip_prefix(prefix).is_some_and(|address| {
+            !address.is_empty() && address.chars().all(|x| x.is_ascii_hexdigit())
+        })
+    })
+}
+
+/// Give a placeholder name to the functions of the mapping without one, so
+/// that they get loaded.
+pub fn name_unnamed(mapping: &mut Mapping) {
+    for function in mapping.function.iter_mut().flatten() {
+        if function.name.is_none() {
+            function.name = Some(format!("sub_{:x}", function.address));
+        }
+    }
+}
+
+/// Number of uses of each mnemonic in a function.
+struct Fingerprint<'a> {
+    name: &'a str,
+    mnemonics: HashMap<String, usize>,
+    count: usize,
+}
+
+impl<'a> Fingerprint<'a> {
+    fn compute(
+        ctx: &Capstone,
+        executable: &Executable,
+        function: &'a Function,
+    ) -> Result<Self, ExecutableError> {
+        let options = DiffOptions::instruction_match(Pairing::Name);
+        let instructions = executable.decode_function(ctx, function, &options)?;
+        let mut mnemonics = HashMap::new();
+
+        for instruction in &instructions {
+            *mnemonics.entry(instruction.mnemonic.clone()).or_default() += 1;
+        }
+
+        Ok(Self {
+            name: &function.name,
+            mnemonics,
+            count: instructions.len(),
+        })
+    }
+
+    /// Dice coefficient of the mnemonics of both functions, as a percentage.
+    fn similarity(&self, other: &Self) -> f32 {
+        let common: usize = self
+            .mnemonics
+            .iter()
+            .map(|(mnemonic, count)| (*count).min(*other.mnemonics.get(mnemonic).unwrap_or(&0)))
+            .sum();
+
+        (common * 2) as f32 / (self.count + other.count) as f32 * 100.0
+    }
+}
+
+/// A name suggested for a function of the mapping.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suggestion {
+    /// Name of the function of the reimplementation.
+    pub name: String,
+    /// Address of the function, relative to the image base of the mapping.
+    pub address: usize,
+    pub size: usize,
+    /// Current name of the function, a placeholder.
+    pub original_name: String,
+    pub score: f32,
+}
+
+/// Index and score of the most similar fingerprint, when no other one is as
+/// similar.
+fn best_match(fingerprint: &Fingerprint<'_>, others: &[Fingerprint<'_>]) -> Option<(usize, f32)> {
+    let mut best: Option<(usize, f32)> = None;
+    let mut is_unique = false;
+
+    for (idx, other) in others.iter().enumerate() {
+        let score = fingerprint.similarity(other);
+
+        match best {
+            Some((_, best_score)) if score < best_score => {}
+            Some((_, best_score)) if score == best_score => is_unique = false,
+            _ => {
+                best = Some((idx, score));
+                is_unique = true;
+            }
+        }
+    }
+
+    best.filter(|_| is_unique)
+}
+
+/// Fingerprints of the functions of an executable selected by name, sorted
+/// by name, the smallest ones left out.
+fn fingerprints<'a>(
+    ctx: &Capstone,
+    executable: &'a Executable,
+    is_candidate: impl Fn(&str) -> bool,
+) -> Result<Vec<Fingerprint<'a>>, ExecutableError> {
+    let mut res = Vec::new();
+
+    for (name, function) in executable.functions_iter() {
+        if !is_candidate(name) {
+            continue;
+        }
+
+        let fingerprint = Fingerprint::compute(ctx, executable, function)?;
+
+        if fingerprint.count >= MIN_INSTRUCTIONS {
+            res.push(fingerprint);
+        }
+    }
+
+    res.sort_by_key(|x| x.name);
+
+    Ok(res)
+}
+
+/// Suggest names for the unnamed functions of the mapping, keeping the
+/// pairs scoring at least `min_score` percents, sorted by address.
+///
+/// `original` must be loaded from a mapping given placeholder names by
+/// [`name_unnamed`].
+pub fn suggest(
+    ctx: &Capstone,
+    original: &Executable,
+    reimplementation: &Executable,
+    mapping: &Mapping,
+    min_score: f32,
+) -> Result<Vec<Suggestion>, ExecutableError> {
+    let unnamed = fingerprints(ctx, original, is_placeholder_name)?;
+    let unknown = fingerprints(ctx, reimplementation, |name| {
+        !is_placeholder_name(name) && mapping.get_function_def(name).is_none()
+    })?;
+
+    let mut res = Vec::new();
+
+    for fingerprint in &unnamed {
+        let Some((idx, score)) = best_match(fingerprint, &unknown) else {
+            continue;
+        };
+
+        let is_mutual = best_match(&unknown[idx], &unnamed)
+            .is_some_and(|(other, _)| unnamed[other].name == fingerprint.name);
+
+        let Some(function) = mapping.get_function_def(fingerprint.name) else {
+            continue;
+        };
+
+        if is_mutual && score >= min_score {
+            res.push(Suggestion {
+                name: unknown[idx].name.to_string(),
+                address: function.address,
+                size: function.size,
+                original_name: fingerprint.name.to_string(),
+                score,
+            });
+        }
+    }
+
+    res.sort_by_key(|x| x.address);
+
+    Ok(res)
+}
+
+/// Render the suggestions as `[[function]]` entries to paste in the mapping
+/// in place of the unnamed ones.
+pub fn render_toml(suggestions: &[Suggestion]) -> Result<String, std::fmt::Error> {
+    let mut res = String::new();
+
+    for suggestion in suggestions {
+        writeln!(
+            res,
+            "# {}, {:.2}% similar",
+            suggestion.original_name, suggestion.score
+        )?;
+        writeln!(res, "[[function]]")?;
+        writeln!(res, "name = \"{}\"", suggestion.name)?;
+        writeln!(res, "address = {:#x}", suggestion.address)?;
+        writeln!(res, "size = {:#x}", suggestion.size)?;
+        writeln!(res)?;
+    }
+
+    Ok(res)
+}
+
+/// A function of the reimplementation similar to an original function.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimilarFunction {
+    pub name: String,
+    pub score: f32,
+    /// Whether the mapping already knows the function under this name.
+    pub is_mapped: bool,
+}
+
+/// Rank the functions of the reimplementation by similarity to an original
+/// function, most similar first.
+///
+/// Instructions are aligned before being compared, relocated operands
+/// masked since the functions are at different addresses.
+pub fn find_similar(
+    ctx: &Capstone,
+    original: &Executable,
+    function: &Function,
+    reimplementation: &Executable,
+    mapping: &Mapping,
+) -> Result<Vec<SimilarFunction>, ExecutableError> {
+    let options = DiffOptions::instruction_match(Pairing::Name);
+    let instructions = original.decode_function(ctx, function, &options)?;
+    let mut res = Vec::new();
+
+    for (name, other) in reimplementation.functions_iter() {
+        let other_instructions = reimplementation.decode_function(ctx, other, &options)?;
+        let score = diff::score(
+            options.algorithm,
+            (&instructions, function.compared_data(ctx, &options)?),
+            (&other_instructions, other.compared_data(ctx, &options)?),
+        );
+
+        res.push(SimilarFunction {
+            name: name.clone(),
+            score,
+            is_mapped: mapping.get_function_def(name).is_some(),
+        });
+    }
+
+    res.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    Ok(res)
+}
+
+/// Render the `count` most similar functions, one per line.
+pub fn render_similar(
+    functions: &[SimilarFunction],
+    count: usize,
+) -> Result<String, std::fmt::Error> {
+    let mut res = String::new();
+
+    for function in functions.iter().take(count) {
+        write!(res, "{:>7.2}%  {}", function.score, function.name)?;
+
+        if function.is_mapped {
+            write!(res, " (mapped)")?;
+        }
+
+        writeln!(res)?;
+    }
+
+    Ok(res)
+}
@@ -0,0 +1,340 @@
+//! Constant tables referenced by functions.
+//!
+//! Data tables (float constants, lookup tables...) addressed by the original
+//! code have to be transcribed in the reimplementation. References are
+//! collected across every function so a table stops where another one starts,
+//! then the contents are read from the original executable and rendered as C
+//! array initializers.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use capstone::arch::x86::X86OperandType;
+use capstone::Capstone;
+use object::{File, Object, ObjectSection, SectionKind};
+
+use crate::trace::operands;
+use crate::{ExecutableError, Function};
+
+/// Initialized data section of an executable.
+#[derive(Clone, Debug)]
+pub struct DataSection {
+    pub name: String,
+    pub address: usize,
+    pub data: Vec<u8>,
+    pub read_only: bool,
+}
+
+impl DataSection {
+    fn contains(&self, address: usize) -> bool {
+        (self.address..self.address + self.data.len()).contains(&address)
+    }
+}
+
+/// Collect the initialized data sections of an executable.
+pub fn data_sections(raw_obj: &File) -> Result<Vec<DataSection>, ExecutableError> {
+    let mut res = Vec::new();
+
+    for section in raw_obj.sections() {
+        let read_only = match section.kind() {
+            SectionKind::ReadOnlyData | SectionKind::ReadOnlyString => true,
+            SectionKind::Data => false,
+            _ => continue,
+        };
+
+        if section.address() == 0 {
+            continue;
+        }
+
+        res.push(DataSection {
+            name: section.name()?.into(),
+            address: section.address() as usize,
+            data: section.data()?.to_vec(),
+            read_only,
+        });
+    }
+
+    Ok(res)
+}
+
+/// Type of the elements of a table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ElementType {
+    Int8,
+    Int16,
+    Int32,
+    Float,
+    Double,
+}
+
+impl ElementType {
+    /// Guess the type of a memory operand from its size and the instruction using it.
+    fn from_access(mnemonic: &str, size: u8) -> Self {
+        let is_float = mnemonic.starts_with('f')
+            || mnemonic.ends_with("ss")
+            || mnemonic.ends_with("sd")
+            || mnemonic.ends_with("ps")
+            || mnemonic.ends_with("pd");
+
+        match (is_float, size) {
+            (true, 8) => ElementType::Double,
+            (true, 4) => ElementType::Float,
+            (_, 1) => ElementType::Int8,
+            (_, 2) => ElementType::Int16,
+            _ => ElementType::Int32,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            ElementType::Int8 => 1,
+            ElementType::Int16 => 2,
+            ElementType::Int32 | ElementType::Float => 4,
+            ElementType::Double => 8,
+        }
+    }
+
+    pub fn c_type(&self) -> &'static str {
+        match self {
+            ElementType::Int8 => "unsigned char",
+            ElementType::Int16 => "short",
+            ElementType::Int32 => "int",
+            ElementType::Float => "float",
+            ElementType::Double => "double",
+        }
+    }
+
+    fn per_line(&self) -> usize {
+        match self {
+            ElementType::Int8 => 16,
+            ElementType::Int16 | ElementType::Int32 => 8,
+            ElementType::Float | ElementType::Double => 4,
+        }
+    }
+
+    fn format_element(&self, bytes: &[u8]) -> String {
+        let float = |value: f64, suffix: &str| {
+            if value.is_nan() {
+                "NAN".into()
+            } else if value.is_infinite() {
+                if value > 0.0 { "INFINITY" } else { "-INFINITY" }.into()
+            } else {
+                format!("{value:?}{suffix}")
+            }
+        };
+
+        match self {
+            ElementType::Int8 => format!("{:#04x}", bytes[0]),
+            ElementType::Int16 => i16::from_le_bytes([bytes[0], bytes[1]]).to_string(),
+            ElementType::Int32 => {
+                i32::from_le_bytes(bytes[..4].try_into().unwrap_or_default()).to_string()
+            }
+            ElementType::Float => float(
+                f32::from_le_bytes(bytes[..4].try_into().unwrap_or_default()) as f64,
+                "f",
+            ),
+            ElementType::Double => float(
+                f64::from_le_bytes(bytes[..8].try_into().unwrap_or_default()),
+                "",
+            ),
+        }
+    }
+}
+
+/// A reference from code to data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct DataReference {
+    address: usize,
+    /// Type guessed from the access, `None` when only the address is taken.
+    element: Option<ElementType>,
+    /// Indexed accesses and taken addresses point to the start of an array.
+    is_array: bool,
+}
+
+fn find_references(
+    ctx: &Capstone,
+    function: &Function,
+    sections: &[DataSection],
+) -> Result<Vec<DataReference>, ExecutableError> {
+    let instructions = ctx.disasm_all(&function.data, function.address as u64)?;
+    let in_data = |address: i64| {
+        usize::try_from(address)
+            .ok()
+            .filter(|x| sections.iter().any(|section| section.contains(*x)))
+    };
+    let mut res = Vec::new();
+
+    for instruction in instructions.iter() {
+        let mnemonic = instruction.mnemonic().unwrap_or_default();
+
+        for operand in operands(ctx, instruction)? {
+            match operand.op_type {
+                // Absolute memory operand, possibly indexed.
+                X86OperandType::Mem(mem) if mem.base().0 == 0 => {
+                    if let Some(address) = in_data(mem.disp()) {
+                        res.push(DataReference {
+                            address,
+                            element: Some(ElementType::from_access(mnemonic, operand.size)),
+                            is_array: mem.index().0 != 0,
+                        });
+                    }
+                }
+                X86OperandType::Imm(value) if mnemonic != "call" && !mnemonic.starts_with('j') => {
+                    if let Some(address) = in_data(value) {
+                        res.push(DataReference {
+                            address,
+                            element: None,
+                            is_array: true,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(res)
+}
+
+/// A constant table extracted from the original executable.
+#[derive(Clone, Debug)]
+pub struct ConstantTable {
+    pub address: usize,
+    pub element: ElementType,
+    pub data: Vec<u8>,
+    pub read_only: bool,
+    /// Functions referencing the table.
+    pub referenced_by: BTreeSet<String>,
+}
+
+impl ConstantTable {
+    pub fn len(&self) -> usize {
+        self.data.len() / self.element.size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn name(&self) -> String {
+        format!("table_{:08x}", self.address)
+    }
+}
+
+/// Extract the tables referenced by the given functions.
+///
+/// A table extends up to the start of the next table, the end of its section,
+/// or `max_elements` elements, whichever comes first. Addresses only accessed
+/// directly (without index) are scalars and are not extracted, unless they are
+/// part of a table.
+pub fn extract_tables<'a>(
+    ctx: &Capstone,
+    functions: impl IntoIterator<Item = &'a Function>,
+    sections: &[DataSection],
+    max_elements: usize,
+) -> Result<Vec<ConstantTable>, ExecutableError> {
+    let mut references: BTreeMap<usize, (Vec<DataReference>, BTreeSet<String>)> = BTreeMap::new();
+
+    for function in functions {
+        for reference in find_references(ctx, function, sections)? {
+            let entry = references.entry(reference.address).or_default();
+            entry.0.push(reference);
+            entry.1.insert(function.name.clone());
+        }
+    }
+
+    let addresses: Vec<usize> = references.keys().copied().collect();
+    let mut res: Vec<ConstantTable> = Vec::new();
+
+    for (idx, address) in addresses.iter().enumerate() {
+        let (uses, referenced_by) = &references[address];
+
+        if !uses.iter().any(|x| x.is_array) {
+            continue;
+        }
+
+        let element = uses
+            .iter()
+            .filter_map(|x| x.element)
+            .max()
+            .unwrap_or(ElementType::Int32);
+
+        let section = match sections.iter().find(|x| x.contains(*address)) {
+            Some(section) => section,
+            None => continue,
+        };
+
+        let next_array = addresses[idx + 1..]
+            .iter()
+            .find(|x| references[x].0.iter().any(|x| x.is_array))
+            .copied()
+            .unwrap_or(usize::MAX);
+        let end = next_array
+            .min(section.address + section.data.len())
+            .min(address + max_elements * element.size());
+        let len = (end - address) / element.size() * element.size();
+
+        if len == 0 {
+            continue;
+        }
+
+        let offset = address - section.address;
+        let mut referenced_by = referenced_by.clone();
+
+        // Functions accessing elements directly also use the table.
+        for (_, (_, other)) in references.range(address + 1..address + len) {
+            referenced_by.extend(other.iter().cloned());
+        }
+
+        res.push(ConstantTable {
+            address: *address,
+            element,
+            data: section.data[offset..offset + len].to_vec(),
+            read_only: section.read_only,
+            referenced_by,
+        });
+    }
+
+    Ok(res)
+}
+
+/// Render tables as C array initializers.
+pub fn render(tables: &[ConstantTable]) -> Result<String, std::fmt::Error> {
+    let mut res = String::new();
+
+    for table in tables {
+        if !res.is_empty() {
+            writeln!(res)?;
+        }
+
+        let referenced_by: Vec<&str> = table.referenced_by.iter().map(|x| x.as_str()).collect();
+        writeln!(
+            res,
+            "// {:#x}, used by {}",
+            table.address,
+            referenced_by.join(", ")
+        )?;
+        writeln!(
+            res,
+            "static {}{} {}[{}] = {{",
+            if table.read_only { "const " } else { "" },
+            table.element.c_type(),
+            table.name(),
+            table.len()
+        )?;
+
+        let elements: Vec<String> = table
+            .data
+            .chunks_exact(table.element.size())
+            .map(|x| table.element.format_element(x))
+            .collect();
+
+        for line in elements.chunks(table.element.per_line()) {
+            writeln!(res, "    {},", line.join(", "))?;
+        }
+
+        writeln!(res, "}};")?;
+    }
+
+    Ok(res)
+}
@@ -0,0 +1,342 @@
+//! Operand value provenance tracing.
+//!
+//! Given an operand of an instruction, walk the function backwards to find
+//! where its value comes from (an immediate, a global, a parameter...). The
+//! analysis is a simple linear def-use walk that ignores control flow, which
+//! is usually enough to figure out what C expression produced a value.
+
+use std::fmt::Write;
+
+use capstone::arch::x86::{X86OpMem, X86Operand, X86OperandType, X86Reg};
+use capstone::arch::ArchOperand;
+use capstone::{Capstone, Insn, RegId};
+
+use crate::{ExecutableError, Function};
+
+/// Maximum number of hops followed before giving up.
+const MAX_DEPTH: usize = 32;
+
+/// Origin of a traced value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValueSource {
+    /// A constant encoded in the instruction.
+    Immediate(i64),
+    /// A load from a global at the given address.
+    Global(u64),
+    /// A stack parameter, starting at zero.
+    Parameter(usize),
+    /// A local variable never written in the function.
+    Local(i64),
+    /// A register never written in the function.
+    Register(String),
+    /// Something the analysis cannot follow.
+    Unknown(String),
+}
+
+impl std::fmt::Display for ValueSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValueSource::Immediate(value) => write!(f, "immediate {value:#x}"),
+            ValueSource::Global(address) => write!(f, "global at {address:#x}"),
+            ValueSource::Parameter(idx) => write!(f, "parameter {idx}"),
+            ValueSource::Local(disp) => {
+                let sign = if *disp < 0 { '-' } else { '+' };
+
+                write!(
+                    f,
+                    "uninitialized local [ebp {sign} {:#x}]",
+                    disp.unsigned_abs()
+                )
+            }
+            ValueSource::Register(name) if name == "ecx" => {
+                write!(f, "register ecx at entry (this pointer for __thiscall)")
+            }
+            ValueSource::Register(name) => write!(f, "register {name} at entry"),
+            ValueSource::Unknown(reason) => write!(f, "unknown ({reason})"),
+        }
+    }
+}
+
+/// An instruction visited while tracing.
+#[derive(Clone, Debug)]
+pub struct TraceStep {
+    /// Offset relative to the start of the function.
+    pub offset: usize,
+    pub instruction: String,
+}
+
+/// Result of a trace.
+#[derive(Clone, Debug)]
+pub struct Trace {
+    pub steps: Vec<TraceStep>,
+    pub origin: ValueSource,
+}
+
+impl Trace {
+    pub fn render(&self) -> Result<String, std::fmt::Error> {
+        let mut res = String::new();
+
+        for step in &self.steps {
+            writeln!(res, "    +{:#06x}: {}", step.offset, step.instruction)?;
+        }
+
+        writeln!(res, "origin: {}", self.origin)?;
+
+        Ok(res)
+    }
+}
+
+/// Map a register name to the full 32-bit register containing it.
+pub(crate) fn full_register(name: &str) -> String {
+    match name {
+        "al" | "ah" | "ax" => "eax".into(),
+        "bl" | "bh" | "bx" => "ebx".into(),
+        "cl" | "ch" | "cx" => "ecx".into(),
+        "dl" | "dh" | "dx" => "edx".into(),
+        "si" => "esi".into(),
+        "di" => "edi".into(),
+        "bp" => "ebp".into(),
+        "sp" => "esp".into(),
+        _ => name.into(),
+    }
+}
+
+fn is_register(reg: RegId, id: u32) -> bool {
+    u32::from(reg.0) == id
+}
+
+pub(crate) fn operands(
+    ctx: &Capstone,
+    instruction: &Insn<'_>,
+) -> Result<Vec<X86Operand>, ExecutableError> {
+    let detail = ctx.insn_detail(instruction)?;
+
+    Ok(detail
+        .arch_detail()
+        .operands()
+        .into_iter()
+        .filter_map(|operand| match operand {
+            ArchOperand::X86Operand(operand) => Some(operand),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Pick the operand read by an instruction, preferring the last one.
+fn source_operand(operands: &[X86Operand]) -> Option<usize> {
+    operands
+        .iter()
+        .rposition(|x| x.access.map(|access| access.is_readable()).unwrap_or(true))
+        .or_else(|| operands.len().checked_sub(1))
+}
+
+fn same_stack_slot(a: &X86OpMem, b: &X86OpMem) -> bool {
+    a.base() == b.base() && a.index() == b.index() && a.disp() == b.disp()
+}
+
+fn writes_register(
+    ctx: &Capstone,
+    instruction: &Insn<'_>,
+    register: &str,
+) -> Result<bool, ExecutableError> {
+    let detail = ctx.insn_detail(instruction)?;
+    let matches = |reg: RegId| {
+        ctx.reg_name(reg)
+            .map(|name| full_register(&name) == register)
+            .unwrap_or(false)
+    };
+
+    if detail.regs_write().iter().any(|reg| matches(*reg)) {
+        return Ok(true);
+    }
+
+    // Calls clobber the caller-saved registers, eax holding the return value.
+    if instruction.mnemonic() == Some("call") {
+        return Ok(matches!(register, "eax" | "ecx" | "edx"));
+    }
+
+    Ok(operands(ctx, instruction)?.iter().any(|operand| {
+        matches!(operand.op_type, X86OperandType::Reg(reg) if matches(reg))
+            && operand.access.map(|x| x.is_writable()).unwrap_or(false)
+    }))
+}
+
+fn writes_stack_slot(
+    ctx: &Capstone,
+    instruction: &Insn<'_>,
+    slot: &X86OpMem,
+) -> Result<bool, ExecutableError> {
+    Ok(operands(ctx, instruction)?.iter().any(|operand| {
+        matches!(operand.op_type, X86OperandType::Mem(mem) if same_stack_slot(&mem, slot))
+            && operand.access.map(|x| x.is_writable()).unwrap_or(false)
+    }))
+}
+
+/// Whether the instruction is a `xor reg, reg` zeroing idiom.
+fn is_zeroing(ctx: &Capstone, instruction: &Insn<'_>) -> Result<bool, ExecutableError> {
+    if instruction.mnemonic() != Some("xor") {
+        return Ok(false);
+    }
+
+    let operands = operands(ctx, instruction)?;
+
+    Ok(operands.len() == 2
+        && matches!(operands[0].op_type, X86OperandType::Reg(_))
+        && operands[0].op_type == operands[1].op_type)
+}
+
+fn step(instruction: &Insn<'_>) -> TraceStep {
+    TraceStep {
+        offset: instruction.address() as usize,
+        instruction: format!(
+            "{} {}",
+            instruction.mnemonic().unwrap_or_default(),
+            instruction.op_str().unwrap_or_default()
+        )
+        .trim_end()
+        .into(),
+    }
+}
+
+/// Trace the origin of an operand of the instruction at the given offset.
+///
+/// When `operand_index` is `None`, the last operand read by the instruction is
+/// traced.
+pub fn trace_operand(
+    ctx: &Capstone,
+    function: &Function,
+    offset: usize,
+    operand_index: Option<usize>,
+) -> Result<Trace, ExecutableError> {
+    let instructions = ctx.disasm_all(&function.data, 0)?;
+    let instructions: Vec<_> = instructions.iter().collect();
+
+    let mut idx = instructions
+        .iter()
+        .position(|x| x.address() as usize == offset)
+        .ok_or(ExecutableError::InstructionNotFound { offset })?;
+
+    let mut steps = Vec::new();
+    let mut operand_index = operand_index;
+
+    for _ in 0..MAX_DEPTH {
+        let instruction = instructions[idx];
+        let mnemonic = instruction.mnemonic().unwrap_or_default();
+
+        steps.push(step(instruction));
+
+        if is_zeroing(ctx, instruction)? {
+            return Ok(Trace {
+                steps,
+                origin: ValueSource::Immediate(0),
+            });
+        }
+
+        let operands = operands(ctx, instruction)?;
+        let operand = match operand_index.take().or_else(|| source_operand(&operands)) {
+            Some(operand) if operand < operands.len() => operands[operand].clone(),
+            _ => {
+                return Ok(Trace {
+                    steps,
+                    origin: ValueSource::Unknown(format!("no operand to trace in {mnemonic}")),
+                })
+            }
+        };
+
+        let origin = match operand.op_type {
+            X86OperandType::Imm(value) => ValueSource::Immediate(value),
+            X86OperandType::Mem(mem) if mnemonic == "lea" => {
+                ValueSource::Unknown(format!("address computation at {:#x}", mem.disp()))
+            }
+            X86OperandType::Mem(mem)
+                if is_register(mem.base(), X86Reg::X86_REG_INVALID)
+                    && is_register(mem.index(), X86Reg::X86_REG_INVALID) =>
+            {
+                ValueSource::Global(mem.disp() as u32 as u64)
+            }
+            X86OperandType::Mem(mem)
+                if is_register(mem.base(), X86Reg::X86_REG_EBP) && mem.disp() >= 8 =>
+            {
+                ValueSource::Parameter(((mem.disp() - 8) / 4) as usize)
+            }
+            X86OperandType::Mem(mem) if is_register(mem.base(), X86Reg::X86_REG_EBP) => {
+                let mut writer = None;
+
+                for candidate in (0..idx).rev() {
+                    if writes_stack_slot(ctx, instructions[candidate], &mem)? {
+                        writer = Some(candidate);
+                        break;
+                    }
+                }
+
+                match writer {
+                    Some(writer) => {
+                        idx = writer;
+                        continue;
+                    }
+                    None => ValueSource::Local(mem.disp()),
+                }
+            }
+            X86OperandType::Mem(_) => ValueSource::Unknown("indirect memory access".into()),
+            X86OperandType::Reg(reg) => {
+                let register = full_register(&ctx.reg_name(reg).unwrap_or_default());
+                let mut writer = None;
+
+                for candidate in (0..idx).rev() {
+                    if writes_register(ctx, instructions[candidate], &register)? {
+                        writer = Some(candidate);
+                        break;
+                    }
+                }
+
+                match writer {
+                    Some(writer) => {
+                        let writer_instruction = instructions[writer];
+                        let writer_mnemonic = writer_instruction.mnemonic().unwrap_or_default();
+
+                        if writer_mnemonic.starts_with("mov")
+                            || is_zeroing(ctx, writer_instruction)?
+                        {
+                            idx = writer;
+                            continue;
+                        }
+
+                        steps.push(step(writer_instruction));
+
+                        if writer_mnemonic == "call" {
+                            ValueSource::Unknown("return value of call".into())
+                        } else {
+                            ValueSource::Unknown(format!("result of {writer_mnemonic}"))
+                        }
+                    }
+                    None => ValueSource::Register(register),
+                }
+            }
+            X86OperandType::Invalid => ValueSource::Unknown("invalid operand".into()),
+        };
+
+        return Ok(Trace { steps, origin });
+    }
+
+    Ok(Trace {
+        steps,
+        origin: ValueSource::Unknown("trace too long".into()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn displays_locals_with_their_sign() {
+        assert_eq!(
+            ValueSource::Local(-0x10).to_string(),
+            "uninitialized local [ebp - 0x10]"
+        );
+        assert_eq!(
+            ValueSource::Local(4).to_string(),
+            "uninitialized local [ebp + 0x4]"
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Sanity checks of a mapping against the executable it describes.
+//!
+//! Mistakes in the mapping are otherwise silent: a function outside the code
+//! section is skipped, a duplicated name keeps only its first definition and
+//! an overlap makes two functions share bytes.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use object::{File, Object, ObjectSection};
+
+use crate::{CodeSection, Executable, ExecutableError, FunctionDef, Mapping};
+
+/// A problem of a mapping entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Problem {
+    /// The function is empty.
+    ZeroSize { name: String, address: usize },
+    /// The name is used by several entries, only the first is loaded.
+    DuplicateName { name: String, addresses: Vec<usize> },
+    /// The function shares bytes with the previous one.
+    Overlap {
+        name: String,
+        address: usize,
+        other: String,
+        other_end: usize,
+    },
+    /// The function isn't fully contained in a code section.
+    OutsideSection {
+        name: String,
+        address: usize,
+        size: usize,
+    },
+    /// The size of the function isn't the length of its PDB procedure.
+    SizeMismatch {
+        name: String,
+        address: usize,
+        size: usize,
+        pdb_size: usize,
+    },
+    /// The global variable isn't contained in the section named by the mapping.
+    DataOutsideSection {
+        name: String,
+        address: usize,
+        section: String,
+    },
+}
+
+impl Problem {
+    /// Short identifier of the kind of problem.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Problem::ZeroSize { .. } => "zero-size",
+            Problem::DuplicateName { .. } => "duplicate-name",
+            Problem::Overlap { .. } => "overlap",
+            Problem::OutsideSection { .. } => "outside-section",
+            Problem::SizeMismatch { .. } => "size-mismatch",
+            Problem::DataOutsideSection { .. } => "data-section",
+        }
+    }
+
+    /// Address of the entry, the first one for duplicated names.
+    pub fn address(&self) -> usize {
+        match self {
+            Problem::ZeroSize { address, .. }
+            | Problem::Overlap { address, .. }
+            | Problem::OutsideSection { address, .. }
+            | Problem::SizeMismatch { address, .. }
+            | Problem::DataOutsideSection { address, .. } => *address,
+            Problem::DuplicateName { addresses, .. } => addresses.first().copied().unwrap_or(0),
+        }
+    }
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::ZeroSize { name, .. } => write!(f, "{name} has a size of zero"),
+            Problem::DuplicateName { name, addresses } => {
+                let addresses: Vec<String> = addresses.iter().map(|x| format!("{x:#x}")).collect();
+
+                write!(f, "{name} is defined at {}", addresses.join(", "))
+            }
+            Problem::Overlap {
+                name,
+                other,
+                other_end,
+                ..
+            } => write!(f, "{name} overlaps {other}, which ends at {other_end:#x}"),
+            Problem::OutsideSection { name, size, .. } => write!(
+                f,
+                "{name} ({size:#x} bytes) isn't contained in a code section"
+            ),
+            Problem::SizeMismatch {
+                name,
+                size,
+                pdb_size,
+                ..
+            } => write!(
+                f,
+                "{name} has a size of {size:#x}, the pdb says {pdb_size:#x}"
+            ),
+            Problem::DataOutsideSection { name, section, .. } => {
+                write!(f, "{name} isn't contained in section {section}")
+            }
+        }
+    }
+}
+
+/// Name of an entry, its address for unnamed ones.
+fn entry_name(function: &FunctionDef) -> String {
+    function
+        .name
+        .clone()
+        .unwrap_or_else(|| format!("{:#x}", function.address))
+}
+
+/// Check the functions and global variables of a mapping against an
+/// executable and, if given, the executable loaded from its PDB.
+///
+/// Problems are sorted by address.
+pub fn validate(
+    raw_obj: &File,
+    mapping: &Mapping,
+    pdb_executable: Option<&Executable>,
+) -> Result<Vec<Problem>, ExecutableError> {
+    let mut res = Vec::new();
+    let code_sections = CodeSection::from_object(raw_obj, mapping.section_name())?;
+    let functions = mapping.function.as_deref().unwrap_or_default();
+
+    let mut addresses_by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+
+    for function in functions {
+        let name = entry_name(function);
+
+        if let Some(function_name) = &function.name {
+            addresses_by_name
+                .entry(function_name)
+                .or_default()
+                .push(function.address);
+        }
+
+        if function.size == 0 {
+            res.push(Problem::ZeroSize {
+                name: name.clone(),
+                address: function.address,
+            });
+        }
+
+        let is_contained = mapping
+            .rebase(raw_obj, function.address)
+            .and_then(|address| {
+                let section = CodeSection::find(&code_sections, address)?;
+                let end = address.checked_add(function.size)?;
+
+                Some(end <= section.range().end)
+            })
+            .unwrap_or(false);
+
+        if !is_contained {
+            res.push(Problem::OutsideSection {
+                name: name.clone(),
+                address: function.address,
+                size: function.size,
+            });
+        }
+
+        let pdb_function = function
+            .name
+            .as_ref()
+            .and_then(|x| pdb_executable?.get_function(x));
+
+        if let Some(pdb_function) = pdb_function {
+            if pdb_function.data.len() != function.size {
+                res.push(Problem::SizeMismatch {
+                    name,
+                    address: function.address,
+                    size: function.size,
+                    pdb_size: pdb_function.data.len(),
+                });
+            }
+        }
+    }
+
+    for (name, addresses) in addresses_by_name {
+        if addresses.len() > 1 {
+            res.push(Problem::DuplicateName {
+                name: name.into(),
+                addresses,
+            });
+        }
+    }
+
+    let mut sorted: Vec<&FunctionDef> = functions.iter().filter(|x| x.size != 0).collect();
+    sorted.sort_by_key(|x| (x.address, x.size));
+
+    // Entry reaching the furthest so far, overlapped by any entry starting before its end.
+    let mut furthest: Option<&FunctionDef> = None;
+
+    for function in sorted {
+        if let Some(previous) = furthest {
+            let previous_end = previous.address.saturating_add(previous.size);
+
+            if function.address < previous_end {
+                res.push(Problem::Overlap {
+                    name: entry_name(function),
+                    address: function.address,
+                    other: entry_name(previous),
+                    other_end: previous_end,
+                });
+            }
+
+            if function.address.saturating_add(function.size) <= previous_end {
+                continue;
+            }
+        }
+
+        furthest = Some(function);
+    }
+
+    for data in &mapping.data {
+        let Some(section_name) = &data.section else {
+            continue;
+        };
+
+        let is_contained = mapping
+            .rebase(raw_obj, data.address)
+            .and_then(|address| {
+                let section = raw_obj.section_by_name(section_name)?;
+                let range = section.address()..section.address() + section.size();
+                let end = address.checked_add(data.size.max(1))? as u64;
+
+                Some(range.contains(&(address as u64)) && end <= range.end)
+            })
+            .unwrap_or(false);
+
+        if !is_contained {
+            res.push(Problem::DataOutsideSection {
+                name: data.name.clone(),
+                address: data.address,
+                section: section_name.clone(),
+            });
+        }
+    }
+
+    res.sort_by(|a, b| {
+        (a.address(), a.kind())
+            .cmp(&(b.address(), b.kind()))
+            .then_with(|| a.to_string().cmp(&b.to_string()))
+    });
+
+    Ok(res)
+}
+
+/// Render the problems, one per line: address, kind and description.
+pub fn render(problems: &[Problem]) -> Result<String, std::fmt::Error> {
+    let mut res = String::new();
+
+    for problem in problems {
+        writeln!(
+            res,
+            "{:#010x}  {:<15}  {problem}",
+            problem.address(),
+            problem.kind()
+        )?;
+    }
+
+    Ok(res)
+}
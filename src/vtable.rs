@@ -0,0 +1,219 @@
+//! Comparison of vtables.
+//!
+//! The `[[vtable]]` entries of the mapping locate the vtables of the original
+//! executable, found by name in the reimplementation. Each slot is resolved
+//! to the function it points to on both sides, following the incremental
+//! linking thunks, so that a virtual method declared in the wrong order or
+//! left as an empty stub shows up even when every function matches.
+
+use std::fmt::Write;
+
+use crate::{CallTarget, Executable, Mapping, VtableDef};
+
+/// Names of the functions the compiler puts in the slots of pure virtual methods.
+const PURECALL_NAMES: &[&str] = &["__purecall", "_purecall"];
+
+/// How a slot of a vtable matches the reimplementation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlotStatus {
+    /// Both slots point to the same function.
+    Match,
+    /// The reimplementation points to an empty method or `_purecall`.
+    Stubbed,
+    /// The reimplementation points to another function.
+    Wrong,
+    /// The reimplementation has no such slot.
+    Missing,
+    /// The original slot points to a function that isn't mapped.
+    Unmapped,
+}
+
+impl SlotStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SlotStatus::Match => "match",
+            SlotStatus::Stubbed => "stubbed",
+            SlotStatus::Wrong => "wrong",
+            SlotStatus::Missing => "missing",
+            SlotStatus::Unmapped => "unmapped",
+        }
+    }
+}
+
+/// A slot of a vtable, the name of its function on each side or its address
+/// when unknown.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VtableSlot {
+    pub index: usize,
+    pub original: String,
+    pub reimplementation: Option<String>,
+    pub status: SlotStatus,
+}
+
+/// Comparison of a vtable of the mapping.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VtableReport {
+    pub name: String,
+    /// Whether the reimplementation defines the vtable.
+    pub is_found: bool,
+    pub slots: Vec<VtableSlot>,
+}
+
+impl VtableReport {
+    pub fn matching_count(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|x| x.status == SlotStatus::Match)
+            .count()
+    }
+}
+
+/// Addresses stored in the slots of a vtable.
+fn slots(executable: &Executable, name: &str, pointer_size: usize) -> Option<Vec<usize>> {
+    let data = executable.get_data(name)?;
+
+    Some(
+        data.chunks_exact(pointer_size)
+            .map(|x| {
+                let mut bytes = [0; 8];
+                bytes[..pointer_size].copy_from_slice(x);
+
+                u64::from_le_bytes(bytes) as usize
+            })
+            .collect(),
+    )
+}
+
+/// Whether a function returns right away: `ret`, `ret N`, `xor eax, eax`
+/// followed by one of them, or a breakpoint, `int3`/`nop` padding aside.
+fn is_stub(data: &[u8]) -> bool {
+    // Both padding instructions are a single byte long, so every byte
+    // following a whole instruction is one of them.
+    let is_padding = |data: &[u8]| data.iter().all(|x| matches!(x, 0xcc | 0x90));
+    let is_ret = |data: &[u8]| match data {
+        [0xc3, rest @ ..] | [0xc2, _, _, rest @ ..] => is_padding(rest),
+        _ => false,
+    };
+
+    match data {
+        [0x31 | 0x33, 0xc0, rest @ ..] => is_ret(rest),
+        data => is_ret(data) || is_padding(data),
+    }
+}
+
+/// Name of the function a slot points to, its address when unknown.
+fn slot_name(target: Option<CallTarget<'_>>, address: usize) -> String {
+    match target {
+        Some(target) => target.name().to_string(),
+        None => format!("{address:#x}"),
+    }
+}
+
+/// Compare a vtable of the mapping with the one of the same name in the
+/// reimplementation.
+pub fn compare(
+    original: &Executable,
+    reimplementation: &Executable,
+    vtable: &VtableDef,
+    pointer_size: usize,
+) -> VtableReport {
+    let original_slots = slots(original, &vtable.name, pointer_size).unwrap_or_default();
+    let other_slots = slots(reimplementation, &vtable.name, pointer_size);
+
+    let slots = original_slots
+        .iter()
+        .enumerate()
+        .map(|(index, address)| {
+            let target = original.resolve_call_target(*address);
+            let other_address = other_slots.as_ref().and_then(|x| x.get(index));
+            let other_target =
+                other_address.and_then(|address| reimplementation.resolve_call_target(*address));
+
+            let status = match (target, other_address, other_target) {
+                (None, _, _) => SlotStatus::Unmapped,
+                (_, None, _) => SlotStatus::Missing,
+                (Some(a), _, Some(b)) if a.name() == b.name() => SlotStatus::Match,
+                (_, _, Some(CallTarget::Function(function)))
+                    if is_stub(&function.data)
+                        || PURECALL_NAMES.contains(&function.name.as_str()) =>
+                {
+                    SlotStatus::Stubbed
+                }
+                _ => SlotStatus::Wrong,
+            };
+
+            VtableSlot {
+                index,
+                original: slot_name(target, *address),
+                reimplementation: other_address.map(|address| slot_name(other_target, *address)),
+                status,
+            }
+        })
+        .collect();
+
+    VtableReport {
+        name: vtable.name.clone(),
+        is_found: other_slots.is_some(),
+        slots,
+    }
+}
+
+/// Compare every vtable of the mapping.
+pub fn compare_all(
+    original: &Executable,
+    reimplementation: &Executable,
+    mapping: &Mapping,
+) -> Vec<VtableReport> {
+    mapping
+        .vtable
+        .iter()
+        .map(|vtable| {
+            compare(
+                original,
+                reimplementation,
+                vtable,
+                mapping.arch().pointer_size(),
+            )
+        })
+        .collect()
+}
+
+/// Render the reports, a line per slot: index, status and the functions of
+/// both sides.
+pub fn render(reports: &[VtableReport]) -> Result<String, std::fmt::Error> {
+    let mut res = String::new();
+
+    for report in reports {
+        if !report.is_found {
+            writeln!(res, "{}: not found in reimplementation", report.name)?;
+            continue;
+        }
+
+        writeln!(
+            res,
+            "{}: {}/{} slots matching",
+            report.name,
+            report.matching_count(),
+            report.slots.len()
+        )?;
+
+        for slot in &report.slots {
+            write!(
+                res,
+                "  [{:>2}] {:<8} {}",
+                slot.index,
+                slot.status.name(),
+                slot.original
+            )?;
+
+            match (&slot.reimplementation, slot.status) {
+                (Some(other), SlotStatus::Stubbed | SlotStatus::Wrong) => {
+                    writeln!(res, " -> {other}")?
+                }
+                _ => writeln!(res)?,
+            }
+        }
+    }
+
+    Ok(res)
+}
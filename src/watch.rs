@@ -0,0 +1,109 @@
+//! Continuous comparison of a reimplementation being worked on.
+//!
+//! Files are polled for modification rather than watched through the OS, as
+//! linkers often replace the executable and PDB in several steps. A change is
+//! only reported once the files stopped changing for a full poll interval.
+
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::color::Colorizer;
+use crate::compare;
+use crate::format::PercentageFormat;
+use crate::report::StatsReport;
+
+/// Modification times of a set of files.
+#[derive(Clone, Debug)]
+pub struct FileWatcher {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    /// Whether a change was seen and the files are settling.
+    pending: bool,
+}
+
+fn modified(path: &PathBuf) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|x| x.modified()).ok()
+}
+
+impl FileWatcher {
+    pub fn new(paths: impl IntoIterator<Item = PathBuf>) -> Self {
+        Self {
+            files: paths
+                .into_iter()
+                .map(|path| {
+                    let time = modified(&path);
+                    (path, time)
+                })
+                .collect(),
+            pending: false,
+        }
+    }
+
+    /// Poll the files, returning `true` once they changed and then stayed
+    /// untouched since the previous poll.
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+
+        for (path, time) in &mut self.files {
+            let current = modified(path);
+
+            if current != *time {
+                *time = current;
+                changed = true;
+            }
+        }
+
+        // Missing files are being rewritten.
+        let complete = self.files.iter().all(|(_, time)| time.is_some());
+
+        if changed || !complete {
+            self.pending = true;
+            return false;
+        }
+
+        std::mem::take(&mut self.pending)
+    }
+}
+
+/// Render the functions whose match percentage changed between two runs.
+pub fn render_changes(
+    before: &StatsReport,
+    after: &StatsReport,
+    percentage_format: &PercentageFormat,
+    colorizer: &Colorizer,
+) -> Result<String, std::fmt::Error> {
+    let mut res = String::new();
+    let changes = compare::compare(before, after);
+
+    if changes.is_empty() {
+        writeln!(res, "No function changed")?;
+    }
+
+    for change in &changes {
+        writeln!(
+            res,
+            "{}",
+            compare::render_change(change, percentage_format, colorizer)
+        )?;
+    }
+
+    Ok(res)
+}
+
+/// Render the global percentage of a run and its change since the previous one.
+pub fn render_global(
+    before: &StatsReport,
+    after: &StatsReport,
+    percentage_format: &PercentageFormat,
+    colorizer: &Colorizer,
+) -> String {
+    format!(
+        "GLOBAL: {} ({})\n",
+        percentage_format.format(after.global.percent),
+        compare::format_delta(
+            after.global.percent - before.global.percent,
+            percentage_format,
+            colorizer
+        )
+    )
+}
@@ -0,0 +1,658 @@
+//! Workspaces comparing several binaries at once.
+//!
+//! Some reimplementations ship several binaries (the game executable and
+//! custom DLLs). A workspace file lists every original/reimplementation pair
+//! so their stats are computed in one run, reported per binary and combined.
+//!
+//! ```toml
+//! [[target]]
+//! name = "th06"
+//! original = "th06.exe"
+//! reimplementation = "build/th06e.exe"
+//! pdb = "build/th06e.pdb"
+//! mapping = "config/mapping.toml"
+//! ```
+//!
+//! The same file gives the default arguments of the commands run from its
+//! directory, so that `satsuki stats` doesn't need every path:
+//!
+//! ```toml
+//! [defaults]
+//! original = "th06.exe"
+//! reimplementation = "build/th06e.exe"
+//! pdb = "build/th06e.pdb"
+//! mapping = "config/mapping.toml"
+//!
+//! [defaults.flags.stats]
+//! ignore-relocs = true
+//! diff-algorithm = "aligned"
+//! ```
+//!
+//! A switch enabled there is turned off for a single run with its `--no-`
+//! form, `satsuki stats --no-ignore-relocs`.
+//!
+//! The paths missing from the command line are taken from there: the
+//! positional executables and pdb, and the `--pdb-file`, `--reimplementation`
+//! and `--mapping` options of the commands having them.
+//!
+//! The `SATSUKI_MAPPING`, `SATSUKI_ORIG_EXE`, `SATSUKI_REIMPL_EXE` and
+//! `SATSUKI_PDB` environment variables take precedence over the default paths
+//! of the file, and are used the same way without it.
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::Colorizer;
+use crate::format::PercentageFormat;
+use crate::objdiff;
+use crate::report::{self, GlobalReport, ReportError, ReportFormat, StatsReport};
+use crate::sink::ReportSink;
+
+/// Default name of the workspace file.
+pub const DEFAULT_WORKSPACE_FILE: &str = "satsuki.toml";
+
+#[derive(Debug)]
+pub enum WorkspaceError {
+    IoError { error: std::io::Error },
+    TomlError { error: toml::de::Error },
+    NoTarget,
+}
+
+impl std::fmt::Display for WorkspaceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkspaceError::NoTarget => write!(f, "Workspace has no target!"),
+            _ => std::fmt::Debug::fmt(self, f),
+        }
+    }
+}
+
+impl std::error::Error for WorkspaceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WorkspaceError::IoError { error } => Some(error),
+            WorkspaceError::TomlError { error } => Some(error),
+            WorkspaceError::NoTarget => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for WorkspaceError {
+    fn from(error: std::io::Error) -> Self {
+        Self::IoError { error }
+    }
+}
+
+impl From<toml::de::Error> for WorkspaceError {
+    fn from(error: toml::de::Error) -> Self {
+        Self::TomlError { error }
+    }
+}
+
+/// A pair of binaries to compare.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct Target {
+    pub name: String,
+    pub original: PathBuf,
+    pub reimplementation: PathBuf,
+    /// PDB of the reimplementation.
+    pub pdb: Option<PathBuf>,
+    /// Linker map file of the reimplementation, used when no PDB is given.
+    pub map: Option<PathBuf>,
+    /// Mapping TOML file of the original binary.
+    pub mapping: Option<PathBuf>,
+    /// Mapping CSV file of the original binary.
+    pub mapping_csv: Option<PathBuf>,
+    /// IDA names export of the original binary.
+    pub mapping_ida: Option<PathBuf>,
+    /// Version of the original binary the mapping addresses are selected
+    /// for, when the mapping describes several versions.
+    pub game_version: Option<String>,
+}
+
+impl Target {
+    fn resolve_paths(&mut self, base: &Path) {
+        let resolve = |path: &mut PathBuf| *path = base.join(&*path);
+
+        resolve(&mut self.original);
+        resolve(&mut self.reimplementation);
+
+        for path in [
+            &mut self.pdb,
+            &mut self.map,
+            &mut self.mapping,
+            &mut self.mapping_csv,
+            &mut self.mapping_ida,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            resolve(path);
+        }
+    }
+}
+
+/// Environment variables overriding the default paths.
+pub const MAPPING_VAR: &str = "SATSUKI_MAPPING";
+pub const ORIGINAL_VAR: &str = "SATSUKI_ORIG_EXE";
+pub const REIMPLEMENTATION_VAR: &str = "SATSUKI_REIMPL_EXE";
+pub const PDB_VAR: &str = "SATSUKI_PDB";
+
+/// Value of a flag in the defaults.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum FlagValue {
+    /// A switch, only passed when true.
+    Switch(bool),
+    Integer(i64),
+    Text(String),
+}
+
+/// Default arguments of the commands, the `[defaults]` table.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+pub struct Defaults {
+    /// Original executable.
+    pub original: Option<PathBuf>,
+    pub reimplementation: Option<PathBuf>,
+    /// PDB of the reimplementation.
+    pub pdb: Option<PathBuf>,
+    /// Mapping TOML file of the original executable.
+    pub mapping: Option<PathBuf>,
+    /// Version of the original executable the mapping addresses are
+    /// selected for.
+    pub game_version: Option<String>,
+    /// Flags of each command, by command and flag name.
+    #[serde(default)]
+    pub flags: BTreeMap<String, BTreeMap<String, FlagValue>>,
+}
+
+impl Defaults {
+    /// Load the defaults of a workspace file, paths being relative to the
+    /// directory containing it.
+    pub fn load(path: &Path) -> Result<Self, WorkspaceError> {
+        let workspace: Workspace = toml::from_str(&std::fs::read_to_string(path)?)?;
+        let mut res = workspace.defaults;
+        let base = path.parent().unwrap_or(Path::new(""));
+
+        for path in [
+            &mut res.original,
+            &mut res.reimplementation,
+            &mut res.pdb,
+            &mut res.mapping,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *path = base.join(&*path);
+        }
+
+        Ok(res)
+    }
+
+    /// Override the default paths with the environment variables set to a
+    /// non-empty value, relative to the working directory.
+    pub fn apply_env(&mut self) {
+        self.apply_vars(std::env::var_os);
+    }
+
+    /// Override the default paths with the variables `lookup` gives a
+    /// non-empty value.
+    fn apply_vars(&mut self, lookup: impl Fn(&'static str) -> Option<OsString>) {
+        for (var, path) in [
+            (MAPPING_VAR, &mut self.mapping),
+            (ORIGINAL_VAR, &mut self.original),
+            (REIMPLEMENTATION_VAR, &mut self.reimplementation),
+            (PDB_VAR, &mut self.pdb),
+        ] {
+            if let Some(value) = lookup(var).filter(|x| !x.is_empty()) {
+                *path = Some(value.into());
+            }
+        }
+    }
+
+    /// Insert the default paths of the options a command accepts before its
+    /// arguments, leaving out the ones already given. `--pdb-file` is left
+    /// out as well when `--map-file` is given.
+    pub fn insert_paths(&self, accepted: &[&str], args: &mut Vec<String>) {
+        let mut res = Vec::new();
+
+        for (option, path, alternatives) in [
+            ("--pdb-file", &self.pdb, &["--map-file"][..]),
+            ("--reimplementation", &self.reimplementation, &[]),
+            ("--mapping", &self.mapping, &[]),
+        ] {
+            let is_given = args
+                .iter()
+                .any(|x| x == option || alternatives.contains(&x.as_str()));
+
+            if let (true, false, Some(path)) = (accepted.contains(&option), is_given, path) {
+                res.extend([option.into(), path.to_string_lossy().into_owned()]);
+            }
+        }
+
+        args.splice(0..0, res);
+    }
+
+    /// Default path of a positional argument, by the name of the argument.
+    /// `None` when the argument isn't a path the defaults give.
+    fn positional_path(&self, name: &str) -> Option<&Option<PathBuf>> {
+        match name {
+            "original_executable_file" | "executable_file" => Some(&self.original),
+            "reimplementation_executable_file" => Some(&self.reimplementation),
+            "pdb_file" => Some(&self.pdb),
+            _ => None,
+        }
+    }
+
+    /// Insert the default paths of the positional arguments missing from the
+    /// arguments of a command. `positionals` are the names of the required
+    /// positional arguments of the command and `options` its options taking
+    /// a value, whose values aren't counted as positional arguments.
+    ///
+    /// The arguments given are assigned to the trailing positional arguments
+    /// without default first (the function name of `diff`), then to the
+    /// leading paths in order, the remaining paths being taken from the
+    /// defaults. Nothing is inserted when one of them has no default.
+    pub fn insert_positionals(
+        &self,
+        positionals: &[&str],
+        options: &[&str],
+        args: &mut Vec<String>,
+    ) {
+        let paths: Vec<_> = positionals
+            .iter()
+            .map_while(|x| self.positional_path(x))
+            .collect();
+
+        let mut given = Vec::new();
+        let mut iter = args.iter().enumerate();
+
+        while let Some((idx, arg)) = iter.next() {
+            if options.contains(&arg.as_str()) {
+                iter.next();
+            } else if !arg.starts_with('-') {
+                given.push(idx);
+            }
+        }
+
+        let missing = positionals
+            .len()
+            .saturating_sub(given.len())
+            .min(paths.len());
+        let defaults: Option<Vec<String>> = paths[paths.len() - missing..]
+            .iter()
+            .map(|x| x.as_ref().map(|x| x.to_string_lossy().into_owned()))
+            .collect();
+
+        if let Some(defaults) = defaults {
+            let position = given
+                .get(paths.len() - missing)
+                .copied()
+                .unwrap_or(args.len());
+            args.splice(position..position, defaults);
+        }
+    }
+
+    /// Insert the default flags of a command before its arguments, leaving
+    /// out the ones already given and the ones it doesn't accept. `accepted`
+    /// are the flags of the command, `--` included.
+    ///
+    /// A switch enabled by default is turned off by giving it prefixed with
+    /// `no-` (`--no-keep-padding`), unless the command has such a flag, the
+    /// negation being removed from the arguments.
+    pub fn insert_flags(&self, command: &str, accepted: &[&str], args: &mut Vec<String>) {
+        let mut res = Vec::new();
+
+        for (name, value) in self.flags.get(command).into_iter().flatten() {
+            let flag = format!("--{name}");
+
+            if !accepted.contains(&flag.as_str()) || args.contains(&flag) {
+                continue;
+            }
+
+            match value {
+                FlagValue::Switch(true) => {
+                    let negation = format!("--no-{name}");
+
+                    if accepted.contains(&negation.as_str()) || !args.contains(&negation) {
+                        res.push(flag);
+                    } else {
+                        args.retain(|x| *x != negation);
+                    }
+                }
+                FlagValue::Switch(false) => {}
+                FlagValue::Integer(value) => res.extend([flag, value.to_string()]),
+                FlagValue::Text(value) => res.extend([flag, value.clone()]),
+            }
+        }
+
+        args.splice(0..0, res);
+    }
+}
+
+/// Binaries of a multi-binary project.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+pub struct Workspace {
+    #[serde(default, rename = "target")]
+    pub targets: Vec<Target>,
+    #[serde(default)]
+    pub defaults: Defaults,
+}
+
+impl Workspace {
+    /// Load a workspace file, paths being relative to the directory containing it.
+    pub fn load(path: &Path) -> Result<Self, WorkspaceError> {
+        let mut res: Self = toml::from_str(&std::fs::read_to_string(path)?)?;
+
+        if res.targets.is_empty() {
+            return Err(WorkspaceError::NoTarget);
+        }
+
+        let base = path.parent().unwrap_or(Path::new(""));
+
+        for target in &mut res.targets {
+            target.resolve_paths(base);
+        }
+
+        Ok(res)
+    }
+}
+
+/// Stats of a single target.
+#[derive(Clone, Debug, Serialize)]
+pub struct TargetReport {
+    pub name: String,
+    #[serde(flatten)]
+    pub report: StatsReport,
+}
+
+/// Stats of every target of a workspace.
+#[derive(Clone, Debug, Serialize)]
+pub struct WorkspaceReport {
+    pub targets: Vec<TargetReport>,
+    /// Aggregate of all the functions of all targets.
+    pub combined: GlobalReport,
+}
+
+impl WorkspaceReport {
+    pub fn new(targets: Vec<TargetReport>) -> Self {
+        let functions: Vec<_> = targets
+            .iter()
+            .flat_map(|x| x.report.functions.iter())
+            .collect();
+        let combined = GlobalReport::summarize(&functions);
+
+        Self { targets, combined }
+    }
+
+    fn render_summary(
+        &self,
+        res: &mut String,
+        percentage_format: &PercentageFormat,
+    ) -> Result<(), std::fmt::Error> {
+        let combined = &self.combined;
+
+        writeln!(
+            res,
+            "COMBINED: {} ({} functions, {} matching, {} missing)",
+            percentage_format.format(combined.percent),
+            combined.function_count,
+            combined.matching_count,
+            combined.missing_count
+        )
+    }
+
+    /// Render the report of each target followed by the combined stats.
+    ///
+    /// HTML is not supported.
+    pub fn render(
+        &self,
+        format: ReportFormat,
+        percentage_format: &PercentageFormat,
+        colorizer: &Colorizer,
+    ) -> Result<String, ReportError> {
+        let mut res = String::new();
+
+        match format {
+            ReportFormat::Text => {
+                for target in &self.targets {
+                    writeln!(res, "[{}]", target.name)?;
+                    res.push_str(&target.report.render_text(percentage_format, colorizer)?);
+                    writeln!(
+                        res,
+                        "GLOBAL: {}\n",
+                        percentage_format.format(target.report.global.percent)
+                    )?;
+                }
+
+                self.render_summary(&mut res, percentage_format)?;
+            }
+            ReportFormat::Markdown => {
+                for target in &self.targets {
+                    writeln!(res, "## {}\n", target.name)?;
+                    res.push_str(&target.report.render_markdown(percentage_format)?);
+                    writeln!(res)?;
+                }
+
+                let combined = &self.combined;
+
+                writeln!(
+                    res,
+                    "**Combined: {}** ({} functions, {} matching, {} missing)",
+                    percentage_format.format(combined.percent),
+                    combined.function_count,
+                    combined.matching_count,
+                    combined.missing_count
+                )?;
+            }
+            ReportFormat::Csv => {
+                // Every target is compared with the same options, hence the
+                // same columns.
+                let mut header = Vec::new();
+                let mut rows = Vec::new();
+
+                for target in &self.targets {
+                    let (target_header, target_rows) = target.report.csv_records(percentage_format);
+
+                    header = target_header;
+                    rows.extend(
+                        target_rows
+                            .into_iter()
+                            .map(|row| std::iter::once(target.name.clone()).chain(row).collect()),
+                    );
+                }
+
+                header.insert(0, "Target".into());
+
+                res = report::write_csv(&header, &rows)?;
+            }
+            ReportFormat::Json => {
+                let mut report = self.clone();
+
+                for target in &mut report.targets {
+                    target.report = target.report.rounded(percentage_format);
+                }
+
+                report.combined.percent = percentage_format.round(report.combined.percent);
+
+                res = serde_json::to_string_pretty(&report)? + "\n";
+            }
+            ReportFormat::Objdiff => {
+                let units = self
+                    .targets
+                    .iter()
+                    .map(|x| objdiff::ReportUnit::from_stats(&x.report, &x.name))
+                    .collect();
+
+                res = serde_json::to_string_pretty(&objdiff::Report::from_units(units))? + "\n";
+            }
+            ReportFormat::Html => return Err(ReportError::UnsupportedFormat { format }),
+        }
+
+        Ok(res)
+    }
+
+    /// Render the report in the given format and write it to a sink.
+    pub fn write_to(
+        &self,
+        sink: &mut dyn ReportSink,
+        format: ReportFormat,
+        percentage_format: &PercentageFormat,
+        colorizer: &Colorizer,
+    ) -> Result<(), ReportError> {
+        let res = self.render(format, percentage_format, colorizer)?;
+
+        sink.write_report(&res, format.content_type())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_paths_are_relative_to_the_workspace_file() {
+        let dir = std::env::temp_dir().join(format!("satsuki-workspace-{}", std::process::id()));
+        let path = dir.join(DEFAULT_WORKSPACE_FILE);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            &path,
+            "[defaults]\noriginal = \"th06.exe\"\npdb = \"build/th06e.pdb\"\n",
+        )
+        .unwrap();
+
+        let defaults = Defaults::load(&path);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let defaults = defaults.unwrap();
+        assert_eq!(defaults.original, Some(dir.join("th06.exe")));
+        assert_eq!(defaults.pdb, Some(dir.join("build/th06e.pdb")));
+        assert_eq!(defaults.reimplementation, None);
+    }
+
+    #[test]
+    fn default_flags_leave_out_the_given_and_unknown_ones() {
+        let workspace: Workspace = toml::from_str(
+            r#"
+            [defaults.flags.stats]
+            ignore-relocs = true
+            keep-padding = true
+            precision = 2
+            diff-algorithm = "aligned"
+            game-version = "1.02h"
+            "#,
+        )
+        .unwrap();
+        let accepted = [
+            "--ignore-relocs",
+            "--keep-padding",
+            "--precision",
+            "--diff-algorithm",
+        ];
+        let mut args: Vec<String> = ["--precision", "1", "--no-keep-padding", "th06.exe"]
+            .map(String::from)
+            .to_vec();
+
+        workspace
+            .defaults
+            .insert_flags("stats", &accepted, &mut args);
+
+        assert_eq!(
+            args,
+            [
+                "--diff-algorithm",
+                "aligned",
+                "--ignore-relocs",
+                "--precision",
+                "1",
+                "th06.exe"
+            ]
+        );
+    }
+
+    #[test]
+    fn default_positionals_complete_the_given_ones() {
+        let mut defaults = Defaults {
+            original: Some("th06.exe".into()),
+            reimplementation: Some("build/th06e.exe".into()),
+            ..Default::default()
+        };
+        defaults.apply_vars(|var| match var {
+            PDB_VAR => Some("build/th06e.pdb".into()),
+            _ => None,
+        });
+
+        let positionals = [
+            "original_executable_file",
+            "reimplementation_executable_file",
+            "pdb_file",
+            "function_name",
+        ];
+        let complete = |defaults: &Defaults, args: &[&str]| {
+            let mut args: Vec<String> = args.iter().map(|x| x.to_string()).collect();
+            defaults.insert_positionals(&positionals, &["--diff-algorithm"], &mut args);
+            args
+        };
+
+        assert_eq!(
+            complete(
+                &defaults,
+                &["--diff-algorithm", "aligned", "th06_102h.exe", "main"]
+            ),
+            [
+                "--diff-algorithm",
+                "aligned",
+                "th06_102h.exe",
+                "build/th06e.exe",
+                "build/th06e.pdb",
+                "main"
+            ]
+        );
+        assert_eq!(
+            complete(&defaults, &["main", "--diff-algorithm", "aligned"]),
+            [
+                "th06.exe",
+                "build/th06e.exe",
+                "build/th06e.pdb",
+                "main",
+                "--diff-algorithm",
+                "aligned"
+            ]
+        );
+        assert_eq!(
+            complete(&defaults, &["a.exe", "b.exe", "b.pdb", "main"]),
+            ["a.exe", "b.exe", "b.pdb", "main"]
+        );
+
+        defaults.pdb = None;
+        assert_eq!(
+            complete(&defaults, &["th06_102h.exe", "main"]),
+            ["th06_102h.exe", "main"]
+        );
+    }
+
+    #[test]
+    fn environment_variables_override_the_default_paths() {
+        let mut defaults = Defaults {
+            original: Some("th06.exe".into()),
+            pdb: Some("build/th06e.pdb".into()),
+            ..Default::default()
+        };
+
+        defaults.apply_vars(|var| match var {
+            ORIGINAL_VAR => Some("th06_102h.exe".into()),
+            PDB_VAR => Some("".into()),
+            _ => None,
+        });
+
+        assert_eq!(defaults.original, Some("th06_102h.exe".into()));
+        assert_eq!(defaults.pdb, Some("build/th06e.pdb".into()));
+        assert_eq!(defaults.mapping, None);
+    }
+}
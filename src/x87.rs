@@ -0,0 +1,140 @@
+//! Normalization of x87 floating point code.
+//!
+//! Compilers emit different but equivalent x87 sequences for the same float
+//! expression: `fwait` before the instructions checking for exceptions,
+//! `fxch` to reorder the register stack, operands of commutative operations
+//! loaded in either order. Each rule rewrites one of these variations to a
+//! single form before the instructions are compared.
+
+use std::str::FromStr;
+
+use crate::diff::DecodedInstruction;
+
+/// Waiting forms of the x87 control instructions, with their no-wait variant.
+const WAITING_FORMS: [(&str, &str); 6] = [
+    ("fstsw", "fnstsw"),
+    ("fstcw", "fnstcw"),
+    ("finit", "fninit"),
+    ("fclex", "fnclex"),
+    ("fsave", "fnsave"),
+    ("fstenv", "fnstenv"),
+];
+
+/// Normalization rules applied to x87 instructions, none by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct X87Rules {
+    /// Drop `wait` and compare waiting instructions (`fstsw`) with their
+    /// no-wait variants (`fnstsw`).
+    pub wait: bool,
+    /// Drop the `fxch` reordering the register stack.
+    pub fxch: bool,
+    /// Sort the operands of additions and multiplications, loaded by a
+    /// `fld` right before the operation.
+    pub commute: bool,
+}
+
+impl X87Rules {
+    pub fn all() -> Self {
+        Self {
+            wait: true,
+            fxch: true,
+            commute: true,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Names of the enabled rules.
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            (self.wait, "wait"),
+            (self.fxch, "fxch"),
+            (self.commute, "commute"),
+        ]
+        .into_iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, name)| name)
+        .collect()
+    }
+}
+
+impl FromStr for X87Rules {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut res = Self::default();
+
+        for rule in s.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+            match rule {
+                "wait" => res.wait = true,
+                "fxch" => res.fxch = true,
+                "commute" => res.commute = true,
+                "all" => res = Self::all(),
+                _ => {
+                    return Err(format!(
+                        "Unknown x87 rule \"{rule}\", expected one of wait, fxch, commute or all"
+                    ))
+                }
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+/// Whether an operand is a register of the x87 stack (`st(1)`).
+fn is_stack_register(operand: &str) -> bool {
+    operand.starts_with("st(")
+}
+
+/// Apply the rules to decoded instructions.
+pub fn normalize(instructions: &mut Vec<DecodedInstruction>, rules: &X87Rules) {
+    if rules.wait {
+        instructions.retain(|x| x.mnemonic != "wait" && x.mnemonic != "fwait");
+
+        for instruction in instructions.iter_mut() {
+            if let Some((_, no_wait)) = WAITING_FORMS
+                .iter()
+                .find(|(waiting, _)| instruction.mnemonic == *waiting)
+            {
+                instruction.mnemonic = no_wait.to_string();
+            }
+        }
+    }
+
+    if rules.fxch {
+        instructions.retain(|x| x.mnemonic != "fxch");
+    }
+
+    if rules.commute {
+        commute(instructions);
+    }
+}
+
+/// Sort the operands of `fld a; fmul b` and `fld a; fld b; fmulp st(1)`.
+fn commute(instructions: &mut [DecodedInstruction]) {
+    let is_load = |x: &DecodedInstruction| x.mnemonic == "fld" && !is_stack_register(&x.operands);
+
+    for idx in 1..instructions.len() {
+        let operation = &instructions[idx];
+
+        let (a, b) = match operation.mnemonic.as_str() {
+            "fadd" | "fmul" if !is_stack_register(&operation.operands) => (idx - 1, idx),
+            "faddp" | "fmulp"
+                if idx >= 2
+                    && matches!(operation.operands.as_str(), "" | "st(1)" | "st(1), st(0)")
+                    && is_load(&instructions[idx - 1]) =>
+            {
+                (idx - 2, idx - 1)
+            }
+            _ => continue,
+        };
+
+        if is_load(&instructions[a]) && instructions[a].operands > instructions[b].operands {
+            let operands = std::mem::take(&mut instructions[a].operands);
+            instructions[a].operands = std::mem::replace(&mut instructions[b].operands, operands);
+        }
+    }
+}